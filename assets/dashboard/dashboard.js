@@ -0,0 +1,270 @@
+// The dashboard only calls the admin API, relative to /admin/dashboard, with the token the
+// operator entered; it's kept for the browser tab only.
+"use strict";
+
+const REFRESH_EVERY = 10000;
+const DIRECTIONS = ["zammad-to-jira", "jira-to-zammad"];
+
+let refreshTimer = null;
+
+function token() {
+  return sessionStorage.getItem("admin-token");
+}
+
+async function api(method, path, body) {
+  const response = await fetch(path, {
+    method,
+    headers: {
+      Authorization: `Bearer ${token()}`,
+      ...(body === undefined ? {} : { "Content-Type": "application/json" }),
+    },
+    body: body === undefined ? undefined : JSON.stringify(body),
+  });
+  if (response.status === 401) {
+    logOut("The token was rejected.");
+    throw new Error("unauthorized");
+  }
+  if (!response.ok) {
+    throw new Error(`${method} ${path}: ${response.status}`);
+  }
+  return response.status === 204 ? null : response.json();
+}
+
+function cell(row, text, className) {
+  const td = row.insertCell();
+  td.textContent = text ?? "";
+  if (className) {
+    td.className = className;
+  }
+  return td;
+}
+
+function button(td, label, onClick) {
+  const element = document.createElement("button");
+  element.textContent = label;
+  element.addEventListener("click", async () => {
+    element.disabled = true;
+    try {
+      await onClick();
+    } finally {
+      refresh();
+    }
+  });
+  td.append(element);
+}
+
+function time(value) {
+  return value ? new Date(value).toLocaleString() : "";
+}
+
+function fill(id, items, render, empty) {
+  const body = document.getElementById(id);
+  body.replaceChildren();
+  if (items.length === 0) {
+    cell(body.insertRow(), empty).colSpan = 6;
+  }
+  for (const item of items) {
+    render(body.insertRow(), item);
+  }
+}
+
+async function showPause() {
+  const status = await api("GET", "pause");
+  const container = document.getElementById("pause");
+  container.replaceChildren();
+  for (const [label, direction, state] of [
+    ["All syncing", null, status],
+    ...DIRECTIONS.map((direction) => [direction, direction, status.directions[direction]]),
+  ]) {
+    const line = document.createElement("div");
+    const text = document.createElement("span");
+    text.textContent = `${label}: ${state.paused ? "paused" : "running"}`;
+    if (state.paused) {
+      text.className = "paused";
+      if (state.reason) {
+        text.textContent += ` (${state.reason})`;
+      }
+    }
+    line.append(text, " ");
+    const query = direction ? `?direction=${direction}` : "";
+    if (state.configured) {
+      line.append("paused in the configuration");
+    } else if (direction && state.paused && !state.paused_at) {
+      line.append("paused with all syncing");
+    } else if (state.paused) {
+      button(line, "Resume", () => api("POST", `resume${query}`));
+    } else {
+      button(line, "Pause", () => {
+        const reason = document.getElementById("pause-reason").value || null;
+        return api("POST", `pause${query}`, { reason });
+      });
+    }
+    container.append(line);
+  }
+  const queued = document.createElement("div");
+  queued.textContent = `${status.queued} webhooks waiting in the outbox`;
+  container.append(queued);
+}
+
+async function showStats() {
+  const stats = await api("GET", "stats?days=1");
+  const totals = stats.totals;
+  document.getElementById("stats").textContent =
+    `Today: ${totals.syncs} syncs, ${totals.created_issues} issues created, ` +
+    `${totals.synced_comments} comments synced, ${totals.failures} failed`;
+}
+
+async function showSyncs() {
+  const entries = await api("GET", "audit?limit=25");
+  fill("syncs", entries, (row, entry) => {
+    cell(row, time(entry.created_at));
+    cell(row, entry.operation);
+    cell(row, entry.zammad_id);
+    cell(row, entry.jira_id);
+    if (entry.error) {
+      cell(row, entry.error, "error failed");
+    } else {
+      cell(row, ["synced", ...entry.fields].join(" "), "synced");
+    }
+    cell(row, `${entry.duration_ms} ms`);
+  }, "No syncs yet");
+}
+
+async function showDeadLetters() {
+  const deadLetters = await api("GET", "dead-letters");
+  fill("dead-letters", deadLetters, (row, deadLetter) => {
+    cell(row, time(deadLetter.failed_at));
+    cell(row, deadLetter.operation);
+    cell(row, deadLetter.zammad_id);
+    cell(row, deadLetter.attempts);
+    cell(row, deadLetter.error, "error");
+    const actions = cell(row);
+    button(actions, "Replay", () => api("POST", `dead-letters/${deadLetter.id}/replay`));
+    button(actions, "Discard", () => api("DELETE", `dead-letters/${deadLetter.id}`));
+    button(actions, "Note", () => annotate({ dead_letter_id: deadLetter.id }));
+  }, "No dead letters");
+}
+
+async function showFailures() {
+  const failures = await api("GET", "sync-failures");
+  fill("failures", failures, (row, failure) => {
+    cell(row, time(failure.failed_at));
+    cell(row, failure.operation);
+    cell(row, failure.zammad_id);
+    cell(row, failure.error, "error");
+    const actions = cell(row);
+    button(actions, "Dismiss", () => api("DELETE", `sync-failures/${failure.id}`));
+    button(actions, "Note", () => annotate({ sync_failure_id: failure.id }));
+  }, "No failures");
+}
+
+async function showMappings() {
+  const zammadId = document.getElementById("mapping-zammad-id").value;
+  const query = zammadId ? `&zammad_id=${encodeURIComponent(zammadId)}` : "";
+  const mappings = await api("GET", `mappings?limit=50${query}`);
+  fill("mappings", mappings, (row, mapping) => {
+    cell(row, mapping.zammad_id);
+    cell(row, mapping.jira_id);
+    cell(row, mapping.restricted ? "yes" : "");
+  }, "No mappings");
+}
+
+async function showAnnotations() {
+  const zammadId = document.getElementById("annotation-zammad-id").value;
+  const query = zammadId ? `?zammad_id=${encodeURIComponent(zammadId)}` : "";
+  const annotations = await api("GET", `annotations${query}`);
+  fill("annotations", annotations.slice(-25).reverse(), (row, annotation) => {
+    cell(row, time(annotation.created_at));
+    cell(row, annotation.zammad_id);
+    if (annotation.dead_letter_id) {
+      cell(row, `dead letter ${annotation.dead_letter_id}`);
+    } else if (annotation.sync_failure_id) {
+      cell(row, `failure ${annotation.sync_failure_id}`);
+    } else {
+      cell(row, "ticket");
+    }
+    cell(row, annotation.author);
+    cell(row, annotation.note);
+  }, "No annotations");
+}
+
+// Notes on a dead letter or failure are asked for; their ticket is added by the server.
+async function annotate(target) {
+  const note = prompt("Note");
+  if (note && note.trim()) {
+    const author = document.getElementById("annotation-author").value || null;
+    await api("POST", "annotations", { ...target, author, note });
+  }
+}
+
+async function refresh() {
+  clearTimeout(refreshTimer);
+  const results = await Promise.allSettled([
+    showPause(),
+    showStats(),
+    showSyncs(),
+    showDeadLetters(),
+    showFailures(),
+    showMappings(),
+    showAnnotations(),
+  ]);
+  for (const result of results) {
+    if (result.status === "rejected") {
+      console.error(result.reason);
+    }
+  }
+  if (token()) {
+    refreshTimer = setTimeout(refresh, REFRESH_EVERY);
+  }
+}
+
+function logIn() {
+  document.getElementById("login").hidden = true;
+  document.getElementById("dashboard").hidden = false;
+  document.getElementById("logout").hidden = false;
+  refresh();
+}
+
+function logOut(error) {
+  clearTimeout(refreshTimer);
+  sessionStorage.removeItem("admin-token");
+  document.getElementById("login").hidden = false;
+  document.getElementById("dashboard").hidden = true;
+  document.getElementById("logout").hidden = true;
+  document.getElementById("login-error").textContent = error ?? "";
+}
+
+document.getElementById("login").addEventListener("submit", (event) => {
+  event.preventDefault();
+  sessionStorage.setItem("admin-token", document.getElementById("token").value);
+  logIn();
+});
+document.getElementById("logout").addEventListener("click", () => logOut());
+document.getElementById("mapping-filter").addEventListener("submit", (event) => {
+  event.preventDefault();
+  refresh();
+});
+document.getElementById("annotation-filter").addEventListener("submit", (event) => {
+  event.preventDefault();
+  refresh();
+});
+document.getElementById("annotation-form").addEventListener("submit", async (event) => {
+  event.preventDefault();
+  const note = document.getElementById("annotation-note");
+  try {
+    await api("POST", "annotations", {
+      zammad_id: Number(document.getElementById("annotation-ticket").value),
+      author: document.getElementById("annotation-author").value || null,
+      note: note.value,
+    });
+    note.value = "";
+  } finally {
+    refresh();
+  }
+});
+
+if (token()) {
+  logIn();
+} else {
+  logOut();
+}
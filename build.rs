@@ -0,0 +1,5 @@
+fn main() {
+    // Embedded via include_dir!, which doesn't track the files itself
+    println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed=assets");
+}
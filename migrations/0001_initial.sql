@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS assignments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    zammad_id INTEGER,
+    jira_id INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS backfill_cursors (
+    name TEXT PRIMARY KEY,
+    position INTEGER NOT NULL
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS asset_references (
+    jira_id INTEGER PRIMARY KEY,
+    rendered TEXT NOT NULL
+);
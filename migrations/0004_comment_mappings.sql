@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS comment_mappings (
+    zammad_article_id INTEGER NOT NULL UNIQUE,
+    jira_comment_id INTEGER NOT NULL UNIQUE
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS user_mappings (
+    zammad_user_id INTEGER PRIMARY KEY,
+    jira_account_id TEXT NOT NULL UNIQUE,
+    email TEXT,
+    created_at TEXT NOT NULL
+);
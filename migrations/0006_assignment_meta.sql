@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS assignment_meta (
+    zammad_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (zammad_id, key)
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS field_sync_state (
+    zammad_id INTEGER NOT NULL,
+    field TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source TEXT NOT NULL,
+    changed_at TEXT NOT NULL,
+    synced_at TEXT NOT NULL,
+    PRIMARY KEY (zammad_id, field)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS sync_hashes (
+    zammad_id INTEGER NOT NULL,
+    target TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    PRIMARY KEY (zammad_id, target)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS source_updates (
+    zammad_id INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (zammad_id, source)
+);
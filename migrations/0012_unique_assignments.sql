@@ -0,0 +1,10 @@
+-- Keep one assignment per ticket and issue, preferring the ones linked to both sides
+DELETE FROM assignments WHERE zammad_id IS NOT NULL AND id NOT IN (
+    SELECT COALESCE(MIN(CASE WHEN jira_id IS NOT NULL THEN id END), MIN(id))
+    FROM assignments WHERE zammad_id IS NOT NULL GROUP BY zammad_id
+);
+DELETE FROM assignments WHERE jira_id IS NOT NULL AND id NOT IN (
+    SELECT MIN(id) FROM assignments WHERE jira_id IS NOT NULL GROUP BY jira_id
+);
+CREATE UNIQUE INDEX IF NOT EXISTS assignments_zammad_id ON assignments (zammad_id);
+CREATE UNIQUE INDEX IF NOT EXISTS assignments_jira_id ON assignments (jira_id);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS sync_failures (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation TEXT NOT NULL,
+    zammad_id INTEGER NULL,
+    error TEXT NOT NULL,
+    decisions TEXT NOT NULL,
+    failed_at TEXT NOT NULL
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS checklist_items (
+    zammad_item_id INTEGER PRIMARY KEY,
+    zammad_id INTEGER NOT NULL,
+    jira_id INTEGER NULL,
+    text TEXT NOT NULL,
+    checked INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS checklist_items_zammad_id ON checklist_items (zammad_id);
+CREATE UNIQUE INDEX IF NOT EXISTS checklist_items_jira_id ON checklist_items (jira_id);
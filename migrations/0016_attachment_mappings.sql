@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS attachment_mappings (
+    jira_attachment_id INTEGER PRIMARY KEY,
+    zammad_article_id INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS attachment_mappings_zammad_article_id
+    ON attachment_mappings (zammad_article_id);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS outbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation TEXT NOT NULL,
+    zammad_id INTEGER NULL,
+    payload TEXT NOT NULL,
+    received_at TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS restricted_assignments (
+    zammad_id INTEGER PRIMARY KEY,
+    error TEXT NOT NULL,
+    note_article_id INTEGER NULL,
+    restricted_at TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS description_articles (
+    zammad_id INTEGER PRIMARY KEY,
+    zammad_article_id INTEGER NOT NULL
+);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS annotations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    zammad_id INTEGER NULL,
+    sync_failure_id INTEGER NULL,
+    dead_letter_id INTEGER NULL,
+    author TEXT NULL,
+    note TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS annotations_zammad_id ON annotations (zammad_id);
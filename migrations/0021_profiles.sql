@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS profiles (
+    name TEXT PRIMARY KEY,
+    config TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- The profile queued and failed webhooks arrived for; webhooks of the file's systems have none
+CREATE TABLE IF NOT EXISTS outbox_job_profiles (
+    job_id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS dead_letter_profiles (
+    dead_letter_id INTEGER PRIMARY KEY,
+    profile TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- The X-Request-Id of the webhook a queued job came from, forwarded with its Jira and Zammad
+-- requests
+CREATE TABLE IF NOT EXISTS outbox_job_request_ids (
+    job_id INTEGER PRIMARY KEY,
+    request_id TEXT NOT NULL
+);
@@ -0,0 +1,19 @@
+-- Every sync of a stored webhook, with the calls it made and the decisions behind them
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    operation TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    zammad_id INTEGER,
+    jira_id INTEGER,
+    request_id TEXT,
+    fields TEXT NOT NULL,
+    calls TEXT NOT NULL,
+    decisions TEXT NOT NULL,
+    error TEXT,
+    duration_ms INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_zammad_id ON audit_log (zammad_id);
+CREATE INDEX IF NOT EXISTS audit_log_jira_id ON audit_log (jira_id);
+CREATE INDEX IF NOT EXISTS audit_log_created_at ON audit_log (created_at);
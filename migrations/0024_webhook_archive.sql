@@ -0,0 +1,17 @@
+-- Raw payloads of authenticated webhooks, kept for the configured retention
+CREATE TABLE IF NOT EXISTS webhook_archive (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    route TEXT NOT NULL,
+    profile TEXT,
+    request_id TEXT,
+    zammad_id INTEGER,
+    jira_id INTEGER,
+    status INTEGER NOT NULL,
+    payload TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    received_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS webhook_archive_zammad_id ON webhook_archive (zammad_id);
+CREATE INDEX IF NOT EXISTS webhook_archive_jira_id ON webhook_archive (jira_id);
+CREATE INDEX IF NOT EXISTS webhook_archive_received_at ON webhook_archive (received_at);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS sync_pause (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    reason TEXT NULL,
+    paused_at TEXT NOT NULL
+);
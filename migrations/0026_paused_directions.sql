@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS paused_directions (
+    direction TEXT PRIMARY KEY,
+    reason TEXT NULL,
+    paused_at TEXT NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Outbox jobs whose target system was rate limited, failing or in maintenance; they are
+-- synced again once it can be called
+CREATE TABLE IF NOT EXISTS outbox_job_deferrals (
+    job_id INTEGER PRIMARY KEY,
+    not_before TEXT NOT NULL
+);
@@ -0,0 +1,25 @@
+-- The links of tickets to issues and the audit log, with db_path set to a mysql:// URL; the
+-- other tables stay in SQLite
+CREATE TABLE IF NOT EXISTS assignments (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT,
+    zammad_id INT UNIQUE,
+    jira_id INT UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT,
+    operation TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    zammad_id INT,
+    jira_id INT,
+    request_id TEXT,
+    fields LONGTEXT NOT NULL,
+    calls LONGTEXT NOT NULL,
+    decisions LONGTEXT NOT NULL,
+    error TEXT,
+    duration_ms BIGINT NOT NULL,
+    created_at DATETIME(6) NOT NULL,
+    INDEX audit_log_zammad_id (zammad_id),
+    INDEX audit_log_jira_id (jira_id),
+    INDEX audit_log_created_at (created_at)
+);
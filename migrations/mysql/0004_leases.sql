@@ -0,0 +1,8 @@
+-- Leases held by one replica at a time, e.g. to run the background jobs
+CREATE TABLE IF NOT EXISTS leases (
+    name VARCHAR(255) PRIMARY KEY,
+    holder TEXT NOT NULL,
+    acquired_at DATETIME(6) NOT NULL,
+    renewed_at DATETIME(6) NOT NULL,
+    expires_at DATETIME(6) NOT NULL
+);
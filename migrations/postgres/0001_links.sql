@@ -0,0 +1,26 @@
+-- The links of tickets to issues and the audit log, with db_path set to a postgres:// URL;
+-- the other tables stay in SQLite
+CREATE TABLE IF NOT EXISTS assignments (
+    id BIGSERIAL PRIMARY KEY,
+    zammad_id INTEGER UNIQUE,
+    jira_id INTEGER UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    operation TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    zammad_id INTEGER,
+    jira_id INTEGER,
+    request_id TEXT,
+    fields TEXT NOT NULL,
+    calls TEXT NOT NULL,
+    decisions TEXT NOT NULL,
+    error TEXT,
+    duration_ms BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_zammad_id ON audit_log (zammad_id);
+CREATE INDEX IF NOT EXISTS audit_log_jira_id ON audit_log (jira_id);
+CREATE INDEX IF NOT EXISTS audit_log_created_at ON audit_log (created_at);
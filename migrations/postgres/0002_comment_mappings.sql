@@ -0,0 +1,5 @@
+-- The mappings of articles to comments, kept with the links
+CREATE TABLE IF NOT EXISTS comment_mappings (
+    zammad_article_id BIGINT NOT NULL UNIQUE,
+    jira_comment_id INTEGER NOT NULL UNIQUE
+);
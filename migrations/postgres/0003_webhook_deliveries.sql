@@ -0,0 +1,5 @@
+-- Deliveries claimed by any replica, so a retried webhook is processed once
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    delivery_id TEXT PRIMARY KEY,
+    received_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,66 @@
+//! Live sync activity: every sync is broadcast as it finishes, with the audit entry stored
+//! for it, to whoever watches `/admin/events`, e.g. a dashboard or `curl -N` during a
+//! migration. Nothing is kept for watchers connecting later; the audit log has the history.
+
+use std::convert::Infallible;
+use std::sync::LazyLock;
+
+use axum::response::sse::Event;
+use serde_json::Value;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::{Stream, wrappers::ReceiverStream};
+use tracing::warn;
+
+use crate::models::db::AuditEntry;
+use crate::shutdown;
+
+/// Syncs a watcher may fall behind by before it misses some
+const CAPACITY: usize = 256;
+
+static SYNCS: LazyLock<broadcast::Sender<Value>> = LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+/// Broadcasts a finished sync to the watchers, if any.
+pub fn publish(entry: &AuditEntry) {
+    if SYNCS.receiver_count() == 0 {
+        return;
+    }
+    let Ok(Value::Object(mut sync)) = serde_json::to_value(entry) else {
+        return;
+    };
+    // Assigned by the database, so unknown here
+    sync.remove("id");
+    let _ = SYNCS.send(Value::Object(sync));
+}
+
+/// The syncs from now on, of the Zammad ticket if given, as server-sent events named "sync".
+/// A watcher falling behind gets a "lagged" event with the number of syncs it missed. The
+/// stream ends on shutdown, so it doesn't hold up the server.
+pub fn watch(zammad_id: Option<i32>) -> impl Stream<Item = Result<Event, Infallible>> {
+    let mut syncs = SYNCS.subscribe();
+    let (sender, receiver) = mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                sync = syncs.recv() => match sync {
+                    Ok(sync) if zammad_id.is_some_and(|id| sync["zammad_id"] != id) => continue,
+                    Ok(sync) => Event::default().event("sync").json_data(sync),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        Ok(Event::default().event("lagged").data(missed.to_string()))
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = sender.closed() => return,
+                _ = shutdown::stopping() => return,
+            };
+            match event {
+                Ok(event) => {
+                    if sender.send(Ok(event)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => warn!("Failed to send a sync to a watcher: {}", e),
+            }
+        }
+    });
+    ReceiverStream::new(receiver)
+}
@@ -0,0 +1,1641 @@
+use anyhow::anyhow;
+use axum::{
+    Json, Router,
+    extract::{Path, Query, Request, State},
+    http::header::AUTHORIZATION,
+    middleware::{self, Next},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{delete, get, post, put},
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use subtle::ConstantTimeEq;
+use tokio_stream::Stream;
+use tracing::{error, info};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::config::{self, Config, Direction};
+use crate::models::{
+    api_request::{
+        JiraCreateIssueRequest, JiraGetIssueRequest, JiraUpdateIssueRequest,
+        ZammadCreateTicketRequest, ZammadGetTicketRequest,
+        convert_jira_priority_to_zammad_priority, convert_jira_status_category_to_zammad_state,
+    },
+    db::{
+        Annotation, AnnotationTarget, ArchiveFilter, ArchivedPayload, AuditEntry, AuditFilter, DB,
+        DeadLetter, Mapping, MappingFilter, OutboxJob, QuarantinedEvent, RestrictedAssignment,
+        SyncFailure, UserMapping,
+    },
+    jira::{JiraApiIssue, JiraWebhook},
+    zammad::ZammadWebhook,
+};
+use crate::state::AppState;
+use crate::{
+    activity, archive, dashboard, dead_letters, decisions, endpoints, filters, identities, leader,
+    locks, pause, profiles, purge, reconcile, replay, restrictions, stats, ticket_numbers,
+};
+
+/// Rejects requests that don't carry the bearer token `token` picks from the configuration in
+/// use, so a reloaded token applies right away.
+pub(crate) async fn authenticate(
+    token: fn(&Config) -> Option<&str>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = config::get();
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .zip(token(&config))
+        // Compared in constant time, so the token can't be guessed byte by byte
+        .is_some_and(|(value, token)| bool::from(value.as_bytes().ct_eq(token.as_bytes())));
+    drop(config);
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+fn internal_error(e: anyhow::Error) -> StatusCode {
+    error!("Admin request failed: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+#[utoipa::path(
+    get,
+    path = "/user-mappings",
+    responses(
+        (status = 200, body = Vec<UserMapping>),
+    )
+)]
+async fn list_user_mappings(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<UserMapping>>, StatusCode> {
+    let db = &state.db;
+    let mappings = db.get_user_mappings().await.map_err(internal_error)?;
+    Ok(Json(mappings))
+}
+
+#[utoipa::path(
+    post,
+    path = "/user-mappings",
+    request_body = UserMapping,
+    responses(
+        (status = 201, description = "Created"),
+        (status = 409, description = "Either user is mapped already"),
+    )
+)]
+async fn create_user_mapping(
+    State(state): State<AppState>,
+    Json(mapping): Json<UserMapping>,
+) -> StatusCode {
+    let db = &state.db;
+    match db.create_user_mapping(&mapping).await {
+        Ok(_) => StatusCode::CREATED,
+        // Either side of the mapping already exists
+        Err(e)
+            if e.downcast_ref::<sqlx::Error>().is_some_and(
+                |e| matches!(e, sqlx::Error::Database(e) if e.is_unique_violation()),
+            ) =>
+        {
+            StatusCode::CONFLICT
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/user-mappings/{zammad_user_id}",
+    params(
+        ("zammad_user_id" = i64, Path, description = "ID of the Zammad user"),
+    ),
+    responses(
+        (status = 204, description = "Done"),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn delete_user_mapping(
+    State(state): State<AppState>,
+    Path(zammad_user_id): Path<i64>,
+) -> StatusCode {
+    let db = &state.db;
+    match db.delete_user_mapping(zammad_user_id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Linked tickets and issues filtered by `zammad_id` and `jira_id`, ordered by ticket; at most
+/// `limit` (default 100) from `offset` on.
+#[utoipa::path(
+    get,
+    path = "/mappings",
+    params(MappingFilter),
+    responses(
+        (status = 200, body = Vec<Mapping>),
+    )
+)]
+async fn list_mappings(
+    State(state): State<AppState>,
+    Query(filter): Query<MappingFilter>,
+) -> Result<Json<Vec<Mapping>>, StatusCode> {
+    let db = &state.db;
+    let mappings = db.get_mappings(&filter).await.map_err(internal_error)?;
+    Ok(Json(mappings))
+}
+
+#[utoipa::path(
+    get,
+    path = "/mappings/{zammad_id}",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+    ),
+    responses(
+        (status = 200, body = Mapping),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn get_mapping(
+    State(state): State<AppState>,
+    Path(zammad_id): Path<i32>,
+) -> Result<Json<Mapping>, StatusCode> {
+    let db = &state.db;
+    let filter = MappingFilter {
+        zammad_id: Some(zammad_id),
+        ..Default::default()
+    };
+    let mapping = db.get_mappings(&filter).await.map_err(internal_error)?;
+    mapping
+        .into_iter()
+        .next()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Deserialize, ToSchema)]
+struct NewMapping {
+    zammad_id: i32,
+    jira_id: i32,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct MappingUpdate {
+    jira_id: i32,
+}
+
+/// Fails with 409 if the issue is linked to another ticket than `zammad_id`.
+async fn check_issue_unlinked(db: &DB, jira_id: i32, zammad_id: i32) -> Result<(), StatusCode> {
+    match db.get_zammad_id_by_jira_id(&jira_id).await {
+        Ok(Some(linked)) if linked != zammad_id => Err(StatusCode::CONFLICT),
+        Ok(_) => Ok(()),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// Links a ticket to an issue as is, e.g. one created by hand; neither system is called.
+/// Fails with 409 if either is linked already.
+#[utoipa::path(
+    post,
+    path = "/mappings",
+    request_body = NewMapping,
+    responses(
+        (status = 201, body = Mapping),
+        (status = 409, description = "The ticket or issue is linked already"),
+    )
+)]
+async fn create_mapping(
+    State(state): State<AppState>,
+    Json(mapping): Json<NewMapping>,
+) -> Result<(StatusCode, Json<Mapping>), StatusCode> {
+    let db = &state.db;
+    let linked = db
+        .find_jira_id_by_zammad_id(&mapping.zammad_id)
+        .await
+        .map_err(internal_error)?;
+    if linked.is_some() {
+        return Err(StatusCode::CONFLICT);
+    }
+    check_issue_unlinked(db, mapping.jira_id, mapping.zammad_id).await?;
+    db.create_assignment(&mapping.zammad_id, &mapping.jira_id)
+        .await
+        .map_err(internal_error)?;
+    let restricted = db
+        .is_assignment_restricted(mapping.zammad_id)
+        .await
+        .map_err(internal_error)?;
+    Ok((
+        StatusCode::CREATED,
+        Json(Mapping {
+            zammad_id: mapping.zammad_id,
+            jira_id: mapping.jira_id,
+            restricted,
+        }),
+    ))
+}
+
+/// Links the ticket to another issue. Fails with 409 if that one is linked to another ticket.
+#[utoipa::path(
+    put,
+    path = "/mappings/{zammad_id}",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+    ),
+    request_body = MappingUpdate,
+    responses(
+        (status = 200, body = Mapping),
+        (status = 404, description = "The ticket isn't linked"),
+        (status = 409, description = "The issue is linked to another ticket"),
+    )
+)]
+async fn update_mapping(
+    State(state): State<AppState>,
+    Path(zammad_id): Path<i32>,
+    Json(update): Json<MappingUpdate>,
+) -> Result<Json<Mapping>, StatusCode> {
+    let db = &state.db;
+    let linked = db
+        .find_jira_id_by_zammad_id(&zammad_id)
+        .await
+        .map_err(internal_error)?;
+    if linked.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    check_issue_unlinked(db, update.jira_id, zammad_id).await?;
+    db.create_assignment(&zammad_id, &update.jira_id)
+        .await
+        .map_err(internal_error)?;
+    let restricted = db
+        .is_assignment_restricted(zammad_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(Mapping {
+        zammad_id,
+        jira_id: update.jira_id,
+        restricted,
+    }))
+}
+
+/// Unlinks the ticket and drops the sync state kept about it; neither system is changed.
+#[utoipa::path(
+    delete,
+    path = "/mappings/{zammad_id}",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+    ),
+    responses(
+        (status = 204, description = "Done"),
+        (status = 404, description = "The ticket isn't linked"),
+    )
+)]
+async fn delete_mapping(State(state): State<AppState>, Path(zammad_id): Path<i32>) -> StatusCode {
+    let db = &state.db;
+    match db.find_jira_id_by_zammad_id(&zammad_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return StatusCode::NOT_FOUND,
+        Err(e) => return internal_error(e),
+    }
+    match db.delete_assignment(&zammad_id).await {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(e) => internal_error(e),
+    }
+}
+
+/// 404 if Jira or Zammad doesn't know the ticket or issue, 502 if it couldn't be asked.
+fn upstream_error(e: anyhow::Error) -> StatusCode {
+    let not_found = e
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| e.status() == Some(StatusCode::NOT_FOUND));
+    if not_found {
+        return StatusCode::NOT_FOUND;
+    }
+    error!("Admin request failed: {:#}", e);
+    StatusCode::BAD_GATEWAY
+}
+
+#[derive(Deserialize, ToSchema)]
+struct LinkRequest {
+    zammad_id: i32,
+    /// Key or URL of the issue, e.g. "CUN-123"
+    jira_key: String,
+}
+
+/// Links a Zammad ticket to a Jira issue that existed before, e.g. created by hand. Both are
+/// looked up first: 404 if either doesn't exist, 409 if either is linked already.
+#[utoipa::path(
+    post,
+    path = "/link",
+    request_body = LinkRequest,
+    responses(
+        (status = 200, body = Value, description = "`zammad_id`, `jira_id` and `jira_key` of the link"),
+        (status = 400, description = "Invalid issue key"),
+        (status = 404, description = "The ticket or issue doesn't exist"),
+        (status = 409, description = "The ticket or issue is linked already"),
+        (status = 502, description = "Zammad or Jira couldn't be asked"),
+    )
+)]
+async fn link(
+    State(state): State<AppState>,
+    Json(request): Json<LinkRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let db = &state.db;
+    let key =
+        ticket_numbers::normalize_jira_key(&request.jira_key).ok_or(StatusCode::BAD_REQUEST)?;
+    let zammad_id = request.zammad_id;
+    let _lock = locks::lock_ticket(db, zammad_id)
+        .await
+        .map_err(internal_error)?;
+    ZammadGetTicketRequest::new(zammad_id)
+        .submit()
+        .await
+        .map_err(upstream_error)?;
+    let issue = JiraGetIssueRequest::by_key(&key)
+        .submit()
+        .await
+        .map_err(upstream_error)?;
+    let linked = db
+        .find_jira_id_by_zammad_id(&zammad_id)
+        .await
+        .map_err(internal_error)?;
+    if linked.is_some() {
+        return Err(StatusCode::CONFLICT);
+    }
+    check_issue_unlinked(db, issue.id, zammad_id).await?;
+    db.create_assignment(&zammad_id, &issue.id)
+        .await
+        .map_err(internal_error)?;
+    info!(
+        "Linked Zammad ticket {} to Jira issue {} by hand",
+        zammad_id, issue.key
+    );
+    Ok(Json(json!({
+        "zammad_id": zammad_id,
+        "jira_id": issue.id,
+        "jira_key": issue.key,
+    })))
+}
+
+#[derive(Deserialize, ToSchema)]
+struct UnlinkRequest {
+    zammad_id: Option<i32>,
+    /// Key or URL of the issue, e.g. "CUN-123"
+    jira_key: Option<String>,
+}
+
+/// Unlinks a Zammad ticket, or the ticket of a Jira issue, and drops the sync state kept
+/// about it; neither system is changed. 404 if it isn't linked.
+#[utoipa::path(
+    post,
+    path = "/unlink",
+    request_body = UnlinkRequest,
+    responses(
+        (status = 200, body = Value, description = "`zammad_id` and `jira_id` of the removed link"),
+        (status = 400, description = "Neither or both of `zammad_id` and `jira_key` given, or an invalid key"),
+        (status = 404, description = "Not linked, or the issue doesn't exist"),
+        (status = 502, description = "Jira couldn't be asked"),
+    )
+)]
+async fn unlink(
+    State(state): State<AppState>,
+    Json(request): Json<UnlinkRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let db = &state.db;
+    let zammad_id = match (request.zammad_id, request.jira_key) {
+        (Some(zammad_id), None) => zammad_id,
+        (None, Some(key)) => {
+            let key = ticket_numbers::normalize_jira_key(&key).ok_or(StatusCode::BAD_REQUEST)?;
+            let issue = JiraGetIssueRequest::by_key(&key)
+                .submit()
+                .await
+                .map_err(upstream_error)?;
+            db.get_zammad_id_by_jira_id(&issue.id)
+                .await
+                .map_err(internal_error)?
+                .ok_or(StatusCode::NOT_FOUND)?
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let _lock = locks::lock_ticket(db, zammad_id)
+        .await
+        .map_err(internal_error)?;
+    let jira_id = db
+        .find_jira_id_by_zammad_id(&zammad_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    db.delete_assignment(&zammad_id)
+        .await
+        .map_err(internal_error)?;
+    info!(
+        "Unlinked Zammad ticket {} from Jira issue {} by hand",
+        zammad_id, jira_id
+    );
+    Ok(Json(json!({ "zammad_id": zammad_id, "jira_id": jira_id })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/assignments/{zammad_id}/meta",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+    ),
+    responses(
+        (status = 200, body = BTreeMap<String, String>),
+    )
+)]
+async fn get_assignment_meta(
+    State(state): State<AppState>,
+    Path(zammad_id): Path<i32>,
+) -> Result<Json<BTreeMap<String, String>>, StatusCode> {
+    let db = &state.db;
+    let meta = db
+        .get_assignment_meta(&zammad_id)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(meta))
+}
+
+/// Stores the value, given as a JSON string, under the key.
+#[utoipa::path(
+    put,
+    path = "/assignments/{zammad_id}/meta/{key}",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+        ("key" = String, Path),
+    ),
+    request_body = String,
+    responses(
+        (status = 204, description = "Done"),
+    )
+)]
+async fn set_assignment_meta(
+    State(state): State<AppState>,
+    Path((zammad_id, key)): Path<(i32, String)>,
+    Json(value): Json<String>,
+) -> StatusCode {
+    let db = &state.db;
+    match db.set_assignment_meta(&zammad_id, &key, &value).await {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => internal_error(e),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/assignments/{zammad_id}/meta/{key}",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+        ("key" = String, Path),
+    ),
+    responses(
+        (status = 204, description = "Done"),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn delete_assignment_meta(
+    State(state): State<AppState>,
+    Path((zammad_id, key)): Path<(i32, String)>,
+) -> StatusCode {
+    let db = &state.db;
+    match db.delete_assignment_meta(&zammad_id, &key).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => internal_error(e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/quarantine",
+    responses(
+        (status = 200, body = Vec<QuarantinedEvent>),
+    )
+)]
+async fn list_quarantined_events(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<QuarantinedEvent>>, StatusCode> {
+    let db = &state.db;
+    let events = db.get_quarantined_events().await.map_err(internal_error)?;
+    Ok(Json(events))
+}
+
+/// Discards a quarantined event after review.
+#[utoipa::path(
+    delete,
+    path = "/quarantine/{id}",
+    params(
+        ("id" = i64, Path, description = "ID of the quarantined event"),
+    ),
+    responses(
+        (status = 204, description = "Done"),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn delete_quarantined_event(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> StatusCode {
+    let db = &state.db;
+    match db.delete_quarantined_event(id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Syncs a quarantined event after review: it's queued like a webhook that just arrived and
+/// removed from the quarantine.
+#[utoipa::path(
+    post,
+    path = "/quarantine/{id}/release",
+    params(
+        ("id" = i64, Path, description = "ID of the quarantined event"),
+    ),
+    responses(
+        (status = 202, description = "Queued to be synced"),
+        (status = 404, description = "Not found"),
+        (status = 422, description = "The event isn't a webhook that is synced"),
+        (status = 503, description = "The outbox is full"),
+    )
+)]
+async fn release_quarantined_event(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    let db = &state.db;
+    let event = match db.get_quarantined_event(id).await {
+        Ok(Some(event)) => event,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => return internal_error(e).into_response(),
+    };
+    match replay::release(db, &event).await {
+        Ok(true) => StatusCode::ACCEPTED.into_response(),
+        Ok(false) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "error": format!("{:#}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+/// Matches users by email address right away instead of waiting for the next scheduled run.
+#[utoipa::path(
+    post,
+    path = "/user-mappings/provision",
+    responses(
+        (status = 200, body = usize, description = "Number of users mapped"),
+    )
+)]
+async fn provision_user_mappings(State(state): State<AppState>) -> Result<Json<usize>, StatusCode> {
+    identities::provision(&state.db)
+        .await
+        .map(Json)
+        .map_err(internal_error)
+}
+
+/// The report of the last reconciliation run; 404 before the first one.
+#[utoipa::path(
+    get,
+    path = "/reconciliation",
+    responses(
+        (status = 200, body = reconcile::Report),
+        (status = 404, description = "No run finished yet"),
+    )
+)]
+async fn get_reconciliation() -> Result<Json<reconcile::Report>, StatusCode> {
+    reconcile::last_report()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ReconcileQuery {
+    repair: Option<bool>,
+}
+
+/// Reconciles all linked tickets now. Discrepancies are repaired with `repair=true`, or as
+/// configured.
+#[utoipa::path(
+    post,
+    path = "/reconciliation",
+    params(ReconcileQuery),
+    responses(
+        (status = 200, body = reconcile::Report),
+        (status = 409, description = "A run is in progress, or reconciliation isn't possible with profiles"),
+    )
+)]
+async fn reconcile_tickets(
+    State(state): State<AppState>,
+    Query(query): Query<ReconcileQuery>,
+) -> Response {
+    let repair = query.repair.unwrap_or_else(|| {
+        config::get()
+            .reconciliation
+            .as_ref()
+            .is_some_and(|reconciliation| reconciliation.repair)
+    });
+    if let Err(e) = reconcile::check() {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({ "error": format!("{:#}", e) })),
+        )
+            .into_response();
+    }
+    match reconcile::run(&state.db, repair).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => internal_error(e).into_response(),
+    }
+}
+
+/// The replica holding the lease of the background jobs, and whether it's this one.
+#[utoipa::path(
+    get,
+    path = "/leader",
+    responses(
+        (status = 200, body = Value, description = "`instance`, whether it's the `leader`, and the `lease`"),
+    )
+)]
+async fn get_leader(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let lease = leader::current(&state.db).await.map_err(internal_error)?;
+    Ok(Json(json!({
+        "instance": leader::instance(),
+        "leader": leader::is_leader(),
+        "lease": lease,
+    })))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PauseQuery {
+    /// `zammad-to-jira` or `jira-to-zammad`; unset pauses or resumes everything
+    direction: Option<Direction>,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct PauseRequest {
+    reason: Option<String>,
+}
+
+/// Whether syncing is paused, as a whole and per direction, with the number of webhooks
+/// waiting in the outbox.
+async fn pause_status(db: &DB) -> Result<Json<Value>, StatusCode> {
+    let queued = db.count_outbox_jobs().await.map_err(internal_error)?;
+    let pause = pause::current();
+    let directions: serde_json::Map<String, Value> = Direction::ALL
+        .into_iter()
+        .map(|direction| {
+            let own = pause::of_direction(direction);
+            let status = json!({
+                "paused": pause::is_paused(direction),
+                "reason": own.as_ref().and_then(|pause| pause.reason.as_deref()),
+                "paused_at": own.as_ref().map(|pause| pause.paused_at),
+                "configured": config::get().paused.contains(&direction),
+            });
+            (direction.as_str().to_string(), status)
+        })
+        .collect();
+    Ok(Json(json!({
+        "paused": pause.is_some(),
+        "reason": pause.as_ref().and_then(|pause| pause.reason.as_deref()),
+        "paused_at": pause.as_ref().map(|pause| pause.paused_at),
+        "directions": directions,
+        "queued": queued,
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/pause",
+    responses(
+        (status = 200, body = Value, description = "Whether syncing is `paused`, per direction as well, and the number of `queued` webhooks"),
+    )
+)]
+async fn get_pause(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    pause_status(&state.db).await
+}
+
+/// Pauses syncing, e.g. for a Jira upgrade, or only the `direction` given in the query;
+/// webhooks are queued until it's resumed. The body may give a `reason`.
+#[utoipa::path(
+    post,
+    path = "/pause",
+    params(PauseQuery),
+    request_body = Option<PauseRequest>,
+    responses(
+        (status = 200, body = Value, description = "The pause status, as by GET"),
+    )
+)]
+async fn pause_syncing(
+    State(state): State<AppState>,
+    Query(query): Query<PauseQuery>,
+    body: Option<Json<PauseRequest>>,
+) -> Result<Json<Value>, StatusCode> {
+    let reason = body.and_then(|Json(body)| body.reason);
+    pause::pause(&state.db, query.direction, reason)
+        .await
+        .map_err(internal_error)?;
+    pause_status(&state.db).await
+}
+
+/// Resumes syncing, or only the `direction` given in the query; the webhooks queued
+/// meanwhile are synced in the order they arrived.
+#[utoipa::path(
+    post,
+    path = "/resume",
+    params(PauseQuery),
+    responses(
+        (status = 200, body = Value, description = "The pause status, as by GET /pause"),
+    )
+)]
+async fn resume_syncing(
+    State(state): State<AppState>,
+    Query(query): Query<PauseQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    pause::resume(&state.db, query.direction)
+        .await
+        .map_err(internal_error)?;
+    pause_status(&state.db).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/sync-failures",
+    responses(
+        (status = 200, body = Vec<SyncFailure>),
+    )
+)]
+async fn list_sync_failures(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SyncFailure>>, StatusCode> {
+    let db = &state.db;
+    let failures = db.get_sync_failures().await.map_err(internal_error)?;
+    Ok(Json(failures))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/sync-failures/{id}",
+    params(
+        ("id" = i64, Path, description = "ID of the sync failure"),
+    ),
+    responses(
+        (status = 204, description = "Done"),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn delete_sync_failure(State(state): State<AppState>, Path(id): Path<i64>) -> StatusCode {
+    let db = &state.db;
+    match db.delete_sync_failure(id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Webhooks that were accepted but aren't synced yet.
+#[utoipa::path(
+    get,
+    path = "/outbox",
+    responses(
+        (status = 200, body = Vec<OutboxJob>),
+    )
+)]
+async fn list_outbox_jobs(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<OutboxJob>>, StatusCode> {
+    let db = &state.db;
+    let jobs = db.get_outbox_jobs().await.map_err(internal_error)?;
+    Ok(Json(jobs))
+}
+
+#[utoipa::path(
+    get,
+    path = "/dead-letters",
+    responses(
+        (status = 200, body = Vec<DeadLetter>),
+    )
+)]
+async fn list_dead_letters(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DeadLetter>>, StatusCode> {
+    let db = &state.db;
+    let dead_letters = db.get_dead_letters().await.map_err(internal_error)?;
+    Ok(Json(dead_letters))
+}
+
+/// Syncs a failed webhook again; it's removed if that worked.
+#[utoipa::path(
+    post,
+    path = "/dead-letters/{id}/replay",
+    params(
+        ("id" = i64, Path, description = "ID of the dead letter"),
+    ),
+    responses(
+        (status = 200, body = Value, description = "Whether it `synced`, or the `error`"),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn replay_dead_letter(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, StatusCode> {
+    let db = &state.db;
+    let dead_letter = db
+        .get_dead_letter(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let result = dead_letters::replay(db, &dead_letter).await;
+    Ok(Json(json!({
+        "synced": result.is_ok(),
+        "error": result.err().map(|e| format!("{:#}", e)),
+    })))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/dead-letters/{id}",
+    params(
+        ("id" = i64, Path, description = "ID of the dead letter"),
+    ),
+    responses(
+        (status = 204, description = "Done"),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn delete_dead_letter(State(state): State<AppState>, Path(id): Path<i64>) -> StatusCode {
+    let db = &state.db;
+    match db.delete_dead_letter(id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Tickets not synced to Jira because the connector lacks permissions on their issue.
+#[utoipa::path(
+    get,
+    path = "/restricted",
+    responses(
+        (status = 200, body = Vec<RestrictedAssignment>),
+    )
+)]
+async fn list_restricted_assignments(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<RestrictedAssignment>>, StatusCode> {
+    let db = &state.db;
+    let restricted = db
+        .get_restricted_assignments()
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(restricted))
+}
+
+/// Syncs the webhooks kept for a restricted ticket once its permissions are fixed; the
+/// restriction is lifted if all of them succeeded.
+#[utoipa::path(
+    post,
+    path = "/restricted/{zammad_id}/retry",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+    ),
+    responses(
+        (status = 200, body = Value, description = "Whether all `synced`, the number `replayed`, or the `error`"),
+        (status = 404, description = "The ticket isn't restricted"),
+    )
+)]
+async fn retry_restricted_assignment(
+    State(state): State<AppState>,
+    Path(zammad_id): Path<i32>,
+) -> Result<Json<Value>, StatusCode> {
+    let db = &state.db;
+    if !db
+        .is_assignment_restricted(zammad_id)
+        .await
+        .map_err(internal_error)?
+    {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let result = restrictions::retry(db, zammad_id).await;
+    Ok(Json(json!({
+        "synced": result.is_ok(),
+        "replayed": result.as_ref().ok(),
+        "error": result.err().map(|e| format!("{:#}", e)),
+    })))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct NewAnnotation {
+    #[serde(flatten)]
+    target: AnnotationTarget,
+    author: Option<String>,
+    note: String,
+}
+
+/// Archived webhook payloads filtered by `zammad_id` and `jira_id`, newest first; at most
+/// `limit` (default 100).
+#[utoipa::path(
+    get,
+    path = "/archive",
+    params(ArchiveFilter),
+    responses(
+        (status = 200, body = Vec<ArchivedPayload>),
+    )
+)]
+async fn list_archived_payloads(
+    State(state): State<AppState>,
+    Query(filter): Query<ArchiveFilter>,
+) -> Result<Json<Vec<ArchivedPayload>>, StatusCode> {
+    let db = &state.db;
+    let payloads = db
+        .get_archived_payloads(&filter)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(payloads))
+}
+
+#[utoipa::path(
+    get,
+    path = "/archive/{id}",
+    params(
+        ("id" = i64, Path, description = "ID of the archived payload"),
+    ),
+    responses(
+        (status = 200, body = ArchivedPayload),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn get_archived_payload(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ArchivedPayload>, StatusCode> {
+    let db = &state.db;
+    match db.get_archived_payload(id).await {
+        Ok(Some(payload)) => Ok(Json(payload)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+/// Syncs an archived payload again.
+#[utoipa::path(
+    post,
+    path = "/archive/{id}/replay",
+    params(
+        ("id" = i64, Path, description = "ID of the archived payload"),
+    ),
+    responses(
+        (status = 200, body = Value, description = "Whether it `synced`, or the `error`"),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn replay_archived_payload(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, StatusCode> {
+    let db = &state.db;
+    let archived = db
+        .get_archived_payload(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let result = archive::rerun(db, &archived).await;
+    Ok(Json(json!({
+        "synced": result.is_ok(),
+        "error": result.err().map(|e| format!("{:#}", e)),
+    })))
+}
+
+/// Syncs the archived payloads of a ticket again in the order they arrived, stopping at the
+/// first that fails.
+#[utoipa::path(
+    post,
+    path = "/tickets/{zammad_id}/replay",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+    ),
+    responses(
+        (status = 200, body = Value, description = "The number `replayed`; on failure the ID of the payload that `failed` and the `error`"),
+    )
+)]
+async fn replay_ticket(
+    State(state): State<AppState>,
+    Path(zammad_id): Path<i32>,
+) -> Result<Json<Value>, StatusCode> {
+    let db = &state.db;
+    let payloads = archive::ticket_payloads(db, zammad_id)
+        .await
+        .map_err(internal_error)?;
+    let mut replayed = 0;
+    for archived in &payloads {
+        if let Err(e) = archive::rerun(db, archived).await {
+            return Ok(Json(json!({
+                "replayed": replayed,
+                "failed": archived.id,
+                "error": format!("{:#}", e),
+            })));
+        }
+        replayed += 1;
+    }
+    Ok(Json(json!({ "replayed": replayed })))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct EventsQuery {
+    zammad_id: Option<i32>,
+}
+
+/// Server-sent events of the syncs as they finish, of the ticket `zammad_id` if given.
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(EventsQuery),
+    responses(
+        (status = 200, content_type = "text/event-stream", description = "`sync` events with the audit entry of each sync, `lagged` events with the number of syncs missed"),
+    )
+)]
+async fn stream_events(
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    Sse::new(activity::watch(query.zammad_id)).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct StatsQuery {
+    days: Option<u32>,
+}
+
+/// Totals and per-day counts of the last `days` (default 30) days.
+#[utoipa::path(
+    get,
+    path = "/stats",
+    params(StatsQuery),
+    responses(
+        (status = 200, body = stats::Stats),
+    )
+)]
+async fn get_stats(
+    State(state): State<AppState>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<stats::Stats>, StatusCode> {
+    let db = &state.db;
+    let stats = stats::collect(db, query.days.unwrap_or(30))
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(stats))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HistoryQuery {
+    limit: Option<u32>,
+}
+
+/// What happened to a ticket, oldest first: each sync from the audit log with its outcome and
+/// the payload that triggered it, archived webhooks that weren't synced, e.g. because they
+/// were filtered out, and the notes operators left on the ticket or its failures and dead
+/// letters. Covers the latest `limit` (default 100) syncs.
+#[utoipa::path(
+    get,
+    path = "/tickets/{zammad_id}/history",
+    params(
+        ("zammad_id" = i32, Path, description = "ID of the Zammad ticket"),
+        HistoryQuery,
+    ),
+    responses(
+        (status = 200, body = Vec<Value>, description = "Syncs, webhooks that weren't synced and annotations, oldest first"),
+    )
+)]
+async fn get_ticket_history(
+    State(state): State<AppState>,
+    Path(zammad_id): Path<i32>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<Value>>, StatusCode> {
+    let db = &state.db;
+    let limit = query.limit.unwrap_or(100);
+    let entries = db
+        .get_audit_entries(&AuditFilter {
+            zammad_id: Some(zammad_id),
+            limit: Some(limit),
+            ..Default::default()
+        })
+        .await
+        .map_err(internal_error)?;
+    let mut payloads = db
+        .get_archived_payloads(&ArchiveFilter {
+            zammad_id: Some(zammad_id),
+            limit: Some(limit),
+            ..Default::default()
+        })
+        .await
+        .map_err(internal_error)?;
+    let annotations = db
+        .get_annotations(&AnnotationTarget {
+            zammad_id: Some(zammad_id),
+            ..Default::default()
+        })
+        .await
+        .map_err(internal_error)?;
+
+    let mut history = Vec::new();
+    for entry in entries {
+        let payload = payloads
+            .iter()
+            .position(|archived| {
+                archived.request_id.is_some() && archived.request_id == entry.request_id
+            })
+            .map(|index| payloads.remove(index));
+        history.push((
+            entry.created_at,
+            json!({
+                "at": entry.created_at,
+                "operation": entry.operation,
+                "direction": entry.direction,
+                "request_id": entry.request_id,
+                "outcome": if entry.error.is_some() { "failed" } else { "synced" },
+                "error": entry.error,
+                "fields": entry.fields,
+                "decisions": entry.decisions,
+                "calls": entry.calls,
+                "duration_ms": entry.duration_ms,
+                "payload": payload.map(|archived| history_payload(&archived)),
+            }),
+        ));
+    }
+    // Webhooks without an audit entry were answered without syncing
+    for archived in payloads {
+        history.push((
+            archived.received_at,
+            json!({
+                "at": archived.received_at,
+                "route": archived.route,
+                "request_id": archived.request_id,
+                "outcome": "not synced",
+                "status": archived.status,
+                "payload": history_payload(&archived),
+            }),
+        ));
+    }
+    for annotation in annotations {
+        history.push((
+            annotation.created_at,
+            json!({
+                "at": annotation.created_at,
+                "outcome": "annotated",
+                "annotation_id": annotation.id,
+                "sync_failure_id": annotation.target.sync_failure_id,
+                "dead_letter_id": annotation.target.dead_letter_id,
+                "author": annotation.author,
+                "note": annotation.note,
+            }),
+        ));
+    }
+    history.sort_by_key(|(at, _)| *at);
+    Ok(Json(history.into_iter().map(|(_, item)| item).collect()))
+}
+
+/// The archived payload as JSON, or as it was stored if it isn't.
+fn history_payload(archived: &ArchivedPayload) -> Value {
+    serde_json::from_str(&archived.payload).unwrap_or_else(|_| archived.payload.clone().into())
+}
+
+#[derive(Deserialize, ToSchema)]
+struct PurgeRequest {
+    ticket: Option<i32>,
+    email: Option<String>,
+}
+
+/// Deletes everything stored about a Zammad `ticket`, or mentioning an `email` address, for
+/// deletion requests. They're given in the body, so addresses don't end up in access logs.
+#[utoipa::path(
+    post,
+    path = "/purge",
+    request_body = PurgeRequest,
+    responses(
+        (status = 200, body = Value, description = "The number of rows `removed` per table"),
+        (status = 400, description = "Neither or both of `ticket` and `email` given"),
+    )
+)]
+async fn purge(
+    State(state): State<AppState>,
+    Json(request): Json<PurgeRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let db = &state.db;
+    let removed = match (request.ticket, request.email) {
+        (Some(zammad_id), None) => purge::purge_ticket(db, zammad_id).await,
+        (None, Some(email)) if email.contains('@') => purge::purge_email(db, &email).await,
+        _ => return Err(StatusCode::BAD_REQUEST),
+    }
+    .map_err(internal_error)?;
+    Ok(Json(json!({ "removed": removed })))
+}
+
+/// Audit entries filtered by `zammad_id`, `jira_id`, a changed `field` and a `since`/`until`
+/// time range, newest first; at most `limit` (default 100).
+#[utoipa::path(
+    get,
+    path = "/audit",
+    params(AuditFilter),
+    responses(
+        (status = 200, body = Vec<AuditEntry>),
+    )
+)]
+async fn list_audit_entries(
+    State(state): State<AppState>,
+    Query(filter): Query<AuditFilter>,
+) -> Result<Json<Vec<AuditEntry>>, StatusCode> {
+    let db = &state.db;
+    let entries = db
+        .get_audit_entries(&filter)
+        .await
+        .map_err(internal_error)?;
+    Ok(Json(entries))
+}
+
+/// Annotations filtered by `zammad_id`, `sync_failure_id` and `dead_letter_id`.
+#[utoipa::path(
+    get,
+    path = "/annotations",
+    params(AnnotationTarget),
+    responses(
+        (status = 200, body = Vec<Annotation>),
+    )
+)]
+async fn list_annotations(
+    State(state): State<AppState>,
+    Query(target): Query<AnnotationTarget>,
+) -> Result<Json<Vec<Annotation>>, StatusCode> {
+    let db = &state.db;
+    let annotations = db.get_annotations(&target).await.map_err(internal_error)?;
+    Ok(Json(annotations))
+}
+
+/// Leaves a note on an assignment, sync failure or dead letter. Notes on a sync failure or
+/// dead letter are linked to its ticket as well.
+#[utoipa::path(
+    post,
+    path = "/annotations",
+    request_body = NewAnnotation,
+    responses(
+        (status = 201, body = Value, description = "The `id` of the annotation"),
+        (status = 400, description = "No target or an empty note"),
+        (status = 404, description = "The sync failure or dead letter doesn't exist"),
+    )
+)]
+async fn create_annotation(
+    State(state): State<AppState>,
+    Json(annotation): Json<NewAnnotation>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let db = &state.db;
+    let mut target = annotation.target;
+    if let Some(id) = target.sync_failure_id {
+        let failure = db
+            .get_sync_failure(id)
+            .await
+            .map_err(internal_error)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+        target.zammad_id = target.zammad_id.or(failure.zammad_id);
+    }
+    if let Some(id) = target.dead_letter_id {
+        let dead_letter = db
+            .get_dead_letter(id)
+            .await
+            .map_err(internal_error)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+        target.zammad_id = target.zammad_id.or(dead_letter.zammad_id);
+    }
+    let has_target = target.zammad_id.is_some()
+        || target.sync_failure_id.is_some()
+        || target.dead_letter_id.is_some();
+    if !has_target || annotation.note.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let id = db
+        .create_annotation(&target, annotation.author.as_deref(), &annotation.note)
+        .await
+        .map_err(internal_error)?;
+    Ok((StatusCode::CREATED, Json(json!({ "id": id }))))
+}
+
+/// The stored profiles with their systems; credentials aren't returned.
+#[utoipa::path(
+    get,
+    path = "/profiles",
+    responses(
+        (status = 200, body = Vec<Value>, description = "`name`, whether it's `active`, the `jira` and `zammad` endpoints and when it was `updated_at`"),
+    )
+)]
+async fn list_profiles(State(state): State<AppState>) -> Result<Json<Vec<Value>>, StatusCode> {
+    let db = &state.db;
+    let stored = db.get_profiles().await.map_err(internal_error)?;
+    let profiles = stored
+        .iter()
+        .map(|stored| {
+            let profile = profiles::get(&stored.name);
+            json!({
+                "name": stored.name,
+                "active": profile.is_some(),
+                "jira": profile.as_ref().map(|profile| &profile.config.jira.endpoint),
+                "zammad": profile.as_ref().map(|profile| &profile.config.zammad.endpoint),
+                "updated_at": stored.updated_at,
+            })
+        })
+        .collect();
+    Ok(Json(profiles))
+}
+
+fn invalid_profile(e: anyhow::Error) -> Response {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({ "error": format!("{:#}", e) })),
+    )
+        .into_response()
+}
+
+/// Creates or replaces a profile, given as YAML or JSON with its webhook IDs and `jira` and
+/// `zammad` sections like the file's. It's checked against both APIs, then stored and active
+/// right away.
+#[utoipa::path(
+    put,
+    path = "/profiles/{name}",
+    params(
+        ("name" = String, Path),
+    ),
+    request_body(content = String, content_type = "application/yaml", description = "The profile in YAML or JSON"),
+    responses(
+        (status = 201, description = "Created"),
+        (status = 204, description = "Replaced"),
+        (status = 409, description = "Its webhook IDs are taken by another profile"),
+        (status = 422, body = Value, description = "Invalid, or rejected by Jira or Zammad; the `error` tells why"),
+    )
+)]
+async fn put_profile(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    body: String,
+) -> Response {
+    let db = &state.db;
+    if name == "default" {
+        return invalid_profile(anyhow!(
+            "the name default is reserved for the file's systems"
+        ));
+    }
+    let profile = match profiles::prepare(&name, &body).await {
+        Ok(profile) => profile,
+        Err(e) => return invalid_profile(e),
+    };
+    let taken = [
+        profiles::find("zammad", &profile.config.zammad_id),
+        profiles::find("jira", &profile.config.jira_id),
+    ];
+    if taken.iter().flatten().any(|other| other.name != name) {
+        return StatusCode::CONFLICT.into_response();
+    }
+    if let Err(e) = profiles::check(&profile).await {
+        return invalid_profile(e);
+    }
+
+    match db.set_profile(&name, &body).await {
+        Ok(created) => {
+            profiles::activate(profile);
+            if created {
+                StatusCode::CREATED.into_response()
+            } else {
+                StatusCode::NO_CONTENT.into_response()
+            }
+        }
+        Err(e) => internal_error(e).into_response(),
+    }
+}
+
+/// Removes a profile; webhooks still queued for it become dead letters.
+#[utoipa::path(
+    delete,
+    path = "/profiles/{name}",
+    params(
+        ("name" = String, Path),
+    ),
+    responses(
+        (status = 204, description = "Done"),
+        (status = 404, description = "Not found"),
+    )
+)]
+async fn delete_profile(State(state): State<AppState>, Path(name): Path<String>) -> StatusCode {
+    let db = &state.db;
+    match db.delete_profile(&name).await {
+        Ok(true) => {
+            profiles::deactivate(&name);
+            StatusCode::NO_CONTENT
+        }
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Maps a Zammad webhook like the sync would, without sending anything, and returns the
+/// Jira requests with the decisions that produced them.
+#[utoipa::path(
+    post,
+    path = "/simulate/zammad",
+    request_body = ZammadWebhook,
+    responses(
+        (status = 200, body = Value, description = "Whether it would be `synced`, the Jira requests to `create` or `update` the issue, the `error` and the `decisions`"),
+    )
+)]
+async fn simulate_zammad(Json(webhook): Json<ZammadWebhook>) -> Json<Value> {
+    let (mapped, decisions) = decisions::trace(async {
+        let synced = filters::is_zammad_ticket_synced(&webhook.ticket);
+        let create = JiraCreateIssueRequest::from_zammad_webhook(&webhook);
+        let update = JiraUpdateIssueRequest::from_zammad_webhook(&webhook);
+        (synced, create, update)
+    })
+    .await;
+    let (synced, create, update) = mapped;
+    Json(json!({
+        "synced": synced,
+        "create": create.as_ref().ok(),
+        "update": update,
+        "error": create.err().map(|e| format!("{:#}", e)),
+        "decisions": decisions,
+    }))
+}
+
+/// Maps a Jira webhook like the sync would, without sending anything. The status is only
+/// mapped if the webhook contains its category.
+#[utoipa::path(
+    post,
+    path = "/simulate/jira",
+    request_body = JiraWebhook<JiraApiIssue>,
+    responses(
+        (status = 200, body = Value, description = "Whether it would be `synced`, the Zammad requests to `create` or `update` the ticket, the `error` and the `decisions`"),
+    )
+)]
+async fn simulate_jira(Json(webhook): Json<JiraWebhook<JiraApiIssue>>) -> Json<Value> {
+    let issue = &webhook.issue;
+    let (mapped, decisions) = decisions::trace(async {
+        let synced = filters::is_jira_issue_synced(issue);
+        let create = ZammadCreateTicketRequest::from_jira_issue(issue);
+        let priority = convert_jira_priority_to_zammad_priority(
+            issue.fields.priority.as_ref().map(|p| p.name.as_str()),
+        );
+        let state = issue
+            .fields
+            .status
+            .as_ref()
+            .and_then(|status| status.status_category.as_ref())
+            .map(|category| convert_jira_status_category_to_zammad_state(category.key));
+        (synced, create, priority, state)
+    })
+    .await;
+    let (synced, create, priority, state) = mapped;
+    Json(json!({
+        "synced": synced,
+        "create": create.as_ref().ok(),
+        "update": {
+            "priority_id": priority as i32,
+            "state": state.map(|state| state.as_str()),
+        },
+        "error": create.err().map(|e| format!("{:#}", e)),
+        "decisions": decisions,
+    }))
+}
+
+/// The admin API, under /admin.
+#[derive(OpenApi)]
+#[openapi(paths(
+    list_user_mappings,
+    create_user_mapping,
+    delete_user_mapping,
+    provision_user_mappings,
+    get_reconciliation,
+    reconcile_tickets,
+    list_mappings,
+    create_mapping,
+    get_mapping,
+    update_mapping,
+    delete_mapping,
+    link,
+    unlink,
+    get_assignment_meta,
+    set_assignment_meta,
+    delete_assignment_meta,
+    list_quarantined_events,
+    delete_quarantined_event,
+    release_quarantined_event,
+    list_sync_failures,
+    delete_sync_failure,
+    endpoints::list,
+    list_outbox_jobs,
+    get_leader,
+    get_pause,
+    pause_syncing,
+    resume_syncing,
+    list_dead_letters,
+    delete_dead_letter,
+    replay_dead_letter,
+    list_restricted_assignments,
+    retry_restricted_assignment,
+    list_annotations,
+    create_annotation,
+    list_audit_entries,
+    get_stats,
+    stream_events,
+    list_archived_payloads,
+    get_archived_payload,
+    replay_archived_payload,
+    get_ticket_history,
+    replay_ticket,
+    purge,
+    list_profiles,
+    put_profile,
+    delete_profile,
+    simulate_zammad,
+    simulate_jira,
+))]
+pub struct AdminApi;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/user-mappings",
+            get(list_user_mappings).post(create_user_mapping),
+        )
+        .route("/user-mappings/provision", post(provision_user_mappings))
+        .route(
+            "/reconciliation",
+            get(get_reconciliation).post(reconcile_tickets),
+        )
+        .route(
+            "/user-mappings/:zammad_user_id",
+            delete(delete_user_mapping),
+        )
+        .route("/mappings", get(list_mappings).post(create_mapping))
+        .route(
+            "/mappings/:zammad_id",
+            get(get_mapping).put(update_mapping).delete(delete_mapping),
+        )
+        .route("/link", post(link))
+        .route("/unlink", post(unlink))
+        .route("/assignments/:zammad_id/meta", get(get_assignment_meta))
+        .route(
+            "/assignments/:zammad_id/meta/:key",
+            put(set_assignment_meta).delete(delete_assignment_meta),
+        )
+        .route("/quarantine", get(list_quarantined_events))
+        .route("/quarantine/:id", delete(delete_quarantined_event))
+        .route("/quarantine/:id/release", post(release_quarantined_event))
+        .route("/sync-failures", get(list_sync_failures))
+        .route("/sync-failures/:id", delete(delete_sync_failure))
+        .route("/endpoints", get(endpoints::list))
+        .route("/outbox", get(list_outbox_jobs))
+        .route("/leader", get(get_leader))
+        .route("/pause", get(get_pause).post(pause_syncing))
+        .route("/resume", post(resume_syncing))
+        .route("/dead-letters", get(list_dead_letters))
+        .route("/dead-letters/:id", delete(delete_dead_letter))
+        .route("/dead-letters/:id/replay", post(replay_dead_letter))
+        .route("/restricted", get(list_restricted_assignments))
+        .route(
+            "/restricted/:zammad_id/retry",
+            post(retry_restricted_assignment),
+        )
+        .route(
+            "/annotations",
+            get(list_annotations).post(create_annotation),
+        )
+        .route("/audit", get(list_audit_entries))
+        .route("/stats", get(get_stats))
+        .route("/events", get(stream_events))
+        .route("/archive", get(list_archived_payloads))
+        .route("/archive/:id", get(get_archived_payload))
+        .route("/archive/:id/replay", post(replay_archived_payload))
+        .route("/tickets/:zammad_id/history", get(get_ticket_history))
+        .route("/tickets/:zammad_id/replay", post(replay_ticket))
+        .route("/purge", post(purge))
+        .route("/profiles", get(list_profiles))
+        .route("/profiles/:name", put(put_profile).delete(delete_profile))
+        .route("/simulate/zammad", post(simulate_zammad))
+        .route("/simulate/jira", post(simulate_jira))
+        .layer(middleware::from_fn(|request, next| {
+            authenticate(
+                |config| config.admin.as_ref().map(|admin| admin.token.as_str()),
+                request,
+                next,
+            )
+        }))
+        // Loaded by the browser without the token, which the page asks for
+        .route("/dashboard", get(dashboard::page))
+        .route("/dashboard/:file", get(dashboard::file))
+}
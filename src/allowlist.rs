@@ -0,0 +1,64 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipnet::IpNet;
+use reqwest::StatusCode;
+use tracing::warn;
+
+use crate::config;
+
+const FORWARDED_FOR_HEADER: &str = "X-Forwarded-For";
+
+/// Rejects webhooks that don't come from the networks configured for their system.
+pub async fn restrict_senders(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(allowlist) = &config::get().allowlist else {
+        return next.run(request).await;
+    };
+    let path = request.uri().path();
+    let networks = if path.starts_with("/ticket-sync/zammad") {
+        &allowlist.zammad
+    } else if path.starts_with("/ticket-sync/jira") {
+        &allowlist.jira
+    } else {
+        return next.run(request).await;
+    };
+    if networks.is_empty() {
+        return next.run(request).await;
+    }
+
+    let sender = sender(&request, peer.ip(), allowlist.trust_forwarded_for);
+    if !sender.is_some_and(|sender| is_allowed(networks, sender)) {
+        warn!("Rejecting webhook from {:?} to {}", sender, path);
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    next.run(request).await
+}
+
+/// The address the request was sent from. Behind a proxy that's the last X-Forwarded-For
+/// entry, as the entries before it are set by the sender and can't be trusted.
+pub(crate) fn sender(request: &Request, peer: IpAddr, trust_forwarded_for: bool) -> Option<IpAddr> {
+    if !trust_forwarded_for {
+        return Some(peer);
+    }
+    match request.headers().get(FORWARDED_FOR_HEADER) {
+        Some(value) => value.to_str().ok()?.rsplit(',').next()?.trim().parse().ok(),
+        None => Some(peer),
+    }
+}
+
+fn is_allowed(networks: &[IpNet], sender: IpAddr) -> bool {
+    // IPv4 senders may connect through an IPv6 socket
+    let sender = match sender {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(sender),
+        IpAddr::V4(_) => sender,
+    };
+    networks.iter().any(|network| network.contains(&sender))
+}
@@ -0,0 +1,161 @@
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::config::{PiiScrubbingConfig, ScrubMode};
+use crate::redact;
+
+/// Fields holding email addresses or logins
+const EMAIL_FIELDS: &[&str] = &[
+    "email",
+    "emailAddress",
+    "login",
+    "origin_by",
+    "created_by",
+    "updated_by",
+];
+
+/// Fields holding names of people, possibly combined with an address ("Name <mail>")
+const NAME_FIELDS: &[&str] = &[
+    "firstname",
+    "lastname",
+    "displayName",
+    "from",
+    "to",
+    "cc",
+    "reply_to",
+];
+
+/// Fields holding free text written by customers or agents
+const TEXT_FIELDS: &[&str] = &[
+    "title",
+    "summary",
+    "subject",
+    "body",
+    "description",
+    "note",
+    "comment",
+];
+
+/// Fields holding other contact details
+const CONTACT_FIELDS: &[&str] = &[
+    "phone", "mobile", "fax", "street", "city", "zip", "address", "web",
+];
+
+/// Fields holding phone numbers
+const PHONE_FIELDS: &[&str] = &["phone", "mobile", "fax"];
+
+/// Fields holding email addresses, names or phone numbers, which are scrubbed.
+pub fn pii_fields() -> impl Iterator<Item = &'static str> {
+    EMAIL_FIELDS
+        .iter()
+        .chain(NAME_FIELDS)
+        .chain(PHONE_FIELDS)
+        .copied()
+}
+
+/// Scrubs email addresses, names and phone numbers in a payload as configured: the values of
+/// fields holding them, and addresses and phone numbers within other texts. IDs, states and
+/// the structure stay untouched.
+pub fn scrub(value: &mut Value, config: &PiiScrubbingConfig) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    // "-" is the system user
+                    Value::String(text) if text.is_empty() || text == "-" => {}
+                    Value::String(text) if pii_fields().any(|field| field == key) => {
+                        *text = scrubbed(text, config)
+                    }
+                    _ => scrub(value, config),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| scrub(value, config)),
+        Value::String(text) => *text = redact::scrub_pii(text, config),
+        _ => {}
+    }
+}
+
+/// What a scrubbed value is replaced with: a hash keyed with the salt, the same for the same
+/// person, or a marker.
+pub fn scrubbed(value: &str, config: &PiiScrubbingConfig) -> String {
+    match config.mode {
+        ScrubMode::Strip => "[removed]".to_string(),
+        ScrubMode::Hash => {
+            let salt = config.salt.as_deref().unwrap_or_default();
+            let mut mac = Hmac::<Sha256>::new_from_slice(salt.as_bytes())
+                .expect("HMAC accepts keys of any length");
+            mac.update(value.trim().to_lowercase().as_bytes());
+            let digest = mac.finalize().into_bytes();
+            let hash: String = digest[..8]
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            format!("hash:{}", hash)
+        }
+    }
+}
+
+/// Replaces names, emails, contact details and free text in a webhook payload with fake data.
+///
+/// The replacement is derived from a hash of the original value, so the same person gets the
+/// same fake identity across all payloads, while IDs, states and the structure stay untouched.
+pub fn anonymize(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(text) if !text.is_empty() => {
+                        if let Some(replacement) = anonymize_field(key, text) {
+                            *text = replacement;
+                        }
+                    }
+                    _ => anonymize(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(anonymize),
+        _ => {}
+    }
+}
+
+fn anonymize_field(key: &str, text: &str) -> Option<String> {
+    if EMAIL_FIELDS.contains(&key) {
+        // created_by/updated_by are either user objects or logins; "-" is the system user
+        (text != "-").then(|| fake_email(text))
+    } else if NAME_FIELDS.contains(&key) {
+        Some(fake_name(text))
+    } else if TEXT_FIELDS.contains(&key) {
+        Some(format!("Text {}", pseudonym(text)))
+    } else if CONTACT_FIELDS.contains(&key) {
+        Some(format!("Contact {}", pseudonym(text)))
+    } else {
+        None
+    }
+}
+
+/// Keeps a trailing `<address>` so "Name <mail>" fields keep their shape.
+fn fake_name(text: &str) -> String {
+    match text.split_once('<') {
+        Some((name, address)) => format!(
+            "Person {} <{}>",
+            pseudonym(name.trim()),
+            fake_email(address.trim_end_matches('>'))
+        ),
+        None if text.contains('@') => fake_email(text),
+        None => format!("Person {}", pseudonym(text)),
+    }
+}
+
+fn fake_email(text: &str) -> String {
+    format!("user-{}@example.invalid", pseudonym(&text.to_lowercase()))
+}
+
+fn pseudonym(text: &str) -> String {
+    let digest = Sha256::digest(text.as_bytes());
+    digest[..4]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,232 @@
+//! The raw payload of every authenticated webhook is kept with its ticket and the status it
+//! was answered with, so failed or suspicious syncs can still be inspected after the request
+//! returned. Payloads are removed after `max_age` days, and the oldest once they take more
+//! than `max_size`.
+//!
+//! Archived payloads can be synced again, e.g. after fixing the configuration or deploying a
+//! fix: one by ID, or all of a ticket in the order they arrived.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{TimeDelta, Utc};
+use clap::Args;
+use serde_json::{Value, json};
+use tracing::{Instrument, error, info};
+
+use crate::config::{self, ArchiveConfig};
+use crate::models::db::{ArchiveFilter, ArchivedPayload, DB};
+use crate::output::{OutputFormat, Progress};
+use crate::state::AppState;
+use crate::{anonymize, audit, logging, outbox, profiles, redact, request_id, webhook_body};
+
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// ID des archivierten Webhooks
+    #[arg(required_unless_present = "ticket", conflicts_with = "ticket")]
+    id: Option<i64>,
+
+    /// Alle archivierten Webhooks dieses Zammad-Tickets in Eingangsreihenfolge
+    #[arg(long, value_name = "ZAMMAD_ID")]
+    ticket: Option<i32>,
+}
+
+/// How often expired payloads are removed.
+const PRUNE_EVERY: Duration = Duration::from_secs(60 * 60);
+
+/// Stores the payload of the webhook once it was answered. Runs after authentication, so
+/// unauthenticated requests aren't kept, and before deduplication and the freshness check,
+/// so rejected deliveries are.
+pub async fn archive_payloads(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if config::get().archive.is_none() {
+        return next.run(request).await;
+    }
+    let Some(route) = request.extensions().get::<MatchedPath>().map(|path| {
+        path.as_str()
+            .trim_start_matches("/ticket-sync/")
+            .trim_end_matches("/:id")
+            .to_string()
+    }) else {
+        return next.run(request).await;
+    };
+
+    let (parts, body) = request.into_parts();
+    let bytes = match webhook_body::read(body).await {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+    let profile = profiles::current().map(|profile| profile.name.clone());
+    let request_id = request_id::current();
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes.clone())))
+        .await;
+
+    let mut payload: Value = serde_json::from_slice(&bytes).unwrap_or_default();
+    let (zammad_id, jira_id) = ticket(&state.db, &route, &payload).await;
+    let raw = String::from_utf8_lossy(&bytes).into_owned();
+    let payload = match &config::get().pii_scrubbing {
+        Some(pii_scrubbing) if payload.is_null() => redact::scrub_pii(&raw, pii_scrubbing),
+        Some(pii_scrubbing) => {
+            anonymize::scrub(&mut payload, pii_scrubbing);
+            payload.to_string()
+        }
+        None => raw,
+    };
+    let archived = ArchivedPayload {
+        id: 0,
+        route,
+        profile,
+        request_id,
+        zammad_id,
+        jira_id,
+        status: response.status().as_u16(),
+        received_at: Utc::now(),
+        payload,
+    };
+    if let Err(e) = state.db.create_archived_payload(&archived).await {
+        error!("Failed to archive the webhook payload: {}", e);
+    }
+    response
+}
+
+/// The Zammad ticket and Jira issue the payload belongs to, as far as known.
+pub async fn ticket(db: &DB, route: &str, payload: &Value) -> (Option<i32>, Option<i32>) {
+    if route.starts_with("zammad/") {
+        let zammad_id = payload["ticket"]["id"]
+            .as_i64()
+            .and_then(|id| i32::try_from(id).ok());
+        let jira_id = match zammad_id {
+            Some(zammad_id) => db
+                .find_jira_id_by_zammad_id(&zammad_id)
+                .await
+                .ok()
+                .flatten(),
+            None => None,
+        };
+        (zammad_id, jira_id)
+    } else {
+        let jira_id = logging::jira_issue_id(payload);
+        let zammad_id = match jira_id {
+            Some(jira_id) => db.get_zammad_id_by_jira_id(&jira_id).await.ok().flatten(),
+            None => None,
+        };
+        (zammad_id, jira_id)
+    }
+}
+
+/// Removes expired payloads every hour, for the lifetime of the server.
+pub async fn prune_periodically(db: DB, config: ArchiveConfig) {
+    let mut interval = tokio::time::interval(PRUNE_EVERY);
+    loop {
+        interval.tick().await;
+        let before = Utc::now() - TimeDelta::days(config.max_age as i64);
+        let max_bytes = config.max_size.map(|megabytes| megabytes * 1024 * 1024);
+        match db.prune_archive(before, max_bytes).await {
+            Ok(0) => {}
+            Ok(removed) => info!("Removed {} archived webhook payloads", removed),
+            Err(e) => error!("Failed to prune the webhook archive: {}", e),
+        }
+    }
+}
+
+/// The outbox operation a webhook of `route` is synced with.
+pub fn operation(route: &str) -> Result<&'static str> {
+    match route {
+        "zammad/create-ticket" => Ok(outbox::ZAMMAD_CREATE),
+        "zammad/update-ticket" => Ok(outbox::ZAMMAD_UPDATE),
+        "jira/update-ticket" => Ok("jira.update"),
+        route => bail!("webhooks of {} aren't synced", route),
+    }
+}
+
+/// Syncs an archived payload again, with the profile and request ID it arrived with. Payloads
+/// that were rejected as unauthenticated aren't synced.
+pub async fn rerun(db: &DB, archived: &ArchivedPayload) -> Result<()> {
+    if matches!(archived.status, 401 | 403) {
+        bail!(
+            "archived payload {} was rejected with {}",
+            archived.id,
+            archived.status
+        );
+    }
+    let operation = operation(&archived.route)?;
+    let payload: Value = serde_json::from_str(&archived.payload)
+        .with_context(|| format!("archived payload {} isn't JSON", archived.id))?;
+    let span = logging::sync_span(operation, archived.zammad_id, &payload);
+    let sync = profiles::scope_named(archived.profile.as_deref(), async {
+        let (result, _) = audit::record(
+            db,
+            operation,
+            archived.zammad_id,
+            &payload,
+            outbox::sync(db, operation, payload.clone()),
+        )
+        .await;
+        result
+    });
+    request_id::scope(archived.request_id.clone(), sync)
+        .instrument(span)
+        .await?;
+    info!("Replayed archived payload {} ({})", archived.id, operation);
+    Ok(())
+}
+
+/// The archived payloads of the Zammad ticket that can be synced again, oldest first.
+pub async fn ticket_payloads(db: &DB, zammad_id: i32) -> Result<Vec<ArchivedPayload>> {
+    let filter = ArchiveFilter {
+        zammad_id: Some(zammad_id),
+        limit: Some(u32::MAX),
+        ..Default::default()
+    };
+    let mut payloads = db.get_archived_payloads(&filter).await?;
+    payloads.retain(|archived| {
+        !matches!(archived.status, 401 | 403) && operation(&archived.route).is_ok()
+    });
+    payloads.reverse();
+    Ok(payloads)
+}
+
+pub async fn run(args: ReplayArgs, output: OutputFormat) -> Result<()> {
+    let db = DB::new().await?;
+    profiles::load(&db).await?;
+    let progress = Progress::new("replay", output);
+    let payloads = match (args.id, args.ticket) {
+        (Some(id), _) => vec![
+            db.get_archived_payload(id)
+                .await?
+                .with_context(|| format!("archived payload {} doesn't exist", id))?,
+        ],
+        (None, Some(zammad_id)) => ticket_payloads(&db, zammad_id).await?,
+        (None, None) => Vec::new(),
+    };
+
+    // Later payloads of a ticket build on the earlier ones, so replaying stops at a failure
+    let total = payloads.len() as u64;
+    for (index, archived) in payloads.iter().enumerate() {
+        let started = Instant::now();
+        let result = rerun(&db, archived).await;
+        progress.step(
+            &format!("{} {}", archived.id, archived.route),
+            result.is_ok(),
+            started.elapsed().as_millis(),
+            result.as_ref().err().map(|e| format!("{:#}", e)),
+        );
+        progress.update(index as u64 + 1, Some(total));
+        result?;
+    }
+    progress.finish(
+        &format!("Replayed {} archived payloads", total),
+        json!({ "replayed": total }),
+    );
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use std::{fs, path::Path};
+
+use include_dir::{Dir, include_dir};
+use tracing::info;
+
+/// SQL migrations, applied by `sqlx::migrate!`; embedded to extract them
+static MIGRATIONS: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/migrations");
+
+/// Example configuration and other files operators may want to customize
+static ASSETS: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/assets");
+
+/// Returns an embedded asset, e.g. "demo.yml".
+pub fn get(path: &str) -> Option<&'static str> {
+    ASSETS.get_file(path).and_then(|file| file.contents_utf8())
+}
+
+/// Writes all embedded files into `target`, so they can be customized.
+pub fn extract(target: &Path) -> anyhow::Result<()> {
+    for (name, dir) in [("assets", &ASSETS), ("migrations", &MIGRATIONS)] {
+        let target = target.join(name);
+        fs::create_dir_all(&target)?;
+        dir.extract(&target)?;
+        info!("Extracted {} to {}", name, target.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! Every sync of a stored webhook is kept in the audit log: what triggered it, the Jira and
+//! Zammad calls it made with the fields they changed and the status they got, the mapping
+//! decisions behind them and how long it took. Support managers can prove when and why a
+//! field changed.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::Instant;
+
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::activity;
+use crate::decisions::{self, Decision};
+use crate::event_webhooks;
+use crate::logging;
+use crate::models::db::{AuditEntry, DB};
+use crate::request_id;
+
+/// A request to Jira or Zammad made while syncing.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpstreamCall {
+    pub method: String,
+    /// Host and path; the query is left out, as it may carry credentials
+    pub url: String,
+    /// Status of the last attempt; unset if no response was received
+    pub status: Option<u16>,
+    /// Fields the request set, for updates
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+}
+
+tokio::task_local! {
+    static CALLS: RefCell<Vec<UpstreamCall>>;
+}
+
+/// The fields a request sets: those of Jira's `fields` and `update`, when creating or
+/// updating an issue, or the attributes of an updated Zammad ticket.
+pub fn changed_fields(method: &Method, body: Option<&[u8]>) -> Vec<String> {
+    let Some(Value::Object(body)) = body.and_then(|body| serde_json::from_slice(body).ok()) else {
+        return Vec::new();
+    };
+    let jira_fields: Vec<String> = ["fields", "update"]
+        .iter()
+        .filter_map(|key| body.get(*key)?.as_object())
+        .flat_map(|fields| fields.keys().cloned())
+        .collect();
+    match *method {
+        _ if !jira_fields.is_empty() => jira_fields,
+        Method::PUT | Method::PATCH => body.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Adds a call to the trail of the current sync. Outside of a sync, e.g. while polling
+/// status pages, calls aren't recorded.
+pub fn record_call(method: &Method, url: &Url, status: Option<StatusCode>, fields: Vec<String>) {
+    let _ = CALLS.try_with(|calls| {
+        calls.borrow_mut().push(UpstreamCall {
+            method: method.to_string(),
+            url: format!("{}{}", url.host_str().unwrap_or_default(), url.path()),
+            status: status.map(|status| status.as_u16()),
+            fields,
+        })
+    });
+}
+
+/// Runs the sync of a webhook of `operation` and stores its audit entry. Returns its result
+/// with the decisions made, for failures to be reviewed.
+pub async fn record(
+    db: &DB,
+    operation: &str,
+    zammad_id: Option<i32>,
+    payload: &Value,
+    sync: impl Future<Output = anyhow::Result<()>>,
+) -> (anyhow::Result<()>, Vec<Decision>) {
+    let started_at = Instant::now();
+    // Syncs are deeply nested futures; on the heap, they don't overflow the worker's stack
+    let sync = Box::pin(sync);
+    let ((result, calls), decisions) = decisions::trace(CALLS.scope(RefCell::default(), async {
+        let result = sync.await;
+        (result, CALLS.with(|calls| calls.take()))
+    }))
+    .await;
+    let duration_ms = started_at.elapsed().as_millis() as i64;
+
+    let jira_id = match (logging::jira_issue_id(payload), zammad_id) {
+        (Some(jira_id), _) => Some(jira_id),
+        (None, Some(zammad_id)) => db
+            .find_jira_id_by_zammad_id(&zammad_id)
+            .await
+            .ok()
+            .flatten(),
+        (None, None) => None,
+    };
+    let entry = AuditEntry {
+        id: 0,
+        operation: operation.to_string(),
+        direction: logging::direction(operation).to_string(),
+        zammad_id,
+        jira_id,
+        request_id: request_id::current(),
+        fields: fields(&calls),
+        calls,
+        decisions: serde_json::to_value(&decisions).unwrap_or_default(),
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        duration_ms,
+        created_at: chrono::Utc::now(),
+    };
+    if let Err(e) = db.create_audit_entry(&entry).await {
+        error!("Failed to store the audit entry: {}", e);
+    }
+    activity::publish(&entry);
+    event_webhooks::notify(&entry);
+    (result, decisions)
+}
+
+/// The fields changed by successful calls, each once.
+fn fields(calls: &[UpstreamCall]) -> Vec<String> {
+    let mut fields: Vec<String> = calls
+        .iter()
+        .filter(|call| call.status.is_some_and(|status| status < 400))
+        .flat_map(|call| call.fields.iter().cloned())
+        .collect();
+    fields.sort();
+    fields.dedup();
+    fields
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::config;
+use crate::filters;
+use crate::locks::{self, TicketLock};
+use crate::models::{
+    api_request::{
+        JiraBulkCreateIssuesRequest, JiraCreateIssueRequest, JiraSearchRequest,
+        ZammadCreateTicketRequest, ZammadGetTicketArticlesRequest, ZammadSearchTicketsRequest,
+    },
+    db::DB,
+    zammad::{self, ZammadArticle, ZammadWebhook},
+};
+use crate::output::{OutputFormat, Progress};
+
+#[derive(Args, Debug)]
+pub struct BackfillArgs {
+    /// Richtung, in die übernommen wird
+    #[arg(long, value_enum)]
+    direction: Direction,
+
+    /// JQL-Abfrage für die zu übernehmenden Jira-Issues (jira-to-zammad)
+    #[arg(long, required_if_eq("direction", "jira-to-zammad"))]
+    jql: Option<String>,
+
+    /// Zammad-Suchabfrage für die zu übernehmenden Tickets (zammad-to-jira)
+    #[arg(long, required_if_eq("direction", "zammad-to-jira"))]
+    query: Option<String>,
+
+    /// Anzahl Issues bzw. Tickets pro Seite
+    #[arg(long, default_value_t = 50)]
+    batch_size: u32,
+
+    /// Gespeicherten Cursor verwerfen und von vorne beginnen
+    #[arg(long)]
+    restart: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum Direction {
+    /// Zammad-Tickets für bestehende Jira-Issues anlegen
+    JiraToZammad,
+    /// Jira-Issues für bestehende Zammad-Tickets anlegen
+    ZammadToJira,
+}
+
+pub async fn run(args: BackfillArgs, output: OutputFormat) -> Result<()> {
+    let progress = Progress::new("backfill", output);
+    match args.direction {
+        Direction::JiraToZammad => jira_to_zammad(&args, &progress).await,
+        Direction::ZammadToJira => zammad_to_jira(&args, &progress).await,
+    }
+}
+
+async fn jira_to_zammad(args: &BackfillArgs, progress: &Progress) -> Result<()> {
+    let db = DB::new().await?;
+
+    // The cursor is an offset into the search result, so the result order has to be stable
+    // between runs.
+    let jql = args.jql.as_deref().context("--jql is required")?;
+    let jql = if jql.to_lowercase().contains("order by") {
+        jql.to_string()
+    } else {
+        format!("{} ORDER BY created ASC", jql)
+    };
+    let cursor_name = format!("jira-to-zammad:{}", jql);
+
+    let mut start_at = if args.restart {
+        0
+    } else {
+        db.get_backfill_cursor(&cursor_name).await?.unwrap_or(0)
+    };
+    info!("Starting Jira → Zammad backfill at position {}", start_at);
+
+    let (mut created, mut skipped) = (0, 0);
+    loop {
+        let page = JiraSearchRequest::new(&jql, start_at, args.batch_size)
+            .submit()
+            .await?;
+        if page.issues.is_empty() {
+            break;
+        }
+
+        for issue in &page.issues {
+            // Issues that are already linked (e.g. from an interrupted run) are skipped
+            if !filters::is_jira_issue_synced(issue)
+                || db.get_zammad_id_by_jira_id(&issue.id).await?.is_some()
+            {
+                skipped += 1;
+                continue;
+            }
+
+            let ticket = ZammadCreateTicketRequest::from_jira_issue(issue)?
+                .submit()
+                .await?;
+            db.create_assignment(&ticket.id, &issue.id).await?;
+            info!(
+                "Created Zammad ticket #{} for Jira issue {}",
+                ticket.number, issue.key
+            );
+            created += 1;
+        }
+
+        start_at = page.start_at + page.issues.len() as u32;
+        db.set_backfill_cursor(&cursor_name, start_at).await?;
+        progress.update(start_at as u64, Some(page.total as u64));
+        if start_at >= page.total {
+            break;
+        }
+    }
+
+    progress.finish(
+        &format!(
+            "Backfill finished: {} tickets created, {} already linked or excluded by rules",
+            created, skipped
+        ),
+        json!({ "created": created, "skipped": skipped, "position": start_at }),
+    );
+    Ok(())
+}
+
+async fn zammad_to_jira(args: &BackfillArgs, progress: &Progress) -> Result<()> {
+    let db = DB::new().await?;
+    let query = args.query.as_deref().context("--query is required")?;
+
+    // The cursor is the next page of the search result, sorted by ticket ID
+    let cursor_name = format!("zammad-to-jira:{}:{}", query, args.batch_size);
+    if args.restart {
+        db.delete_backfill_cursor(&cursor_name).await?;
+    }
+    let counts = backfill_zammad_tickets(
+        &db,
+        &cursor_name,
+        query,
+        args.batch_size,
+        None,
+        |position| progress.update(position, None),
+    )
+    .await?;
+
+    progress.finish(
+        &format!(
+            "Backfill finished: {} issues created, {} failed, {} already linked or excluded by rules",
+            counts.created, counts.failed, counts.skipped
+        ),
+        json!({
+            "created": counts.created,
+            "failed": counts.failed,
+            "skipped": counts.skipped,
+            "page": counts.page,
+        }),
+    );
+    Ok(())
+}
+
+/// Outcome of a Zammad → Jira backfill.
+pub struct BackfillCounts {
+    pub created: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    /// The page the cursor points to afterwards
+    pub page: u32,
+}
+
+/// Creates Jira issues for the Zammad tickets found by `query`, resuming at the page stored
+/// under `cursor_name`. With `max_per_minute`, it pauses after each page long enough to stay
+/// below that many created issues per minute. `progress` gets the number of tickets seen.
+pub async fn backfill_zammad_tickets(
+    db: &DB,
+    cursor_name: &str,
+    query: &str,
+    batch_size: u32,
+    max_per_minute: Option<u32>,
+    progress: impl Fn(u64),
+) -> Result<BackfillCounts> {
+    let mut page = db.get_backfill_cursor(cursor_name).await?.unwrap_or(1);
+    info!("Starting Zammad → Jira backfill at page {}", page);
+
+    let mut position = 0;
+    let (mut created, mut skipped, mut failed) = (0, 0, 0);
+    loop {
+        let tickets = ZammadSearchTicketsRequest::new(query, page, batch_size)
+            .submit()
+            .await?;
+        if tickets.is_empty() {
+            break;
+        }
+        position += tickets.len() as u64;
+        let last_page = tickets.len() < batch_size as usize;
+
+        let mut webhooks = Vec::new();
+        for ticket in tickets {
+            let ticket = ticket.into_ticket();
+            // Tickets that are already linked (e.g. from an interrupted run) are skipped
+            if !filters::is_zammad_ticket_synced(&ticket)
+                || db.find_jira_id_by_zammad_id(&ticket.id).await?.is_some()
+            {
+                skipped += 1;
+                continue;
+            }
+            let article = first_article(ticket.id).await?;
+            webhooks.push(ZammadWebhook { ticket, article });
+        }
+
+        let (page_created, page_failed, page_skipped) = create_issues(db, &webhooks).await?;
+        created += page_created;
+        failed += page_failed;
+        skipped += page_skipped;
+
+        page += 1;
+        db.set_backfill_cursor(cursor_name, page).await?;
+        progress(position);
+        if last_page {
+            break;
+        }
+        if let Some(max_per_minute) = max_per_minute.filter(|_| page_created > 0) {
+            let pause = Duration::from_secs(60 * page_created as u64) / max_per_minute.max(1);
+            tokio::time::sleep(pause).await;
+        }
+    }
+
+    Ok(BackfillCounts {
+        created,
+        failed,
+        skipped,
+        page,
+    })
+}
+
+/// Creates the issues of one page; returns the number of created, failed and skipped
+/// issues.
+async fn create_issues(db: &DB, webhooks: &[ZammadWebhook]) -> Result<(u32, u32, u32)> {
+    let (mut created, mut failed, mut skipped) = (0, 0, 0);
+    // Customer requests can't be created in bulk
+    if config::get_jira().service_desk.is_some() {
+        for webhook in webhooks {
+            let (_locks, unlinked) = lock_unlinked(db, std::slice::from_ref(webhook)).await?;
+            if unlinked.is_empty() {
+                skipped += 1;
+                continue;
+            }
+            match zammad::create_jira_issue(webhook).await {
+                Ok(jira_issue_id) => {
+                    link(db, webhook, jira_issue_id).await?;
+                    created += 1;
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to create Jira issue for Zammad ticket #{}: {:#}",
+                        webhook.ticket.number, e
+                    );
+                    failed += 1;
+                }
+            }
+        }
+    } else {
+        for chunk in webhooks.chunks(JiraBulkCreateIssuesRequest::LIMIT) {
+            let (_locks, unlinked) = lock_unlinked(db, chunk).await?;
+            skipped += (chunk.len() - unlinked.len()) as u32;
+            if unlinked.is_empty() {
+                continue;
+            }
+            let (chunk_created, chunk_failed) = create_in_bulk(db, &unlinked).await?;
+            created += chunk_created;
+            failed += chunk_failed;
+        }
+    }
+    Ok((created, failed, skipped))
+}
+
+/// Locks the tickets and returns the ones that are still unlinked, as a webhook may have
+/// created their issue since the page was read. The locks are held until the issues are
+/// linked, so a webhook arriving meanwhile doesn't create a second one. They're taken in
+/// order of the ticket IDs, so concurrent backfills can't deadlock.
+async fn lock_unlinked<'a>(
+    db: &DB,
+    webhooks: &'a [ZammadWebhook],
+) -> Result<(Vec<TicketLock>, Vec<&'a ZammadWebhook>)> {
+    let mut webhooks: Vec<_> = webhooks.iter().collect();
+    webhooks.sort_by_key(|webhook| webhook.ticket.id);
+    webhooks.dedup_by_key(|webhook| webhook.ticket.id);
+    let mut held = Vec::with_capacity(webhooks.len());
+    let mut unlinked = Vec::with_capacity(webhooks.len());
+    for webhook in webhooks {
+        let lock = locks::lock_ticket(db, webhook.ticket.id).await?;
+        if let Some(jira_issue_id) = db.find_jira_id_by_zammad_id(&webhook.ticket.id).await? {
+            info!(
+                "Zammad ticket #{} was linked to Jira issue {} meanwhile, skipping it",
+                webhook.ticket.number, jira_issue_id
+            );
+            continue;
+        }
+        held.push(lock);
+        unlinked.push(webhook);
+    }
+    Ok((held, unlinked))
+}
+
+/// The first article of the ticket, which becomes the issue description.
+async fn first_article(ticket_id: i32) -> Result<ZammadArticle> {
+    let article = ZammadGetTicketArticlesRequest::new(ticket_id)
+        .submit()
+        .await?
+        .into_iter()
+        .next();
+    Ok(ZammadArticle {
+        id: article.as_ref().map(|article| article.id),
+        ticket_id: article.as_ref().map(|article| article.ticket_id),
+        body: article.map(|article| article.body),
+        content_type: None,
+        created_at: None,
+        updated_at: None,
+        sender: None,
+        from: None,
+        to: None,
+        cc: None,
+        attachments: Vec::new(),
+    })
+}
+
+/// Creates the issues with one request. Issues Jira rejected are retried one by one, so a
+/// single invalid ticket doesn't fail the others; returns the number of created and failed
+/// issues.
+async fn create_in_bulk(db: &DB, webhooks: &[&ZammadWebhook]) -> Result<(u32, u32)> {
+    let requests = webhooks
+        .iter()
+        .map(|webhook| JiraCreateIssueRequest::from_zammad_webhook(webhook))
+        .collect::<Result<Vec<_>>>()?;
+    let response = JiraBulkCreateIssuesRequest::new(&requests).submit().await?;
+    let errors: HashMap<_, _> = response
+        .errors
+        .iter()
+        .map(|error| (error.failed_element_number, &error.element_errors))
+        .collect();
+
+    let mut issues = response.issues.into_iter();
+    let (mut created, mut failed) = (0, 0);
+    for (index, (webhook, request)) in webhooks.iter().zip(&requests).enumerate() {
+        let jira_issue_id = match errors.get(&index) {
+            None => {
+                issues
+                    .next()
+                    .context("Jira returned fewer issues than it created")?
+                    .id
+            }
+            Some(error) => {
+                warn!(
+                    "Jira rejected the issue for Zammad ticket #{} in bulk, creating it alone: {}",
+                    webhook.ticket.number, error
+                );
+                match request.submit().await {
+                    Ok(issue) => issue.id,
+                    Err(e) => {
+                        warn!(
+                            "Failed to create Jira issue for Zammad ticket #{}: {:#}",
+                            webhook.ticket.number, e
+                        );
+                        failed += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+        link(db, webhook, jira_issue_id).await?;
+        created += 1;
+    }
+    Ok((created, failed))
+}
+
+async fn link(db: &DB, webhook: &ZammadWebhook, jira_issue_id: i32) -> Result<()> {
+    db.create_assignment_from_zammad(&webhook.ticket.id).await?;
+    db.add_jira_id_to_assignment(&jira_issue_id, &webhook.ticket.id)
+        .await?;
+    zammad::record_description_article(db, webhook).await?;
+    info!(
+        "Created Jira issue {} for Zammad ticket #{}",
+        jira_issue_id, webhook.ticket.number
+    );
+    Ok(())
+}
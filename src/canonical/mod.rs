@@ -0,0 +1,4 @@
+// Canonical ticket/event model for external consumers, independent of the Zammad and Jira
+// structs used internally. Each schema version (assets/proto/ticket_sync/<version>) has its
+// own module, which is kept as long as consumers may rely on it.
+pub mod v1;
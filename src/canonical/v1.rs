@@ -0,0 +1,255 @@
+// Mirrors assets/proto/ticket_sync/v1/canonical.proto, which is the schema of record.
+// Written by hand instead of generated, so building doesn't require protoc; tags and types
+// have to match the .proto file exactly.
+use chrono::Utc;
+use prost::Message;
+use serde_json::{Map, Value, json};
+
+use crate::models::{
+    jira::{JiraApiIssue, JiraStatusCategoryKey, JiraWebhook, parse_jira_time},
+    zammad::{ZammadState, ZammadWebhook},
+};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum System {
+    Unspecified = 0,
+    Zammad = 1,
+    Jira = 2,
+}
+
+impl System {
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            System::Unspecified => "SYSTEM_UNSPECIFIED",
+            System::Zammad => "SYSTEM_ZAMMAD",
+            System::Jira => "SYSTEM_JIRA",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum TicketState {
+    Unspecified = 0,
+    Open = 1,
+    Closed = 2,
+}
+
+impl TicketState {
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            TicketState::Unspecified => "TICKET_STATE_UNSPECIFIED",
+            TicketState::Open => "TICKET_STATE_OPEN",
+            TicketState::Closed => "TICKET_STATE_CLOSED",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Ticket {
+    #[prost(enumeration = "System", tag = "1")]
+    pub system: i32,
+    #[prost(string, tag = "2")]
+    pub id: String,
+    #[prost(string, tag = "3")]
+    pub reference: String,
+    #[prost(string, tag = "4")]
+    pub title: String,
+    #[prost(enumeration = "TicketState", tag = "5")]
+    pub state: i32,
+    #[prost(string, tag = "6")]
+    pub priority: String,
+    #[prost(string, optional, tag = "7")]
+    pub customer_email: Option<String>,
+    #[prost(string, optional, tag = "8")]
+    pub group: Option<String>,
+    #[prost(string, repeated, tag = "9")]
+    pub tags: Vec<String>,
+    #[prost(int64, optional, tag = "10")]
+    pub updated_at: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Comment {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub body: String,
+    #[prost(string, optional, tag = "3")]
+    pub author: Option<String>,
+    #[prost(int64, optional, tag = "4")]
+    pub created_at: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Event {
+    #[prost(uint32, tag = "1")]
+    pub schema_version: u32,
+    #[prost(int64, tag = "2")]
+    pub occurred_at: i64,
+    #[prost(message, optional, tag = "3")]
+    pub ticket: Option<Ticket>,
+    #[prost(message, optional, tag = "4")]
+    pub comment: Option<Comment>,
+}
+
+impl Event {
+    pub fn from_zammad_webhook(webhook: &ZammadWebhook) -> Self {
+        let ticket = &webhook.ticket;
+        let article = &webhook.article;
+        Self {
+            schema_version: SCHEMA_VERSION,
+            occurred_at: ticket.updated_at.timestamp_millis(),
+            ticket: Some(Ticket {
+                system: System::Zammad as i32,
+                id: ticket.id.to_string(),
+                reference: ticket.number.clone(),
+                title: ticket.title.clone(),
+                state: match ticket.state {
+                    ZammadState::Open => TicketState::Open,
+                    ZammadState::Closed => TicketState::Closed,
+                } as i32,
+                priority: ticket
+                    .priority
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| (ticket.priority.id as i32).to_string()),
+                customer_email: ticket
+                    .customer
+                    .as_ref()
+                    .map(|customer| customer.email.clone()),
+                group: ticket.group.as_ref().map(|group| group.name.clone()),
+                tags: ticket.tags.clone(),
+                updated_at: Some(ticket.updated_at.timestamp_millis()),
+            }),
+            comment: match (article.id, &article.body) {
+                (Some(id), Some(body)) => Some(Comment {
+                    id: id.to_string(),
+                    body: body.clone(),
+                    author: article.from.clone(),
+                    created_at: article.created_at.map(|time| time.timestamp_millis()),
+                }),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn from_jira_webhook(webhook: &JiraWebhook<JiraApiIssue>) -> Self {
+        let issue = &webhook.issue;
+        let updated_at = issue.updated_at().map(|time| time.timestamp_millis());
+        Self {
+            schema_version: SCHEMA_VERSION,
+            occurred_at: webhook
+                .timestamp
+                .or(updated_at)
+                .unwrap_or_else(|| Utc::now().timestamp_millis()),
+            ticket: Some(Ticket {
+                system: System::Jira as i32,
+                id: issue.id.to_string(),
+                reference: issue.key.clone(),
+                title: issue.fields.summary.clone(),
+                state: match issue
+                    .fields
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.status_category.as_ref())
+                    .map(|category| category.key)
+                {
+                    Some(JiraStatusCategoryKey::Done) => TicketState::Closed,
+                    Some(_) => TicketState::Open,
+                    None => TicketState::Unspecified,
+                } as i32,
+                priority: issue
+                    .fields
+                    .priority
+                    .as_ref()
+                    .map(|priority| priority.name.clone())
+                    .unwrap_or_default(),
+                customer_email: issue
+                    .fields
+                    .reporter
+                    .as_ref()
+                    .and_then(|reporter| reporter.email_address.clone()),
+                group: issue.project_key().map(str::to_string),
+                tags: issue.labels().into_iter().map(str::to_string).collect(),
+                updated_at,
+            }),
+            comment: webhook.comment.as_ref().map(|comment| Comment {
+                id: comment.id.to_string(),
+                body: comment.body.clone(),
+                author: comment
+                    .author
+                    .as_ref()
+                    .and_then(|author| author.display_name.clone()),
+                created_at: comment
+                    .created
+                    .as_deref()
+                    .and_then(parse_jira_time)
+                    .map(|time| time.timestamp_millis()),
+            }),
+        }
+    }
+
+    /// Binary protobuf encoding.
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        self.encode_to_vec()
+    }
+
+    /// The proto3 JSON mapping: lowerCamelCase names, enum names and 64-bit integers as
+    /// strings, unset optional fields left out.
+    pub fn to_json(&self) -> Value {
+        let mut event = Map::new();
+        event.insert("schemaVersion".into(), json!(self.schema_version));
+        event.insert("occurredAt".into(), json!(self.occurred_at.to_string()));
+        if let Some(ticket) = &self.ticket {
+            event.insert("ticket".into(), ticket.to_json());
+        }
+        if let Some(comment) = &self.comment {
+            event.insert("comment".into(), comment.to_json());
+        }
+        Value::Object(event)
+    }
+}
+
+impl Ticket {
+    fn to_json(&self) -> Value {
+        let mut ticket = Map::new();
+        ticket.insert("system".into(), json!(self.system().as_str_name()));
+        ticket.insert("id".into(), json!(self.id));
+        ticket.insert("reference".into(), json!(self.reference));
+        ticket.insert("title".into(), json!(self.title));
+        ticket.insert("state".into(), json!(self.state().as_str_name()));
+        ticket.insert("priority".into(), json!(self.priority));
+        if let Some(customer_email) = &self.customer_email {
+            ticket.insert("customerEmail".into(), json!(customer_email));
+        }
+        if let Some(group) = &self.group {
+            ticket.insert("group".into(), json!(group));
+        }
+        if !self.tags.is_empty() {
+            ticket.insert("tags".into(), json!(self.tags));
+        }
+        if let Some(updated_at) = self.updated_at {
+            ticket.insert("updatedAt".into(), json!(updated_at.to_string()));
+        }
+        Value::Object(ticket)
+    }
+}
+
+impl Comment {
+    fn to_json(&self) -> Value {
+        let mut comment = Map::new();
+        comment.insert("id".into(), json!(self.id));
+        comment.insert("body".into(), json!(self.body));
+        if let Some(author) = &self.author {
+            comment.insert("author".into(), json!(author));
+        }
+        if let Some(created_at) = self.created_at {
+            comment.insert("createdAt".into(), json!(created_at.to_string()));
+        }
+        Value::Object(comment)
+    }
+}
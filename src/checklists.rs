@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tracing::{debug, info};
+
+use crate::config::{self, ChecklistTarget};
+use crate::locks;
+use crate::models::{
+    api_request::{
+        JiraCreateIssueRequest, JiraUpdateIssueRequest, ZammadChecklistItem,
+        ZammadGetChecklistRequest, ZammadUpdateChecklistItemRequest,
+    },
+    db::{ChecklistItem, DB},
+    jira::{self, JiraApiIssue, JiraStatusCategoryKey},
+    zammad::ZammadTicket,
+};
+
+/// Syncs the checklist of the ticket to its Jira issue: new items become sub-tasks whose
+/// status follows the item, or the checklist field is rewritten if any item changed.
+pub async fn sync_to_jira(
+    db: &DB,
+    ticket: &ZammadTicket,
+    jira_issue_id: i32,
+) -> anyhow::Result<()> {
+    let Some(checklist_config) = &config::get_jira().checklists else {
+        return Ok(());
+    };
+    let Some(checklist) = ZammadGetChecklistRequest::new(ticket.id).submit().await? else {
+        return Ok(());
+    };
+    let synced: HashMap<i64, ChecklistItem> = db
+        .get_checklist_items(&ticket.id)
+        .await?
+        .into_iter()
+        .map(|item| (item.zammad_item_id, item))
+        .collect();
+
+    match checklist_config.target {
+        ChecklistTarget::Subtasks => {
+            for item in &checklist.items {
+                let mut synced_item = match synced.get(&item.id) {
+                    Some(synced_item) if synced_item.checked == item.checked => continue,
+                    Some(synced_item) => synced_item.clone(),
+                    None => {
+                        let subtask = JiraCreateIssueRequest::subtask(
+                            ticket,
+                            jira_issue_id,
+                            &item.text,
+                            &checklist_config.subtask_issue_type,
+                        )
+                        .submit()
+                        .await?;
+                        info!(
+                            "Created Jira sub-task {} for checklist item {} of Zammad ticket #{}",
+                            subtask.id, item.id, ticket.number
+                        );
+                        // Stored right away, so a failed transition doesn't create it again
+                        let created = ChecklistItem {
+                            zammad_item_id: item.id,
+                            zammad_id: ticket.id,
+                            jira_id: Some(subtask.id),
+                            text: item.text.clone(),
+                            checked: false,
+                        };
+                        db.set_checklist_item(&created).await?;
+                        if !item.checked {
+                            continue;
+                        }
+                        created
+                    }
+                };
+
+                if let Some(subtask_id) = synced_item.jira_id {
+                    transition_subtask(subtask_id, item.checked).await?;
+                }
+                synced_item.checked = item.checked;
+                db.set_checklist_item(&synced_item).await?;
+            }
+        }
+        ChecklistTarget::Field => {
+            let unchanged = checklist.items.len() == synced.len()
+                && checklist.items.iter().all(|item| {
+                    synced.get(&item.id).is_some_and(|synced_item| {
+                        synced_item.checked == item.checked && synced_item.text == item.text
+                    })
+                });
+            // Validated when the configuration is loaded
+            let Some(field) = &checklist_config.field else {
+                return Ok(());
+            };
+            if unchanged {
+                return Ok(());
+            }
+
+            JiraUpdateIssueRequest::field(field, Value::String(render(&checklist.items)))
+                .submit(&jira_issue_id)
+                .await?;
+            for item in &checklist.items {
+                db.set_checklist_item(&ChecklistItem {
+                    zammad_item_id: item.id,
+                    zammad_id: ticket.id,
+                    jira_id: None,
+                    text: item.text.clone(),
+                    checked: item.checked,
+                })
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks or unchecks the checklist item a sub-task was created for, according to whether
+/// the sub-task is done. Returns `false` if the issue isn't such a sub-task.
+pub async fn sync_subtask_to_zammad(db: &DB, issue: &JiraApiIssue) -> anyhow::Result<bool> {
+    if config::get_jira().checklists.is_none() {
+        return Ok(false);
+    }
+    let Some(item) = db.find_checklist_item_by_jira_id(&issue.id).await? else {
+        return Ok(false);
+    };
+    let _lock = locks::lock_ticket(db, item.zammad_id).await?;
+    // The item may have changed while waiting for the lock
+    let Some(mut item) = db.find_checklist_item_by_jira_id(&issue.id).await? else {
+        return Ok(true);
+    };
+
+    let checked = jira::get_status_category(issue).await? == JiraStatusCategoryKey::Done;
+    if item.checked != checked {
+        info!(
+            "Jira sub-task {} changed checklist item {} of Zammad ticket {} to checked: {}",
+            issue.key, item.zammad_item_id, item.zammad_id, checked
+        );
+        ZammadUpdateChecklistItemRequest::new(item.zammad_item_id, checked)
+            .submit()
+            .await?;
+        item.checked = checked;
+        db.set_checklist_item(&item).await?;
+    }
+    Ok(true)
+}
+
+/// Checks or unchecks the Zammad checklist items according to the checklist field of the
+/// issue. Items are matched by text, so lines edited in Jira are ignored.
+pub async fn sync_field_to_zammad(
+    db: &DB,
+    issue: &JiraApiIssue,
+    zammad_ticket_id: i32,
+) -> anyhow::Result<()> {
+    let Some(checklist_config) = &config::get_jira().checklists else {
+        return Ok(());
+    };
+    let (ChecklistTarget::Field, Some(field)) = (checklist_config.target, &checklist_config.field)
+    else {
+        return Ok(());
+    };
+    let Some(value) = issue.fields.extra_fields.get(field).and_then(Value::as_str) else {
+        return Ok(());
+    };
+
+    let lines = parse(value);
+    for mut item in db.get_checklist_items(&zammad_ticket_id).await? {
+        let Some((checked, _)) = lines.iter().find(|(_, text)| *text == item.text) else {
+            debug!("Checklist item {:?} isn't in the Jira field", item.text);
+            continue;
+        };
+        if *checked != item.checked {
+            ZammadUpdateChecklistItemRequest::new(item.zammad_item_id, *checked)
+                .submit()
+                .await?;
+            item.checked = *checked;
+            db.set_checklist_item(&item).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn transition_subtask(subtask_id: i32, checked: bool) -> anyhow::Result<()> {
+    let Some(statuses) = &config::get_jira().statuses else {
+        debug!(
+            "No Jira statuses configured, not transitioning sub-task {}",
+            subtask_id
+        );
+        return Ok(());
+    };
+    let path = if checked {
+        &statuses.closed
+    } else {
+        &statuses.open
+    };
+    jira::transition_issue(subtask_id, path).await
+}
+
+/// One "[x] text" or "[ ] text" line per item.
+fn render(items: &[ZammadChecklistItem]) -> String {
+    items
+        .iter()
+        .map(|item| format!("[{}] {}", if item.checked { "x" } else { " " }, item.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The checked state and text of the lines written by [`render`], also if a list marker
+/// was put in front of them.
+fn parse(value: &str) -> Vec<(bool, &str)> {
+    value
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start_matches(['-', '*', ' ']);
+            let (checked, text) = if let Some(text) = line
+                .strip_prefix("[x]")
+                .or_else(|| line.strip_prefix("[X]"))
+            {
+                (true, text)
+            } else {
+                (false, line.strip_prefix("[ ]")?)
+            };
+            Some((checked, text.trim()))
+        })
+        .collect()
+}
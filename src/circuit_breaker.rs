@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tracing::{info, warn};
+
+use crate::config;
+
+/// Breakers by host name, so Jira being down doesn't stop syncs to Zammad.
+static BREAKERS: LazyLock<Mutex<HashMap<String, Breaker>>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Default)]
+struct Breaker {
+    /// Failed requests in a row
+    failures: u32,
+    state: State,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+enum State {
+    #[default]
+    Closed,
+    /// Requests fail right away until the time has passed
+    Open { until: DateTime<Utc> },
+    /// A single request probes whether the host recovered
+    HalfOpen { since: DateTime<Utc> },
+}
+
+/// The error of requests that weren't sent because the host's breaker is open.
+#[derive(Debug)]
+pub struct CircuitOpen {
+    pub host: String,
+}
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "circuit breaker for {} is open", self.host)
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// Whether a request to `host` may be sent. Once the breaker was open long enough, the next
+/// request is let through as a probe. A probe that never reported back, e.g. because its
+/// sync was cancelled, is replaced by another one after the same time.
+pub fn allow(host: &str) -> bool {
+    let open_for = TimeDelta::seconds(config::get().circuit_breaker.open_for as i64);
+    let now = Utc::now();
+    let mut breakers = BREAKERS.lock().expect("circuit breaker lock poisoned");
+    let breaker = breakers.entry(host.to_string()).or_default();
+    match breaker.state {
+        State::Closed => true,
+        State::Open { until } if until <= now => {
+            info!("Probing whether {} recovered", host);
+            breaker.state = State::HalfOpen { since: now };
+            true
+        }
+        State::HalfOpen { since } if since + open_for <= now => {
+            info!("The last probe of {} didn't finish, probing again", host);
+            breaker.state = State::HalfOpen { since: now };
+            true
+        }
+        State::Open { .. } | State::HalfOpen { .. } => false,
+    }
+}
+
+pub fn record_success(host: &str) {
+    let mut breakers = BREAKERS.lock().expect("circuit breaker lock poisoned");
+    let breaker = breakers.entry(host.to_string()).or_default();
+    if !matches!(breaker.state, State::Closed) {
+        info!("{} recovered, closing the circuit breaker", host);
+    }
+    *breaker = Breaker::default();
+}
+
+/// Settles a probe that was answered with 429: the host is up, if busy, so the breaker is
+/// closed again. Otherwise being rate limited neither counts as success nor as failure.
+pub fn record_throttled(host: &str) {
+    let mut breakers = BREAKERS.lock().expect("circuit breaker lock poisoned");
+    if let Some(breaker) = breakers.get_mut(host)
+        && matches!(breaker.state, State::HalfOpen { .. })
+    {
+        info!("{} answered, closing the circuit breaker", host);
+        *breaker = Breaker::default();
+    }
+}
+
+/// Opens the breaker after the configured number of failures in a row, or right away if
+/// the probe failed.
+pub fn record_failure(host: &str) {
+    let breaker_config = &config::get().circuit_breaker;
+    let mut breakers = BREAKERS.lock().expect("circuit breaker lock poisoned");
+    let breaker = breakers.entry(host.to_string()).or_default();
+    breaker.failures += 1;
+    if matches!(breaker.state, State::HalfOpen { .. })
+        || breaker.failures >= breaker_config.failure_threshold
+    {
+        warn!(
+            "{} failed {} times in a row, opening the circuit breaker for {}s",
+            host, breaker.failures, breaker_config.open_for
+        );
+        breaker.state = State::Open {
+            until: Utc::now() + TimeDelta::seconds(breaker_config.open_for as i64),
+        };
+    }
+}
+
+/// How long the breaker of `host` stays open, if it is. A probe in flight counts as open
+/// until it finished.
+pub fn open_for(host: &str) -> Option<Duration> {
+    let breakers = BREAKERS.lock().expect("circuit breaker lock poisoned");
+    match breakers.get(host)?.state {
+        State::Closed => None,
+        State::Open { until } => (until - Utc::now()).to_std().ok(),
+        State::HalfOpen { .. } => Some(Duration::from_secs(1)),
+    }
+}
@@ -1,12 +1,716 @@
-use anyhow::Result;
-use serde::Deserialize;
+use anyhow::{Context, Result};
+use ipnet::IpNet;
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::fs;
-use std::sync::OnceLock;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock, RwLock};
+use utoipa::ToSchema;
+
+use crate::profiles::{self, Profile};
+use crate::{redact, secrets};
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub jira: JiraConfig,
     pub zammad: ZammadConfig,
+    /// Stores inbound webhooks on disk to reproduce issues; unset disables sampling
+    #[serde(default)]
+    pub event_samples: Option<EventSampleConfig>,
+    /// Admin API under /admin; unset disables it
+    #[serde(default)]
+    pub admin: Option<AdminConfig>,
+    /// Read-only lookup of linked tickets under /api/lookup; unset disables it
+    #[serde(default)]
+    pub lookup: Option<LookupConfig>,
+    /// Automatic linking of Zammad users to Jira accounts; unset disables it
+    #[serde(default)]
+    pub user_mappings: Option<UserMappingConfig>,
+    /// Handlebars templates for synced texts; unset fields are copied as they are
+    #[serde(default)]
+    pub templates: TemplateConfig,
+    /// Restricts which tickets and issues are synced; without rules everything is synced
+    #[serde(default)]
+    pub rules: RulesConfig,
+    /// Freshness window for webhook timestamps; unset applies events of any age
+    #[serde(default)]
+    pub replay_protection: Option<ReplayProtectionConfig>,
+    /// Which way each field is synced, to have a single source of truth per field
+    #[serde(default)]
+    pub directions: FieldDirections,
+    /// Capabilities that can be switched off, e.g. to roll them out one by one
+    #[serde(default)]
+    pub features: Features,
+    /// Status pages announcing maintenance windows and incidents, during which webhooks that
+    /// need the affected system are deferred; unset disables polling
+    #[serde(default)]
+    pub status_pages: Option<StatusPageConfig>,
+    /// What happens if both sides changed the same field between two syncs
+    #[serde(default)]
+    pub conflicts: ConflictStrategy,
+    /// Retries of Jira and Zammad requests that failed for transient reasons
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// Stops calling Jira or Zammad for a while after repeated failures
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Workers syncing accepted webhooks and the size of their queue
+    #[serde(default)]
+    pub outbox: OutboxConfig,
+    /// Networks webhooks are accepted from; unset accepts them from anywhere
+    #[serde(default)]
+    pub allowlist: Option<AllowlistConfig>,
+    /// Limits on how many webhooks are accepted; unset accepts any number
+    #[serde(default)]
+    pub rate_limits: Option<RateLimitConfig>,
+    /// Largest webhook body accepted, in bytes; larger ones are answered with 413
+    #[serde(default = "default_webhook_body_limit")]
+    pub webhook_body_limit: usize,
+    /// Serve HTTPS with this certificate instead of plain HTTP
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Keeps the raw payload of every authenticated webhook to inspect and replay it; unset
+    /// keeps none
+    #[serde(default)]
+    pub archive: Option<ArchiveConfig>,
+    /// Days a webhook delivery is remembered, so redeliveries of it are dropped
+    #[serde(default = "default_delivery_max_age")]
+    pub delivery_max_age: u64,
+    /// Log level or per-module filter, e.g. `info,sqlx=warn`; `--log-level` and `RUST_LOG`
+    /// take precedence
+    #[serde(default)]
+    pub log_level: Option<String>,
+    /// Periodic comparison of all linked tickets with their issues; unset disables it
+    #[serde(default)]
+    pub reconciliation: Option<ReconciliationConfig>,
+    /// Zammad → Jira backfills run at fixed times
+    #[serde(default)]
+    pub scheduled_backfills: Vec<ScheduledBackfillConfig>,
+    /// Directions not synced; their webhooks are queued until removed here
+    #[serde(default)]
+    pub paused: Vec<Direction>,
+    /// URLs sent a JSON event whenever a sync succeeds or fails, e.g. for alerting
+    #[serde(default)]
+    pub event_webhooks: Vec<EventWebhookConfig>,
+    /// Scrubs customer emails, names and phone numbers from archived payloads and logs;
+    /// unset keeps them
+    #[serde(default)]
+    pub pii_scrubbing: Option<PiiScrubbingConfig>,
+    /// Minutes after which credentials referencing a secret manager are resolved again
+    #[serde(default = "default_secrets_refresh_every")]
+    pub secrets_refresh_every: u64,
+    /// Key SQLCipher encrypts the database with; needs a build with the `sqlcipher` feature.
+    /// Unset keeps the database unencrypted
+    #[serde(default)]
+    pub database_key: Option<String>,
+    /// SQLite database file, `database.db` in the working directory if unset. A `postgres://`
+    /// or `mysql://` URL keeps the links, comment mappings and the audit log in that database
+    /// instead, shared by replicas; it needs a build with that feature. `--db-path` takes
+    /// precedence
+    #[serde(default)]
+    pub db_path: Option<String>,
+}
+
+fn default_secrets_refresh_every() -> u64 {
+    15
+}
+
+fn default_delivery_max_age() -> u64 {
+    7
+}
+
+pub fn default_webhook_body_limit() -> usize {
+    2 * 1024 * 1024
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PiiScrubbingConfig {
+    #[serde(default)]
+    pub mode: ScrubMode,
+    /// Key of the hashes; without it, the hash of a known address can be recomputed
+    #[serde(default)]
+    pub salt: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrubMode {
+    /// Replace them with a hash, the same for the same person, so syncs can still be followed
+    #[default]
+    Hash,
+    /// Replace them with `[removed]`
+    Strip,
+}
+
+impl Config {
+    /// The sync directions for an issue of `issue_type`. Fields of disabled features are only
+    /// set on creation.
+    pub fn directions_for(&self, issue_type: Option<&str>) -> FieldDirections {
+        let directions = issue_type
+            .and_then(|issue_type| self.jira.issue_types.get(issue_type))
+            .and_then(|config| config.directions)
+            .unwrap_or(self.directions);
+        let enabled = |enabled: bool, direction| {
+            if enabled {
+                direction
+            } else {
+                SyncDirection::None
+            }
+        };
+        FieldDirections {
+            priority: enabled(self.features.priority, directions.priority),
+            status: enabled(self.features.status, directions.status),
+            comments: enabled(self.features.comments, directions.comments),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Features {
+    /// Articles and comments, both ways
+    #[serde(default = "default_true")]
+    pub comments: bool,
+    #[serde(default = "default_true")]
+    pub priority: bool,
+    /// Zammad state and Jira status
+    #[serde(default = "default_true")]
+    pub status: bool,
+    /// Uploads to Jira and notes about files attached in Jira
+    #[serde(default = "default_true")]
+    pub attachments: bool,
+    /// Due date of new Jira issues
+    #[serde(default = "default_true")]
+    pub due_dates: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            comments: true,
+            priority: true,
+            status: true,
+            attachments: true,
+            due_dates: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct FieldDirections {
+    #[serde(default = "default_priority_direction")]
+    pub priority: SyncDirection,
+    /// Zammad state and Jira status
+    #[serde(default)]
+    pub status: SyncDirection,
+    #[serde(default)]
+    pub comments: SyncDirection,
+}
+
+impl Default for FieldDirections {
+    fn default() -> Self {
+        Self {
+            priority: default_priority_direction(),
+            status: SyncDirection::default(),
+            comments: SyncDirection::default(),
+        }
+    }
+}
+
+fn default_priority_direction() -> SyncDirection {
+    SyncDirection::ZammadToJira
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    #[default]
+    Both,
+    ZammadToJira,
+    JiraToZammad,
+    /// Only set when the ticket or issue is created
+    None,
+}
+
+impl SyncDirection {
+    pub fn to_jira(self) -> bool {
+        matches!(self, SyncDirection::Both | SyncDirection::ZammadToJira)
+    }
+
+    pub fn to_zammad(self) -> bool {
+        matches!(self, SyncDirection::Both | SyncDirection::JiraToZammad)
+    }
+}
+
+/// The way a webhook is synced, by the system that sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    ZammadToJira,
+    JiraToZammad,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 2] = [Direction::ZammadToJira, Direction::JiraToZammad];
+
+    /// The direction of an outbox operation like `zammad.update`.
+    pub fn of_operation(operation: &str) -> Option<Self> {
+        match operation.split_once('.') {
+            Some(("zammad", _)) => Some(Direction::ZammadToJira),
+            Some(("jira", _)) => Some(Direction::JiraToZammad),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::ZammadToJira => "zammad-to-jira",
+            Direction::JiraToZammad => "jira-to-zammad",
+        }
+    }
+
+    pub fn reverse(self) -> Self {
+        match self {
+            Direction::ZammadToJira => Direction::JiraToZammad,
+            Direction::JiraToZammad => Direction::ZammadToJira,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetryConfig {
+    /// Attempts including the first one; 1 disables retries
+    #[serde(default = "default_retry_max_attempts")]
+    pub max_attempts: u32,
+    /// Milliseconds before the first retry, doubled for every further one
+    #[serde(default = "default_retry_initial_delay")]
+    pub initial_delay: u64,
+    /// Upper bound of the delay in milliseconds
+    #[serde(default = "default_retry_max_delay")]
+    pub max_delay: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_max_attempts(),
+            initial_delay: default_retry_initial_delay(),
+            max_delay: default_retry_max_delay(),
+        }
+    }
+}
+
+fn default_retry_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_initial_delay() -> u64 {
+    500
+}
+
+fn default_retry_max_delay() -> u64 {
+    10_000
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AllowlistConfig {
+    /// Networks Zammad sends webhooks from, e.g. "203.0.113.10/32"; empty accepts any
+    #[serde(default)]
+    pub zammad: Vec<IpNet>,
+    /// Networks Jira sends webhooks from; empty accepts any
+    #[serde(default)]
+    pub jira: Vec<IpNet>,
+    /// Take the sender from the last X-Forwarded-For entry, as added by a reverse proxy in
+    /// front of the service. Only enable it behind such a proxy, as senders can set the
+    /// header themselves
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TlsConfig {
+    /// PEM file with the certificate, followed by its intermediates
+    pub cert: PathBuf,
+    /// PEM file with the private key (PKCS#8, PKCS#1 or SEC1)
+    pub key: PathBuf,
+    /// PEM file with the CAs whose client certificates are trusted; if set, webhooks are
+    /// only accepted from clients presenting such a certificate
+    #[serde(default)]
+    pub client_ca: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RateLimitConfig {
+    /// Limit of each webhook route, for all senders together
+    #[serde(default)]
+    pub route: Option<RateLimit>,
+    /// Limit of each sender on each webhook route
+    #[serde(default)]
+    pub source: Option<RateLimit>,
+    /// Limits of single routes by their path below /ticket-sync, e.g.
+    /// "zammad/update-ticket"; they replace the ones above for the route
+    #[serde(default)]
+    pub routes: HashMap<String, RouteRateLimits>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RouteRateLimits {
+    #[serde(default)]
+    pub route: Option<RateLimit>,
+    #[serde(default)]
+    pub source: Option<RateLimit>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimit {
+    /// Webhooks accepted per second on average
+    pub per_second: f64,
+    /// Webhooks accepted at once after a quiet period
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+}
+
+fn default_burst() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutboxConfig {
+    /// Webhooks synced at the same time; webhooks of the same ticket are synced in order
+    #[serde(default = "default_outbox_workers")]
+    pub workers: usize,
+    /// Queued webhooks at which new ones are rejected with 503
+    #[serde(default = "default_outbox_queue_size")]
+    pub queue_size: u64,
+    /// Seconds rejected senders are asked to wait before delivering again
+    #[serde(default = "default_outbox_retry_after")]
+    pub retry_after: u64,
+    /// Milliseconds Zammad updates wait for further updates of the same ticket, which are
+    /// synced together; 0 syncs every update on its own
+    #[serde(default)]
+    pub debounce: u64,
+    /// Seconds the workers get to finish their jobs on shutdown; jobs still running are
+    /// synced again after the next start
+    #[serde(default = "default_outbox_drain_timeout")]
+    pub drain_timeout: u64,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            workers: default_outbox_workers(),
+            queue_size: default_outbox_queue_size(),
+            retry_after: default_outbox_retry_after(),
+            debounce: 0,
+            drain_timeout: default_outbox_drain_timeout(),
+        }
+    }
+}
+
+fn default_outbox_workers() -> usize {
+    4
+}
+
+fn default_outbox_queue_size() -> u64 {
+    1000
+}
+
+fn default_outbox_drain_timeout() -> u64 {
+    20
+}
+
+fn default_outbox_retry_after() -> u64 {
+    30
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Failed requests in a row, after retries, that open the breaker
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds the breaker stays open before a request probes for recovery
+    #[serde(default = "default_open_for")]
+    pub open_for: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: default_failure_threshold(),
+            open_for: default_open_for(),
+        }
+    }
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_open_for() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatusPageConfig {
+    /// Base URL of the Statuspage of Jira, e.g. https://jira-software.status.atlassian.com
+    pub jira: Option<String>,
+    /// Base URL of a Statuspage for Zammad
+    pub zammad: Option<String>,
+    /// Seconds between two polls
+    #[serde(default = "default_status_page_poll_every")]
+    pub poll_every: u64,
+    /// Impacts of unresolved incidents that count as maintenance
+    #[serde(default = "default_pausing_impacts")]
+    pub pausing_impacts: Vec<String>,
+}
+
+fn default_status_page_poll_every() -> u64 {
+    60
+}
+
+fn default_pausing_impacts() -> Vec<String> {
+    vec!["major".to_string(), "critical".to_string()]
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// The change with the later timestamp is kept
+    #[default]
+    LastWriteWins,
+    ZammadWins,
+    JiraWins,
+    /// The later arriving change isn't synced; a note on both sides asks for a manual decision
+    FlagAndSkip,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayProtectionConfig {
+    /// Seconds after which an event counts as stale
+    #[serde(default = "default_max_event_age")]
+    pub max_age: u64,
+    /// Seconds the Zammad clock may be off
+    #[serde(default = "default_clock_skew")]
+    pub zammad_clock_skew: u64,
+    /// Seconds the Jira clock may be off
+    #[serde(default = "default_clock_skew")]
+    pub jira_clock_skew: u64,
+    /// Keep stale events for review in the admin API instead of rejecting them
+    #[serde(default = "default_true")]
+    pub quarantine: bool,
+}
+
+fn default_max_event_age() -> u64 {
+    300
+}
+
+fn default_clock_skew() -> u64 {
+    30
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RulesConfig {
+    /// Zammad tickets that create Jira issues have to match one of these
+    #[serde(default)]
+    pub zammad: Vec<SyncRule>,
+    /// Jira issues that are synced to Zammad have to match one of these
+    #[serde(default)]
+    pub jira: Vec<SyncRule>,
+}
+
+/// Criteria a ticket or issue has to match; each list matches if it contains any of the
+/// values, empty lists match everything.
+#[derive(Debug, Default, Deserialize)]
+pub struct SyncRule {
+    /// Zammad group names, or Jira project keys
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Zammad tags, or Jira labels
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Priority names (e.g. "3 high" or "Highest") or Zammad priority IDs
+    #[serde(default)]
+    pub priorities: Vec<String>,
+    /// Zammad states, or Jira status names
+    #[serde(default)]
+    pub states: Vec<String>,
+    /// Email domains of the customer or reporter
+    #[serde(default)]
+    pub customer_domains: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateConfig {
+    /// Rendered with `ticket`, `article` and `meta` (assignment metadata)
+    #[serde(default)]
+    pub zammad_to_jira: DirectionTemplates,
+    /// Rendered with `issue`, `comment` and `meta` (assignment metadata)
+    #[serde(default)]
+    pub jira_to_zammad: DirectionTemplates,
+    /// Tables for the `table` helper by name, each mapping keys to values
+    #[serde(default)]
+    pub tables: HashMap<String, HashMap<String, String>>,
+    /// Services for the `http_lookup` helper by name
+    #[serde(default)]
+    pub http_lookups: HashMap<String, HttpLookupConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpLookupConfig {
+    /// Fetched with GET, `{key}` is replaced with the URL encoded key
+    pub url: String,
+    /// JSON pointer of the value in the response, e.g. /account_manager/name; unset uses
+    /// the whole body
+    pub pointer: Option<String>,
+    /// Sent with every request, e.g. for authentication
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Milliseconds before the lookup is given up and the default is used
+    #[serde(default = "default_lookup_timeout")]
+    pub timeout: u64,
+    /// Seconds a looked up value is reused
+    #[serde(default = "default_lookup_cache_for")]
+    pub cache_for: u64,
+}
+
+fn default_lookup_timeout() -> u64 {
+    2000
+}
+
+fn default_lookup_cache_for() -> u64 {
+    300
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DirectionTemplates {
+    /// Jira issue summary or Zammad ticket title
+    pub summary: Option<String>,
+    /// Jira issue description or first Zammad article
+    pub description: Option<String>,
+    /// Synced comments, without the comment header
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminConfig {
+    /// Bearer token every admin request has to send
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LookupConfig {
+    /// Bearer token every lookup request has to send
+    pub token: String,
+    /// Seconds clients may cache lookup results
+    #[serde(default = "default_lookup_max_age")]
+    pub max_age: u64,
+}
+
+pub fn default_lookup_max_age() -> u64 {
+    3600
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserMappingConfig {
+    /// Minutes between two runs matching users by email address
+    #[serde(default = "default_provision_every")]
+    pub provision_every: u64,
+}
+
+fn default_provision_every() -> u64 {
+    60
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventSampleConfig {
+    pub directory: PathBuf,
+    /// Every n-th inbound webhook is stored
+    #[serde(default = "default_sample_every")]
+    pub every: u64,
+    /// Replace names, emails and free text before writing the sample
+    #[serde(default = "default_true")]
+    pub anonymize: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArchiveConfig {
+    /// Days a payload is kept
+    #[serde(default = "default_archive_max_age")]
+    pub max_age: u64,
+    /// Megabytes all payloads may take; beyond, the oldest are removed
+    #[serde(default)]
+    pub max_size: Option<u64>,
+}
+
+fn default_archive_max_age() -> u64 {
+    30
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventWebhookConfig {
+    pub url: String,
+    /// Outcomes of syncs sent; both if unset
+    #[serde(default = "default_event_outcomes")]
+    pub on: Vec<SyncOutcome>,
+    /// Sent with every event, e.g. for authentication
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncOutcome {
+    Succeeded,
+    Failed,
+}
+
+fn default_event_outcomes() -> Vec<SyncOutcome> {
+    vec![SyncOutcome::Succeeded, SyncOutcome::Failed]
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReconciliationConfig {
+    /// Minutes between two runs
+    #[serde(default = "default_reconcile_every")]
+    pub every: u64,
+    /// Write the newer value to the outdated side; otherwise discrepancies are only reported
+    #[serde(default)]
+    pub repair: bool,
+}
+
+fn default_reconcile_every() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduledBackfillConfig {
+    /// Names the stored position, so an interrupted run resumes
+    pub name: String,
+    /// Cron expression with seconds, e.g. `0 0 2 * * *`, in the server's local time
+    pub schedule: cron::Schedule,
+    /// Zammad group whose open tickets get a Jira issue
+    pub group: String,
+    /// Tickets per search page
+    #[serde(default = "default_backfill_batch_size")]
+    pub batch_size: u32,
+    /// Jira issues created per minute at most; unset doesn't pause
+    #[serde(default)]
+    pub max_per_minute: Option<u32>,
+}
+
+fn default_backfill_batch_size() -> u32 {
+    50
+}
+
+fn default_sample_every() -> u64 {
+    1
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Debug, Deserialize)]
@@ -15,6 +719,249 @@ pub struct JiraConfig {
     pub username: String,
     pub token: String,
     pub project_id: i32,
+    /// Additional headers sent with every request to Jira
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Jira statuses Zammad states are synced to; unset disables status sync
+    #[serde(default)]
+    pub statuses: Option<JiraStatusConfig>,
+    /// How Zammad organizations are reflected in Jira
+    #[serde(default)]
+    pub organizations: OrganizationConfig,
+    /// Settings per Jira issue type name, applied when an issue is created with or converted
+    /// to that type
+    #[serde(default)]
+    pub issue_types: HashMap<String, IssueTypeConfig>,
+    /// Overrides for new issues per Zammad group name, e.g. to file each group into its own
+    /// component
+    #[serde(default)]
+    pub groups: HashMap<String, GroupMapping>,
+    /// Assets (Insight) object references shown in Zammad; unset disables them
+    #[serde(default)]
+    pub assets: Option<AssetsConfig>,
+    /// Create Jira Service Management customer requests instead of plain issues
+    #[serde(default)]
+    pub service_desk: Option<ServiceDeskConfig>,
+    /// Where Zammad SLA escalation deadlines are shown in Jira
+    #[serde(default)]
+    pub escalation: EscalationConfig,
+    /// Where story points or estimates are taken from; unset disables them
+    #[serde(default)]
+    pub estimate: Option<EstimateConfig>,
+    /// Header prepended to comments synced from Zammad, with the placeholders {author},
+    /// {number}, {time} and {meta.<key>} for assignment metadata; null disables it
+    #[serde(default = "default_jira_comment_header")]
+    pub comment_header: Option<String>,
+    /// How attachments of Zammad articles are handled, by MIME type
+    #[serde(default)]
+    pub attachments: AttachmentConfig,
+    /// Where Zammad checklist items are shown in Jira; unset disables checklist sync
+    #[serde(default)]
+    pub checklists: Option<ChecklistConfig>,
+    /// How inbound Jira webhooks are authenticated; unset accepts all of them
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub webhook_auth: Option<JiraWebhookAuth>,
+    /// Cross-check every inbound update webhook against the Jira API before syncing it
+    #[serde(default)]
+    pub verify_webhooks: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JiraWebhookAuth {
+    /// Secret expected in the `token` query parameter of the webhook URL
+    Token(String),
+    /// Atlassian Connect JWT, signed with the app's shared secret
+    Jwt {
+        shared_secret: String,
+        /// Client key the JWT must be issued by; unset accepts any
+        #[serde(default)]
+        issuer: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttachmentConfig {
+    /// Policy per MIME type, either exact ("text/calendar") or per type ("audio/*")
+    #[serde(default = "default_attachment_policies")]
+    pub policies: HashMap<String, AttachmentPolicy>,
+    /// Policy for MIME types without an entry in `policies`
+    #[serde(default)]
+    pub default: AttachmentPolicy,
+    /// Uploads files attached in Jira to the Zammad note announcing them, if their policy
+    /// is `pass`; otherwise the note links to them
+    #[serde(default)]
+    pub mirror_to_zammad: bool,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            policies: default_attachment_policies(),
+            default: AttachmentPolicy::default(),
+            mirror_to_zammad: false,
+        }
+    }
+}
+
+impl AttachmentConfig {
+    pub fn policy(&self, mime_type: &str) -> AttachmentPolicy {
+        let wildcard = mime_type
+            .split_once('/')
+            .map(|(kind, _)| format!("{}/*", kind));
+        self.policies
+            .get(mime_type)
+            .or_else(|| wildcard.and_then(|wildcard| self.policies.get(&wildcard)))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentPolicy {
+    /// Upload the file to the Jira issue
+    #[default]
+    Pass,
+    /// Leave the file out and mention it in the comment
+    Skip,
+    /// Replace calendar invites by a readable summary in the comment; other files are
+    /// skipped
+    Summarize,
+}
+
+fn default_attachment_policies() -> HashMap<String, AttachmentPolicy> {
+    HashMap::from([
+        ("text/calendar".to_string(), AttachmentPolicy::Summarize),
+        ("audio/*".to_string(), AttachmentPolicy::Skip),
+    ])
+}
+
+fn default_jira_comment_header() -> Option<String> {
+    Some("💬 {author} via Zammad #{number} at {time}".to_string())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChecklistConfig {
+    #[serde(default)]
+    pub target: ChecklistTarget,
+    /// Issue type of the sub-tasks, e.g. "Sub-task" in company-managed projects
+    #[serde(default = "default_subtask_issue_type")]
+    pub subtask_issue_type: String,
+    /// Text custom field holding the checklist, required for the field target
+    pub field: Option<String>,
+}
+
+fn default_subtask_issue_type() -> String {
+    "Subtask".to_string()
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecklistTarget {
+    /// One sub-task per item, done when the item is checked
+    #[default]
+    Subtasks,
+    /// All items as "[x] text" lines in a custom field
+    Field,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EstimateConfig {
+    /// Zammad ticket attribute holding the estimate
+    pub zammad_attribute: Option<String>,
+    /// Prefix of Zammad tags holding the estimate, e.g. "sp:" for "sp:5"
+    pub tag_prefix: Option<String>,
+    /// Jira field the estimate is written to: the story points custom field, or
+    /// "timetracking" for the original estimate
+    pub field: String,
+    /// Unit of the original estimate, e.g. "h" or "d"
+    #[serde(default = "default_estimate_unit")]
+    pub unit: String,
+}
+
+fn default_estimate_unit() -> String {
+    "h".to_string()
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EscalationConfig {
+    /// Jira date-time custom field for the first response deadline
+    pub first_response_field: Option<String>,
+    /// Jira date-time custom field for the update deadline
+    pub update_field: Option<String>,
+    /// Jira date-time custom field for the solution deadline
+    pub close_field: Option<String>,
+    /// Label added to the issue while the Zammad ticket is escalated
+    pub escalated_label: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceDeskConfig {
+    pub id: String,
+    /// Request type used if the Zammad group has no entry in `request_types`
+    pub request_type_id: String,
+    /// Request type per Zammad group name
+    #[serde(default)]
+    pub request_types: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssetsConfig {
+    /// Jira custom fields holding Assets object references
+    pub fields: Vec<String>,
+    /// Zammad ticket attribute the object names are written to; if unset, they are
+    /// posted as an internal note whenever they change
+    pub zammad_field: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct OrganizationConfig {
+    /// Jira field the organization is written to: "components", "labels" or a custom
+    /// field ID like "customfield_10050"; unset disables organization sync
+    pub field: Option<String>,
+    /// Overrides per Zammad organization name
+    #[serde(default)]
+    pub mapping: HashMap<String, OrganizationMapping>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrganizationMapping {
+    /// Value written to the organization field; defaults to the organization name
+    pub value: Option<String>,
+    /// Project issues of this organization are created in, instead of `jira.project_id`
+    pub project_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueTypeConfig {
+    /// Tag the Zammad ticket carries while the issue has this type
+    pub zammad_tag: Option<String>,
+    /// Value written to the "type" attribute of the Zammad ticket
+    pub zammad_type: Option<String>,
+    /// Replaces the top-level `directions` for issues of this type
+    pub directions: Option<FieldDirections>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GroupMapping {
+    /// Project issues of this group are created in, unless their organization sets one
+    pub project_id: Option<i32>,
+    /// Component issues of this group are filed into
+    pub component: Option<String>,
+    /// Labels added to issues of this group
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Account ID of the Jira user issues of this group are assigned to
+    pub assignee: Option<String>,
+}
+
+/// Status path per Zammad state. The last entry is the target status; earlier entries are
+/// intermediate statuses the issue is moved through if the target isn't directly reachable.
+#[derive(Debug, Deserialize)]
+pub struct JiraStatusConfig {
+    pub open: Vec<String>,
+    pub closed: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -22,25 +969,417 @@ pub struct ZammadConfig {
     pub endpoint: String,
     pub username: String,
     pub token: String,
+    /// Additional headers sent with every request to Zammad
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Group that tickets created from Jira issues are filed into
+    #[serde(default = "default_zammad_group")]
+    pub group: String,
+    /// Customer used when a Jira issue has no reporter email address
+    #[serde(default)]
+    pub default_customer: Option<String>,
+    /// Cross-check every inbound webhook against the Zammad API before syncing it
+    #[serde(default)]
+    pub verify_webhooks: bool,
+    /// Secret Zammad signs webhooks with (X-Hub-Signature); webhooks without a valid
+    /// signature are rejected. Unset accepts unsigned webhooks
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    /// Suggest matching knowledge base answers on new Jira issues; unset disables it
+    #[serde(default)]
+    pub knowledge_base: Option<KnowledgeBaseConfig>,
+    /// Header prepended to comments synced from Jira, with the placeholders {author},
+    /// {number}, {time} and {meta.<key>} for assignment metadata; null disables it
+    #[serde(default = "default_zammad_comment_header")]
+    pub comment_header: Option<String>,
 }
 
-static CONFIG: OnceLock<Config> = OnceLock::new();
+fn default_zammad_comment_header() -> Option<String> {
+    Some("💬 {author} via Jira {number} at {time}".to_string())
+}
 
-pub fn init() -> Result<()> {
-    let config_str = fs::read_to_string("config.yml")?;
-    let config: Config = serde_yaml::from_str(&config_str)?;
-    CONFIG.set(config).unwrap();
+#[derive(Debug, Deserialize)]
+pub struct KnowledgeBaseConfig {
+    #[serde(default = "default_knowledge_base_id")]
+    pub id: i32,
+    #[serde(default = "default_knowledge_base_locale")]
+    pub locale: String,
+    /// Number of answers suggested at most
+    #[serde(default = "default_knowledge_base_limit")]
+    pub limit: usize,
+}
+
+fn default_knowledge_base_id() -> i32 {
+    1
+}
+
+fn default_knowledge_base_locale() -> String {
+    "en-us".to_string()
+}
+
+fn default_knowledge_base_limit() -> usize {
+    3
+}
+
+fn default_zammad_group() -> String {
+    "Users".to_string()
+}
+
+/// Searched for the configuration if no file is given, in this order; without any, it's read
+/// from the `TSS_` variables.
+const SEARCH_PATHS: [&str; 2] = ["config.yml", "/etc/ticket-sync/config.yml"];
+
+static PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// The configuration in use. Reloading replaces it; replaced ones are freed once the syncs in
+/// flight that hold them are done. It's a global rather than part of the `AppState`, as it's
+/// read wherever syncs run, including the outbox workers, background jobs and CLI commands.
+static CONFIG: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+/// Loads the configuration from `path`, or the first of the search paths that exists.
+pub async fn init(path: Option<PathBuf>) -> Result<()> {
+    let path = path.or_else(|| {
+        SEARCH_PATHS
+            .into_iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists())
+    });
+    PATH.get_or_init(|| path);
+    set(Arc::new(load().await?));
     Ok(())
 }
 
-pub fn get() -> &'static Config {
-    CONFIG.get().expect("Config not initialized")
+/// The file the configuration is read from; unset if it's read from the `TSS_` variables.
+pub fn path() -> Option<&'static Path> {
+    PATH.get().and_then(Option::as_deref)
+}
+
+/// Prefix of the environment variables configuring the service when there's no file, with
+/// `__` between nested keys, e.g. `TSS_JIRA__TOKEN`.
+pub const ENV_PREFIX: &str = "TSS_";
+
+/// Reads and checks the file, or the `TSS_` variables if there's none, and resolves the
+/// secrets it references.
+pub async fn load() -> Result<Config> {
+    let config_str = match path() {
+        Some(path) => substitute_env(
+            &fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+        )?,
+        None => env_config()?.with_context(|| {
+            format!(
+                "no configuration found: neither {} exists, nor are {}* variables set",
+                SEARCH_PATHS.join(" nor "),
+                ENV_PREFIX
+            )
+        })?,
+    };
+    let mut config: Config = serde_yaml::from_str(&config_str)?;
+    prepare_systems(&config.jira, &config.zammad)?;
+    for webhook in &config.event_webhooks {
+        check_headers(&webhook.headers)?;
+    }
+    if let Some(rate_limits) = &config.rate_limits {
+        check_rate_limits(rate_limits)?;
+    }
+    secrets::resolve_systems(&mut config.jira, &mut config.zammad).await?;
+    if let Some(admin) = &mut config.admin {
+        secrets::resolve_field("admin.token", &mut admin.token).await?;
+    }
+    if let Some(lookup) = &mut config.lookup {
+        secrets::resolve_field("lookup.token", &mut lookup.token).await?;
+    }
+    if let Some(key) = &mut config.database_key {
+        secrets::resolve_field("database_key", key).await?;
+    }
+    if let Some(db_path) = &mut config.db_path {
+        secrets::resolve_field("db_path", db_path).await?;
+    }
+    Ok(config)
+}
+
+/// Swaps in `config` for everything reading the configuration from now on.
+pub fn set(config: Arc<Config>) {
+    redact::add_secrets(system_secrets(&config.jira, &config.zammad));
+    redact::add_secrets(config.admin.iter().map(|admin| admin.token.as_str()));
+    redact::add_secrets(config.lookup.iter().map(|lookup| lookup.token.as_str()));
+    redact::add_secrets(config.database_key.as_deref());
+    redact::set_pii_scrubbing(config.pii_scrubbing.clone());
+    *CONFIG.write().expect("config lock poisoned") = Some(config);
+}
+
+/// The tokens and webhook secrets of both systems, which are masked in logs.
+pub fn system_secrets<'a>(jira: &'a JiraConfig, zammad: &'a ZammadConfig) -> Vec<&'a str> {
+    let mut secrets = vec![jira.token.as_str(), zammad.token.as_str()];
+    match &jira.webhook_auth {
+        Some(JiraWebhookAuth::Token(token)) => secrets.push(token),
+        Some(JiraWebhookAuth::Jwt { shared_secret, .. }) => secrets.push(shared_secret),
+        None => {}
+    }
+    secrets.extend(zammad.webhook_secret.as_deref());
+    secrets
+}
+
+impl Config {
+    /// Keeps the endpoints, headers and webhook secrets of `current`, which the HTTP clients
+    /// were built with and senders were set up for. Returns whether this configuration
+    /// changed any of them.
+    pub fn keep_connections(&mut self, current: &Config) -> bool {
+        let (jira, zammad) = (&current.jira, &current.zammad);
+        let changed = self.jira.endpoint != jira.endpoint
+            || self.jira.headers != jira.headers
+            || self.jira.webhook_auth != jira.webhook_auth
+            || self.zammad.endpoint != zammad.endpoint
+            || self.zammad.headers != zammad.headers
+            || self.zammad.webhook_secret != zammad.webhook_secret;
+        self.jira.endpoint.clone_from(&jira.endpoint);
+        self.jira.headers.clone_from(&jira.headers);
+        self.jira.webhook_auth.clone_from(&jira.webhook_auth);
+        self.zammad.endpoint.clone_from(&zammad.endpoint);
+        self.zammad.headers.clone_from(&zammad.headers);
+        self.zammad
+            .webhook_secret
+            .clone_from(&zammad.webhook_secret);
+        changed
+    }
+}
+
+/// Rejects limits that would never refill a bucket.
+fn check_rate_limits(rate_limits: &RateLimitConfig) -> Result<()> {
+    let limits = [
+        ("rate_limits.route".to_string(), rate_limits.route),
+        ("rate_limits.source".to_string(), rate_limits.source),
+    ]
+    .into_iter()
+    .chain(rate_limits.routes.iter().flat_map(|(route, overrides)| {
+        [
+            (
+                format!("rate_limits.routes.{}.route", route),
+                overrides.route,
+            ),
+            (
+                format!("rate_limits.routes.{}.source", route),
+                overrides.source,
+            ),
+        ]
+    }));
+    for (name, limit) in limits {
+        if let Some(limit) = limit
+            && !(limit.per_second.is_finite() && limit.per_second > 0.0)
+        {
+            anyhow::bail!("{}.per_second has to be a positive number", name);
+        }
+    }
+    Ok(())
+}
+
+/// Checks what can't be expressed in the types of the system sections.
+fn prepare_systems(jira: &JiraConfig, zammad: &ZammadConfig) -> Result<()> {
+    check_headers(&jira.headers)?;
+    check_headers(&zammad.headers)?;
+    if let Some(checklists) = &jira.checklists
+        && checklists.target == ChecklistTarget::Field
+        && checklists.field.is_none()
+    {
+        anyhow::bail!("jira.checklists.field is required for the field target");
+    }
+    Ok(())
+}
+
+/// A further pair of Jira and Zammad systems, managed via the admin API and stored in the
+/// database. Its webhooks are synced with its own `jira` and `zammad` sections and the
+/// other settings of the file.
+#[derive(Debug, Deserialize)]
+pub struct ProfileConfig {
+    /// ID Zammad uses in the webhook URLs of the profile
+    pub zammad_id: String,
+    /// ID Jira uses in the webhook URLs of the profile
+    pub jira_id: String,
+    pub jira: JiraConfig,
+    pub zammad: ZammadConfig,
+}
+
+impl ProfileConfig {
+    /// Parses a profile given as YAML or JSON, checked and resolved like the file.
+    pub async fn parse(config: &str) -> Result<Self> {
+        let mut profile: Self = serde_yaml::from_str(&substitute_env(config)?)?;
+        prepare_systems(&profile.jira, &profile.zammad)?;
+        secrets::resolve_systems(&mut profile.jira, &mut profile.zammad).await?;
+        Ok(profile)
+    }
+}
+
+fn check_headers(headers: &HashMap<String, String>) -> Result<()> {
+    for (name, value) in headers {
+        HeaderName::from_str(name).with_context(|| format!("invalid header name {}", name))?;
+        HeaderValue::from_str(value)
+            .with_context(|| format!("invalid value for header {}", name))?;
+    }
+    Ok(())
+}
+
+/// Replaces `${VAR}` with the environment variable, or `${VAR:-default}` with the default if
+/// it isn't set, so secrets don't have to be stored in the file. Comments are left as they
+/// are. Values are inserted as written, so those containing YAML syntax need quotes around
+/// the reference.
+fn substitute_env(text: &str) -> Result<String> {
+    let mut resolved = String::with_capacity(text.len());
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let (mut rest, comment) = line.split_at(comment_start(line));
+        while let Some(start) = rest.find("${") {
+            let end = rest[start..]
+                .find('}')
+                .with_context(|| format!("unterminated variable on line {}", index + 1))?
+                + start;
+            let reference = &rest[start + 2..end];
+            let (variable, default) = match reference.split_once(":-") {
+                Some((variable, default)) => (variable, Some(default)),
+                None => (reference, None),
+            };
+            let value = match (env::var(variable), default) {
+                (Ok(value), _) => value,
+                (Err(_), Some(default)) => default.to_string(),
+                (Err(_), None) => anyhow::bail!(
+                    "environment variable {} on line {} is not set",
+                    variable,
+                    index + 1
+                ),
+            };
+            resolved.push_str(&rest[..start]);
+            resolved.push_str(&value);
+            rest = &rest[end + 1..];
+        }
+        resolved.push_str(rest);
+        resolved.push_str(comment);
+    }
+    Ok(resolved)
+}
+
+/// Where the comment of a YAML line starts: at a `#` at its start or after whitespace, outside
+/// of quotes. The length of the line if it has none.
+fn comment_start(line: &str) -> usize {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') if previous.is_whitespace() => return index,
+            (None, '"' | '\'') if previous.is_whitespace() || "[{,:".contains(previous) => {
+                quote = Some(c)
+            }
+            (Some(open), _) if c == open => quote = None,
+            _ => {}
+        }
+        previous = c;
+    }
+    line.len()
+}
+
+/// An option set through a `TSS_` variable, or a section of them.
+enum EnvOption {
+    Value(String),
+    Section(BTreeMap<String, EnvOption>),
+}
+
+/// The configuration given by the `TSS_` variables, as YAML; unset if there are none.
+fn env_config() -> Result<Option<String>> {
+    let mut options = BTreeMap::new();
+    for (name, value) in env::vars_os() {
+        let (Ok(name), Ok(value)) = (name.into_string(), value.into_string()) else {
+            continue;
+        };
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let mut section = &mut options;
+        let mut path: Vec<String> = key.to_lowercase().split("__").map(String::from).collect();
+        let last = path.pop().unwrap_or_default();
+        for key in path {
+            let option = section
+                .entry(key)
+                .or_insert_with(|| EnvOption::Section(BTreeMap::new()));
+            let EnvOption::Section(nested) = option else {
+                anyhow::bail!("{} sets an option within a value", name);
+            };
+            section = nested;
+        }
+        if section.insert(last, EnvOption::Value(value)).is_some() {
+            anyhow::bail!("{} replaces a section of options", name);
+        }
+    }
+    if options.is_empty() {
+        return Ok(None);
+    }
+    let mut yaml = String::new();
+    write_env_section(&mut yaml, &options, 0);
+    Ok(Some(yaml))
+}
+
+fn write_env_section(yaml: &mut String, section: &BTreeMap<String, EnvOption>, indent: usize) {
+    for (key, option) in section {
+        match option {
+            EnvOption::Value(value) => {
+                yaml.push_str(&format!("{:indent$}{}: {}\n", "", key, env_value(value)))
+            }
+            EnvOption::Section(section) => {
+                yaml.push_str(&format!("{:indent$}{}:\n", "", key));
+                write_env_section(yaml, section, indent + 2);
+            }
+        }
+    }
+}
+
+/// Numbers, booleans and single-line lists or maps like `[a, b]` are written as they are, so
+/// they fit options of any type; everything else is quoted as a string.
+fn env_value(value: &str) -> String {
+    let collection = value.starts_with(['[', '{']) && !value.contains('\n');
+    match serde_yaml::from_str(value) {
+        Ok(serde_yaml::Value::Number(_) | serde_yaml::Value::Bool(_)) => value.to_string(),
+        Ok(serde_yaml::Value::Sequence(_) | serde_yaml::Value::Mapping(_)) if collection => {
+            value.to_string()
+        }
+        _ => serde_json::to_string(value).unwrap_or_default(),
+    }
+}
+
+/// The configuration in use; it stays valid while held, even if it's replaced meanwhile.
+pub fn get() -> Arc<Config> {
+    CONFIG
+        .read()
+        .expect("config lock poisoned")
+        .clone()
+        .expect("Config not initialized")
+}
+
+/// A system section of the profile being synced, or of the configuration in use, which it
+/// holds.
+pub enum Section<T: 'static> {
+    Profile(Arc<Profile>, fn(&ProfileConfig) -> &T),
+    File(Arc<Config>, fn(&Config) -> &T),
+}
+
+impl<T> Deref for Section<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Section::Profile(profile, section) => section(&profile.config),
+            Section::File(config, section) => section(config),
+        }
+    }
 }
 
-pub fn get_jira() -> &'static JiraConfig {
-    &get().jira
+/// The Jira section of the profile being synced, or of the file.
+pub fn get_jira() -> Section<JiraConfig> {
+    match profiles::current() {
+        Some(profile) => Section::Profile(profile, |config| &config.jira),
+        None => Section::File(get(), |config| &config.jira),
+    }
 }
 
-pub fn get_zammad() -> &'static ZammadConfig {
-    &get().zammad
+/// The Zammad section of the profile being synced, or of the file.
+pub fn get_zammad() -> Section<ZammadConfig> {
+    match profiles::current() {
+        Some(profile) => Section::Profile(profile, |config| &config.zammad),
+        None => Section::File(get(), |config| &config.zammad),
+    }
 }
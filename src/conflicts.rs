@@ -0,0 +1,209 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::config::{self, ConflictStrategy};
+use crate::decisions;
+use crate::models::{
+    api_request::{
+        JiraAddCommentRequest, JiraUpdateIssueRequest, ZammadCreateArticleRequest,
+        ZammadUpdateTicketRequest,
+    },
+    db::{DB, FieldSyncState},
+    jira,
+    zammad::{ZammadPriorityId, ZammadState},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Zammad,
+    Jira,
+}
+
+impl Side {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Zammad => "zammad",
+            Side::Jira => "jira",
+        }
+    }
+}
+
+/// A field synced both ways, with its value in Zammad terms so both sides compare equal.
+#[derive(Debug, Clone, Copy)]
+pub enum FieldValue {
+    Priority(ZammadPriorityId),
+    Status(ZammadState),
+}
+
+impl FieldValue {
+    pub fn field(&self) -> &'static str {
+        match self {
+            FieldValue::Priority(_) => "priority",
+            FieldValue::Status(_) => "status",
+        }
+    }
+
+    pub fn to_stored(self) -> String {
+        match self {
+            FieldValue::Priority(priority) => (priority as i32).to_string(),
+            FieldValue::Status(state) => state.as_str().to_string(),
+        }
+    }
+}
+
+/// The value of a field as reported by a webhook of `side`.
+pub struct FieldChange {
+    pub zammad_ticket_id: i32,
+    pub jira_issue_id: i32,
+    pub side: Side,
+    pub value: FieldValue,
+    /// When the ticket or issue was changed, according to the webhook
+    pub changed_at: DateTime<Utc>,
+}
+
+impl FieldChange {
+    /// Whether the value should be synced to the other side.
+    ///
+    /// Values that were synced already are skipped. If the other side changed the field before
+    /// this change arrived, but after this change was made, both sides changed it between two
+    /// syncs and the configured strategy decides. When this change wins, it is written back to
+    /// its own side as well, since the sync of the other change has overwritten it there.
+    pub async fn should_apply(&self, db: &DB) -> anyhow::Result<bool> {
+        let field = self.value.field();
+        let Some(last) = db
+            .get_field_sync_state(&self.zammad_ticket_id, field)
+            .await?
+        else {
+            return Ok(true);
+        };
+
+        let value = self.value.to_stored();
+        if last.value == value {
+            return Ok(false);
+        }
+        if last.source == self.side.as_str() || self.changed_at >= last.synced_at {
+            return Ok(true);
+        }
+
+        let strategy = config::get().conflicts;
+        warn!(
+            "Conflicting changes of {} on Zammad ticket {}: {} is {:?} (changed {}), {} is {:?} (changed {}), resolving with {:?}",
+            field,
+            self.zammad_ticket_id,
+            self.side.as_str(),
+            value,
+            self.changed_at,
+            last.source,
+            last.value,
+            last.changed_at,
+            strategy
+        );
+        let wins = match strategy {
+            ConflictStrategy::LastWriteWins => self.changed_at > last.changed_at,
+            ConflictStrategy::ZammadWins => self.side == Side::Zammad,
+            ConflictStrategy::JiraWins => self.side == Side::Jira,
+            ConflictStrategy::FlagAndSkip => {
+                self.flag(db, &last, &value).await?;
+                false
+            }
+        };
+
+        decisions::record(
+            format!("conflict.{}", field),
+            json!({
+                "strategy": format!("{:?}", strategy),
+                "incoming": { "side": self.side.as_str(), "value": value, "changed_at": self.changed_at },
+                "synced": { "side": last.source, "value": last.value, "changed_at": last.changed_at },
+            }),
+            if wins { "apply" } else { "skip" },
+        );
+        if wins {
+            info!(
+                "Writing {} {:?} back to {}",
+                field,
+                value,
+                self.side.as_str()
+            );
+            self.write_to(self.side).await?;
+        }
+        Ok(wins)
+    }
+
+    /// Remembers the value as synced, once it was sent to the other side.
+    pub async fn record(&self, db: &DB) -> anyhow::Result<()> {
+        db.set_field_sync_state(
+            &self.zammad_ticket_id,
+            self.value.field(),
+            &FieldSyncState {
+                value: self.value.to_stored(),
+                source: self.side.as_str().to_string(),
+                changed_at: self.changed_at,
+                synced_at: Utc::now(),
+            },
+        )
+        .await
+    }
+
+    /// Writes the value to `side`. A Jira status is only written with `jira.statuses`
+    /// configured.
+    pub async fn write_to(&self, side: Side) -> anyhow::Result<()> {
+        match (side, self.value) {
+            (Side::Zammad, FieldValue::Priority(priority)) => {
+                ZammadUpdateTicketRequest::new()
+                    .with_priority_id(priority)
+                    .submit(&self.zammad_ticket_id)
+                    .await
+            }
+            (Side::Zammad, FieldValue::Status(state)) => {
+                ZammadUpdateTicketRequest::new()
+                    .with_state(state)
+                    .submit(&self.zammad_ticket_id)
+                    .await
+            }
+            (Side::Jira, FieldValue::Priority(priority)) => {
+                JiraUpdateIssueRequest::priority(priority)
+                    .submit(&self.jira_issue_id)
+                    .await
+            }
+            (Side::Jira, FieldValue::Status(state)) => {
+                let Some(statuses) = &config::get_jira().statuses else {
+                    return Ok(());
+                };
+                let path = match state {
+                    ZammadState::Open => &statuses.open,
+                    ZammadState::Closed => &statuses.closed,
+                };
+                jira::transition_issue(self.jira_issue_id, path).await
+            }
+        }
+    }
+
+    /// Leaves a note on both sides asking for a manual decision. The notes are linked as
+    /// comment mappings, so neither is synced to the other side.
+    async fn flag(&self, db: &DB, last: &FieldSyncState, value: &str) -> anyhow::Result<()> {
+        let (zammad_value, jira_value) = match self.side {
+            Side::Zammad => (value, last.value.as_str()),
+            Side::Jira => (last.value.as_str(), value),
+        };
+        let note = format!(
+            "The {} was changed in both Zammad ({}) and Jira ({}) at the same time. The {} change was not synced, please check which value is correct.",
+            self.value.field(),
+            zammad_value,
+            jira_value,
+            self.side.as_str()
+        );
+
+        let article =
+            ZammadCreateArticleRequest::internal_note(self.zammad_ticket_id, note.clone())
+                .submit()
+                .await?;
+        let comment = JiraAddCommentRequest::internal(note)
+            .submit(&self.jira_issue_id)
+            .await?;
+        db.create_comment_mapping(&article.id, &comment.id).await
+    }
+}
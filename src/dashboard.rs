@@ -0,0 +1,27 @@
+//! A small web UI at /admin/dashboard for operators who don't use the admin API directly:
+//! recent syncs, dead letters, failures, mappings and the pause switches. The page and its
+//! assets hold no data; the browser asks for the admin token and calls the admin API with it.
+
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{Html, IntoResponse};
+
+use crate::assets;
+
+pub async fn page() -> Result<Html<&'static str>, StatusCode> {
+    assets::get("dashboard/index.html")
+        .map(Html)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// The stylesheet and script the page loads.
+pub async fn file(Path(name): Path<String>) -> Result<impl IntoResponse, StatusCode> {
+    let content_type = match name.rsplit_once('.') {
+        Some((_, "css")) => "text/css; charset=utf-8",
+        Some((_, "js")) => "text/javascript; charset=utf-8",
+        _ => return Err(StatusCode::NOT_FOUND),
+    };
+    let body = assets::get(&format!("dashboard/{name}")).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(([(CONTENT_TYPE, content_type)], body))
+}
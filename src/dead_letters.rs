@@ -0,0 +1,144 @@
+use anyhow::{Context, Result, bail};
+use clap::Subcommand;
+use serde_json::json;
+use tracing::{Instrument, error, info};
+
+use crate::models::db::{DB, DeadLetter, OutboxJob};
+use crate::output::{OutputFormat, Progress};
+use crate::{audit, logging, outbox, profiles, restrictions};
+
+#[derive(Subcommand, Debug)]
+pub enum DeadLettersCommand {
+    /// Fehlgeschlagene Webhooks auflisten
+    List,
+    /// Fehlgeschlagene Webhooks erneut synchronisieren
+    Replay {
+        /// ID des Webhooks
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        id: Option<i64>,
+
+        /// Alle fehlgeschlagenen Webhooks erneut synchronisieren
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+/// Keeps the outbox job whose sync failed, so it can be replayed later.
+pub async fn store(db: &DB, job: &OutboxJob, e: &anyhow::Error) {
+    let result = db
+        .create_dead_letter(
+            &job.operation,
+            job.zammad_id,
+            job.profile.as_deref(),
+            &job.payload,
+            &format!("{:#}", e),
+        )
+        .await;
+    if let Err(e) = result {
+        error!("Failed to store the dead letter: {}", e);
+    }
+}
+
+/// Syncs the webhook again, with the profile it arrived for. It's removed if that worked,
+/// otherwise the error is kept.
+pub async fn replay(db: &DB, dead_letter: &DeadLetter) -> Result<()> {
+    let result = profiles::scope_named(dead_letter.profile.as_deref(), async {
+        let (result, _) = audit::record(
+            db,
+            &dead_letter.operation,
+            dead_letter.zammad_id,
+            &dead_letter.payload,
+            outbox::sync(db, &dead_letter.operation, dead_letter.payload.clone()),
+        )
+        .await;
+        if let Err(e) = &result
+            && let Some(zammad_id) = dead_letter.zammad_id
+            && restrictions::is_permission_error(e)
+        {
+            restrictions::restrict(db, zammad_id, e).await;
+        }
+        result
+    })
+    .instrument(logging::sync_span(
+        &dead_letter.operation,
+        dead_letter.zammad_id,
+        &dead_letter.payload,
+    ))
+    .await;
+    match result {
+        Ok(()) => {
+            info!(
+                "Replayed dead letter {} ({})",
+                dead_letter.id, dead_letter.operation
+            );
+            db.delete_dead_letter(dead_letter.id).await?;
+            Ok(())
+        }
+        Err(e) => {
+            db.update_dead_letter_error(dead_letter.id, &format!("{:#}", e))
+                .await?;
+            Err(e)
+        }
+    }
+}
+
+pub async fn run(command: DeadLettersCommand, output: OutputFormat) -> Result<()> {
+    let db = DB::new().await?;
+    profiles::load(&db).await?;
+    let progress = Progress::new("dead-letters", output);
+    match command {
+        DeadLettersCommand::List => {
+            let dead_letters = db.get_dead_letters().await?;
+            let summary = dead_letters
+                .iter()
+                .map(|dead_letter| {
+                    format!(
+                        "{:>5} {:<14} {} ({} attempts): {}",
+                        dead_letter.id,
+                        dead_letter.operation,
+                        dead_letter.failed_at.format("%Y-%m-%d %H:%M:%S"),
+                        dead_letter.attempts,
+                        dead_letter.error
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            progress.finish(&summary, serde_json::to_value(&dead_letters)?);
+        }
+        DeadLettersCommand::Replay { id, all } => {
+            let dead_letters = match id {
+                Some(id) if !all => vec![
+                    db.get_dead_letter(id)
+                        .await?
+                        .with_context(|| format!("dead letter {} doesn't exist", id))?,
+                ],
+                _ => db.get_dead_letters().await?,
+            };
+
+            let total = dead_letters.len() as u64;
+            let mut failed = 0;
+            for (index, dead_letter) in dead_letters.iter().enumerate() {
+                let started = std::time::Instant::now();
+                let result = replay(&db, dead_letter).await;
+                if result.is_err() {
+                    failed += 1;
+                }
+                progress.step(
+                    &format!("{} {}", dead_letter.id, dead_letter.operation),
+                    result.is_ok(),
+                    started.elapsed().as_millis(),
+                    result.err().map(|e| format!("{:#}", e)),
+                );
+                progress.update(index as u64 + 1, Some(total));
+            }
+            if failed > 0 {
+                bail!("{} of {} dead letters failed again", failed, total);
+            }
+            progress.finish(
+                &format!("Replayed {} dead letters", total),
+                json!({ "replayed": total }),
+            );
+        }
+    }
+    Ok(())
+}
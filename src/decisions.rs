@@ -0,0 +1,59 @@
+use std::cell::RefCell;
+use std::future::Future;
+
+use serde::Serialize;
+use serde_json::Value;
+use tracing::error;
+
+use crate::models::db::DB;
+
+/// A rule or transform that was applied while mapping an event, e.g. the priority
+/// conversion, with what it got and what it produced.
+#[derive(Debug, Clone, Serialize)]
+pub struct Decision {
+    pub rule: String,
+    pub input: Value,
+    pub output: Value,
+}
+
+tokio::task_local! {
+    static DECISIONS: RefCell<Vec<Decision>>;
+}
+
+/// Adds a decision to the log of the current event. Outside of `trace`, e.g. in the
+/// backfill, decisions aren't recorded.
+pub fn record(rule: impl Into<String>, input: impl Serialize, output: impl Serialize) {
+    let _ = DECISIONS.try_with(|decisions| {
+        decisions.borrow_mut().push(Decision {
+            rule: rule.into(),
+            input: serde_json::to_value(input).unwrap_or_default(),
+            output: serde_json::to_value(output).unwrap_or_default(),
+        })
+    });
+}
+
+/// Runs `future` with its own decision log and returns the decisions recorded by it.
+pub async fn trace<T>(future: impl Future<Output = T>) -> (T, Vec<Decision>) {
+    DECISIONS
+        .scope(RefCell::new(Vec::new()), async {
+            let result = future.await;
+            (result, DECISIONS.with(|decisions| decisions.take()))
+        })
+        .await
+}
+
+/// Stores the decisions of a failed sync, so they can be reviewed in the admin API.
+pub async fn store_failure(
+    db: &DB,
+    operation: &str,
+    zammad_ticket_id: Option<i32>,
+    e: &anyhow::Error,
+    decisions: &[Decision],
+) {
+    let result = db
+        .create_sync_failure(operation, zammad_ticket_id, &format!("{:#}", e), decisions)
+        .await;
+    if let Err(e) = result {
+        error!("Failed to store the failed sync: {}", e);
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{TimeDelta, Utc};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+
+use crate::config;
+use crate::models::db::DB;
+use crate::state::AppState;
+use crate::webhook_body;
+
+/// How often expired deliveries are forgotten.
+const PRUNE_EVERY: Duration = Duration::from_secs(60 * 60);
+
+/// Headers Zammad and Jira use to identify a delivery; retries carry the same value
+const DELIVERY_HEADERS: &[&str] = &["x-zammad-delivery", "x-atlassian-webhook-identifier"];
+
+/// Drops webhook deliveries that were already received, e.g. retries or deliveries load
+/// balanced to several replicas. The delivery is claimed with an atomic insert into the
+/// database, so exactly one request processes it; the claim is released again if processing
+/// fails, so the sender's retry isn't swallowed.
+pub async fn deduplicate_deliveries(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let bytes = match webhook_body::read(body).await {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+    let delivery_id = delivery_id(&parts.headers, &bytes);
+
+    let db = &state.db;
+    match db.claim_delivery(&delivery_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            info!("Skipping duplicate delivery {}", delivery_id);
+            return StatusCode::OK.into_response();
+        }
+        Err(e) => {
+            error!("Failed to claim delivery {}: {}", delivery_id, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    if !response.status().is_success()
+        && let Err(e) = db.release_delivery(&delivery_id).await
+    {
+        error!("Failed to release delivery {}: {}", delivery_id, e);
+    }
+    response
+}
+
+/// Uses the sender's delivery ID, falling back to a hash of the payload.
+fn delivery_id(headers: &HeaderMap, body: &[u8]) -> String {
+    for header in DELIVERY_HEADERS {
+        if let Some(value) = headers.get(*header).and_then(|value| value.to_str().ok()) {
+            return format!("{}:{}", header, value);
+        }
+    }
+
+    let digest = Sha256::digest(body);
+    let hash: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256:{}", hash)
+}
+
+/// Forgets expired deliveries every hour, for the lifetime of the server. Senders give up
+/// retrying long before, and payload hashes would otherwise be kept forever.
+pub async fn prune_periodically(db: DB) {
+    let mut interval = tokio::time::interval(PRUNE_EVERY);
+    loop {
+        interval.tick().await;
+        let before = Utc::now() - TimeDelta::days(config::get().delivery_max_age as i64);
+        match db.prune_deliveries(before).await {
+            Ok(0) => {}
+            Ok(removed) => info!("Forgot {} expired webhook deliveries", removed),
+            Err(e) => error!("Failed to prune the webhook deliveries: {}", e),
+        }
+    }
+}
@@ -0,0 +1,613 @@
+//! `--demo` runs the service against fake Jira and Zammad servers in the same process, so it
+//! can be tried without either. The fakes keep their tickets and issues in memory and send
+//! webhooks like the real systems when something is changed through `/demo`.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{LazyLock, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::{
+    Json, Router,
+    extract::Path,
+    routing::{get, post},
+};
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::assets;
+use crate::state::AppState;
+
+/// Tickets created in the fake Zammad when the demo starts, by title and first article.
+const SAMPLE_TICKETS: [(&str, &str); 3] = [
+    (
+        "Printer on the 3rd floor is jammed",
+        "The printer reports a paper jam since this morning, but there is no paper stuck.",
+    ),
+    (
+        "VPN disconnects every few minutes",
+        "Since the last update my VPN connection drops every few minutes when working from home.",
+    ),
+    (
+        "Laptop for a new colleague",
+        "Our new colleague starts on Monday and needs a laptop with the standard setup.",
+    ),
+];
+
+/// ID the fakes use in their webhook URLs.
+pub const WEBHOOK_ID: &str = "demo";
+
+static BACKENDS: LazyLock<Mutex<Backends>> = LazyLock::new(Default::default);
+
+/// Port of the connector the fakes send their webhooks to, set while the demo runs.
+static CONNECTOR_PORT: OnceLock<u16> = OnceLock::new();
+
+/// Everything the fake Zammad and Jira know.
+#[derive(Default, Serialize)]
+struct Backends {
+    tickets: BTreeMap<i32, Ticket>,
+    articles: Vec<Article>,
+    issues: BTreeMap<i32, Issue>,
+    comments: Vec<Comment>,
+    /// IDs are shared by all objects, so they are unique across both systems
+    #[serde(skip)]
+    last_id: i32,
+}
+
+impl Backends {
+    fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn issue_mut(&mut self, id_or_key: &str) -> Option<&mut Issue> {
+        self.issues
+            .values_mut()
+            .find(|issue| issue.id.to_string() == id_or_key || issue.key == id_or_key)
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct Ticket {
+    id: i32,
+    number: String,
+    title: String,
+    /// "open" or "closed"
+    state: String,
+    priority_id: i64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize)]
+struct Article {
+    id: i32,
+    ticket_id: i32,
+    body: String,
+    internal: bool,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize)]
+struct Issue {
+    id: i32,
+    key: String,
+    /// As sent by the connector, e.g. summary and priority
+    fields: Map<String, Value>,
+    /// Status category key: "new", "indeterminate" or "done"
+    status: String,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Serialize)]
+struct Comment {
+    id: i32,
+    issue_id: i32,
+    body: String,
+    created_at: DateTime<Utc>,
+}
+
+fn backends() -> MutexGuard<'static, Backends> {
+    BACKENDS.lock().expect("demo lock poisoned")
+}
+
+pub fn is_running() -> bool {
+    CONNECTOR_PORT.get().is_some()
+}
+
+/// Starts the fake servers and moves into a new directory with a configuration pointing to
+/// them, so the demo doesn't touch the database or configuration of a real setup. The sample
+/// tickets are created once the connector listens on `port`.
+pub async fn start(port: u16) -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("ticket-sync-demo-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    std::env::set_current_dir(&dir)?;
+
+    let jira = spawn_server(jira_router()).await?;
+    let zammad = spawn_server(zammad_router()).await?;
+    let config = assets::get("demo.yml")
+        .context("the demo configuration isn't embedded")?
+        .replace("{jira}", &format!("http://{}", jira))
+        .replace("{zammad}", &format!("http://{}", zammad));
+    std::fs::write("config.yml", config)?;
+    CONNECTOR_PORT.set(port).ok();
+
+    info!(
+        "Demo running in {} with a fake Jira at http://{} and a fake Zammad at http://{}",
+        dir.display(),
+        jira,
+        zammad
+    );
+    info!(
+        "See http://localhost:{}/demo for their tickets; POST to /demo/zammad/tickets, /demo/zammad/tickets/<id>/articles or /demo/jira/issues/<key>/comments to change them",
+        port
+    );
+    tokio::spawn(seed());
+    Ok(())
+}
+
+async fn spawn_server(router: Router) -> anyhow::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router).await {
+            error!("Demo server failed: {}", e);
+        }
+    });
+    Ok(address)
+}
+
+/// Creates the sample tickets once the connector accepts their webhooks.
+async fn seed() {
+    let Some(port) = CONNECTOR_PORT.get() else {
+        return;
+    };
+    let url = format!("http://127.0.0.1:{}/metrics", port);
+    for _ in 0..50 {
+        if reqwest::get(&url).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    for (title, body) in SAMPLE_TICKETS {
+        let (ticket, article) = open_ticket(title, body);
+        let webhook = zammad_webhook(&ticket, Some(&article));
+        if let Err(e) = send_webhook("zammad/create-ticket", &webhook).await {
+            error!("Failed to create sample ticket: {}", e);
+        }
+    }
+}
+
+/// Sends a webhook to the connector like Zammad or Jira would.
+async fn send_webhook(route: &str, payload: &Value) -> anyhow::Result<StatusCode> {
+    let port = CONNECTOR_PORT.get().context("the demo isn't running")?;
+    let response = reqwest::Client::new()
+        .post(format!(
+            "http://127.0.0.1:{}/ticket-sync/{}/{}",
+            port, route, WEBHOOK_ID
+        ))
+        .json(payload)
+        .send()
+        .await?;
+    Ok(response.status())
+}
+
+fn zammad_webhook(ticket: &Ticket, article: Option<&Article>) -> Value {
+    let agent = json!({
+        "id": 1, "email": "agent@demo.example", "firstname": "Demo", "lastname": "Agent",
+    });
+    let customer = json!({
+        "id": 2, "email": "customer@demo.example", "firstname": "Demo", "lastname": "Customer",
+    });
+    let article = match article {
+        Some(article) => json!({
+            "id": article.id,
+            "ticket_id": article.ticket_id,
+            "body": article.body,
+            "content_type": "text/plain",
+            "created_at": article.created_at,
+            "updated_at": article.created_at,
+            "sender": "Customer",
+            "from": "Demo Customer",
+            "to": "Users",
+        }),
+        None => json!({
+            "id": null, "ticket_id": null, "body": null, "content_type": null,
+            "created_at": null, "updated_at": null, "sender": null, "from": null, "to": null,
+        }),
+    };
+    json!({
+        "ticket": {
+            "id": ticket.id,
+            "number": ticket.number,
+            "title": ticket.title,
+            "state": ticket.state,
+            "priority": { "id": ticket.priority_id },
+            "created_at": ticket.created_at,
+            "updated_at": ticket.updated_at,
+            "created_by": customer,
+            "owner": agent,
+            "customer": customer,
+            "group": { "id": 1, "name": "Users" },
+            "tags": [],
+        },
+        "article": article,
+    })
+}
+
+fn jira_webhook(issue: &Issue, comment: Option<&Comment>) -> Value {
+    json!({
+        "issue": issue_json(issue),
+        "timestamp": issue.updated_at.timestamp_millis(),
+        "comment": comment.map(comment_json),
+    })
+}
+
+fn issue_json(issue: &Issue) -> Value {
+    let mut fields = issue.fields.clone();
+    let name = match issue.status.as_str() {
+        "done" => "Done",
+        "indeterminate" => "In Progress",
+        _ => "To Do",
+    };
+    fields.insert(
+        "status".to_string(),
+        json!({ "name": name, "statusCategory": { "key": issue.status } }),
+    );
+    fields.insert("updated".to_string(), json!(jira_time(issue.updated_at)));
+    json!({ "id": issue.id.to_string(), "key": issue.key, "fields": fields })
+}
+
+fn comment_json(comment: &Comment) -> Value {
+    json!({
+        "id": comment.id.to_string(),
+        "body": comment.body,
+        "author": { "displayName": "Demo Developer", "emailAddress": "developer@demo.example" },
+        "created": jira_time(comment.created_at),
+    })
+}
+
+fn jira_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string()
+}
+
+/// Adds a ticket with its first article to the fake Zammad.
+fn open_ticket(title: &str, body: &str) -> (Ticket, Article) {
+    let mut backends = backends();
+    let now = Utc::now();
+    let id = backends.next_id();
+    let ticket = Ticket {
+        id,
+        number: (31000 + id).to_string(),
+        title: title.to_string(),
+        state: "open".to_string(),
+        priority_id: 2,
+        created_at: now,
+        updated_at: now,
+    };
+    let article = Article {
+        id: backends.next_id(),
+        ticket_id: id,
+        body: body.to_string(),
+        internal: false,
+        created_at: now,
+    };
+    backends.tickets.insert(id, ticket.clone());
+    backends.articles.push(article.clone());
+    (ticket, article)
+}
+
+// Actions under /demo, which change the fakes and send the webhooks
+
+#[derive(Deserialize)]
+struct NewTicket {
+    title: String,
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct NewText {
+    body: String,
+}
+
+fn webhook_result(
+    result: anyhow::Result<StatusCode>,
+    object: Value,
+) -> Result<Json<Value>, StatusCode> {
+    match result {
+        Ok(status) => Ok(Json(
+            json!({ "object": object, "webhook_status": status.as_u16() }),
+        )),
+        Err(e) => {
+            error!("Failed to send the demo webhook: {}", e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
+async fn overview() -> Json<Value> {
+    Json(serde_json::to_value(&*backends()).unwrap_or_default())
+}
+
+async fn create_ticket(Json(new): Json<NewTicket>) -> Result<Json<Value>, StatusCode> {
+    let (ticket, article) = open_ticket(&new.title, &new.body);
+    let webhook = zammad_webhook(&ticket, Some(&article));
+    let result = send_webhook("zammad/create-ticket", &webhook).await;
+    webhook_result(result, json!(ticket))
+}
+
+async fn add_article(
+    Path(id): Path<i32>,
+    Json(new): Json<NewText>,
+) -> Result<Json<Value>, StatusCode> {
+    let (ticket, article) = {
+        let mut backends = backends();
+        let article_id = backends.next_id();
+        let ticket = backends.tickets.get_mut(&id).ok_or(StatusCode::NOT_FOUND)?;
+        ticket.updated_at = Utc::now();
+        let ticket = ticket.clone();
+        let article = Article {
+            id: article_id,
+            ticket_id: id,
+            body: new.body,
+            internal: false,
+            created_at: ticket.updated_at,
+        };
+        backends.articles.push(article.clone());
+        (ticket, article)
+    };
+    let webhook = zammad_webhook(&ticket, Some(&article));
+    let result = send_webhook("zammad/update-ticket", &webhook).await;
+    webhook_result(result, json!(article))
+}
+
+async fn close_ticket(Path(id): Path<i32>) -> Result<Json<Value>, StatusCode> {
+    let ticket = {
+        let mut backends = backends();
+        let ticket = backends.tickets.get_mut(&id).ok_or(StatusCode::NOT_FOUND)?;
+        ticket.state = "closed".to_string();
+        ticket.updated_at = Utc::now();
+        ticket.clone()
+    };
+    let webhook = zammad_webhook(&ticket, None);
+    let result = send_webhook("zammad/update-ticket", &webhook).await;
+    webhook_result(result, json!(ticket))
+}
+
+async fn add_comment(
+    Path(issue): Path<String>,
+    Json(new): Json<NewText>,
+) -> Result<Json<Value>, StatusCode> {
+    let (issue, comment) = {
+        let mut backends = backends();
+        let comment_id = backends.next_id();
+        let issue = backends.issue_mut(&issue).ok_or(StatusCode::NOT_FOUND)?;
+        issue.updated_at = Utc::now();
+        let issue = issue.clone();
+        let comment = Comment {
+            id: comment_id,
+            issue_id: issue.id,
+            body: new.body,
+            created_at: issue.updated_at,
+        };
+        backends.comments.push(comment.clone());
+        (issue, comment)
+    };
+    let webhook = jira_webhook(&issue, Some(&comment));
+    let result = send_webhook("jira/update-ticket", &webhook).await;
+    webhook_result(result, json!(comment))
+}
+
+async fn resolve_issue(Path(issue): Path<String>) -> Result<Json<Value>, StatusCode> {
+    let issue = {
+        let mut backends = backends();
+        let issue = backends.issue_mut(&issue).ok_or(StatusCode::NOT_FOUND)?;
+        issue.status = "done".to_string();
+        issue.updated_at = Utc::now();
+        issue.clone()
+    };
+    let webhook = jira_webhook(&issue, None);
+    let result = send_webhook("jira/update-ticket", &webhook).await;
+    webhook_result(result, json!(issue))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(overview))
+        .route("/zammad/tickets", post(create_ticket))
+        .route("/zammad/tickets/:id/articles", post(add_article))
+        .route("/zammad/tickets/:id/close", post(close_ticket))
+        .route("/jira/issues/:issue/comments", post(add_comment))
+        .route("/jira/issues/:issue/done", post(resolve_issue))
+}
+
+// The fake Jira REST API
+
+fn jira_router() -> Router {
+    Router::new()
+        .route("/rest/api/2/issue", post(jira_create_issue))
+        .route(
+            "/rest/api/2/issue/:issue",
+            get(jira_get_issue).put(jira_update_issue),
+        )
+        .route(
+            "/rest/api/2/issue/:issue/comment",
+            get(jira_get_comments).post(jira_add_comment),
+        )
+        .route(
+            "/rest/api/2/issue/:issue/comment/:id",
+            get(jira_get_comment),
+        )
+        .route(
+            "/rest/api/2/issue/:issue/transitions",
+            get(|| async { Json(json!({ "transitions": [] })) }),
+        )
+        .route(
+            "/rest/api/2/myself",
+            get(|| async { Json(json!({ "name": "demo", "emailAddress": "agent@demo.example" })) }),
+        )
+}
+
+async fn jira_create_issue(Json(request): Json<Value>) -> (StatusCode, Json<Value>) {
+    let mut backends = backends();
+    let id = backends.next_id();
+    let issue = Issue {
+        id,
+        key: format!("DEMO-{}", backends.issues.len() + 1),
+        fields: request["fields"].as_object().cloned().unwrap_or_default(),
+        status: "new".to_string(),
+        updated_at: Utc::now(),
+    };
+    let response = json!({ "id": id.to_string(), "key": issue.key });
+    backends.issues.insert(id, issue);
+    (StatusCode::CREATED, Json(response))
+}
+
+async fn jira_get_issue(Path(issue): Path<String>) -> Result<Json<Value>, StatusCode> {
+    let mut backends = backends();
+    let issue = backends.issue_mut(&issue).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(issue_json(issue)))
+}
+
+async fn jira_update_issue(Path(issue): Path<String>, Json(request): Json<Value>) -> StatusCode {
+    let mut backends = backends();
+    let Some(issue) = backends.issue_mut(&issue) else {
+        return StatusCode::NOT_FOUND;
+    };
+    for (field, value) in request["fields"].as_object().into_iter().flatten() {
+        issue.fields.insert(field.clone(), value.clone());
+    }
+    issue.updated_at = Utc::now();
+    StatusCode::NO_CONTENT
+}
+
+async fn jira_get_comments(Path(issue): Path<String>) -> Result<Json<Value>, StatusCode> {
+    let mut backends = backends();
+    let issue_id = backends.issue_mut(&issue).ok_or(StatusCode::NOT_FOUND)?.id;
+    let comments: Vec<Value> = backends
+        .comments
+        .iter()
+        .filter(|comment| comment.issue_id == issue_id)
+        .map(comment_json)
+        .collect();
+    Ok(Json(json!({ "comments": comments })))
+}
+
+async fn jira_get_comment(
+    Path((issue, id)): Path<(String, i32)>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut backends = backends();
+    let issue_id = backends.issue_mut(&issue).ok_or(StatusCode::NOT_FOUND)?.id;
+    backends
+        .comments
+        .iter()
+        .find(|comment| comment.issue_id == issue_id && comment.id == id)
+        .map(|comment| Json(comment_json(comment)))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn jira_add_comment(
+    Path(issue): Path<String>,
+    Json(request): Json<Value>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let mut backends = backends();
+    let id = backends.next_id();
+    let issue_id = backends.issue_mut(&issue).ok_or(StatusCode::NOT_FOUND)?.id;
+    let comment = Comment {
+        id,
+        issue_id,
+        body: request["body"].as_str().unwrap_or_default().to_string(),
+        created_at: Utc::now(),
+    };
+    let response = comment_json(&comment);
+    backends.comments.push(comment);
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+// The fake Zammad REST API
+
+fn zammad_router() -> Router {
+    Router::new()
+        .route("/api/v1/tickets", post(zammad_create_ticket))
+        .route(
+            "/api/v1/tickets/:id",
+            get(zammad_get_ticket).put(zammad_update_ticket),
+        )
+        .route("/api/v1/ticket_articles", post(zammad_create_article))
+        .route(
+            "/api/v1/users/me",
+            get(|| async { Json(json!({ "id": 1, "login": "demo" })) }),
+        )
+}
+
+fn ticket_json(ticket: &Ticket) -> Value {
+    json!({
+        "id": ticket.id,
+        "number": ticket.number,
+        "title": ticket.title,
+        "state": ticket.state,
+        "priority_id": ticket.priority_id,
+        "customer_id": 2,
+        "customer": "customer@demo.example",
+        "owner_id": 1,
+        "owner": "agent@demo.example",
+        "created_by_id": 2,
+        "created_at": ticket.created_at,
+        "updated_at": ticket.updated_at,
+    })
+}
+
+async fn zammad_create_ticket(Json(request): Json<Value>) -> (StatusCode, Json<Value>) {
+    let title = request["title"].as_str().unwrap_or_default();
+    let body = request["article"]["body"].as_str().unwrap_or_default();
+    let (ticket, _) = open_ticket(title, body);
+    (StatusCode::CREATED, Json(ticket_json(&ticket)))
+}
+
+async fn zammad_get_ticket(Path(id): Path<i32>) -> Result<Json<Value>, StatusCode> {
+    let backends = backends();
+    let ticket = backends.tickets.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(ticket_json(ticket)))
+}
+
+async fn zammad_update_ticket(
+    Path(id): Path<i32>,
+    Json(request): Json<Value>,
+) -> Result<Json<Value>, StatusCode> {
+    let mut backends = backends();
+    let ticket = backends.tickets.get_mut(&id).ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(state) = request["state"].as_str() {
+        ticket.state = state.to_string();
+    }
+    if let Some(priority_id) = request["priority_id"].as_i64() {
+        ticket.priority_id = priority_id;
+    }
+    ticket.updated_at = Utc::now();
+    Ok(Json(ticket_json(ticket)))
+}
+
+async fn zammad_create_article(
+    Json(request): Json<Value>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let mut backends = backends();
+    let id = backends.next_id();
+    let ticket_id = request["ticket_id"].as_i64().unwrap_or_default() as i32;
+    if !backends.tickets.contains_key(&ticket_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let article = Article {
+        id,
+        ticket_id,
+        body: request["body"].as_str().unwrap_or_default().to_string(),
+        internal: request["internal"].as_bool().unwrap_or_default(),
+        created_at: Utc::now(),
+    };
+    backends.articles.push(article.clone());
+    Ok((StatusCode::CREATED, Json(json!(article))))
+}
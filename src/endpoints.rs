@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use axum::{Json, extract::MatchedPath, extract::Request, middleware::Next, response::Response};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::{self, JiraConfig, JiraWebhookAuth, ZammadConfig};
+use crate::profiles;
+
+/// The webhook routes mounted by the server, with the system expected to call them.
+const WEBHOOKS: [(&str, &str); 4] = [
+    ("zammad", "/ticket-sync/zammad/create-ticket/:id"),
+    ("zammad", "/ticket-sync/zammad/update-ticket/:id"),
+    ("jira", "/ticket-sync/jira/create-ticket/:id"),
+    ("jira", "/ticket-sync/jira/update-ticket/:id"),
+];
+
+/// Profile of the systems of the file.
+const DEFAULT_PROFILE: &str = "default";
+
+/// A profile and one of its routes.
+type ProfileRoute = (String, String);
+
+/// When each route of each profile last received a webhook, since the server started.
+static LAST_RECEIVED: LazyLock<Mutex<HashMap<ProfileRoute, DateTime<Utc>>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Endpoint {
+    pub path: &'static str,
+    pub profile: String,
+    pub system: &'static str,
+    pub auth: &'static str,
+    pub last_received_at: Option<DateTime<Utc>>,
+}
+
+/// Notes when a webhook route received a request. Runs after the signature check, so
+/// rejected requests don't count.
+pub async fn record_received(request: Request, next: Next) -> Response {
+    if let Some(path) = request.extensions().get::<MatchedPath>() {
+        let profile = profiles::current().map_or_else(
+            || DEFAULT_PROFILE.to_string(),
+            |profile| profile.name.clone(),
+        );
+        LAST_RECEIVED
+            .lock()
+            .expect("endpoints lock poisoned")
+            .insert((profile, path.as_str().to_string()), Utc::now());
+    }
+    next.run(request).await
+}
+
+/// How webhooks of the system are authenticated.
+fn auth_mode(system: &str, jira: &JiraConfig, zammad: &ZammadConfig) -> &'static str {
+    match (system, &jira.webhook_auth) {
+        ("zammad", _) if zammad.webhook_secret.is_some() => "hmac",
+        ("zammad", _) if zammad.verify_webhooks => "api-verification",
+        ("jira", Some(JiraWebhookAuth::Token(_))) => "token",
+        ("jira", Some(JiraWebhookAuth::Jwt { .. })) => "jwt",
+        ("jira", None) if jira.verify_webhooks => "api-verification",
+        _ => "none",
+    }
+}
+
+/// Every webhook route of every profile, so setups can be audited without reading the
+/// configuration.
+#[utoipa::path(get, path = "/endpoints", responses((status = 200, body = Vec<Endpoint>)))]
+pub async fn list() -> Json<Vec<Endpoint>> {
+    let config = config::get();
+    let active: Vec<_> = profiles::names()
+        .into_iter()
+        .filter_map(|name| profiles::get(&name))
+        .collect();
+    let profiles = std::iter::once((DEFAULT_PROFILE.to_string(), &config.jira, &config.zammad))
+        .chain(active.iter().map(|profile| {
+            (
+                profile.name.clone(),
+                &profile.config.jira,
+                &profile.config.zammad,
+            )
+        }));
+    let last_received = LAST_RECEIVED.lock().expect("endpoints lock poisoned");
+    let mut endpoints = Vec::new();
+    for (profile, jira, zammad) in profiles {
+        endpoints.extend(WEBHOOKS.iter().map(|(system, path)| {
+            Endpoint {
+                path,
+                profile: profile.clone(),
+                system,
+                auth: auth_mode(system, jira, zammad),
+                last_received_at: last_received
+                    .get(&(profile.clone(), path.to_string()))
+                    .copied(),
+            }
+        }));
+    }
+    Json(endpoints)
+}
@@ -0,0 +1,73 @@
+//! Sends each finished sync as a JSON event to the configured `event_webhooks`, for
+//! alerting or automation platforms like n8n. Events are posted once, in the background; a
+//! receiver that's down misses them, the audit log keeps them all.
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use reqwest::Client;
+use serde_json::json;
+use tracing::warn;
+
+use crate::config::{self, SyncOutcome};
+use crate::models::db::AuditEntry;
+
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("failed to build HTTP client")
+});
+
+/// Posts the sync to each event webhook interested in its outcome.
+pub fn notify(entry: &AuditEntry) {
+    let outcome = match entry.error {
+        None => SyncOutcome::Succeeded,
+        Some(_) => SyncOutcome::Failed,
+    };
+    let config = config::get();
+    let webhooks: Vec<_> = config
+        .event_webhooks
+        .iter()
+        .filter(|webhook| webhook.on.contains(&outcome))
+        .collect();
+    if webhooks.is_empty() {
+        return;
+    }
+    let event = json!({
+        "event": match outcome {
+            SyncOutcome::Succeeded => "sync.succeeded",
+            SyncOutcome::Failed => "sync.failed",
+        },
+        "operation": entry.operation,
+        "direction": entry.direction,
+        "zammad_id": entry.zammad_id,
+        "jira_id": entry.jira_id,
+        "request_id": entry.request_id,
+        "fields": entry.fields,
+        "error": entry.error,
+        "duration_ms": entry.duration_ms,
+        "at": entry.created_at,
+    });
+    for webhook in webhooks {
+        let mut request = CLIENT.post(&webhook.url).json(&event);
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+        tokio::spawn(async move {
+            let result = request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = result {
+                // Without the URL, as its path may carry a token
+                let host = e.url().and_then(|url| url.host_str()).map(str::to_string);
+                warn!(
+                    "Failed to send the sync event to {}: {}",
+                    host.unwrap_or_default(),
+                    e.without_url()
+                );
+            }
+        });
+    }
+}
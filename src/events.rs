@@ -0,0 +1,155 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use axum::{
+    body::Body,
+    extract::{OriginalUri, Request},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use clap::{Subcommand, ValueEnum};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    anonymize::anonymize,
+    canonical::v1::Event,
+    config::{self, EventSampleConfig},
+    webhook_body,
+};
+
+#[derive(Subcommand, Debug)]
+pub enum EventsCommand {
+    /// Webhook-Payload anonymisieren, z.B. zum Anhängen an Fehlerberichte
+    Anonymize {
+        /// JSON-Datei mit dem Payload ("-" für stdin)
+        input: PathBuf,
+    },
+    /// Webhook-Payload ins kanonische Event-Format umwandeln
+    Convert {
+        /// System, von dem der Payload stammt
+        #[arg(long, value_enum)]
+        source: Source,
+
+        /// Ausgabeformat
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+
+        /// JSON-Datei mit dem Payload ("-" für stdin)
+        input: PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum Source {
+    Zammad,
+    Jira,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum Format {
+    /// Proto3-JSON-Darstellung
+    Json,
+    /// Binäres Protobuf
+    Protobuf,
+}
+
+pub fn run(command: EventsCommand) -> anyhow::Result<()> {
+    match command {
+        EventsCommand::Anonymize { input } => {
+            let mut payload: Value = serde_json::from_str(&read_input(&input)?)?;
+            anonymize(&mut payload);
+            println!("{}", serde_json::to_string_pretty(&payload)?);
+            Ok(())
+        }
+        EventsCommand::Convert {
+            source,
+            format,
+            input,
+        } => {
+            let payload = read_input(&input)?;
+            let event = match source {
+                Source::Zammad => Event::from_zammad_webhook(&serde_json::from_str(&payload)?),
+                Source::Jira => Event::from_jira_webhook(&serde_json::from_str(&payload)?),
+            };
+            match format {
+                Format::Json => println!("{}", serde_json::to_string_pretty(&event.to_json())?),
+                Format::Protobuf => std::io::stdout().write_all(&event.to_protobuf())?,
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Reads a file, or stdin for "-".
+fn read_input(input: &Path) -> anyhow::Result<String> {
+    let mut content = String::new();
+    if input.as_os_str() == "-" {
+        std::io::stdin().read_to_string(&mut content)?;
+    } else {
+        content = std::fs::read_to_string(input)?;
+    }
+    Ok(content)
+}
+
+static RECEIVED_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Stores every n-th inbound webhook payload in the configured sample directory.
+pub async fn sample_events(
+    OriginalUri(uri): OriginalUri,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(sample_config) = &config::get().event_samples else {
+        return next.run(request).await;
+    };
+    let received = RECEIVED_EVENTS.fetch_add(1, Ordering::Relaxed);
+    if !received.is_multiple_of(sample_config.every.max(1)) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = match webhook_body::read(body).await {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+
+    // The last path segment is the webhook ID, which must not end up in the samples
+    let name = uri
+        .path()
+        .trim_matches('/')
+        .rsplit_once('/')
+        .map_or(uri.path(), |(path, _id)| path)
+        .replace('/', "_");
+    if let Err(e) = store_sample(sample_config, &name, &bytes).await {
+        warn!("Failed to store event sample: {}", e);
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+async fn store_sample(
+    sample_config: &EventSampleConfig,
+    name: &str,
+    body: &[u8],
+) -> anyhow::Result<()> {
+    let mut payload: Value = serde_json::from_slice(body)?;
+    if sample_config.anonymize {
+        anonymize(&mut payload);
+    }
+
+    tokio::fs::create_dir_all(&sample_config.directory).await?;
+    let path = sample_config.directory.join(format!(
+        "{}-{}.json",
+        Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+        name
+    ));
+    tokio::fs::write(&path, serde_json::to_vec_pretty(&payload)?).await?;
+    info!("Stored event sample {}", path.display());
+    Ok(())
+}
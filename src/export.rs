@@ -0,0 +1,378 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use arrow_array::{
+    ArrayRef, RecordBatch,
+    builder::{BinaryBuilder, Float64Builder, Int64Builder, StringBuilder},
+};
+use arrow_schema::{DataType, Field, Schema};
+use clap::{Args, ValueEnum};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use serde_json::json;
+use sqlx::{
+    Column, Row, SqlitePool, TypeInfo, ValueRef,
+    sqlite::{SqliteJournalMode, SqliteRow},
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::db;
+use crate::output::{OutputFormat, Progress};
+
+/// Rows read from the snapshot at a time.
+const PAGE_SIZE: i64 = 500;
+
+#[derive(Args, Debug)]
+pub struct ExportDbArgs {
+    /// Format der Exportdatei
+    #[arg(long, value_enum, default_value_t = ExportFormat::Sqldump)]
+    format: ExportFormat,
+
+    /// Zieldatei ("-" für stdout), bei Parquet ein Verzeichnis
+    file: PathBuf,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ExportFormat {
+    /// SQL-Statements, die sich in SQLite und die meisten anderen Datenbanken laden lassen
+    Sqldump,
+    /// Eine Parquet-Datei je Tabelle, für Analysewerkzeuge wie DuckDB oder Spark
+    Parquet,
+}
+
+/// Exports every table of the database. The export reads a snapshot, so the running
+/// instance can keep writing while it runs and the result is consistent.
+pub async fn run(args: ExportDbArgs, output: OutputFormat) -> Result<()> {
+    // The JSON records would end up in the export
+    if output == OutputFormat::Json && args.file.as_os_str() == "-" {
+        bail!("--output json needs a file to export to");
+    }
+    if matches!(args.format, ExportFormat::Parquet) && args.file.as_os_str() == "-" {
+        bail!("--format parquet needs a directory to export to");
+    }
+    let progress = Progress::new("export-db", output);
+    let snapshot = std::env::temp_dir().join(format!("ticket-sync-export-{}.db", Uuid::new_v4()));
+    let result = export(&args, &snapshot, &progress).await;
+    if let Err(e) = fs::remove_file(&snapshot) {
+        warn!(
+            "Failed to remove the snapshot {}: {}",
+            snapshot.display(),
+            e
+        );
+    }
+
+    let rows = result?;
+    progress.finish(
+        &format!("Exported {} rows", rows),
+        json!({ "rows": rows, "format": format!("{:?}", args.format).to_lowercase() }),
+    );
+    Ok(())
+}
+
+async fn export(args: &ExportDbArgs, snapshot: &Path, progress: &Progress) -> Result<u64> {
+    let file = db::file();
+    let live = SqlitePool::connect_with(db::connect_options(&file)?.read_only(true))
+        .await
+        .with_context(|| format!("failed to open {}", file))?;
+    sqlx::query("VACUUM INTO ?")
+        .bind(snapshot.to_string_lossy())
+        .execute(&live)
+        .await
+        .context("failed to snapshot the database")?;
+    live.close().await;
+    info!("Exporting snapshot {}", snapshot.display());
+
+    // The snapshot is encrypted with the same key as the database, but not in WAL mode, which
+    // a read-only connection can't switch to
+    let snapshot_path = snapshot.to_string_lossy();
+    let options = db::connect_options(&snapshot_path)?
+        .journal_mode(SqliteJournalMode::Delete)
+        .read_only(true);
+    let pool = SqlitePool::connect_with(options).await?;
+    let tables: Vec<(String, String)> = sqlx::query_as(
+        "SELECT name, sql FROM sqlite_master
+         WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )
+    .fetch_all(&pool)
+    .await?;
+    let mut total = 0;
+    for (table, _) in &tables {
+        let count: i64 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", quote_ident(table)))
+                .fetch_one(&pool)
+                .await?;
+        total += count as u64;
+    }
+
+    let exported = match args.format {
+        ExportFormat::Sqldump => write_sqldump(&args.file, &pool, &tables, total, progress).await,
+        ExportFormat::Parquet => write_parquet(&args.file, &pool, &tables, total, progress).await,
+    };
+    pool.close().await;
+    exported
+}
+
+/// Reads a page of the table, in the order it was written.
+async fn page(pool: &SqlitePool, table: &str, offset: i64) -> Result<Vec<SqliteRow>> {
+    Ok(sqlx::query(&format!(
+        "SELECT * FROM {} ORDER BY rowid LIMIT ? OFFSET ?",
+        quote_ident(table)
+    ))
+    .bind(PAGE_SIZE)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?)
+}
+
+async fn write_sqldump(
+    file: &Path,
+    pool: &SqlitePool,
+    tables: &[(String, String)],
+    total: u64,
+    progress: &Progress,
+) -> Result<u64> {
+    let mut out: Box<dyn Write> = if file.as_os_str() == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        let file =
+            File::create(file).with_context(|| format!("failed to create {}", file.display()))?;
+        Box::new(BufWriter::new(file))
+    };
+
+    writeln!(out, "BEGIN TRANSACTION;")?;
+    let mut exported = 0;
+    for (table, schema) in tables {
+        writeln!(out, "{};", schema)?;
+        let mut offset = 0;
+        loop {
+            let rows = page(pool, table, offset).await?;
+            for row in &rows {
+                writeln!(out, "{}", insert_statement(table, row)?)?;
+            }
+            exported += rows.len() as u64;
+            progress.update(exported, Some(total));
+            if (rows.len() as i64) < PAGE_SIZE {
+                break;
+            }
+            offset += PAGE_SIZE;
+        }
+    }
+    // Created after the data, which is faster to load without them
+    let indexes: Vec<String> = sqlx::query_scalar(
+        "SELECT sql FROM sqlite_master WHERE type = 'index' AND sql IS NOT NULL ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await?;
+    for index in &indexes {
+        writeln!(out, "{};", index)?;
+    }
+    writeln!(out, "COMMIT;")?;
+    out.flush()?;
+    Ok(exported)
+}
+
+/// Writes `<table>.parquet` into the directory for each table, one row group per page.
+async fn write_parquet(
+    directory: &Path,
+    pool: &SqlitePool,
+    tables: &[(String, String)],
+    total: u64,
+    progress: &Progress,
+) -> Result<u64> {
+    fs::create_dir_all(directory)
+        .with_context(|| format!("failed to create {}", directory.display()))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut exported = 0;
+    for (table, _) in tables {
+        let schema = Arc::new(parquet_schema(pool, table).await?);
+        let path = directory.join(format!("{}.parquet", table));
+        let file =
+            File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties.clone()))?;
+        let mut offset = 0;
+        loop {
+            let rows = page(pool, table, offset).await?;
+            if !rows.is_empty() {
+                writer.write(&record_batch(&schema, &rows)?)?;
+            }
+            exported += rows.len() as u64;
+            progress.update(exported, Some(total));
+            if (rows.len() as i64) < PAGE_SIZE {
+                break;
+            }
+            offset += PAGE_SIZE;
+        }
+        writer
+            .close()
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(exported)
+}
+
+/// The Arrow schema of a table. SQLite stores each value with its own type, so a column's
+/// type follows the values stored in it: integers, reals (or both), blobs, and text for
+/// anything mixed. Empty columns get the type their declaration implies.
+async fn parquet_schema(pool: &SqlitePool, table: &str) -> Result<Schema> {
+    let columns: Vec<(String, String)> =
+        sqlx::query_as("SELECT name, type FROM pragma_table_info(?) ORDER BY cid")
+            .bind(table)
+            .fetch_all(pool)
+            .await?;
+    let mut fields = Vec::with_capacity(columns.len());
+    for (column, declared) in columns {
+        let stored: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT DISTINCT typeof({0}) FROM {1} WHERE {0} IS NOT NULL",
+            quote_ident(&column),
+            quote_ident(table)
+        ))
+        .fetch_all(pool)
+        .await?;
+        let data_type = match stored.as_slice() {
+            [] => declared_type(&declared),
+            [stored] if stored == "integer" => DataType::Int64,
+            [stored] if stored == "blob" => DataType::Binary,
+            stored if stored.iter().all(|t| t == "integer" || t == "real") => DataType::Float64,
+            _ => DataType::Utf8,
+        };
+        fields.push(Field::new(column, data_type, true));
+    }
+    Ok(Schema::new(fields))
+}
+
+/// The type of a column's affinity, following SQLite's rules for declared types.
+fn declared_type(declared: &str) -> DataType {
+    let declared = declared.to_uppercase();
+    if declared.contains("INT") {
+        DataType::Int64
+    } else if declared.contains("CHAR") || declared.contains("CLOB") || declared.contains("TEXT") {
+        DataType::Utf8
+    } else if declared.is_empty() || declared.contains("BLOB") {
+        DataType::Binary
+    } else if declared.contains("REAL") || declared.contains("FLOA") || declared.contains("DOUB") {
+        DataType::Float64
+    } else {
+        DataType::Utf8
+    }
+}
+
+fn record_batch(schema: &Arc<Schema>, rows: &[SqliteRow]) -> Result<RecordBatch> {
+    let columns = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, field)| column(field.data_type(), rows, index))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+fn column(data_type: &DataType, rows: &[SqliteRow], index: usize) -> Result<ArrayRef> {
+    let is_null = |row: &SqliteRow| -> Result<bool> { Ok(row.try_get_raw(index)?.is_null()) };
+    Ok(match data_type {
+        DataType::Int64 => {
+            let mut builder = Int64Builder::with_capacity(rows.len());
+            for row in rows {
+                builder.append_option(if is_null(row)? {
+                    None
+                } else {
+                    Some(row.try_get_unchecked::<i64, _>(index)?)
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::with_capacity(rows.len());
+            for row in rows {
+                builder.append_option(if is_null(row)? {
+                    None
+                } else {
+                    Some(row.try_get_unchecked::<f64, _>(index)?)
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Binary => {
+            let mut builder = BinaryBuilder::new();
+            for row in rows {
+                builder.append_option(if is_null(row)? {
+                    None
+                } else {
+                    Some(row.try_get_unchecked::<Vec<u8>, _>(index)?)
+                });
+            }
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            for row in rows {
+                builder.append_option(if is_null(row)? {
+                    None
+                } else {
+                    Some(text(row, index)?)
+                });
+            }
+            Arc::new(builder.finish())
+        }
+    })
+}
+
+/// The value as text, for columns holding values of different types.
+fn text(row: &SqliteRow, index: usize) -> Result<String> {
+    let raw = row.try_get_raw(index)?;
+    Ok(match raw.type_info().name() {
+        "INTEGER" => row.try_get_unchecked::<i64, _>(index)?.to_string(),
+        "REAL" => row.try_get_unchecked::<f64, _>(index)?.to_string(),
+        "BLOB" => {
+            String::from_utf8_lossy(&row.try_get_unchecked::<Vec<u8>, _>(index)?).into_owned()
+        }
+        _ => row.try_get_unchecked::<String, _>(index)?,
+    })
+}
+
+fn insert_statement(table: &str, row: &SqliteRow) -> Result<String> {
+    let columns: Vec<String> = row
+        .columns()
+        .iter()
+        .map(|column| quote_ident(column.name()))
+        .collect();
+    let values = (0..row.len())
+        .map(|index| literal(row, index))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!(
+        "INSERT INTO {} ({}) VALUES ({});",
+        quote_ident(table),
+        columns.join(", "),
+        values.join(", ")
+    ))
+}
+
+/// The value as SQL literal, according to the type SQLite stored it with.
+fn literal(row: &SqliteRow, index: usize) -> Result<String> {
+    let raw = row.try_get_raw(index)?;
+    if raw.is_null() {
+        return Ok("NULL".to_string());
+    }
+    let type_name = raw.type_info().name().to_string();
+    Ok(match type_name.as_str() {
+        "INTEGER" => row.try_get_unchecked::<i64, _>(index)?.to_string(),
+        "REAL" => row.try_get_unchecked::<f64, _>(index)?.to_string(),
+        "BLOB" => {
+            let bytes: Vec<u8> = row.try_get_unchecked(index)?;
+            let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+            format!("X'{}'", hex)
+        }
+        _ => quote(&row.try_get_unchecked::<String, _>(index)?),
+    })
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
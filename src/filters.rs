@@ -0,0 +1,92 @@
+use serde::Serialize;
+
+use crate::config::{self, SyncRule};
+use crate::decisions;
+use crate::models::{jira::JiraApiIssue, zammad::ZammadTicket};
+
+/// The attributes rules match on, taken from a Zammad ticket or a Jira issue.
+#[derive(Serialize)]
+struct Candidate<'a> {
+    group: Option<&'a str>,
+    tags: Vec<&'a str>,
+    /// Name and ID, so rules can use either
+    priorities: Vec<String>,
+    state: Option<&'a str>,
+    customer_email: Option<&'a str>,
+}
+
+impl SyncRule {
+    /// A rule matches if every criterion it sets matches; unset criteria match everything.
+    fn matches(&self, candidate: &Candidate) -> bool {
+        let any = |allowed: &[String], value: Option<&str>| {
+            allowed.is_empty()
+                || value.is_some_and(|value| allowed.iter().any(|a| a.eq_ignore_ascii_case(value)))
+        };
+
+        any(&self.groups, candidate.group)
+            && any(&self.states, candidate.state)
+            && (self.tags.is_empty() || candidate.tags.iter().any(|tag| any(&self.tags, Some(tag))))
+            && (self.priorities.is_empty()
+                || candidate
+                    .priorities
+                    .iter()
+                    .any(|priority| any(&self.priorities, Some(priority))))
+            && any(
+                &self.customer_domains,
+                candidate
+                    .customer_email
+                    .and_then(|email| email.rsplit_once('@'))
+                    .map(|(_, domain)| domain),
+            )
+    }
+}
+
+/// Whether an item passes `rules`: without rules everything is synced, otherwise the item
+/// has to match at least one of them.
+fn passes(name: &str, rules: &[SyncRule], candidate: &Candidate) -> bool {
+    let passed = rules.is_empty() || rules.iter().any(|rule| rule.matches(candidate));
+    decisions::record(name, candidate, passed);
+    passed
+}
+
+/// Whether a Jira issue should be created for the Zammad ticket.
+pub fn is_zammad_ticket_synced(ticket: &ZammadTicket) -> bool {
+    let candidate = Candidate {
+        group: ticket.group.as_ref().map(|group| group.name.as_str()),
+        tags: ticket.tags.iter().map(String::as_str).collect(),
+        priorities: [
+            Some((ticket.priority.id as i32).to_string()),
+            ticket.priority.name.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect(),
+        state: Some(ticket.state.as_str()),
+        customer_email: ticket
+            .customer
+            .as_ref()
+            .map(|customer| customer.email.as_str()),
+    };
+    passes("rules.zammad", &config::get().rules.zammad, &candidate)
+}
+
+/// Whether the Jira issue is synced to Zammad. Groups match the project key and tags
+/// match labels.
+pub fn is_jira_issue_synced(issue: &JiraApiIssue) -> bool {
+    let fields = &issue.fields;
+    let candidate = Candidate {
+        group: issue.project_key(),
+        tags: issue.labels(),
+        priorities: fields
+            .priority
+            .iter()
+            .map(|priority| priority.name.clone())
+            .collect(),
+        state: fields.status.as_ref().map(|status| status.name.as_str()),
+        customer_email: fields
+            .reporter
+            .as_ref()
+            .and_then(|reporter| reporter.email_address.as_deref()),
+    };
+    passes("rules.jira", &config::get().rules.jira, &candidate)
+}
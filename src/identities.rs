@@ -0,0 +1,81 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::models::{
+    api_request::{JiraSearchUsersRequest, ZammadListUsersRequest},
+    db::{DB, UserMapping},
+};
+
+const USERS_PER_PAGE: u32 = 100;
+
+/// Links every unmapped Zammad user to the Jira account with the same email address.
+/// Returns the number of mappings created.
+pub async fn provision(db: &DB) -> anyhow::Result<usize> {
+    let mapped: HashSet<i64> = db
+        .get_user_mappings()
+        .await?
+        .iter()
+        .map(|mapping| mapping.zammad_user_id)
+        .collect();
+
+    let mut created = 0;
+    for page in 1.. {
+        let users = ZammadListUsersRequest::new(page, USERS_PER_PAGE)
+            .submit()
+            .await?;
+        for user in &users {
+            let Some(email) = user.email.as_deref().filter(|email| !email.is_empty()) else {
+                continue;
+            };
+            if mapped.contains(&user.id) {
+                continue;
+            }
+
+            // The search also matches names, so only exact address matches count
+            let account_id = JiraSearchUsersRequest::new(email)
+                .submit()
+                .await?
+                .into_iter()
+                .find(|account| {
+                    account
+                        .email_address
+                        .as_deref()
+                        .is_some_and(|address| address.eq_ignore_ascii_case(email))
+                })
+                .and_then(|account| account.account_id);
+            let Some(jira_account_id) = account_id else {
+                continue;
+            };
+
+            let mapping = UserMapping {
+                zammad_user_id: user.id,
+                jira_account_id,
+                email: Some(email.to_string()),
+            };
+            // The Jira account may already be mapped to another Zammad user
+            match db.create_user_mapping(&mapping).await {
+                Ok(_) => created += 1,
+                Err(e) => warn!("Failed to map Zammad user {}: {}", user.id, e),
+            }
+        }
+        if users.len() < USERS_PER_PAGE as usize {
+            break;
+        }
+    }
+
+    info!("User provisioning finished: {} mappings created", created);
+    Ok(created)
+}
+
+/// Runs [`provision`] every `minutes`, for the lifetime of the server.
+pub async fn provision_periodically(db: DB, minutes: u64) {
+    let mut interval = tokio::time::interval(Duration::from_secs(minutes.max(1) * 60));
+    loop {
+        interval.tick().await;
+        if let Err(e) = provision(&db).await {
+            warn!("Failed to provision user mappings: {}", e);
+        }
+    }
+}
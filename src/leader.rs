@@ -0,0 +1,93 @@
+//! With several replicas sharing the store, the reconciliation and the scheduled backfills run
+//! on one of them only, the leader: it holds a lease in the database and renews it while it
+//! runs. If it stops renewing, e.g. after a crash, another replica takes the lease over once it
+//! expired; on shutdown it's released right away. A single instance leads as well, after a
+//! crash once the lease of its previous run expired.
+
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use crate::models::db::{DB, Lease};
+use crate::shutdown;
+
+const LEASE: &str = "background-jobs";
+/// How long the lease lasts without being renewed
+const LEASE_TTL: Duration = Duration::from_secs(30);
+const RENEW_EVERY: Duration = Duration::from_secs(10);
+
+static IS_LEADER: AtomicBool = AtomicBool::new(false);
+
+/// The host name and process ID, which tell the replicas apart as lease holders.
+static INSTANCE: LazyLock<String> = LazyLock::new(|| {
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "localhost".to_string());
+    format!("{}:{}", host, std::process::id())
+});
+
+pub fn instance() -> &'static str {
+    &INSTANCE
+}
+
+/// Whether this instance runs the background jobs that only one replica may run.
+pub fn is_leader() -> bool {
+    IS_LEADER.load(Ordering::Relaxed)
+}
+
+/// Tries to take the lease, then keeps renewing or trying to take it over until the shutdown.
+/// The first attempt is made before returning, so jobs started afterwards know whether they
+/// lead.
+pub async fn spawn(db: DB) {
+    elect(&db).await;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RENEW_EVERY);
+        // The first tick completes right away
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => elect(&db).await,
+                _ = shutdown::stopping() => return,
+            }
+        }
+    });
+}
+
+async fn elect(db: &DB) {
+    let leader = match db.acquire_lease(LEASE, instance(), LEASE_TTL).await {
+        Ok(leader) => leader,
+        Err(e) => {
+            // Another replica takes over once the lease expired, so stop before that
+            warn!("Failed to renew the lease of the background jobs: {:#}", e);
+            false
+        }
+    };
+    if leader != IS_LEADER.swap(leader, Ordering::Relaxed) {
+        if leader {
+            info!("Leading the background jobs as {}", instance());
+        } else {
+            info!("No longer leading the background jobs");
+        }
+    }
+}
+
+/// Releases the lease on shutdown, so another replica takes over without waiting for it to
+/// expire.
+pub async fn resign(db: &DB) {
+    if !IS_LEADER.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    if let Err(e) = db.release_lease(LEASE, instance()).await {
+        warn!("Failed to release the lease of the background jobs: {}", e);
+    }
+}
+
+/// The current holder of the lease, if any holds it.
+pub async fn current(db: &DB) -> anyhow::Result<Option<Lease>> {
+    db.get_lease(LEASE).await
+}
@@ -0,0 +1,339 @@
+//! Syncs Zammad tickets with Jira issues. The binary runs `run`; applications embedding the
+//! connector can keep the links, comment mappings, webhook deliveries and the audit log in
+//! their own store by implementing `MappingStore` and opening the database with
+//! `DB::with_store`, once the configuration is loaded with `load_config`.
+
+mod activity;
+mod admin;
+mod allowlist;
+mod anonymize;
+mod archive;
+mod assets;
+mod audit;
+mod backfill;
+mod canonical;
+mod checklists;
+mod circuit_breaker;
+mod config;
+mod conflicts;
+mod dashboard;
+mod dead_letters;
+mod decisions;
+mod dedup;
+mod demo;
+mod endpoints;
+mod event_webhooks;
+mod events;
+mod export;
+mod filters;
+mod identities;
+mod leader;
+mod locks;
+mod logging;
+mod lookup;
+mod maintenance;
+mod metrics;
+mod models;
+mod openapi;
+mod outbox;
+mod output;
+mod pause;
+mod profiles;
+mod purge;
+mod rate_limit;
+mod reconcile;
+mod redact;
+mod reload;
+mod replay;
+mod request_id;
+mod restrictions;
+mod retry;
+mod scheduler;
+mod secrets;
+mod shutdown;
+mod signatures;
+mod smoke_test;
+mod state;
+mod stats;
+mod template_helpers;
+mod templates;
+mod ticket_numbers;
+mod tls;
+mod webhook_body;
+
+use std::{net::SocketAddr, path::PathBuf, process::ExitCode, time::Duration};
+
+pub use config::init as load_config;
+pub use models::db::{AuditEntry, AuditFilter, DB, Lease};
+pub use models::store::{MappingStore, ReplicaLock};
+
+use axum::{Router, middleware, routing::get};
+use models::{
+    db, jira,
+    zammad::{self},
+};
+
+use clap::{Parser, Subcommand};
+use logging::LogFormat;
+use output::{EXIT_CONFIG, EXIT_FAILURE, OutputFormat};
+use signatures::WebhookIds;
+use tracing::info;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, subcommand_negates_reqs = true)]
+struct Cli {
+    /// ID, die Zammad in der Webhook-URL benutzt
+    #[arg(long, env = "ZAMMAD_ID", required_unless_present = "demo")]
+    zammad_id: Option<String>,
+
+    /// ID, die Jira (CUN) in der Webhook-URL benutzt
+    #[arg(long, env = "JIRA_ID", required_unless_present = "demo")]
+    jira_id: Option<String>,
+
+    /// Port (Default 8080)
+    #[arg(short, long, default_value_t = 8000)]
+    port: u16,
+
+    /// Mit eingebauten Fake-Servern für Jira und Zammad und Beispieltickets starten
+    #[arg(long)]
+    demo: bool,
+
+    /// Eingebettete Assets und Migrationen in ein Verzeichnis schreiben
+    #[arg(long, exclusive = true, value_name = "DIR")]
+    extract_assets: Option<PathBuf>,
+
+    /// Konfigurationsdatei; ohne werden config.yml im Arbeitsverzeichnis und
+    /// /etc/ticket-sync/config.yml gesucht, sonst die TSS_-Variablen gelesen
+    #[arg(long, global = true, env = "TSS_CONFIG", value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// SQLite-Datenbankdatei oder postgres://- bzw. mysql://-URL; hat Vorrang vor `db_path`
+    #[arg(long, global = true, env = "DB_PATH", value_name = "PATH")]
+    db_path: Option<String>,
+
+    /// Ausgabeformat der Unterbefehle
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Log-Level oder Filter je Modul, z. B. `info,sqlx=warn,ticket_connector::models::api_request=debug`
+    #[arg(long, global = true, env = "RUST_LOG", value_parser = logging::parse_filter)]
+    log_level: Option<String>,
+
+    /// Format der Logs
+    #[arg(long, global = true, env = "LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Zugangsdaten und E-Mail-Adressen in Logs nicht maskieren, nur zur lokalen Fehlersuche
+    #[arg(long, global = true, env = "LOG_UNREDACTED")]
+    log_unredacted: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Bestehende Tickets/Issues ins jeweils andere System übernehmen
+    Backfill(backfill::BackfillArgs),
+    /// Fehlgeschlagene Webhooks auflisten und erneut synchronisieren
+    DeadLetters {
+        #[command(subcommand)]
+        command: dead_letters::DeadLettersCommand,
+    },
+    /// Gesamte Datenbank für Auswertungen exportieren
+    ExportDb(export::ExportDbArgs),
+    /// Gespeicherte Webhook-Payloads bearbeiten
+    Events {
+        #[command(subcommand)]
+        command: events::EventsCommand,
+    },
+    /// Alle gespeicherten Daten zu einem Ticket oder einer E-Mail-Adresse löschen (DSGVO)
+    Purge(purge::PurgeArgs),
+    /// Archivierte Webhooks erneut synchronisieren, z.B. nach einer Konfigurationskorrektur
+    Replay(archive::ReplayArgs),
+    /// Testticket einmal durch beide Systeme schicken und wieder löschen
+    SmokeTest(smoke_test::SmokeTestArgs),
+}
+
+/// Runs the command given on the command line, or the server without one.
+pub async fn run() -> ExitCode {
+    // a) CLI
+    let cli = Cli::parse();
+
+    // b) Logging
+    logging::init(
+        cli.log_format,
+        cli.command.is_some(),
+        cli.log_level.as_deref(),
+        !cli.log_unredacted,
+    );
+
+    let output = cli.output;
+    if let Some(target) = cli.extract_assets {
+        return exit(output, assets::extract(&target), EXIT_FAILURE);
+    }
+    // The demo writes its own configuration, pointing to its fake servers
+    if cli.demo
+        && let Err(e) = demo::start(cli.port).await
+    {
+        return exit(output, Err(e), EXIT_FAILURE);
+    }
+    // The command line and RUST_LOG take precedence over the configuration
+    let configured = config::init(if cli.demo { None } else { cli.config })
+        .await
+        .and_then(|_| templates::init(&config::get().templates))
+        .and_then(|_| match (&cli.log_level, &config::get().log_level) {
+            (None, Some(filter)) => logging::set_filter(filter),
+            _ => Ok(()),
+        });
+    if configured.is_err() {
+        return exit(output, configured, EXIT_CONFIG);
+    }
+    // The demo keeps its database next to its configuration
+    if let (false, Some(path)) = (cli.demo, cli.db_path) {
+        db::set_path(path);
+    }
+
+    // c) Subcommands
+    let result = match cli.command {
+        Some(Command::Backfill(args)) => backfill::run(args, output).await,
+        Some(Command::DeadLetters { command }) => dead_letters::run(command, output).await,
+        Some(Command::Events { command }) => events::run(command),
+        Some(Command::ExportDb(args)) => export::run(args, output).await,
+        Some(Command::Purge(args)) => purge::run(args, output).await,
+        Some(Command::Replay(args)) => archive::run(args, output).await,
+        Some(Command::SmokeTest(args)) => smoke_test::run(args, output).await,
+        None => {
+            let ids = match (cli.demo, cli.zammad_id, cli.jira_id) {
+                (false, Some(zammad), Some(jira)) => WebhookIds { zammad, jira },
+                // The fakes of the demo use their own ID
+                _ => WebhookIds {
+                    zammad: demo::WEBHOOK_ID.to_string(),
+                    jira: demo::WEBHOOK_ID.to_string(),
+                },
+            };
+            serve(cli.port, ids).await
+        }
+    };
+    exit(output, result, EXIT_FAILURE)
+}
+
+/// Reports a failure and turns it into the exit code `code`.
+fn exit(output: OutputFormat, result: anyhow::Result<()>, code: u8) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            output::report_error(output, &e);
+            ExitCode::from(code)
+        }
+    }
+}
+
+async fn serve(port: u16, ids: WebhookIds) -> anyhow::Result<()> {
+    let state = state::AppState::new().await?;
+    // Settings of the server and its background jobs take a restart
+    let config = config::get();
+    profiles::load(&state.db).await?;
+    pause::load(&state.db).await?;
+
+    // d) Router
+    let mut app = Router::new()
+        .nest("/ticket-sync/zammad", zammad::router())
+        .nest("/ticket-sync/jira", jira::router())
+        .layer(webhook_body::limit(config.webhook_body_limit))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            dedup::deduplicate_deliveries,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            replay::reject_stale_events,
+        ))
+        .layer(middleware::from_fn(events::sample_events))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            archive::archive_payloads,
+        ))
+        .layer(middleware::from_fn(endpoints::record_received))
+        // Outermost, so unauthenticated webhooks don't reach anything that reads or stores them
+        .layer(middleware::from_fn(signatures::verify_zammad_signature))
+        .layer(middleware::from_fn(signatures::verify_jira_webhook))
+        .layer(middleware::from_fn(move |request, next| {
+            signatures::verify_webhook_id(ids.clone(), request, next)
+        }))
+        .layer(middleware::from_fn(rate_limit::limit_webhooks))
+        .layer(middleware::from_fn(allowlist::restrict_senders))
+        .layer(middleware::from_fn(tls::require_client_certificate))
+        // Around everything, so rejections can be correlated too
+        .layer(middleware::from_fn(request_id::assign));
+    // Added after the layers, as metrics, admin and lookup requests are neither deduplicated
+    // nor sampled
+    app = app.route("/metrics", get(metrics::render));
+    app = app.merge(openapi::router());
+    if config.admin.is_some() {
+        app = app.nest("/admin", admin::router());
+    }
+    if config.lookup.is_some() {
+        app = app.nest("/api", lookup::router());
+    }
+    if demo::is_running() {
+        app = app.nest("/demo", demo::router());
+    }
+
+    // e) Background jobs; reconciliation and scheduled backfills only on the leader
+    let workers = outbox::spawn_workers(state.db.clone(), &config.outbox);
+    leader::spawn(state.db.clone()).await;
+    if let Some(status_pages) = &config.status_pages {
+        tokio::spawn(maintenance::poll_periodically(
+            state.db.clone(),
+            status_pages.clone(),
+        ));
+    }
+    if let Some(archive) = &config.archive {
+        tokio::spawn(archive::prune_periodically(
+            state.db.clone(),
+            archive.clone(),
+        ));
+    }
+    tokio::spawn(dedup::prune_periodically(state.db.clone()));
+    if let Some(user_mappings) = &config.user_mappings {
+        tokio::spawn(identities::provision_periodically(
+            state.db.clone(),
+            user_mappings.provision_every,
+        ));
+    }
+    scheduler::spawn_backfills(&state.db, &config.scheduled_backfills);
+    if let Some(reconciliation) = &config.reconciliation {
+        tokio::spawn(reconcile::run_periodically(
+            state.db.clone(),
+            reconciliation.clone(),
+        ));
+    }
+
+    tokio::spawn(reload::run());
+
+    // f) Server, until SIGTERM or SIGINT
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tokio::spawn(shutdown::signal());
+    let db = state.db.clone();
+    if let Some(tls) = &config.tls {
+        let acceptor = tls::acceptor(tls)?;
+        tls::serve(listener, acceptor, app.with_state(state)).await?;
+    } else {
+        axum::serve(
+            listener,
+            app.with_state(state)
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown::stopping())
+        .await?;
+    }
+
+    // g) Drain
+    outbox::drain(workers, Duration::from_secs(config.outbox.drain_timeout)).await;
+    leader::resign(&db).await;
+    db.close().await;
+    info!("Shut down");
+    Ok(())
+}
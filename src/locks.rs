@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+use crate::models::db::DB;
+use crate::models::store::ReplicaLock;
+
+type Locks = Mutex<HashMap<i32, Arc<AsyncMutex<()>>>>;
+
+static LOCKS: OnceLock<Locks> = OnceLock::new();
+
+/// Held while a webhook of a ticket is processed; other webhooks of the same ticket wait
+/// for it to be dropped, also on other replicas sharing the store.
+pub struct TicketLock {
+    zammad_ticket_id: i32,
+    mutex: Arc<AsyncMutex<()>>,
+    _replica: Option<ReplicaLock>,
+    _guard: OwnedMutexGuard<()>,
+}
+
+/// Waits until no other webhook of the Zammad ticket is processed, so e.g. an update can't
+/// overtake the creation of the Jira issue. Waiters are served in order of arrival; with a
+/// store shared by replicas, only the first waiter of this process then waits for the
+/// others.
+pub async fn lock_ticket(db: &DB, zammad_ticket_id: i32) -> anyhow::Result<TicketLock> {
+    let mutex = LOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .expect("ticket lock poisoned")
+        .entry(zammad_ticket_id)
+        .or_default()
+        .clone();
+    let guard = mutex.clone().lock_owned().await;
+    let mut lock = TicketLock {
+        zammad_ticket_id,
+        mutex,
+        _replica: None,
+        _guard: guard,
+    };
+    // Taken once the lock is constructed, so it's cleaned up if this fails
+    lock._replica = db.lock_ticket_across_replicas(zammad_ticket_id).await?;
+    Ok(lock)
+}
+
+impl Drop for TicketLock {
+    fn drop(&mut self) {
+        let mut locks = LOCKS
+            .get_or_init(Default::default)
+            .lock()
+            .expect("ticket lock poisoned");
+        // Held by the map, `mutex` and the guard only, so nobody is waiting
+        if Arc::strong_count(&self.mutex) == 3 {
+            locks.remove(&self.zammad_ticket_id);
+        }
+    }
+}
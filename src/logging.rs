@@ -0,0 +1,122 @@
+//! Logs are human-readable by default; as JSON, one object per line, they can be ingested
+//! by Loki or ELK. Syncs run in a `sync` span with the ticket, the issue, the direction and
+//! the job and request ID, which JSON lines carry as `span`, so every line of a sync can be found by them.
+//!
+//! The level is an `EnvFilter` directive, e.g. `info,sqlx=warn`, taken from `--log-level`,
+//! `RUST_LOG` or the configuration, in that order. Logging starts before the configuration is
+//! read, so its filter replaces the initial one.
+
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::Value;
+use tracing::{Span, field};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use crate::config::Direction;
+use crate::redact::Redacting;
+
+/// Filter without `--log-level`, `RUST_LOG` or `log_level` in the configuration.
+const DEFAULT_FILTER: &str = "info";
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Lesbare Zeilen
+    #[default]
+    Text,
+    /// Ein JSON-Objekt pro Zeile, für Loki/ELK
+    Json,
+}
+
+/// Installs the global subscriber. Subcommands log to stderr, so stdout only carries their
+/// output. Credentials and email addresses are masked unless `redact` is unset.
+pub fn init(format: LogFormat, stderr: bool, filter: Option<&str>, redact: bool) {
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(filter.unwrap_or(DEFAULT_FILTER)));
+    let writer = match (stderr, redact) {
+        (true, true) => BoxMakeWriter::new(Redacting(std::io::stderr)),
+        (true, false) => BoxMakeWriter::new(std::io::stderr),
+        (false, true) => BoxMakeWriter::new(Redacting(std::io::stdout)),
+        (false, false) => BoxMakeWriter::new(std::io::stdout),
+    };
+    let logging = tracing_subscriber::fmt::layer().with_writer(writer);
+    let logging = match format {
+        LogFormat::Text => logging.boxed(),
+        LogFormat::Json => logging.json().with_span_list(false).boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(logging)
+        .init();
+    let _ = FILTER.set(handle);
+}
+
+/// Checks a filter given on the command line, e.g. `info,sqlx=warn`.
+pub fn parse_filter(filter: &str) -> Result<String, String> {
+    EnvFilter::try_new(filter)
+        .map(|_| filter.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Replaces the filter of the running subscriber.
+pub fn set_filter(filter: &str) -> Result<()> {
+    let filter = EnvFilter::try_new(filter)?;
+    if let Some(handle) = FILTER.get() {
+        handle.reload(filter)?;
+    }
+    Ok(())
+}
+
+/// The span a stored webhook of `operation` is synced in. The Jira issue is filled in once
+/// known, see [`record_jira_issue`].
+pub fn sync_span(operation: &str, zammad_id: Option<i32>, payload: &Value) -> Span {
+    let span = tracing::info_span!(
+        "sync",
+        operation,
+        direction = direction(operation),
+        zammad_id,
+        jira_id = field::Empty,
+        jira_key = field::Empty,
+        job = field::Empty,
+        request_id = field::Empty,
+    );
+    if let Some(id) = jira_issue_id(payload) {
+        span.record("jira_id", id);
+    }
+    if let Some(key) = payload["issue"]["key"].as_str() {
+        span.record("jira_key", key);
+    }
+    span
+}
+
+/// Which way a webhook of `operation` is synced.
+pub fn direction(operation: &str) -> &'static str {
+    Direction::of_operation(operation).map_or("unknown", Direction::as_str)
+}
+
+/// The ID of the Jira issue of a stored Jira webhook; a string as sent by Jira, or a number
+/// once parsed and stored.
+pub fn jira_issue_id(payload: &Value) -> Option<i32> {
+    match &payload["issue"]["id"] {
+        Value::String(id) => id.parse().ok(),
+        id => id.as_i64().and_then(|id| i32::try_from(id).ok()),
+    }
+}
+
+/// Adds the Jira issue to the current sync span.
+pub fn record_jira_issue(id: i32, key: Option<&str>) {
+    let span = Span::current();
+    span.record("jira_id", id);
+    if let Some(key) = key {
+        span.record("jira_key", key);
+    }
+}
+
+/// Adds the Zammad ticket to the current sync span.
+pub fn record_zammad_ticket(id: i32) {
+    Span::current().record("zammad_id", id);
+}
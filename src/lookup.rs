@@ -0,0 +1,165 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::header::CACHE_CONTROL,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::admin::authenticate;
+use crate::config::{self, default_lookup_max_age};
+use crate::models::{
+    api_request::{
+        JiraGetIssueRequest, ZammadGetTicketRequest, ZammadSearchTicketsRequest, get_jira_site_url,
+        get_zammad_web_url,
+    },
+    db::DB,
+};
+use crate::state::AppState;
+use crate::ticket_numbers;
+
+/// One side of the link: a Zammad ticket by ID or number, or a Jira issue by key or ID.
+/// Numbers and keys may be written as people paste them, e.g. "Ticket#45 003".
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct LookupQuery {
+    zammad: Option<i32>,
+    number: Option<String>,
+    jira: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct Lookup {
+    zammad: ZammadTicketRef,
+    jira: JiraIssueRef,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct ZammadTicketRef {
+    id: i32,
+    number: String,
+    url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct JiraIssueRef {
+    id: i32,
+    key: String,
+    url: String,
+}
+
+/// The link of a ticket or issue; exactly one of the parameters is given.
+#[utoipa::path(
+    get,
+    path = "/lookup",
+    params(LookupQuery),
+    responses(
+        (status = 200, body = Lookup),
+        (status = 400, description = "Neither or several parameters given, or an invalid one"),
+        (status = 404, description = "Not linked, or it doesn't exist"),
+        (status = 502, description = "Zammad or Jira couldn't be asked"),
+    )
+)]
+async fn lookup(State(state): State<AppState>, Query(query): Query<LookupQuery>) -> Response {
+    let result = match (query.zammad, query.number, query.jira) {
+        (Some(zammad_id), None, None) => lookup_zammad(&state.db, zammad_id).await,
+        (None, Some(number), None) => {
+            let Some(number) = ticket_numbers::normalize_zammad_number(&number) else {
+                return StatusCode::BAD_REQUEST.into_response();
+            };
+            lookup_zammad_number(&state.db, &number).await
+        }
+        (None, None, Some(jira)) => {
+            let issue = match jira.trim().parse() {
+                Ok(jira_id) => JiraGetIssueRequest::new(jira_id),
+                Err(_) => match ticket_numbers::normalize_jira_key(&jira) {
+                    Some(key) => JiraGetIssueRequest::by_key(&key),
+                    None => return StatusCode::BAD_REQUEST.into_response(),
+                },
+            };
+            lookup_jira(&state.db, issue).await
+        }
+        _ => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    let max_age = config::get()
+        .lookup
+        .as_ref()
+        .map_or_else(default_lookup_max_age, |lookup| lookup.max_age);
+    match result {
+        Ok(Some(lookup)) => (
+            // Links rarely change, so clients shouldn't ask again for every ticket
+            [(CACHE_CONTROL, format!("private, max-age={}", max_age))],
+            Json(lookup),
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            error!("Lookup failed: {}", e);
+            StatusCode::BAD_GATEWAY.into_response()
+        }
+    }
+}
+
+async fn lookup_zammad(db: &DB, zammad_id: i32) -> anyhow::Result<Option<Lookup>> {
+    let Some(jira_id) = db.find_jira_id_by_zammad_id(&zammad_id).await? else {
+        return Ok(None);
+    };
+    let issue = JiraGetIssueRequest::new(jira_id).submit().await?;
+    resolve(zammad_id, issue.id, issue.key).await.map(Some)
+}
+
+async fn lookup_zammad_number(db: &DB, number: &str) -> anyhow::Result<Option<Lookup>> {
+    let tickets = ZammadSearchTicketsRequest::new(&format!("number:{}", number), 1, 1)
+        .submit()
+        .await?;
+    let Some(ticket) = tickets.into_iter().find(|ticket| ticket.number == number) else {
+        return Ok(None);
+    };
+    lookup_zammad(db, ticket.id).await
+}
+
+async fn lookup_jira(db: &DB, issue: JiraGetIssueRequest) -> anyhow::Result<Option<Lookup>> {
+    let issue = issue.submit().await?;
+    let Some(zammad_id) = db.get_zammad_id_by_jira_id(&issue.id).await? else {
+        return Ok(None);
+    };
+    resolve(zammad_id, issue.id, issue.key).await.map(Some)
+}
+
+async fn resolve(zammad_id: i32, jira_id: i32, jira_key: String) -> anyhow::Result<Lookup> {
+    let ticket = ZammadGetTicketRequest::new(zammad_id).submit().await?;
+    Ok(Lookup {
+        zammad: ZammadTicketRef {
+            id: zammad_id,
+            number: ticket.number,
+            url: format!("{}/#ticket/zoom/{}", get_zammad_web_url(), zammad_id),
+        },
+        jira: JiraIssueRef {
+            id: jira_id,
+            url: format!("{}/browse/{}", get_jira_site_url(), jira_key),
+            key: jira_key,
+        },
+    })
+}
+
+/// The lookup API, under /api.
+#[derive(OpenApi)]
+#[openapi(paths(lookup))]
+pub struct LookupApi;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/lookup", get(lookup))
+        .layer(middleware::from_fn(|request, next| {
+            authenticate(
+                |config| config.lookup.as_ref().map(|lookup| lookup.token.as_str()),
+                request,
+                next,
+            )
+        }))
+}
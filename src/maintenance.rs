@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::config::{self, StatusPageConfig};
+use crate::models::db::{DB, MaintenanceWindow};
+
+/// The windows last polled, by host name, so outbound requests can be held back without
+/// asking the database.
+static WINDOWS: LazyLock<Mutex<HashMap<String, Vec<MaintenanceWindow>>>> =
+    LazyLock::new(Default::default);
+
+/// The error of requests that weren't sent because their host is in a maintenance window.
+#[derive(Debug)]
+pub struct InMaintenance {
+    pub host: String,
+}
+
+impl fmt::Display for InMaintenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is in an announced maintenance window", self.host)
+    }
+}
+
+impl std::error::Error for InMaintenance {}
+
+/// The parts of a Statuspage summary (`/api/v2/summary.json`) that announce downtime.
+#[derive(Debug, Deserialize)]
+struct StatusPageSummary {
+    #[serde(default)]
+    incidents: Vec<Incident>,
+    #[serde(default)]
+    scheduled_maintenances: Vec<ScheduledMaintenance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Incident {
+    name: String,
+    impact: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduledMaintenance {
+    name: String,
+    /// "scheduled", "in_progress", "verifying" or "completed"
+    status: String,
+    scheduled_for: DateTime<Utc>,
+    scheduled_until: Option<DateTime<Utc>>,
+}
+
+/// Polls the configured status pages and stores their maintenance windows.
+pub async fn poll_periodically(db: DB, config: StatusPageConfig) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.poll_every.max(1)));
+    loop {
+        interval.tick().await;
+        for (system, url) in [("jira", &config.jira), ("zammad", &config.zammad)] {
+            let Some(url) = url else {
+                continue;
+            };
+            if let Err(e) = poll(&db, &config, system, url).await {
+                warn!("Failed to poll the status page of {}: {:#}", system, e);
+            }
+        }
+    }
+}
+
+async fn poll(db: &DB, config: &StatusPageConfig, system: &str, url: &str) -> anyhow::Result<()> {
+    let url = format!("{}/api/v2/summary.json", url.trim_end_matches('/'));
+    let summary = reqwest::get(&url)
+        .await
+        .context("failed to request status page")?
+        .error_for_status()
+        .context("error status from status page")?
+        .json::<StatusPageSummary>()
+        .await?;
+
+    let mut windows = Vec::new();
+    for maintenance in summary.scheduled_maintenances {
+        match maintenance.status.as_str() {
+            // Running maintenances may overrun the announced end
+            "in_progress" | "verifying" => windows.push(MaintenanceWindow {
+                name: maintenance.name,
+                starts_at: maintenance.scheduled_for.min(Utc::now()),
+                ends_at: None,
+            }),
+            "scheduled" => windows.push(MaintenanceWindow {
+                name: maintenance.name,
+                starts_at: maintenance.scheduled_for,
+                ends_at: maintenance.scheduled_until,
+            }),
+            _ => {}
+        }
+    }
+    for incident in summary.incidents {
+        if config.pausing_impacts.contains(&incident.impact) {
+            windows.push(MaintenanceWindow {
+                name: incident.name,
+                starts_at: incident.created_at,
+                ends_at: None,
+            });
+        }
+    }
+
+    db.set_maintenance_windows(system, &windows).await?;
+    let config = config::get();
+    let endpoint = match system {
+        "jira" => &config.jira.endpoint,
+        _ => &config.zammad.endpoint,
+    };
+    if let Some(host) = Url::parse(endpoint)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+    {
+        WINDOWS
+            .lock()
+            .expect("maintenance lock poisoned")
+            .insert(host, windows);
+    }
+    Ok(())
+}
+
+/// How long `host` is still in a maintenance window, if it is; checked by every outbound
+/// request, so nothing calls a system while it's announced to be down.
+pub fn announced_for(host: &str) -> Option<Duration> {
+    let poll_every = config::get().status_pages.as_ref()?.poll_every;
+    let now = Utc::now();
+    let windows = WINDOWS.lock().expect("maintenance lock poisoned");
+    windows
+        .get(host)?
+        .iter()
+        .filter(|window| {
+            window.starts_at <= now && window.ends_at.is_none_or(|ends_at| ends_at > now)
+        })
+        .map(|window| match window.ends_at {
+            Some(ends_at) => Duration::from_secs((ends_at - now).num_seconds().max(1) as u64),
+            None => Duration::from_secs(poll_every),
+        })
+        .max()
+}
@@ -0,0 +1,25 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+
+/// Jira and Zammad requests answered with 429, including ones that were retried.
+pub static THROTTLED_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Webhooks answered with 429 for exceeding the configured rate limits.
+pub static RATE_LIMITED_WEBHOOKS: AtomicU64 = AtomicU64::new(0);
+
+/// The counters in the Prometheus text format, served under /metrics.
+pub async fn render() -> impl IntoResponse {
+    let body = format!(
+        "# HELP ticket_sync_throttled_requests_total Outbound requests answered with 429.\n\
+         # TYPE ticket_sync_throttled_requests_total counter\n\
+         ticket_sync_throttled_requests_total {}\n\
+         # HELP ticket_sync_rate_limited_webhooks_total Webhooks answered with 429.\n\
+         # TYPE ticket_sync_rate_limited_webhooks_total counter\n\
+         ticket_sync_rate_limited_webhooks_total {}\n",
+        THROTTLED_REQUESTS.load(Ordering::Relaxed),
+        RATE_LIMITED_WEBHOOKS.load(Ordering::Relaxed)
+    );
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
@@ -1,46 +1,119 @@
 use super::{
-    jira::{JiraFields, JiraIssueType, JiraPriority, JiraPriorityEnum, JiraProject, JiraStatus},
-    zammad::{ZammadPriorityId, ZammadState, ZammadWebhook},
+    jira::{
+        JiraApiIssue, JiraAssetObject, JiraComment, JiraFields, JiraIssueType, JiraPriority,
+        JiraPriorityEnum, JiraProject, JiraSearchResponse, JiraStatusCategoryKey,
+        JiraTransitionsResponse, JiraUser, parse_jira_time,
+    },
+    zammad::{ZammadApiTicket, ZammadPriorityId, ZammadState, ZammadTicket, ZammadWebhook},
 };
-use crate::config;
+use crate::retry::SendWithRetry;
+use crate::{config, decisions, profiles, templates};
 use anyhow::{Context, Result};
-use reqwest::Client;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Client, StatusCode,
+    header::{HeaderMap, HeaderName, HeaderValue},
+    multipart::{Form, Part},
+};
 use serde::{Deserialize, Deserializer, Serialize};
-use std::str::FromStr;
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    sync::OnceLock,
+};
 use tracing::{debug, info};
 
+/// Starts the part of a Jira description synced from Zammad. It's an invisible anchor in
+/// Jira; text added above it in Jira is kept when the description is updated.
+pub const DESCRIPTION_MARKER: &str = "{anchor:zammad-sync}";
+
+/// The Jira description for the ticket, rendered from the webhook's article.
+pub fn zammad_description(webhook: &ZammadWebhook) -> anyhow::Result<String> {
+    let context = zammad_template_context(webhook, &BTreeMap::new());
+    Ok(
+        templates::render(templates::ZAMMAD_TO_JIRA_DESCRIPTION, &context)?
+            .unwrap_or_else(|| webhook.article.body.clone().unwrap_or_default()),
+    )
+}
+
+/// `current` with the part below the marker replaced by `synced`. Descriptions without the
+/// marker, e.g. of issues created before it was introduced, are replaced entirely.
+pub fn replace_synced_description(current: &str, synced: &str) -> String {
+    let kept = current
+        .find(DESCRIPTION_MARKER)
+        .map_or("", |start| &current[..start]);
+    format!("{}{}\n{}", kept, DESCRIPTION_MARKER, synced)
+}
+
 #[derive(Debug, Serialize)]
 pub struct JiraCreateIssueRequest {
     pub fields: JiraFields,
 }
 
 impl JiraCreateIssueRequest {
-    pub fn from_zammad_webhook(webhook: &ZammadWebhook) -> Self {
+    pub fn from_zammad_webhook(webhook: &ZammadWebhook) -> anyhow::Result<Self> {
         debug!("Ticket: {:?}", &webhook);
-        Self {
+        let context = zammad_template_context(webhook, &BTreeMap::new());
+        Ok(Self {
             fields: JiraFields {
                 project: JiraProject {
-                    id: get_jira_project(),
+                    id: get_jira_project(&webhook.ticket),
                 },
-                summary: webhook.ticket.title.clone(),
-                description: webhook.article.body.clone().unwrap_or_default(),
+                summary: templates::render(templates::ZAMMAD_TO_JIRA_SUMMARY, &context)?
+                    .unwrap_or_else(|| webhook.ticket.title.clone()),
+                description: format!("{}\n{}", DESCRIPTION_MARKER, zammad_description(webhook)?),
                 priority: JiraPriority {
                     name: convert_zammad_priority_to_jira_priority(webhook.ticket.priority.id),
                 },
-                //                status: convert_zammad_state_to_jira_status(ticket.state),
                 issuetype: JiraIssueType {
                     name: "Task".to_string(),
                 },
-                duedate: webhook.ticket.due_date.format("%Y-%m-%d").to_string(),
+                duedate: webhook
+                    .ticket
+                    .due_date
+                    .filter(|_| config::get().features.due_dates)
+                    .map(|due_date| due_date.format("%Y-%m-%d").to_string()),
                 // Jira doesn't allow to create an issue with a status.
-                //                status: JiraStatus::from_zammad_state(webhook.ticket.state),
+                extra_fields: {
+                    let mut fields = get_mapped_fields(&webhook.ticket);
+                    add_group_fields(&webhook.ticket, &mut fields);
+                    fields
+                },
+            },
+        })
+    }
+    /// A sub-task of `parent_id` showing a checklist item of the ticket.
+    pub fn subtask(ticket: &ZammadTicket, parent_id: i32, summary: &str, issue_type: &str) -> Self {
+        Self {
+            fields: JiraFields {
+                // Sub-tasks have to be in the project of their parent
+                project: JiraProject {
+                    id: get_jira_project(ticket),
+                },
+                summary: summary.to_string(),
+                description: String::new(),
+                priority: JiraPriority {
+                    name: convert_zammad_priority_to_jira_priority(ticket.priority.id),
+                },
+                issuetype: JiraIssueType {
+                    name: issue_type.to_string(),
+                },
+                duedate: None,
+                extra_fields: Map::from_iter([(
+                    "parent".to_string(),
+                    json!({ "id": parent_id.to_string() }),
+                )]),
             },
         }
     }
+
     pub async fn submit(&self) -> anyhow::Result<JiraCreateIssueResponse> {
         debug!("Trying to make request to Jira");
 
-        let client = Client::new();
+        let client = get_jira_client();
         let url = get_jira_url();
 
         info!("Jira Request URL: {}", url);
@@ -51,7 +124,7 @@ impl JiraCreateIssueRequest {
             .post(&url)
             .json(&self)
             .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
-            .send()
+            .send_with_retry()
             .await
             .context("failed to send request to Jira API")?
             .error_for_status() // 4xx/5xx → error
@@ -66,24 +139,170 @@ impl JiraCreateIssueRequest {
     }
 }
 
+/// A Jira Service Management customer request, so the portal and SLAs apply to the issue.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraCreateCustomerRequest {
+    service_desk_id: String,
+    request_type_id: String,
+    request_field_values: JiraRequestFieldValues,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raise_on_behalf_of: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    request_participants: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct JiraRequestFieldValues {
+    summary: String,
+    description: String,
+}
+
+impl JiraCreateCustomerRequest {
+    pub fn from_zammad_webhook(webhook: &ZammadWebhook) -> anyhow::Result<Self> {
+        let jira = config::get_jira();
+        let service_desk = jira
+            .service_desk
+            .as_ref()
+            .context("no service desk configured")?;
+        let request_type_id = webhook
+            .ticket
+            .group
+            .as_ref()
+            .and_then(|group| service_desk.request_types.get(&group.name))
+            .unwrap_or(&service_desk.request_type_id)
+            .clone();
+
+        // Everyone in Cc becomes a request participant
+        let request_participants = webhook
+            .article
+            .cc
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|recipient| {
+                let recipient = recipient.trim();
+                let address = match recipient.split_once('<') {
+                    Some((_name, address)) => address.trim_end_matches('>'),
+                    None => recipient,
+                };
+                address.contains('@').then(|| address.to_string())
+            })
+            .collect();
+
+        let context = zammad_template_context(webhook, &BTreeMap::new());
+        Ok(Self {
+            service_desk_id: service_desk.id.clone(),
+            request_type_id,
+            request_field_values: JiraRequestFieldValues {
+                summary: templates::render(templates::ZAMMAD_TO_JIRA_SUMMARY, &context)?
+                    .unwrap_or_else(|| webhook.ticket.title.clone()),
+                description: format!("{}\n{}", DESCRIPTION_MARKER, zammad_description(webhook)?),
+            },
+            raise_on_behalf_of: webhook
+                .ticket
+                .customer
+                .as_ref()
+                .map(|customer| customer.email.clone()),
+            request_participants,
+        })
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<JiraCreateCustomerRequestResponse> {
+        let client = get_jira_client();
+        let url = format!("{}/rest/servicedeskapi/request", get_jira_site_url());
+
+        info!("Jira Request URL: {}", url);
+        info!("Jira Request: {:?}", self);
+
+        let resp = client
+            .post(&url)
+            .json(&self)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira Service Management API")?
+            .error_for_status()
+            .context("error status from Jira Service Management API")?
+            .json::<JiraCreateCustomerRequestResponse>()
+            .await?;
+
+        info!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraCreateCustomerRequestResponse {
+    #[serde(deserialize_with = "string_to_number")]
+    pub issue_id: i32,
+    pub issue_key: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct JiraUpdateIssueRequest {
     fields: JiraUpdateIssueProperties,
+    /// Incremental operations like adding or removing single labels
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    update: Map<String, Value>,
 }
 
 impl JiraUpdateIssueRequest {
     pub fn from_zammad_webhook(webhook: &ZammadWebhook) -> Self {
+        let mut update = Map::new();
+        if let Some(label) = &config::get_jira().escalation.escalated_label {
+            let operation = if is_escalated(&webhook.ticket) {
+                "add"
+            } else {
+                "remove"
+            };
+            update.insert("labels".to_string(), json!([{ operation: label }]));
+        }
+
         Self {
             fields: JiraUpdateIssueProperties {
-                priority: JiraPriority {
+                priority: Some(JiraPriority {
                     name: convert_zammad_priority_to_jira_priority(webhook.ticket.priority.id),
-                },
+                }),
+                extra_fields: get_mapped_fields(&webhook.ticket),
+            },
+            update,
+        }
+    }
+
+    /// An update only setting the priority.
+    pub fn priority(priority: ZammadPriorityId) -> Self {
+        Self {
+            fields: JiraUpdateIssueProperties {
+                priority: Some(JiraPriority {
+                    name: convert_zammad_priority_to_jira_priority(priority),
+                }),
+                extra_fields: Map::new(),
             },
+            update: Map::new(),
         }
     }
 
+    /// An update only setting `field`.
+    pub fn field(field: &str, value: Value) -> Self {
+        Self {
+            fields: JiraUpdateIssueProperties {
+                priority: None,
+                extra_fields: Map::from_iter([(field.to_string(), value)]),
+            },
+            update: Map::new(),
+        }
+    }
+
+    pub fn without_priority(mut self) -> Self {
+        self.fields.priority = None;
+        self
+    }
+
     pub async fn submit(&self, jira_issue_id: &i32) -> anyhow::Result<()> {
-        let client = Client::new();
+        let client = get_jira_client();
         let url = get_jira_url();
 
         let url = format!("{}/{}", &url, jira_issue_id);
@@ -100,10 +319,10 @@ impl JiraUpdateIssueRequest {
             .put(&url)
             .json(&self)
             .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
-            .send()
+            .send_with_retry()
             .await?
             .error_for_status()
-            .map_err(|e| anyhow::anyhow!("Error status from Jira API: {}", e))?
+            .context("error status from Jira API")?
             .text()
             .await
             .context("Failed to get response body")?;
@@ -116,26 +335,79 @@ impl JiraUpdateIssueRequest {
 
 #[derive(Debug, Serialize)]
 pub struct JiraUpdateIssueProperties {
-    priority: JiraPriority,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<JiraPriority>,
+    #[serde(flatten)]
+    extra_fields: Map<String, Value>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct JiraAddCommentRequest {
     body: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<Value>,
 }
 
 impl JiraAddCommentRequest {
-    pub fn from_zammad_webhook(webhook: &ZammadWebhook) -> Self {
+    /// `meta` is the assignment metadata available to the comment header.
+    pub fn from_zammad_webhook(
+        webhook: &ZammadWebhook,
+        meta: &BTreeMap<String, String>,
+    ) -> anyhow::Result<Self> {
         debug!("Article: {:?}", &webhook.article);
+        let body = templates::render(
+            templates::ZAMMAD_TO_JIRA_COMMENT,
+            &zammad_template_context(webhook, meta),
+        )?
+        .unwrap_or_else(|| webhook.article.body.clone().unwrap_or_default());
+        let body = match &config::get_jira().comment_header {
+            Some(template) => format!(
+                "{}\n\n{}",
+                render_comment_header(
+                    template,
+                    webhook.article.from.as_deref().unwrap_or("Unknown"),
+                    &webhook.ticket.number,
+                    webhook.article.created_at.unwrap_or_else(Utc::now),
+                    meta,
+                ),
+                body
+            ),
+            None => body,
+        };
+        Ok(Self {
+            body,
+            properties: Vec::new(),
+        })
+    }
+
+    pub fn new(body: String) -> Self {
+        Self {
+            body,
+            properties: Vec::new(),
+        }
+    }
+
+    /// Appends notes, e.g. about attachments, to the comment.
+    pub fn with_notes(mut self, notes: &[String]) -> Self {
+        for note in notes {
+            self.body.push_str("\n\n");
+            self.body.push_str(note);
+        }
+        self
+    }
+
+    /// A comment hidden from customers in Jira Service Management.
+    pub fn internal(body: String) -> Self {
         Self {
-            body: webhook.article.body.clone().unwrap_or_default(),
+            body,
+            properties: vec![json!({ "key": "sd.public.comment", "value": { "internal": true } })],
         }
     }
 
     pub async fn submit(&self, jira_issue_id: &i32) -> anyhow::Result<JiraAddCommentResponse> {
         debug!("Trying to make request to Jira");
 
-        let client = Client::new();
+        let client = get_jira_client();
         let url = get_jira_url();
 
         let url = format!("{}/{}/{}", &url, jira_issue_id, "comment");
@@ -146,10 +418,10 @@ impl JiraAddCommentRequest {
             .post(&url)
             .json(&self)
             .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
-            .send()
+            .send_with_retry()
             .await?
             .error_for_status()
-            .map_err(|e| anyhow::anyhow!("Error status from Jira API: {}", e))?
+            .context("error status from Jira API")?
             .text()
             .await
             .context("Failed to get response body")?;
@@ -166,53 +438,1765 @@ impl JiraAddCommentRequest {
     }
 }
 
-fn string_to_number<'de, T, D>(deserializer: D) -> Result<T, D::Error>
-where
-    T: FromStr,
-    T::Err: std::fmt::Display,
-    D: Deserializer<'de>,
-{
-    let s = String::deserialize(deserializer)?;
-    T::from_str(&s).map_err(serde::de::Error::custom)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraSearchRequest {
+    jql: String,
+    start_at: u32,
+    max_results: u32,
+    fields: Vec<&'static str>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct JiraAddCommentResponse {
-    #[serde(deserialize_with = "string_to_number")]
-    pub id: i32,
+impl JiraSearchRequest {
+    pub fn new(jql: &str, start_at: u32, max_results: u32) -> Self {
+        Self {
+            jql: jql.to_string(),
+            start_at,
+            max_results,
+            fields: vec!["summary", "description", "priority", "reporter"],
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<JiraSearchResponse> {
+        let client = get_jira_client();
+        let url = format!("{}/search", get_jira_api_url());
+
+        info!("Jira Request URL: {}", url);
+        info!("Jira Request: {:?}", self);
+
+        let resp = client
+            .post(&url)
+            .json(&self)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .json::<JiraSearchResponse>()
+            .await?;
+
+        debug!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct JiraCreateIssueResponse {
+/// Creates several issues with one request, at most `LIMIT` per request.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraBulkCreateIssuesRequest<'a> {
+    issue_updates: &'a [JiraCreateIssueRequest],
+}
+
+impl<'a> JiraBulkCreateIssuesRequest<'a> {
+    pub const LIMIT: usize = 50;
+
+    pub fn new(issues: &'a [JiraCreateIssueRequest]) -> Self {
+        Self {
+            issue_updates: issues,
+        }
+    }
+
+    /// Succeeds if Jira processed the request, even if it rejected some or all issues.
+    pub async fn submit(&self) -> anyhow::Result<JiraBulkCreateIssuesResponse> {
+        let client = get_jira_client();
+        let url = format!("{}/bulk", get_jira_url().trim_end_matches('/'));
+
+        info!("Jira Request URL: {}", url);
+
+        let resp = client
+            .post(&url)
+            .json(&self)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?;
+        // Jira answers 400 if every issue was rejected, with the same body as for partial
+        // failures
+        let resp = if resp.status() == StatusCode::BAD_REQUEST {
+            resp
+        } else {
+            resp.error_for_status()
+                .context("error status from Jira API")?
+        }
+        .json::<JiraBulkCreateIssuesResponse>()
+        .await?;
+
+        debug!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+/// The created issues in the order they were requested, leaving out the rejected ones.
+#[derive(Debug, Deserialize)]
+pub struct JiraBulkCreateIssuesResponse {
+    #[serde(default)]
+    pub issues: Vec<JiraCreateIssueResponse>,
+    #[serde(default)]
+    pub errors: Vec<JiraBulkCreateError>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraBulkCreateError {
+    /// Index of the rejected issue in the request
+    pub failed_element_number: usize,
+    #[serde(default)]
+    pub element_errors: Value,
+}
+
+/// Fetches a single issue from the Jira API.
+#[derive(Debug)]
+pub struct JiraGetIssueRequest {
+    /// Issue ID or key
+    issue: String,
+}
+
+impl JiraGetIssueRequest {
+    pub fn new(issue_id: i32) -> Self {
+        Self {
+            issue: issue_id.to_string(),
+        }
+    }
+
+    pub fn by_key(key: &str) -> Self {
+        Self {
+            issue: key.to_string(),
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<JiraApiIssue> {
+        let client = get_jira_client();
+        let url = format!("{}/{}", get_jira_url(), self.issue);
+
+        info!("Jira Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .json::<JiraApiIssue>()
+            .await?;
+
+        debug!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+/// Deletes an issue, e.g. after a smoke test.
+#[derive(Debug)]
+pub struct JiraDeleteIssueRequest {
+    issue_id: i32,
+}
+
+impl JiraDeleteIssueRequest {
+    pub fn new(issue_id: i32) -> Self {
+        Self { issue_id }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<()> {
+        let client = get_jira_client();
+        let url = format!("{}/{}", get_jira_url(), self.issue_id);
+
+        info!("Jira Request URL: {}", url);
+
+        client
+            .delete(&url)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?;
+
+        Ok(())
+    }
+}
+
+/// Uploads a file to an issue.
+#[derive(Debug)]
+pub struct JiraAddAttachmentRequest {
+    filename: String,
+    mime_type: String,
+    data: Vec<u8>,
+}
+
+impl JiraAddAttachmentRequest {
+    pub fn new(filename: &str, mime_type: &str, data: Vec<u8>) -> Self {
+        Self {
+            filename: filename.to_string(),
+            mime_type: mime_type.to_string(),
+            data,
+        }
+    }
+
+    /// Returns the IDs of the created attachments.
+    pub async fn submit(self, jira_issue_id: &i32) -> anyhow::Result<Vec<i32>> {
+        let client = get_jira_client();
+        let url = format!("{}/{}/attachments", get_jira_url(), jira_issue_id);
+
+        info!("Jira Request URL: {}", url);
+        info!(
+            "Jira Request: {} ({}, {} bytes)",
+            self.filename,
+            self.mime_type,
+            self.data.len()
+        );
+
+        let part = Part::bytes(self.data)
+            .file_name(self.filename)
+            .mime_str(&self.mime_type)?;
+        let resp = client
+            .post(&url)
+            // Jira rejects uploads without this header as possible XSRF
+            .header("X-Atlassian-Token", "no-check")
+            .multipart(Form::new().part("file", part))
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .json::<Vec<JiraAttachment>>()
+            .await?;
+
+        Ok(resp.into_iter().map(|attachment| attachment.id).collect())
+    }
+}
+
+/// Metadata of an attachment of a Jira issue.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraAttachment {
     #[serde(deserialize_with = "string_to_number")]
     pub id: i32,
-    pub key: String,
+    #[serde(default)]
+    pub filename: String,
+    /// In bytes
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub mime_type: String,
+    /// Download URL, which needs authentication
+    #[serde(default)]
+    pub content: String,
 }
 
-fn convert_zammad_priority_to_jira_priority(priority: ZammadPriorityId) -> JiraPriorityEnum {
-    match priority {
-        ZammadPriorityId::Low => JiraPriorityEnum::Lowest,
-        ZammadPriorityId::Normal => JiraPriorityEnum::Medium,
-        ZammadPriorityId::High => JiraPriorityEnum::High,
-    }
+#[derive(Debug)]
+pub struct JiraGetAttachmentRequest {
+    attachment_id: i32,
 }
 
-fn convert_zammad_state_to_jira_status(state: ZammadState) -> JiraStatus {
-    match state {
-        ZammadState::Open => JiraStatus::Open,
-        ZammadState::Closed => JiraStatus::Closed,
+impl JiraGetAttachmentRequest {
+    pub fn new(attachment_id: i32) -> Self {
+        Self { attachment_id }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<JiraAttachment> {
+        let client = get_jira_client();
+        let url = format!("{}/attachment/{}", get_jira_api_url(), self.attachment_id);
+
+        info!("Jira Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .json::<JiraAttachment>()
+            .await?;
+
+        Ok(resp)
+    }
+
+    /// Downloads the content of `attachment`.
+    pub async fn download(attachment: &JiraAttachment) -> anyhow::Result<Vec<u8>> {
+        let client = get_jira_client();
+
+        info!("Jira Request URL: {}", attachment.content);
+
+        let resp = client
+            .get(&attachment.content)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .bytes()
+            .await?;
+
+        Ok(resp.to_vec())
     }
 }
 
-fn get_jira_url() -> String {
-    config::get_jira().endpoint.clone()
+/// Lists the comments of an issue.
+#[derive(Debug)]
+pub struct JiraGetCommentsRequest {
+    issue_id: i32,
 }
 
-fn get_jira_credentials() -> (String, String) {
-    let jira_config = config::get_jira();
-    (jira_config.username.clone(), jira_config.token.clone())
+impl JiraGetCommentsRequest {
+    pub fn new(issue_id: i32) -> Self {
+        Self { issue_id }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<JiraGetCommentsResponse> {
+        let client = get_jira_client();
+        let url = format!("{}/{}/comment", get_jira_url(), self.issue_id);
+
+        info!("Jira Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .json::<JiraGetCommentsResponse>()
+            .await?;
+
+        debug!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JiraGetCommentsResponse {
+    pub comments: Vec<JiraComment>,
+}
+
+/// Fetches a single comment of an issue.
+#[derive(Debug)]
+pub struct JiraGetCommentRequest {
+    issue_id: i32,
+    comment_id: i32,
+}
+
+impl JiraGetCommentRequest {
+    pub fn new(issue_id: i32, comment_id: i32) -> Self {
+        Self {
+            issue_id,
+            comment_id,
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<JiraComment> {
+        let client = get_jira_client();
+        let url = format!(
+            "{}/{}/comment/{}",
+            get_jira_url(),
+            self.issue_id,
+            self.comment_id
+        );
+
+        info!("Jira Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .json::<JiraComment>()
+            .await?;
+
+        debug!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+/// Searches Jira users by name or email address.
+#[derive(Debug)]
+pub struct JiraSearchUsersRequest {
+    query: String,
+}
+
+impl JiraSearchUsersRequest {
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<Vec<JiraUser>> {
+        let client = get_jira_client();
+        let url = format!("{}/user/search", get_jira_api_url());
+
+        info!("Jira Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .query(&[("query", &self.query)])
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .json::<Vec<JiraUser>>()
+            .await?;
+
+        debug!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+/// Lists the transitions available from the issue's current status.
+#[derive(Debug)]
+pub struct JiraGetTransitionsRequest {
+    issue_id: i32,
+}
+
+impl JiraGetTransitionsRequest {
+    pub fn new(issue_id: i32) -> Self {
+        Self { issue_id }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<JiraTransitionsResponse> {
+        let client = get_jira_client();
+        let url = format!("{}/{}/transitions", get_jira_url(), self.issue_id);
+
+        info!("Jira Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira API")?
+            .error_for_status()
+            .context("error status from Jira API")?
+            .json::<JiraTransitionsResponse>()
+            .await?;
+
+        debug!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct JiraDoTransitionRequest {
+    transition: JiraTransitionId,
+}
+
+#[derive(Debug, Serialize)]
+struct JiraTransitionId {
+    id: String,
+}
+
+impl JiraDoTransitionRequest {
+    pub fn new(transition_id: &str) -> Self {
+        Self {
+            transition: JiraTransitionId {
+                id: transition_id.to_string(),
+            },
+        }
+    }
+
+    pub async fn submit(&self, jira_issue_id: &i32) -> anyhow::Result<()> {
+        let client = get_jira_client();
+        let url = format!("{}/{}/transitions", get_jira_url(), jira_issue_id);
+
+        info!("Jira Request URL: {}", url);
+        info!("Jira Request: {:?}", self);
+
+        client
+            .post(&url)
+            .json(&self)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await?
+            .error_for_status()
+            .context("error status from Jira API")?;
+
+        Ok(())
+    }
+}
+
+/// Fetches an Assets (Insight) object from the Jira Service Management Assets API.
+#[derive(Debug)]
+pub struct JiraGetAssetObjectRequest {
+    workspace_id: String,
+    object_id: String,
+}
+
+impl JiraGetAssetObjectRequest {
+    pub fn new(workspace_id: &str, object_id: &str) -> Self {
+        Self {
+            workspace_id: workspace_id.to_string(),
+            object_id: object_id.to_string(),
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<JiraAssetObject> {
+        let client = get_jira_client();
+        let url = format!(
+            "https://api.atlassian.com/jsm/assets/workspace/{}/v1/object/{}",
+            self.workspace_id, self.object_id
+        );
+
+        info!("Jira Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_jira_credentials().0, Some(get_jira_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Jira Assets API")?
+            .error_for_status()
+            .context("error status from Jira Assets API")?
+            .json::<JiraAssetObject>()
+            .await?;
+
+        debug!("Jira Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZammadCreateTicketRequest {
+    title: String,
+    group: String,
+    /// Email of the customer; Zammad creates the customer if it doesn't exist yet
+    customer: String,
+    priority_id: i32,
+    state: ZammadState,
+    article: ZammadCreateArticle,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZammadCreateArticle {
+    subject: String,
+    body: String,
+    r#type: String,
+    internal: bool,
+}
+
+impl ZammadCreateTicketRequest {
+    pub fn from_jira_issue(issue: &JiraApiIssue) -> anyhow::Result<Self> {
+        let zammad_config = config::get_zammad();
+        let customer = issue
+            .fields
+            .reporter
+            .as_ref()
+            .and_then(|reporter| reporter.email_address.clone())
+            .or_else(|| zammad_config.default_customer.clone())
+            .with_context(|| {
+                format!(
+                    "Jira issue {} has no reporter email and no default customer is configured",
+                    issue.key
+                )
+            })?;
+
+        let context = jira_template_context(issue, None, &BTreeMap::new());
+        Ok(Self {
+            title: templates::render(templates::JIRA_TO_ZAMMAD_SUMMARY, &context)?
+                .unwrap_or_else(|| format!("[{}] {}", issue.key, issue.fields.summary)),
+            group: zammad_config.group.clone(),
+            customer,
+            priority_id: convert_jira_priority_to_zammad_priority(
+                issue.fields.priority.as_ref().map(|p| p.name.as_str()),
+            ) as i32,
+            state: ZammadState::Open,
+            article: ZammadCreateArticle {
+                subject: issue.fields.summary.clone(),
+                body: templates::render(templates::JIRA_TO_ZAMMAD_DESCRIPTION, &context)?
+                    .unwrap_or_else(|| issue.fields.description.clone().unwrap_or_default()),
+                r#type: "note".to_string(),
+                internal: false,
+            },
+        })
+    }
+
+    /// A ticket that only exists to test the sync, recognizable by `marker`.
+    pub fn smoke_test(marker: &str, customer: String) -> Self {
+        Self {
+            title: format!("Smoke test {}", marker),
+            group: config::get_zammad().group.clone(),
+            customer,
+            priority_id: ZammadPriorityId::Low as i32,
+            state: ZammadState::Open,
+            article: ZammadCreateArticle {
+                subject: format!("Smoke test {}", marker),
+                body: "Created by the smoke test and deleted afterwards.".to_string(),
+                r#type: "note".to_string(),
+                internal: false,
+            },
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<ZammadCreateTicketResponse> {
+        let client = get_zammad_client();
+        let url = format!("{}/tickets", get_zammad_url());
+
+        info!("Zammad Request URL: {}", url);
+        info!("Zammad Request: {:?}", self);
+
+        let resp = client
+            .post(&url)
+            .json(&self)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<ZammadCreateTicketResponse>()
+            .await?;
+
+        info!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ZammadCreateTicketResponse {
+    pub id: i32,
+    pub number: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZammadUpdateTicketRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<ZammadState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority_id: Option<i32>,
+    /// Ticket attributes like custom object attributes
+    #[serde(flatten)]
+    extra_fields: Map<String, Value>,
+}
+
+impl ZammadUpdateTicketRequest {
+    /// An update only changing the fields set with the `with_*` methods.
+    pub fn new() -> Self {
+        Self {
+            state: None,
+            priority_id: None,
+            extra_fields: Map::new(),
+        }
+    }
+
+    pub fn with_state(mut self, state: ZammadState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_priority_id(mut self, priority: ZammadPriorityId) -> Self {
+        self.priority_id = Some(priority as i32);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.is_none() && self.priority_id.is_none() && self.extra_fields.is_empty()
+    }
+
+    pub fn with_field(mut self, name: &str, value: Value) -> Self {
+        self.extra_fields.insert(name.to_string(), value);
+        self
+    }
+
+    pub async fn submit(&self, zammad_ticket_id: &i32) -> anyhow::Result<()> {
+        let client = get_zammad_client();
+        let url = format!("{}/tickets/{}", get_zammad_url(), zammad_ticket_id);
+
+        info!("Zammad Request URL: {}", url);
+        info!("Zammad Request: {:?}", self);
+
+        let resp = client
+            .put(&url)
+            .json(&self)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await?
+            .error_for_status()
+            .map_err(|e| anyhow::anyhow!("Error status from Zammad API: {}", e))?
+            .text()
+            .await
+            .context("Failed to get response body")?;
+
+        debug!("Zammad Response: {:?}", resp);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZammadCreateArticleRequest {
+    ticket_id: i32,
+    body: String,
+    content_type: String,
+    r#type: String,
+    internal: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<ZammadArticleAttachment>,
+}
+
+/// A file uploaded with an article, Base64 encoded.
+#[derive(Serialize)]
+pub struct ZammadArticleAttachment {
+    filename: String,
+    data: String,
+    #[serde(rename = "mime-type")]
+    mime_type: String,
+}
+
+// The data would flood the request log
+impl std::fmt::Debug for ZammadArticleAttachment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZammadArticleAttachment")
+            .field("filename", &self.filename)
+            .field("mime_type", &self.mime_type)
+            .field("data", &format_args!("{} bytes Base64", self.data.len()))
+            .finish()
+    }
+}
+
+impl ZammadCreateArticleRequest {
+    /// A plain text note only visible to agents.
+    pub fn internal_note(ticket_id: i32, body: String) -> Self {
+        Self {
+            ticket_id,
+            body,
+            content_type: "text/plain".to_string(),
+            r#type: "note".to_string(),
+            internal: true,
+            attachments: Vec::new(),
+        }
+    }
+
+    pub fn with_attachment(mut self, filename: &str, mime_type: &str, data: &[u8]) -> Self {
+        self.attachments.push(ZammadArticleAttachment {
+            filename: filename.to_string(),
+            data: BASE64_STANDARD.encode(data),
+            mime_type: mime_type.to_string(),
+        });
+        self
+    }
+
+    /// An internal note mirroring a Jira comment. `meta` is the assignment metadata available
+    /// to the comment header.
+    pub fn from_jira_comment(
+        ticket_id: i32,
+        issue: &JiraApiIssue,
+        comment: &JiraComment,
+        meta: &BTreeMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        let body = templates::render(
+            templates::JIRA_TO_ZAMMAD_COMMENT,
+            &jira_template_context(issue, Some(comment), meta),
+        )?
+        .unwrap_or_else(|| comment.body.clone());
+        let body = match &config::get_zammad().comment_header {
+            Some(template) => format!(
+                "{}\n\n{}",
+                render_comment_header(
+                    template,
+                    comment
+                        .author
+                        .as_ref()
+                        .and_then(|author| author.display_name.as_deref())
+                        .unwrap_or("Unknown"),
+                    &issue.key,
+                    comment
+                        .created
+                        .as_deref()
+                        .and_then(parse_jira_time)
+                        .unwrap_or_else(Utc::now),
+                    meta,
+                ),
+                body
+            ),
+            None => body,
+        };
+        Ok(Self::internal_note(ticket_id, body))
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<ZammadCreateArticleResponse> {
+        let client = get_zammad_client();
+        let url = format!("{}/ticket_articles", get_zammad_url());
+
+        info!("Zammad Request URL: {}", url);
+        info!("Zammad Request: {:?}", self);
+
+        let resp = client
+            .post(&url)
+            .json(&self)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<ZammadCreateArticleResponse>()
+            .await?;
+
+        info!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ZammadCreateArticleResponse {
+    pub id: u64,
+}
+
+/// Searches the Zammad knowledge base for answers.
+#[derive(Debug, Serialize)]
+pub struct ZammadSearchKnowledgeBaseRequest {
+    knowledge_base_id: i32,
+    locale: String,
+    query: String,
+    flavor: String,
+}
+
+impl ZammadSearchKnowledgeBaseRequest {
+    pub fn new(knowledge_base_id: i32, locale: &str, query: &str) -> Self {
+        Self {
+            knowledge_base_id,
+            locale: locale.to_string(),
+            query: query.to_string(),
+            flavor: "agent".to_string(),
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<ZammadSearchKnowledgeBaseResponse> {
+        let client = get_zammad_client();
+        let url = format!("{}/knowledge_bases/search", get_zammad_url());
+
+        info!("Zammad Request URL: {}", url);
+        info!("Zammad Request: {:?}", self);
+
+        let resp = client
+            .post(&url)
+            .json(&self)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<ZammadSearchKnowledgeBaseResponse>()
+            .await?;
+
+        debug!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZammadSearchKnowledgeBaseResponse {
+    /// One entry per match, ordered by relevance
+    #[serde(default)]
+    pub details: Vec<ZammadKnowledgeBaseMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZammadKnowledgeBaseMatch {
+    pub r#type: String,
+    pub title: String,
+    /// Path of the match relative to the Zammad web UI
+    pub url: String,
+}
+
+/// Searches Zammad tickets, e.g. `state.name:open AND group.name:Support`.
+#[derive(Debug)]
+pub struct ZammadSearchTicketsRequest {
+    query: String,
+    page: u32,
+    per_page: u32,
+}
+
+impl ZammadSearchTicketsRequest {
+    /// `page` starts at 1.
+    pub fn new(query: &str, page: u32, per_page: u32) -> Self {
+        Self {
+            query: query.to_string(),
+            page,
+            per_page,
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<Vec<ZammadApiTicket>> {
+        let client = get_zammad_client();
+        let url = format!("{}/tickets/search", get_zammad_url());
+
+        info!("Zammad Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .query(&[
+                ("query", self.query.as_str()),
+                ("page", &self.page.to_string()),
+                ("per_page", &self.per_page.to_string()),
+                ("expand", "true"),
+                // A stable order, so pages don't shift while tickets are created
+                ("sort_by", "id"),
+                ("order_by", "asc"),
+            ])
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<Vec<ZammadApiTicket>>()
+            .await?;
+
+        debug!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+/// Fetches a ticket from the Zammad API, e.g. to cross-check an inbound webhook.
+#[derive(Debug)]
+pub struct ZammadGetTicketRequest {
+    ticket_id: i32,
+}
+
+impl ZammadGetTicketRequest {
+    pub fn new(ticket_id: i32) -> Self {
+        Self { ticket_id }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<ZammadGetTicketResponse> {
+        let client = get_zammad_client();
+        // expand=true returns the state name instead of only the state_id
+        let url = format!(
+            "{}/tickets/{}?expand=true",
+            get_zammad_url(),
+            self.ticket_id
+        );
+
+        info!("Zammad Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<ZammadGetTicketResponse>()
+            .await?;
+
+        debug!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZammadGetTicketResponse {
+    pub id: i32,
+    pub number: String,
+    pub state: String,
+    pub priority_id: ZammadPriorityId,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Fetches the checklist of a ticket (Zammad 6.2+).
+#[derive(Debug)]
+pub struct ZammadGetChecklistRequest {
+    ticket_id: i32,
+}
+
+impl ZammadGetChecklistRequest {
+    pub fn new(ticket_id: i32) -> Self {
+        Self { ticket_id }
+    }
+
+    /// Returns `None` if the ticket doesn't have a checklist.
+    pub async fn submit(&self) -> anyhow::Result<Option<ZammadChecklist>> {
+        let client = get_zammad_client();
+        let url = format!(
+            "{}/tickets/{}/checklist?expand=true",
+            get_zammad_url(),
+            self.ticket_id
+        );
+
+        info!("Zammad Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let resp = resp
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<Option<ZammadChecklist>>()
+            .await?;
+
+        debug!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZammadChecklist {
+    #[serde(default)]
+    pub items: Vec<ZammadChecklistItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZammadChecklistItem {
+    pub id: i64,
+    pub text: String,
+    pub checked: bool,
+}
+
+/// Checks or unchecks a checklist item.
+#[derive(Debug, Serialize)]
+pub struct ZammadUpdateChecklistItemRequest {
+    #[serde(skip)]
+    item_id: i64,
+    checked: bool,
+}
+
+impl ZammadUpdateChecklistItemRequest {
+    pub fn new(item_id: i64, checked: bool) -> Self {
+        Self { item_id, checked }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<()> {
+        let client = get_zammad_client();
+        let url = format!("{}/checklist_items/{}", get_zammad_url(), self.item_id);
+
+        info!("Zammad Request URL: {}", url);
+        info!("Zammad Request: {:?}", self);
+
+        client
+            .patch(&url)
+            .json(&self)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?;
+
+        Ok(())
+    }
+}
+
+/// Fetches a single ticket article from the Zammad API.
+#[derive(Debug)]
+pub struct ZammadGetArticleRequest {
+    article_id: u64,
+}
+
+impl ZammadGetArticleRequest {
+    pub fn new(article_id: u64) -> Self {
+        Self { article_id }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<ZammadGetArticleResponse> {
+        let client = get_zammad_client();
+        let url = format!("{}/ticket_articles/{}", get_zammad_url(), self.article_id);
+
+        info!("Zammad Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<ZammadGetArticleResponse>()
+            .await?;
+
+        debug!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZammadGetArticleResponse {
+    pub id: u64,
+    pub ticket_id: u64,
+    #[serde(default)]
+    pub body: String,
+}
+
+/// Downloads the content of an article attachment.
+#[derive(Debug)]
+pub struct ZammadGetAttachmentRequest {
+    ticket_id: i32,
+    article_id: u64,
+    attachment_id: u64,
+}
+
+impl ZammadGetAttachmentRequest {
+    pub fn new(ticket_id: i32, article_id: u64, attachment_id: u64) -> Self {
+        Self {
+            ticket_id,
+            article_id,
+            attachment_id,
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<Vec<u8>> {
+        let client = get_zammad_client();
+        let url = format!(
+            "{}/ticket_attachment/{}/{}/{}",
+            get_zammad_url(),
+            self.ticket_id,
+            self.article_id,
+            self.attachment_id
+        );
+
+        info!("Zammad Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .bytes()
+            .await?;
+
+        Ok(resp.to_vec())
+    }
+}
+
+/// Lists one page of Zammad users.
+#[derive(Debug)]
+pub struct ZammadListUsersRequest {
+    page: u32,
+    per_page: u32,
+}
+
+impl ZammadListUsersRequest {
+    pub fn new(page: u32, per_page: u32) -> Self {
+        Self { page, per_page }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<Vec<ZammadListUsersResponse>> {
+        let client = get_zammad_client();
+        let url = format!(
+            "{}/users?page={}&per_page={}",
+            get_zammad_url(),
+            self.page,
+            self.per_page
+        );
+
+        info!("Zammad Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<Vec<ZammadListUsersResponse>>()
+            .await?;
+
+        debug!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ZammadListUsersResponse {
+    pub id: i64,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Lists all articles of a ticket.
+#[derive(Debug)]
+pub struct ZammadGetTicketArticlesRequest {
+    ticket_id: i32,
+}
+
+impl ZammadGetTicketArticlesRequest {
+    pub fn new(ticket_id: i32) -> Self {
+        Self { ticket_id }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<Vec<ZammadGetArticleResponse>> {
+        let client = get_zammad_client();
+        let url = format!(
+            "{}/ticket_articles/by_ticket/{}",
+            get_zammad_url(),
+            self.ticket_id
+        );
+
+        info!("Zammad Request URL: {}", url);
+
+        let resp = client
+            .get(&url)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?
+            .json::<Vec<ZammadGetArticleResponse>>()
+            .await?;
+
+        debug!("Zammad Response: {:?}", resp);
+
+        Ok(resp)
+    }
+}
+
+/// Deletes a ticket, e.g. after a smoke test. Requires admin permissions in Zammad.
+#[derive(Debug)]
+pub struct ZammadDeleteTicketRequest {
+    ticket_id: i32,
+}
+
+impl ZammadDeleteTicketRequest {
+    pub fn new(ticket_id: i32) -> Self {
+        Self { ticket_id }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<()> {
+        let client = get_zammad_client();
+        let url = format!("{}/tickets/{}", get_zammad_url(), self.ticket_id);
+
+        info!("Zammad Request URL: {}", url);
+
+        client
+            .delete(&url)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?;
+
+        Ok(())
+    }
+}
+
+/// Adds a tag to or removes it from a Zammad ticket.
+#[derive(Debug, Serialize)]
+pub struct ZammadTagRequest {
+    #[serde(skip)]
+    add: bool,
+    object: &'static str,
+    o_id: i32,
+    item: String,
+}
+
+impl ZammadTagRequest {
+    pub fn add(ticket_id: i32, tag: &str) -> Self {
+        Self {
+            add: true,
+            object: "Ticket",
+            o_id: ticket_id,
+            item: tag.to_string(),
+        }
+    }
+
+    pub fn remove(ticket_id: i32, tag: &str) -> Self {
+        Self {
+            add: false,
+            ..Self::add(ticket_id, tag)
+        }
+    }
+
+    pub async fn submit(&self) -> anyhow::Result<()> {
+        let client = get_zammad_client();
+        let request = if self.add {
+            client.post(format!("{}/tags/add", get_zammad_url()))
+        } else {
+            client.delete(format!("{}/tags/remove", get_zammad_url()))
+        };
+
+        info!("Zammad Request: {:?}", self);
+
+        request
+            .json(&self)
+            .basic_auth(get_zammad_credentials().0, Some(get_zammad_credentials().1))
+            .send_with_retry()
+            .await
+            .context("failed to send request to Zammad API")?
+            .error_for_status()
+            .context("error status from Zammad API")?;
+
+        Ok(())
+    }
+}
+
+/// Jira sends IDs as strings, payloads we serialized ourselves, e.g. in the outbox, carry
+/// them as numbers.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(i64),
+}
+
+pub(crate) fn string_to_number<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+    D: Deserializer<'de>,
+{
+    let s = match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(s) => s,
+        StringOrNumber::Number(n) => n.to_string(),
+    };
+    T::from_str(&s).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JiraAddCommentResponse {
+    #[serde(deserialize_with = "string_to_number")]
+    pub id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct JiraCreateIssueResponse {
+    #[serde(deserialize_with = "string_to_number")]
+    pub id: i32,
+    pub key: String,
+}
+
+/// Values available to templates of texts synced from Zammad.
+fn zammad_template_context(webhook: &ZammadWebhook, meta: &BTreeMap<String, String>) -> Value {
+    json!({ "ticket": webhook.ticket, "article": webhook.article, "meta": meta })
+}
+
+/// Values available to templates of texts synced from Jira.
+fn jira_template_context(
+    issue: &JiraApiIssue,
+    comment: Option<&JiraComment>,
+    meta: &BTreeMap<String, String>,
+) -> Value {
+    json!({ "issue": issue, "comment": comment, "meta": meta })
+}
+
+/// Fills in the header telling who wrote a synced comment, and where and when. Assignment
+/// metadata is available as `{meta.<key>}`.
+fn render_comment_header(
+    template: &str,
+    author: &str,
+    number: &str,
+    time: DateTime<Utc>,
+    meta: &BTreeMap<String, String>,
+) -> String {
+    let mut header = template
+        .replace("{author}", author)
+        .replace("{number}", number)
+        .replace("{time}", &time.format("%Y-%m-%d %H:%M UTC").to_string());
+    for (key, value) in meta {
+        header = header.replace(&format!("{{meta.{}}}", key), value);
+    }
+    header
+}
+
+fn convert_zammad_priority_to_jira_priority(priority: ZammadPriorityId) -> JiraPriorityEnum {
+    let converted = match priority {
+        ZammadPriorityId::Low => JiraPriorityEnum::Lowest,
+        ZammadPriorityId::Normal => JiraPriorityEnum::Medium,
+        ZammadPriorityId::High => JiraPriorityEnum::High,
+    };
+    decisions::record("priority.zammad_to_jira", priority as i32, &converted);
+    converted
+}
+
+pub fn convert_jira_priority_to_zammad_priority(priority: Option<&str>) -> ZammadPriorityId {
+    let converted = match priority {
+        Some("Highest") | Some("High") => ZammadPriorityId::High,
+        Some("Low") | Some("Lowest") => ZammadPriorityId::Low,
+        _ => ZammadPriorityId::Normal,
+    };
+    decisions::record("priority.jira_to_zammad", priority, converted as i32);
+    converted
+}
+
+pub fn convert_jira_status_category_to_zammad_state(
+    category: JiraStatusCategoryKey,
+) -> ZammadState {
+    let state = match category {
+        JiraStatusCategoryKey::Done => ZammadState::Closed,
+        JiraStatusCategoryKey::New
+        | JiraStatusCategoryKey::Indeterminate
+        | JiraStatusCategoryKey::Undefined => ZammadState::Open,
+    };
+    decisions::record("status.jira_to_zammad", category, state.as_str());
+    state
+}
+
+/// Hash of the serialized request, to detect updates that wouldn't change anything.
+pub fn content_hash(request: &impl Serialize) -> anyhow::Result<String> {
+    let body = serde_json::to_vec(request)?;
+    let digest = Sha256::digest(body);
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn get_jira_url() -> String {
+    config::get_jira().endpoint.clone()
+}
+
+/// Clients of the systems in the file; profiles hold their own. Like the configuration
+/// they're built from, they're globals rather than part of the [`AppState`]: requests are
+/// also sent by the outbox workers, background jobs and CLI commands, which have no state,
+/// and which client is used depends on the profile of the sync in the current task. As
+/// headers only change with a restart, they're never rebuilt.
+///
+/// [`AppState`]: crate::state::AppState
+static JIRA_CLIENT: OnceLock<Client> = OnceLock::new();
+static ZAMMAD_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// HTTP client sending the headers configured for Jira with every request. It is built
+/// once, so all requests share its connection pool.
+fn get_jira_client() -> Client {
+    let profile = profiles::current();
+    let client = match &profile {
+        Some(profile) => &profile.jira_client,
+        None => &JIRA_CLIENT,
+    };
+    client
+        .get_or_init(|| build_client(&config::get_jira().headers))
+        .clone()
+}
+
+/// HTTP client sending the headers configured for Zammad with every request. It is built
+/// once, so all requests share its connection pool.
+fn get_zammad_client() -> Client {
+    let profile = profiles::current();
+    let client = match &profile {
+        Some(profile) => &profile.zammad_client,
+        None => &ZAMMAD_CLIENT,
+    };
+    client
+        .get_or_init(|| build_client(&config::get_zammad().headers))
+        .clone()
+}
+
+/// Checks that Jira accepts the configured credentials, without retrying.
+pub async fn check_jira_access() -> anyhow::Result<()> {
+    let (username, token) = get_jira_credentials();
+    get_jira_client()
+        .get(format!("{}/myself", get_jira_api_url()))
+        .basic_auth(username, Some(token))
+        .send()
+        .await
+        .context("failed to send request to Jira API")?
+        .error_for_status()
+        .context("error status from Jira API")?;
+    Ok(())
+}
+
+/// Checks that Zammad accepts the configured credentials, without retrying.
+pub async fn check_zammad_access() -> anyhow::Result<()> {
+    let (username, token) = get_zammad_credentials();
+    get_zammad_client()
+        .get(format!("{}/users/me", get_zammad_url()))
+        .basic_auth(username, Some(token))
+        .send()
+        .await
+        .context("failed to send request to Zammad API")?
+        .error_for_status()
+        .context("error status from Zammad API")?;
+    Ok(())
+}
+
+fn build_client(headers: &HashMap<String, String>) -> Client {
+    // Headers are validated when the configuration is loaded
+    let headers = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((
+                HeaderName::from_str(name).ok()?,
+                HeaderValue::from_str(value).ok()?,
+            ))
+        })
+        .collect::<HeaderMap>();
+    Client::builder()
+        .default_headers(headers)
+        .build()
+        .expect("failed to build HTTP client")
+}
+
+fn get_jira_credentials() -> (String, String) {
+    let jira_config = config::get_jira();
+    (jira_config.username.clone(), jira_config.token.clone())
+}
+
+/// Uses the project configured for the ticket's organization, if any.
+fn get_jira_project(ticket: &ZammadTicket) -> i32 {
+    let jira_config = config::get_jira();
+    let organization_project = ticket
+        .organization
+        .as_ref()
+        .and_then(|organization| jira_config.organizations.mapping.get(&organization.name))
+        .and_then(|mapping| mapping.project_id);
+    let group_project = ticket
+        .group
+        .as_ref()
+        .and_then(|group| jira_config.groups.get(&group.name))
+        .and_then(|mapping| mapping.project_id);
+    let project_id = organization_project
+        .or(group_project)
+        .unwrap_or(jira_config.project_id);
+    decisions::record(
+        "project",
+        json!({
+            "organization": ticket.organization.as_ref().map(|organization| &organization.name),
+            "group": ticket.group.as_ref().map(|group| &group.name),
+        }),
+        project_id,
+    );
+    project_id
+}
+
+/// Adds the component, labels and assignee configured for the ticket's group. Components
+/// and labels are appended, so they can be combined with the organization field.
+fn add_group_fields(ticket: &ZammadTicket, fields: &mut Map<String, Value>) {
+    let jira = config::get_jira();
+    let Some(mapping) = ticket
+        .group
+        .as_ref()
+        .and_then(|group| jira.groups.get(&group.name))
+    else {
+        return;
+    };
+
+    let before = fields.clone();
+    let mut append = |field: &str, value: Value| {
+        if let Value::Array(values) = fields.entry(field).or_insert_with(|| json!([])) {
+            values.push(value);
+        }
+    };
+    if let Some(component) = &mapping.component {
+        append("components", json!({ "name": component }));
+    }
+    for label in &mapping.labels {
+        // Labels must not contain spaces
+        append("labels", json!(label.replace(' ', "_")));
+    }
+    if let Some(assignee) = &mapping.assignee {
+        fields.insert("assignee".to_string(), json!({ "accountId": assignee }));
+    }
+    decisions::record(
+        "group",
+        ticket.group.as_ref().map(|group| &group.name),
+        fields
+            .iter()
+            .filter(|(field, value)| before.get(*field) != Some(*value))
+            .map(|(field, value)| (field.clone(), value.clone()))
+            .collect::<Map<_, _>>(),
+    );
+}
+
+fn get_mapped_fields(ticket: &ZammadTicket) -> Map<String, Value> {
+    let mut fields = Map::new();
+    for (rule, input, mapped) in [
+        (
+            "organization",
+            json!(
+                ticket
+                    .organization
+                    .as_ref()
+                    .map(|organization| &organization.name)
+            ),
+            get_organization_fields(ticket),
+        ),
+        (
+            "escalation",
+            json!({
+                "first_response_escalation_at": ticket.first_response_escalation_at,
+                "update_escalation_at": ticket.update_escalation_at,
+                "close_escalation_at": ticket.close_escalation_at,
+            }),
+            get_escalation_fields(ticket),
+        ),
+        (
+            "estimate",
+            json!({
+                "attribute": config::get_jira()
+                    .estimate
+                    .as_ref()
+                    .and_then(|estimate| estimate.zammad_attribute.as_ref())
+                    .and_then(|attribute| ticket.extra_fields.get(attribute)),
+                "tags": ticket.tags,
+            }),
+            get_estimate_fields(ticket),
+        ),
+    ] {
+        if !mapped.is_empty() {
+            decisions::record(rule, input, &mapped);
+        }
+        fields.extend(mapped);
+    }
+    fields
+}
+
+/// Jira story points or original estimate, taken from a ticket attribute or tag.
+fn get_estimate_fields(ticket: &ZammadTicket) -> Map<String, Value> {
+    let mut fields = Map::new();
+    let Some(estimate_config) = &config::get_jira().estimate else {
+        return fields;
+    };
+
+    let from_attribute = estimate_config
+        .zammad_attribute
+        .as_ref()
+        .and_then(|attribute| ticket.extra_fields.get(attribute))
+        .and_then(|value| {
+            value
+                .as_f64()
+                .or_else(|| value.as_str()?.trim().parse().ok())
+        });
+    let from_tag = || {
+        let prefix = estimate_config.tag_prefix.as_ref()?;
+        ticket.tags.iter().find_map(|tag| {
+            tag.strip_prefix(prefix.as_str())?
+                .trim()
+                .parse::<f64>()
+                .ok()
+        })
+    };
+    let Some(estimate) = from_attribute.or_else(from_tag) else {
+        return fields;
+    };
+
+    let value = match estimate_config.field.as_str() {
+        "timetracking" => {
+            json!({ "originalEstimate": format!("{}{}", estimate, estimate_config.unit) })
+        }
+        _ => json!(estimate),
+    };
+    fields.insert(estimate_config.field.clone(), value);
+    fields
+}
+
+/// Jira date-time fields holding the ticket's SLA deadlines, according to the configured fields.
+fn get_escalation_fields(ticket: &ZammadTicket) -> Map<String, Value> {
+    let escalation_config = &config::get_jira().escalation;
+    let deadlines = [
+        (
+            &escalation_config.first_response_field,
+            ticket.first_response_escalation_at,
+        ),
+        (&escalation_config.update_field, ticket.update_escalation_at),
+        (&escalation_config.close_field, ticket.close_escalation_at),
+    ];
+
+    deadlines
+        .into_iter()
+        .filter_map(|(field, deadline)| {
+            let value = match deadline {
+                Some(deadline) => json!(deadline.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string()),
+                // Clears the field once the deadline no longer applies
+                None => Value::Null,
+            };
+            field.clone().map(|field| (field, value))
+        })
+        .collect()
+}
+
+/// Whether one of the ticket's SLA deadlines has passed.
+fn is_escalated(ticket: &ZammadTicket) -> bool {
+    ticket
+        .escalation_at
+        .is_some_and(|escalation_at| escalation_at <= Utc::now())
+}
+
+/// Jira fields describing the ticket's organization, according to the configured field.
+fn get_organization_fields(ticket: &ZammadTicket) -> Map<String, Value> {
+    let organization_config = &config::get_jira().organizations;
+    let mut fields = Map::new();
+    let (Some(field), Some(organization)) = (&organization_config.field, &ticket.organization)
+    else {
+        return fields;
+    };
+
+    let value = organization_config
+        .mapping
+        .get(&organization.name)
+        .and_then(|mapping| mapping.value.clone())
+        .unwrap_or_else(|| organization.name.clone());
+    let value = match field.as_str() {
+        "components" => json!([{ "name": value }]),
+        // Labels must not contain spaces
+        "labels" => json!([value.replace(' ', "_")]),
+        _ => json!(value),
+    };
+    fields.insert(field.clone(), value);
+    fields
+}
+
+/// The configured Jira endpoint points to the issue resource; other resources
+/// (search, transitions, ...) live next to it.
+fn get_jira_api_url() -> String {
+    get_jira_url()
+        .trim_end_matches('/')
+        .trim_end_matches("/issue")
+        .to_string()
+}
+
+/// Base URL of the Jira site, used for APIs outside of `/rest/api`.
+pub fn get_jira_site_url() -> String {
+    let url = get_jira_url();
+    match url.split_once("/rest/") {
+        Some((site, _)) => site.to_string(),
+        None => url,
+    }
+}
+
+fn get_zammad_url() -> String {
+    config::get_zammad()
+        .endpoint
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Base URL of the Zammad web UI, used to build links for humans.
+pub fn get_zammad_web_url() -> String {
+    let url = get_zammad_url();
+    match url.split_once("/api/") {
+        Some((web, _)) => web.to_string(),
+        None => url,
+    }
 }
 
-fn get_jira_project() -> i32 {
-    config::get_jira().project_id
+fn get_zammad_credentials() -> (String, String) {
+    let zammad_config = config::get_zammad();
+    (zammad_config.username.clone(), zammad_config.token.clone())
 }
@@ -0,0 +1,207 @@
+use tracing::info;
+
+use super::{
+    api_request::{
+        JiraAddAttachmentRequest, JiraAttachment, JiraGetAttachmentRequest,
+        ZammadCreateArticleRequest, ZammadGetAttachmentRequest,
+    },
+    db::DB,
+    jira::{JiraApiIssue, JiraWebhook},
+    zammad::{ZammadAttachment, ZammadWebhook},
+};
+use crate::config::{self, AttachmentPolicy};
+
+/// Handles the attachments of the webhook's article according to the configured policies:
+/// uploads them to the Jira issue, or leaves them out. Returns notes about the attachments
+/// that weren't uploaded, to be added to the synced comment.
+pub async fn sync_to_jira(
+    db: &DB,
+    webhook: &ZammadWebhook,
+    jira_issue_id: i32,
+) -> anyhow::Result<Vec<String>> {
+    let Some(article_id) = webhook.article.id else {
+        return Ok(Vec::new());
+    };
+
+    let config = &config::get_jira().attachments;
+    let mut notes = Vec::new();
+    for attachment in &webhook.article.attachments {
+        let mime_type = attachment.mime_type();
+        // Disabled attachments are left out like skipped ones
+        let policy = if config::get().features.attachments {
+            config.policy(&mime_type)
+        } else {
+            AttachmentPolicy::Skip
+        };
+        info!(
+            "Attachment {} ({}) is handled with policy {:?}",
+            attachment.filename, mime_type, policy
+        );
+
+        match policy {
+            AttachmentPolicy::Pass => {
+                let data = download(webhook, article_id, attachment).await?;
+                let uploaded =
+                    JiraAddAttachmentRequest::new(&attachment.filename, &mime_type, data)
+                        .submit(&jira_issue_id)
+                        .await?;
+                // Jira announces the upload in the next webhook, which mustn't come back
+                for jira_attachment_id in &uploaded {
+                    db.create_attachment_mapping(jira_attachment_id, &article_id)
+                        .await?;
+                }
+            }
+            AttachmentPolicy::Summarize if mime_type == "text/calendar" => {
+                let data = download(webhook, article_id, attachment).await?;
+                notes.push(summarize_calendar(
+                    &attachment.filename,
+                    &String::from_utf8_lossy(&data),
+                ));
+            }
+            AttachmentPolicy::Skip | AttachmentPolicy::Summarize => {
+                notes.push(format!(
+                    "Attachment {} ({}) was not synced, see the Zammad ticket.",
+                    attachment.filename, mime_type
+                ));
+            }
+        }
+    }
+    Ok(notes)
+}
+
+/// Announces files attached to the Jira issue in an internal note on the Zammad ticket,
+/// with a download link or, if enabled, the file itself. Files uploaded from Zammad aren't
+/// announced.
+pub async fn announce_in_zammad(
+    db: &DB,
+    webhook: &JiraWebhook<JiraApiIssue>,
+    zammad_ticket_id: i32,
+) -> anyhow::Result<()> {
+    if !config::get().features.attachments {
+        return Ok(());
+    }
+    let added = webhook
+        .changelog
+        .iter()
+        .flat_map(|changelog| &changelog.items)
+        .filter(|item| item.field.eq_ignore_ascii_case("attachment"))
+        .filter_map(|item| item.to_id.as_deref()?.parse::<i32>().ok());
+
+    let config = &config::get_jira().attachments;
+    let mut attachments: Vec<(JiraAttachment, Option<Vec<u8>>)> = Vec::new();
+    for attachment_id in added {
+        if db.is_jira_attachment_synced(&attachment_id).await? {
+            continue;
+        }
+        let attachment = JiraGetAttachmentRequest::new(attachment_id)
+            .submit()
+            .await?;
+        let data = if config.mirror_to_zammad
+            && config.policy(&attachment.mime_type) == AttachmentPolicy::Pass
+        {
+            Some(JiraGetAttachmentRequest::download(&attachment).await?)
+        } else {
+            None
+        };
+        attachments.push((attachment, data));
+    }
+    if attachments.is_empty() {
+        return Ok(());
+    }
+
+    let mut lines = vec![format!(
+        "New attachments on Jira issue {}:",
+        webhook.issue.key
+    )];
+    for (attachment, data) in &attachments {
+        lines.push(match data {
+            Some(_) => format!(
+                "* {} ({})",
+                attachment.filename,
+                format_size(attachment.size)
+            ),
+            None => format!(
+                "* {} ({}): {}",
+                attachment.filename,
+                format_size(attachment.size),
+                attachment.content
+            ),
+        });
+    }
+    let mut note = ZammadCreateArticleRequest::internal_note(zammad_ticket_id, lines.join("\n"));
+    for (attachment, data) in &attachments {
+        if let Some(data) = data {
+            note = note.with_attachment(&attachment.filename, &attachment.mime_type, data);
+        }
+    }
+    let article = note.submit().await?;
+    info!(
+        "Announced {} Jira attachments in Zammad article {}",
+        attachments.len(),
+        article.id
+    );
+    for (attachment, _) in &attachments {
+        db.create_attachment_mapping(&attachment.id, &article.id)
+            .await?;
+    }
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+async fn download(
+    webhook: &ZammadWebhook,
+    article_id: u64,
+    attachment: &ZammadAttachment,
+) -> anyhow::Result<Vec<u8>> {
+    ZammadGetAttachmentRequest::new(webhook.ticket.id, article_id, attachment.id)
+        .submit()
+        .await
+}
+
+/// Describes the first event of an iCalendar file in a few lines.
+fn summarize_calendar(filename: &str, content: &str) -> String {
+    // Long lines are folded by starting the continuation with a space or tab
+    let unfolded = content
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut lines = vec![format!("Calendar invite {}:", filename)];
+    let mut in_event = false;
+    for line in unfolded.lines() {
+        match line {
+            "BEGIN:VEVENT" => in_event = true,
+            "END:VEVENT" => break,
+            _ if in_event => {
+                let Some((name, value)) = line.split_once(':') else {
+                    continue;
+                };
+                // Parameters like "DTSTART;TZID=Europe/Berlin" don't matter for a summary
+                let label = match name.split(';').next().unwrap_or_default() {
+                    "SUMMARY" => "Title",
+                    "DTSTART" => "Start",
+                    "DTEND" => "End",
+                    "LOCATION" => "Location",
+                    "ORGANIZER" => "Organizer",
+                    _ => continue,
+                };
+                let value = value
+                    .trim_start_matches("mailto:")
+                    .replace("\\n", " ")
+                    .replace("\\,", ",")
+                    .replace("\\;", ";")
+                    .replace("\\\\", "\\");
+                lines.push(format!("* {}: {}", label, value));
+            }
+            _ => {}
+        }
+    }
+    lines.join("\n")
+}
@@ -1,123 +1,1893 @@
-use serde::Serialize;
-use sqlx::{Connection, Pool, Row, Sqlite, SqliteConnection, SqlitePool, migrate::MigrateDatabase};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    Pool, Row, Sqlite, SqlitePool,
+    migrate::{MigrateDatabase, Migrator},
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteRow, SqliteSynchronous},
+};
 use tracing::{debug, info};
+use utoipa::{IntoParams, ToSchema};
 
-use super::assignment::Assignment;
+use crate::audit::UpstreamCall;
+use crate::config;
+use crate::decisions::Decision;
+use crate::models::store::{self, MappingStore, ReplicaLock, SqliteStore};
 
+/// Clones share the connection pool.
+#[derive(Clone)]
 pub struct DB {
     conn: Pool<Sqlite>,
-    path: &'static str,
+    /// Holds the links, comment mappings and the audit log; the tables in `conn` unless
+    /// `db_path` selects a server database
+    store: Arc<dyn MappingStore>,
+}
+
+/// A webhook held back for review, e.g. because it was outside the freshness window.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuarantinedEvent {
+    pub id: i64,
+    pub path: String,
+    pub event_time: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+    pub payload: String,
+}
+
+/// The raw payload of a webhook as it was received, with the ticket it belongs to.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ArchivedPayload {
+    pub id: i64,
+    /// Route without the webhook ID, e.g. "zammad/update-ticket"
+    pub route: String,
+    /// The profile the webhook arrived for, if not for the systems of the file
+    pub profile: Option<String>,
+    pub request_id: Option<String>,
+    pub zammad_id: Option<i32>,
+    pub jira_id: Option<i32>,
+    /// Status the webhook was answered with
+    pub status: u16,
+    pub received_at: DateTime<Utc>,
+    pub payload: String,
+}
+
+/// Which archived payloads to list, newest first; unset filters match all.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArchiveFilter {
+    pub zammad_id: Option<i32>,
+    pub jira_id: Option<i32>,
+    pub limit: Option<u32>,
+}
+
+/// A ticket linked to its issue, as listed by the admin API.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Mapping {
+    pub zammad_id: i32,
+    pub jira_id: i32,
+    /// Not synced to Jira for lack of permissions
+    pub restricted: bool,
+}
+
+/// Which mappings to list, ordered by ticket; unset filters match all.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MappingFilter {
+    pub zammad_id: Option<i32>,
+    pub jira_id: Option<i32>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// A Zammad checklist item as last synced, with the Jira sub-task it is shown as, if any.
+#[derive(Debug, Clone)]
+pub struct ChecklistItem {
+    pub zammad_item_id: i64,
+    pub zammad_id: i32,
+    pub jira_id: Option<i32>,
+    pub text: String,
+    pub checked: bool,
+}
+
+/// A sync that failed, with the mapping decisions made before it failed.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncFailure {
+    pub id: i64,
+    pub operation: String,
+    pub zammad_id: Option<i32>,
+    pub error: String,
+    pub decisions: serde_json::Value,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// A webhook whose sync failed, kept to be replayed once the cause is fixed.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeadLetter {
+    pub id: i64,
+    /// What the webhook triggered, e.g. "zammad.update"
+    pub operation: String,
+    pub zammad_id: Option<i32>,
+    pub payload: serde_json::Value,
+    /// Error of the last attempt
+    pub error: String,
+    pub attempts: i64,
+    pub failed_at: DateTime<Utc>,
+    /// The profile the webhook arrived for, if not for the systems of the file
+    pub profile: Option<String>,
+}
+
+/// A ticket whose Jira issue the connector isn't allowed to edit; it isn't synced to Jira
+/// until the restriction is lifted.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RestrictedAssignment {
+    pub zammad_id: i32,
+    /// The permission error Jira answered with
+    pub error: String,
+    /// The internal note telling agents about it
+    pub note_article_id: Option<i64>,
+    pub restricted_at: DateTime<Utc>,
+}
+
+/// Syncing was paused by an admin; webhooks are queued meanwhile.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncPause {
+    pub reason: Option<String>,
+    pub paused_at: DateTime<Utc>,
+}
+
+/// What an annotation is about. Annotations of a sync failure or dead letter also carry its
+/// ticket, so they show up with the assignment's annotations.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AnnotationTarget {
+    pub zammad_id: Option<i32>,
+    pub sync_failure_id: Option<i64>,
+    pub dead_letter_id: Option<i64>,
+}
+
+/// A note an operator left on an assignment or sync event, e.g. during an incident.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Annotation {
+    pub id: i64,
+    #[serde(flatten)]
+    pub target: AnnotationTarget,
+    pub author: Option<String>,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A sync of a stored webhook, as kept in the audit log.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub operation: String,
+    pub direction: String,
+    pub zammad_id: Option<i32>,
+    pub jira_id: Option<i32>,
+    pub request_id: Option<String>,
+    /// Fields changed in Jira or Zammad
+    pub fields: Vec<String>,
+    pub calls: Vec<UpstreamCall>,
+    pub decisions: serde_json::Value,
+    /// Why the sync failed, if it did
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Which audit entries to list, newest first; unset filters match all.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilter {
+    pub zammad_id: Option<i32>,
+    pub jira_id: Option<i32>,
+    /// Entries that changed this field
+    pub field: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+}
+
+/// A sync profile as stored by the admin API, with its configuration as it was sent.
+#[derive(Debug)]
+pub struct StoredProfile {
+    pub name: String,
+    pub config: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A webhook that was accepted and waits to be synced.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OutboxJob {
+    pub id: i64,
+    /// What the webhook triggers, e.g. "zammad.update"
+    pub operation: String,
+    pub zammad_id: Option<i32>,
+    pub payload: serde_json::Value,
+    pub received_at: DateTime<Utc>,
+    /// The profile the webhook arrived for, if not for the systems of the file
+    pub profile: Option<String>,
+    /// The X-Request-Id of the webhook
+    pub request_id: Option<String>,
+    /// When it's synced again, if its target system was unavailable
+    pub not_before: Option<DateTime<Utc>>,
+}
+
+/// An outbox job as listed in the queue.
+#[derive(Debug)]
+pub struct QueuedJob {
+    pub id: i64,
+    pub operation: String,
+    pub zammad_id: Option<i32>,
+    pub received_at: DateTime<Utc>,
+    pub not_before: Option<DateTime<Utc>>,
+}
+
+/// The value a field had after it was last synced, and which side it came from.
+#[derive(Debug)]
+pub struct FieldSyncState {
+    pub value: String,
+    pub source: String,
+    /// When the value was changed on its source side
+    pub changed_at: DateTime<Utc>,
+    /// When the value was written to the other side
+    pub synced_at: DateTime<Utc>,
+}
+
+/// A period in which a system is announced to be unavailable. Incidents and running
+/// maintenances don't have an end until the status page says they're over.
+#[derive(Debug, Serialize)]
+pub struct MaintenanceWindow {
+    pub name: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+/// A lease held by one of the replicas sharing the store, e.g. to run the background jobs.
+#[derive(Debug, Serialize)]
+pub struct Lease {
+    pub holder: String,
+    pub acquired_at: DateTime<Utc>,
+    pub renewed_at: DateTime<Utc>,
+    /// Until then no other holder can take it, unless it's released
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Links a Zammad user to the Jira account acting on their behalf.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserMapping {
+    pub zammad_user_id: i64,
+    pub jira_account_id: String,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// The versioned migrations in `migrations/`, each applied once and in order. Applied ones are
+/// verified by checksum, so a released migration is never edited; changes go into a new one.
+/// Databases from before were migrated on every start, so their migrations are idempotent
+/// and simply applied once more.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// The SQLite database file, relative to the working directory, unless `db_path` is set.
+const DB_FILE: &str = "database.db";
+
+/// How long a write waits for another one to finish before it fails.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `--db-path`, which takes precedence over `db_path` in the configuration.
+static PATH: OnceLock<String> = OnceLock::new();
+
+/// Uses `path` instead of the configured `db_path`.
+pub fn set_path(path: String) {
+    let _ = PATH.set(path);
+}
+
+/// `db_path` as given on the command line or configured.
+fn db_path() -> Option<String> {
+    PATH.get()
+        .cloned()
+        .or_else(|| config::get().db_path.clone())
+}
+
+/// Whether `db_path` is the URL of a server database, e.g. `postgres://`, rather than a file.
+fn is_server_url(db_path: &str) -> bool {
+    db_path.contains("://") && !db_path.starts_with("sqlite://")
+}
+
+/// The SQLite database file: `db_path`, unless it's the URL of a server database, which only
+/// holds the links, comment mappings and the audit log.
+pub fn file() -> String {
+    match db_path() {
+        Some(path) if !is_server_url(&path) => {
+            path.strip_prefix("sqlite://").unwrap_or(&path).to_string()
+        }
+        _ => DB_FILE.to_string(),
+    }
+}
+
+/// Options to open the database file at `path`, with the key of `database_key` if it's
+/// encrypted.
+pub fn connect_options(path: &str) -> anyhow::Result<SqliteConnectOptions> {
+    // WAL lets webhooks read while another writes, and a write waits for the one in progress
+    // instead of failing with "database is locked"
+    let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path))?
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(BUSY_TIMEOUT)
+        .foreign_keys(true);
+    match &config::get().database_key {
+        None => Ok(options),
+        Some(_) if !cfg!(feature = "sqlcipher") => {
+            bail!("database_key needs a build with the sqlcipher feature")
+        }
+        // SQLCipher derives the key from the passphrase, given as string literal
+        Some(key) => Ok(options.pragma("key", format!("'{}'", key.replace('\'', "''")))),
+    }
 }
 
 impl DB {
     pub async fn new() -> anyhow::Result<Self> {
-        let db_path = "sqlite://database.db";
-        Self::create_db(db_path).await.unwrap();
-        let conn = SqlitePool::connect(db_path).await.unwrap();
-
-        let db = Self {
-            conn,
-            path: db_path,
+        let store = match db_path() {
+            Some(url) if is_server_url(&url) => Some(store::connect(&url).await?),
+            _ => None,
         };
+        Self::open(store).await
+    }
 
-        db.create_table().await?;
-        Ok(db)
+    /// Opens the database with the links, comment mappings, webhook deliveries and the audit
+    /// log kept by `store` instead of the one `db_path` selects, e.g. a store of an
+    /// application embedding the connector. Everything else stays in the SQLite database.
+    pub async fn with_store(store: Arc<dyn MappingStore>) -> anyhow::Result<Self> {
+        Self::open(Some(store)).await
     }
 
-    async fn create_db(path: &str) -> anyhow::Result<(), String> {
-        if !Sqlite::database_exists(path).await.unwrap_or(false) {
-            println!("Creating database {}", path);
-            match Sqlite::create_database(path).await {
-                Ok(_) => println!("Create db success"),
-                Err(error) => panic!("error: {}", error),
-            }
+    async fn open(store: Option<Arc<dyn MappingStore>>) -> anyhow::Result<Self> {
+        let file = file();
+        let url = &format!("sqlite://{}", file);
+        if Sqlite::database_exists(url).await.unwrap_or(false) {
+            debug!("Database already exists");
         } else {
-            println!("Database already exists");
+            info!("Creating database {}", url);
         }
-        Ok(())
+        // Created by the first connection, so an encrypted database is keyed from the start
+        let options = connect_options(&file)?.create_if_missing(true);
+        let conn = SqlitePool::connect_with(options)
+            .await
+            .with_context(|| format!("failed to open {}", file))?;
+
+        let store = store.unwrap_or_else(|| Arc::new(SqliteStore(conn.clone())));
+        let db = Self { conn, store };
+
+        db.create_table().await?;
+        Ok(db)
+    }
+
+    /// Waits for the queries in flight and closes the connections, so the database is left
+    /// consistent on shutdown.
+    pub async fn close(&self) {
+        self.conn.close().await;
     }
 
     async fn create_table(&self) -> anyhow::Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS assignments (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                zammad_id INTEGER,
-                jira_id INTEGER
-            )",
-        )
-        .execute(&self.conn)
-        .await?;
-        self.show_all_assignments().await?;
+        MIGRATOR
+            .run(&self.conn)
+            .await
+            .context("failed to migrate the database")?;
         Ok(())
     }
 
+    /// Keeps an existing assignment, e.g. of an earlier attempt that failed to create the
+    /// Jira issue.
     pub async fn create_assignment_from_zammad(&self, zammad_id: &i32) -> anyhow::Result<()> {
-        sqlx::query("INSERT INTO assignments (zammad_id) VALUES (?)")
-            .bind(zammad_id)
-            .execute(&self.conn)
-            .await?;
+        self.store.create_assignment_from_zammad(*zammad_id).await?;
         info!("Created assignment with zammad_id: {}", zammad_id);
         Ok(())
     }
 
+    /// Links the ticket to the issue, also if an assignment of the ticket exists already.
+    /// Fails if the issue is linked to another ticket.
+    pub async fn create_assignment(&self, zammad_id: &i32, jira_id: &i32) -> anyhow::Result<()> {
+        self.store.create_assignment(*zammad_id, *jira_id).await?;
+        info!(
+            "Created assignment with zammad_id: {}, jira_id: {}",
+            zammad_id, jira_id
+        );
+        Ok(())
+    }
+
     pub async fn add_jira_id_to_assignment(
         &self,
         jira_id: &i32,
         zammad_id: &i32,
     ) -> anyhow::Result<()> {
-        sqlx::query("UPDATE assignments SET jira_id = (?) WHERE zammad_id = (?)")
-            .bind(jira_id)
+        self.store
+            .add_jira_id_to_assignment(*jira_id, *zammad_id)
+            .await
+    }
+
+    /// Like `get_jira_id_by_zammad_id`, but `None` if the ticket isn't linked (yet).
+    pub async fn find_jira_id_by_zammad_id(&self, zammad_id: &i32) -> anyhow::Result<Option<i32>> {
+        let assignment = self.store.get_assignment_by_zammad_id(*zammad_id).await?;
+        Ok(assignment.flatten())
+    }
+
+    /// The linked tickets and issues that aren't restricted, as `(zammad_id, jira_id)`.
+    pub async fn get_assignments(&self) -> anyhow::Result<Vec<(i32, i32)>> {
+        let restricted: std::collections::BTreeSet<i32> = self
+            .get_restricted_assignments()
+            .await?
+            .into_iter()
+            .map(|restricted| restricted.zammad_id)
+            .collect();
+        let mut assignments = self.store.get_assignments().await?;
+        assignments.retain(|(zammad_id, _)| !restricted.contains(zammad_id));
+        Ok(assignments)
+    }
+
+    /// The linked tickets and issues matching the filter, restricted ones included; at most
+    /// `limit` (default 100) from `offset` on.
+    pub async fn get_mappings(&self, filter: &MappingFilter) -> anyhow::Result<Vec<Mapping>> {
+        let restricted: std::collections::BTreeSet<i32> = self
+            .get_restricted_assignments()
+            .await?
+            .into_iter()
+            .map(|restricted| restricted.zammad_id)
+            .collect();
+        Ok(self
+            .store
+            .get_assignments()
+            .await?
+            .into_iter()
+            .filter(|(zammad_id, jira_id)| {
+                filter.zammad_id.is_none_or(|id| id == *zammad_id)
+                    && filter.jira_id.is_none_or(|id| id == *jira_id)
+            })
+            .skip(filter.offset.unwrap_or(0))
+            .take(filter.limit.unwrap_or(100))
+            .map(|(zammad_id, jira_id)| Mapping {
+                zammad_id,
+                jira_id,
+                restricted: restricted.contains(&zammad_id),
+            })
+            .collect())
+    }
+
+    pub async fn get_jira_id_by_zammad_id(&self, zammad_id: &i32) -> anyhow::Result<i32> {
+        self.store
+            .get_assignment_by_zammad_id(*zammad_id)
+            .await?
+            .flatten()
+            .with_context(|| format!("Zammad ticket {} isn't linked", zammad_id))
+    }
+
+    pub async fn get_zammad_id_by_jira_id(&self, jira_id: &i32) -> anyhow::Result<Option<i32>> {
+        self.store.get_zammad_id_by_jira_id(*jira_id).await
+    }
+
+    pub async fn delete_assignment(&self, zammad_id: &i32) -> anyhow::Result<()> {
+        self.store.delete_assignment(*zammad_id).await?;
+        sqlx::query("DELETE FROM assignment_meta WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .execute(&self.conn)
+            .await?;
+        sqlx::query("DELETE FROM field_sync_state WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .execute(&self.conn)
+            .await?;
+        sqlx::query("DELETE FROM sync_hashes WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .execute(&self.conn)
+            .await?;
+        sqlx::query("DELETE FROM description_articles WHERE zammad_id = ?")
             .bind(zammad_id)
             .execute(&self.conn)
             .await?;
+        sqlx::query("DELETE FROM source_updates WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .execute(&self.conn)
+            .await?;
+        sqlx::query("DELETE FROM checklist_items WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .execute(&self.conn)
+            .await?;
+        info!("Deleted assignment with zammad_id: {}", zammad_id);
         Ok(())
     }
 
-    pub async fn get_jira_id_by_zammad_id(&self, zammad_id: &i32) -> anyhow::Result<i32> {
-        let jira_id = sqlx::query("SELECT * FROM assignments WHERE zammad_id = ?")
+    /// Custom key/value data integrators stored on the assignment of the Zammad ticket.
+    pub async fn get_assignment_meta(
+        &self,
+        zammad_id: &i32,
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let rows = sqlx::query("SELECT key, value FROM assignment_meta WHERE zammad_id = ?")
             .bind(zammad_id)
-            .fetch_one(&self.conn)
-            .await?
-            .try_get("jira_id")
-            .map_err(|e| anyhow::anyhow!("Failed to get jira_id from row: {}", e))?;
+            .fetch_all(&self.conn)
+            .await?;
 
-        Ok(jira_id)
+        rows.iter()
+            .map(|row| Ok((row.try_get("key")?, row.try_get("value")?)))
+            .collect()
     }
 
-    pub async fn show_all_assignments(&self) -> anyhow::Result<()> {
-        let assignments = match sqlx::query("SELECT * FROM assignments")
-            .fetch_all(&self.conn)
-            .await
-        {
-            Ok(rows) => rows,
-            Err(e) => return Err(anyhow::anyhow!("Failed to fetch assignments: {}", e)),
-        };
+    pub async fn set_assignment_meta(
+        &self,
+        zammad_id: &i32,
+        key: &str,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO assignment_meta (zammad_id, key, value) VALUES (?, ?, ?)
+             ON CONFLICT(zammad_id, key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(zammad_id)
+        .bind(key)
+        .bind(value)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns whether the key existed.
+    pub async fn delete_assignment_meta(&self, zammad_id: &i32, key: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM assignment_meta WHERE zammad_id = ? AND key = ?")
+            .bind(zammad_id)
+            .bind(key)
+            .execute(&self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_field_sync_state(
+        &self,
+        zammad_id: &i32,
+        field: &str,
+    ) -> anyhow::Result<Option<FieldSyncState>> {
+        let row = sqlx::query(
+            "SELECT value, source, changed_at, synced_at FROM field_sync_state
+             WHERE zammad_id = ? AND field = ?",
+        )
+        .bind(zammad_id)
+        .bind(field)
+        .fetch_optional(&self.conn)
+        .await?;
+
+        row.map(|row| {
+            Ok(FieldSyncState {
+                value: row.try_get("value")?,
+                source: row.try_get("source")?,
+                changed_at: row.try_get("changed_at")?,
+                synced_at: row.try_get("synced_at")?,
+            })
+        })
+        .transpose()
+    }
+
+    pub async fn set_field_sync_state(
+        &self,
+        zammad_id: &i32,
+        field: &str,
+        state: &FieldSyncState,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO field_sync_state (zammad_id, field, value, source, changed_at, synced_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(zammad_id, field) DO UPDATE SET value = excluded.value,
+                 source = excluded.source, changed_at = excluded.changed_at,
+                 synced_at = excluded.synced_at",
+        )
+        .bind(zammad_id)
+        .bind(field)
+        .bind(&state.value)
+        .bind(&state.source)
+        .bind(state.changed_at)
+        .bind(state.synced_at)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn create_user_mapping(&self, mapping: &UserMapping) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO user_mappings (zammad_user_id, jira_account_id, email, created_at)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(mapping.zammad_user_id)
+        .bind(&mapping.jira_account_id)
+        .bind(&mapping.email)
+        .bind(Utc::now())
+        .execute(&self.conn)
+        .await?;
+        info!(
+            "Created user mapping zammad_user_id: {}, jira_account_id: {}",
+            mapping.zammad_user_id, mapping.jira_account_id
+        );
+        Ok(())
+    }
+
+    pub async fn get_user_mappings(&self) -> anyhow::Result<Vec<UserMapping>> {
+        let rows = sqlx::query(
+            "SELECT zammad_user_id, jira_account_id, email FROM user_mappings
+             ORDER BY zammad_user_id",
+        )
+        .fetch_all(&self.conn)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(UserMapping {
+                    zammad_user_id: row.try_get("zammad_user_id")?,
+                    jira_account_id: row.try_get("jira_account_id")?,
+                    email: row.try_get("email")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns whether a mapping existed.
+    pub async fn delete_user_mapping(&self, zammad_user_id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM user_mappings WHERE zammad_user_id = ?")
+            .bind(zammad_user_id)
+            .execute(&self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_backfill_cursor(&self, name: &str) -> anyhow::Result<Option<u32>> {
+        let row = sqlx::query("SELECT position FROM backfill_cursors WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.conn)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get("position")?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_backfill_cursor(&self, name: &str, position: u32) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO backfill_cursors (name, position) VALUES (?, ?)
+             ON CONFLICT(name) DO UPDATE SET position = excluded.position",
+        )
+        .bind(name)
+        .bind(position)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_backfill_cursor(&self, name: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM backfill_cursors WHERE name = ?")
+            .bind(name)
+            .execute(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_asset_references(&self, jira_id: &i32) -> anyhow::Result<Option<String>> {
+        let row = sqlx::query("SELECT rendered FROM asset_references WHERE jira_id = ?")
+            .bind(jira_id)
+            .fetch_optional(&self.conn)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get("rendered")?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_asset_references(&self, jira_id: &i32, rendered: &str) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO asset_references (jira_id, rendered) VALUES (?, ?)
+             ON CONFLICT(jira_id) DO UPDATE SET rendered = excluded.rendered",
+        )
+        .bind(jira_id)
+        .bind(rendered)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// The synced checklist items of the ticket, in the order they were created.
+    pub async fn get_checklist_items(&self, zammad_id: &i32) -> anyhow::Result<Vec<ChecklistItem>> {
+        let rows = sqlx::query(
+            "SELECT zammad_item_id, zammad_id, jira_id, text, checked FROM checklist_items
+             WHERE zammad_id = ? ORDER BY zammad_item_id",
+        )
+        .bind(zammad_id)
+        .fetch_all(&self.conn)
+        .await?;
+
+        rows.iter().map(checklist_item_from_row).collect()
+    }
+
+    pub async fn find_checklist_item_by_jira_id(
+        &self,
+        jira_id: &i32,
+    ) -> anyhow::Result<Option<ChecklistItem>> {
+        let row = sqlx::query(
+            "SELECT zammad_item_id, zammad_id, jira_id, text, checked FROM checklist_items
+             WHERE jira_id = ?",
+        )
+        .bind(jira_id)
+        .fetch_optional(&self.conn)
+        .await?;
 
-        println!("Found {} assignments:", assignments.len());
-        for row in assignments {
-            // Extract fields from the row using get
-            let id: i32 = match row.try_get("id") {
-                Ok(id) => id,
-                Err(_) => 0,
-            };
+        row.as_ref().map(checklist_item_from_row).transpose()
+    }
 
-            let zammad_id: Option<String> = match row.try_get("zammad_id") {
-                Ok(id) => id,
-                Err(_) => None,
-            };
+    pub async fn set_checklist_item(&self, item: &ChecklistItem) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO checklist_items (zammad_item_id, zammad_id, jira_id, text, checked)
+             VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(zammad_item_id) DO UPDATE SET jira_id = excluded.jira_id,
+                 text = excluded.text, checked = excluded.checked",
+        )
+        .bind(item.zammad_item_id)
+        .bind(item.zammad_id)
+        .bind(item.jira_id)
+        .bind(&item.text)
+        .bind(item.checked)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
 
-            let jira_id: Option<String> = match row.try_get("jira_id") {
-                Ok(id) => id,
-                Err(_) => None,
-            };
+    /// Hash of the last update sent to `target` ("jira" or "zammad") for the assignment.
+    pub async fn get_sync_hash(
+        &self,
+        zammad_id: &i32,
+        target: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let row = sqlx::query("SELECT hash FROM sync_hashes WHERE zammad_id = ? AND target = ?")
+            .bind(zammad_id)
+            .bind(target)
+            .fetch_optional(&self.conn)
+            .await?;
 
-            println!(
-                "DB entries: id={}, zammad_id={}, jira_id={}",
-                id,
-                zammad_id.unwrap_or_else(|| "None".to_string()),
-                jira_id.unwrap_or_else(|| "None".to_string())
-            );
+        match row {
+            Some(row) => Ok(Some(row.try_get("hash")?)),
+            None => Ok(None),
         }
+    }
+
+    pub async fn set_sync_hash(
+        &self,
+        zammad_id: &i32,
+        target: &str,
+        hash: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO sync_hashes (zammad_id, target, hash) VALUES (?, ?, ?)
+             ON CONFLICT(zammad_id, target) DO UPDATE SET hash = excluded.hash",
+        )
+        .bind(zammad_id)
+        .bind(target)
+        .bind(hash)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Forgets the last update sent to `target`, e.g. because that side was changed since.
+    pub async fn delete_sync_hash(&self, zammad_id: &i32, target: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM sync_hashes WHERE zammad_id = ? AND target = ?")
+            .bind(zammad_id)
+            .bind(target)
+            .execute(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Records the article the Jira description of the assignment was created from.
+    pub async fn set_description_article(
+        &self,
+        zammad_id: &i32,
+        zammad_article_id: &u64,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO description_articles (zammad_id, zammad_article_id)
+             VALUES (?, ?)",
+        )
+        .bind(zammad_id)
+        .bind(*zammad_article_id as i64)
+        .execute(&self.conn)
+        .await?;
         Ok(())
     }
+
+    pub async fn get_description_article(&self, zammad_id: &i32) -> anyhow::Result<Option<u64>> {
+        let row =
+            sqlx::query("SELECT zammad_article_id FROM description_articles WHERE zammad_id = ?")
+                .bind(zammad_id)
+                .fetch_optional(&self.conn)
+                .await?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get::<i64, _>("zammad_article_id")? as u64)),
+            None => Ok(None),
+        }
+    }
+
+    /// Records that `source` ("zammad" or "jira") changed the assignment at `updated_at`.
+    /// Returns false, recording nothing, if a later change was recorded already.
+    pub async fn record_source_update(
+        &self,
+        zammad_id: &i32,
+        source: &str,
+        updated_at: DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO source_updates (zammad_id, source, updated_at) VALUES (?, ?, ?)
+             ON CONFLICT(zammad_id, source) DO UPDATE SET updated_at = excluded.updated_at
+             WHERE excluded.updated_at >= source_updates.updated_at",
+        )
+        .bind(zammad_id)
+        .bind(source)
+        .bind(updated_at)
+        .execute(&self.conn)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces the maintenance windows of `system` ("zammad" or "jira").
+    pub async fn set_maintenance_windows(
+        &self,
+        system: &str,
+        windows: &[MaintenanceWindow],
+    ) -> anyhow::Result<()> {
+        let mut transaction = self.conn.begin().await?;
+        sqlx::query("DELETE FROM maintenance_windows WHERE system = ?")
+            .bind(system)
+            .execute(&mut *transaction)
+            .await?;
+        for window in windows {
+            sqlx::query(
+                "INSERT INTO maintenance_windows (system, name, starts_at, ends_at)
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(system)
+            .bind(&window.name)
+            .bind(window.starts_at)
+            .bind(window.ends_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Links a Zammad article to the Jira comment it was synced to (or from).
+    pub async fn create_comment_mapping(
+        &self,
+        zammad_article_id: &u64,
+        jira_comment_id: &i32,
+    ) -> anyhow::Result<()> {
+        self.store
+            .create_comment_mapping(*zammad_article_id, *jira_comment_id)
+            .await
+    }
+
+    /// Whether the article was already synced, or was itself created from a Jira comment.
+    pub async fn is_zammad_article_synced(&self, zammad_article_id: &u64) -> anyhow::Result<bool> {
+        if self
+            .store
+            .is_zammad_article_mapped(*zammad_article_id)
+            .await?
+        {
+            return Ok(true);
+        }
+        let row = sqlx::query(
+            "SELECT 1 FROM attachment_mappings WHERE zammad_article_id = ?
+             UNION ALL SELECT 1 FROM restricted_assignments WHERE note_article_id = ?",
+        )
+        .bind(*zammad_article_id as i64)
+        .bind(*zammad_article_id as i64)
+        .fetch_optional(&self.conn)
+        .await?;
+        Ok(row.is_some())
+    }
+
+    /// Links a Jira attachment to the Zammad article it was uploaded from or announced in.
+    pub async fn create_attachment_mapping(
+        &self,
+        jira_attachment_id: &i32,
+        zammad_article_id: &u64,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT OR IGNORE INTO attachment_mappings (jira_attachment_id, zammad_article_id)
+             VALUES (?, ?)",
+        )
+        .bind(jira_attachment_id)
+        .bind(*zammad_article_id as i64)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Whether the attachment was uploaded from Zammad or already announced there.
+    pub async fn is_jira_attachment_synced(
+        &self,
+        jira_attachment_id: &i32,
+    ) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM attachment_mappings WHERE jira_attachment_id = ?")
+            .bind(jira_attachment_id)
+            .fetch_optional(&self.conn)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Whether the comment was already synced, or was itself created from a Zammad article.
+    pub async fn is_jira_comment_synced(&self, jira_comment_id: &i32) -> anyhow::Result<bool> {
+        self.store.is_jira_comment_mapped(*jira_comment_id).await
+    }
+
+    /// Records a webhook delivery. Returns `false` if the delivery was already claimed, in
+    /// which case it must not be processed again.
+    pub async fn claim_delivery(&self, delivery_id: &str) -> anyhow::Result<bool> {
+        self.store.claim_delivery(delivery_id).await
+    }
+
+    pub async fn release_delivery(&self, delivery_id: &str) -> anyhow::Result<()> {
+        self.store.release_delivery(delivery_id).await
+    }
+
+    pub async fn prune_deliveries(&self, before: DateTime<Utc>) -> anyhow::Result<u64> {
+        self.store.prune_deliveries(before).await
+    }
+
+    /// Takes the lease for `ttl`, or renews it if `holder` holds it already. Returns `false`
+    /// if another holder's lease hasn't expired yet.
+    pub async fn acquire_lease(
+        &self,
+        name: &str,
+        holder: &str,
+        ttl: Duration,
+    ) -> anyhow::Result<bool> {
+        self.store.acquire_lease(name, holder, ttl).await
+    }
+
+    /// Gives up the lease, if `holder` holds it, so another holder can take it right away.
+    pub async fn release_lease(&self, name: &str, holder: &str) -> anyhow::Result<()> {
+        self.store.release_lease(name, holder).await
+    }
+
+    pub async fn get_lease(&self, name: &str) -> anyhow::Result<Option<Lease>> {
+        self.store.get_lease(name).await
+    }
+
+    /// Locks the ticket across the replicas sharing the store, if it's shared; see
+    /// `locks::lock_ticket`.
+    pub async fn lock_ticket_across_replicas(
+        &self,
+        zammad_id: i32,
+    ) -> anyhow::Result<Option<ReplicaLock>> {
+        self.store.lock_ticket(zammad_id).await
+    }
+
+    pub async fn quarantine_event(
+        &self,
+        path: &str,
+        event_time: DateTime<Utc>,
+        payload: &str,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO quarantined_events (path, event_time, received_at, payload)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(path)
+        .bind(event_time)
+        .bind(Utc::now())
+        .bind(payload)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_quarantined_events(&self) -> anyhow::Result<Vec<QuarantinedEvent>> {
+        let rows = sqlx::query(
+            "SELECT id, path, event_time, received_at, payload FROM quarantined_events
+             ORDER BY id",
+        )
+        .fetch_all(&self.conn)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(QuarantinedEvent {
+                    id: row.try_get("id")?,
+                    path: row.try_get("path")?,
+                    event_time: row.try_get("event_time")?,
+                    received_at: row.try_get("received_at")?,
+                    payload: row.try_get("payload")?,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn get_quarantined_event(&self, id: i64) -> anyhow::Result<Option<QuarantinedEvent>> {
+        let row = sqlx::query(
+            "SELECT id, path, event_time, received_at, payload FROM quarantined_events
+             WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.conn)
+        .await?;
+
+        row.map(|row| {
+            Ok(QuarantinedEvent {
+                id: row.try_get("id")?,
+                path: row.try_get("path")?,
+                event_time: row.try_get("event_time")?,
+                received_at: row.try_get("received_at")?,
+                payload: row.try_get("payload")?,
+            })
+        })
+        .transpose()
+    }
+
+    /// Returns whether the event existed.
+    pub async fn delete_quarantined_event(&self, id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM quarantined_events WHERE id = ?")
+            .bind(id)
+            .execute(&self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_sync_failure(
+        &self,
+        operation: &str,
+        zammad_id: Option<i32>,
+        error: &str,
+        decisions: &[Decision],
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO sync_failures (operation, zammad_id, error, decisions, failed_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(operation)
+        .bind(zammad_id)
+        .bind(error)
+        .bind(serde_json::to_string(decisions)?)
+        .bind(Utc::now())
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_sync_failures(&self) -> anyhow::Result<Vec<SyncFailure>> {
+        let rows = sqlx::query(
+            "SELECT id, operation, zammad_id, error, decisions, failed_at FROM sync_failures
+             ORDER BY id",
+        )
+        .fetch_all(&self.conn)
+        .await?;
+
+        rows.iter().map(sync_failure_from_row).collect()
+    }
+
+    /// Returns whether the failure existed.
+    pub async fn delete_sync_failure(&self, id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM sync_failures WHERE id = ?")
+            .bind(id)
+            .execute(&self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_sync_failure(&self, id: i64) -> anyhow::Result<Option<SyncFailure>> {
+        let row = sqlx::query(
+            "SELECT id, operation, zammad_id, error, decisions, failed_at FROM sync_failures
+             WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.conn)
+        .await?;
+        row.as_ref().map(sync_failure_from_row).transpose()
+    }
+
+    pub async fn create_dead_letter(
+        &self,
+        operation: &str,
+        zammad_id: Option<i32>,
+        profile: Option<&str>,
+        payload: &serde_json::Value,
+        error: &str,
+    ) -> anyhow::Result<()> {
+        let mut transaction = self.conn.begin().await?;
+        let result = sqlx::query(
+            "INSERT INTO dead_letters (operation, zammad_id, payload, error, failed_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(operation)
+        .bind(zammad_id)
+        .bind(payload.to_string())
+        .bind(error)
+        .bind(Utc::now())
+        .execute(&mut *transaction)
+        .await?;
+        if let Some(profile) = profile {
+            sqlx::query("INSERT INTO dead_letter_profiles (dead_letter_id, profile) VALUES (?, ?)")
+                .bind(result.last_insert_rowid())
+                .bind(profile)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_dead_letters(&self) -> anyhow::Result<Vec<DeadLetter>> {
+        let rows = sqlx::query(
+            "SELECT id, operation, zammad_id, payload, error, attempts, failed_at, profile
+             FROM dead_letters LEFT JOIN dead_letter_profiles ON dead_letter_id = id
+             ORDER BY id",
+        )
+        .fetch_all(&self.conn)
+        .await?;
+        rows.iter().map(dead_letter_from_row).collect()
+    }
+
+    pub async fn get_dead_letter(&self, id: i64) -> anyhow::Result<Option<DeadLetter>> {
+        let row = sqlx::query(
+            "SELECT id, operation, zammad_id, payload, error, attempts, failed_at, profile
+             FROM dead_letters LEFT JOIN dead_letter_profiles ON dead_letter_id = id
+             WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.conn)
+        .await?;
+        row.as_ref().map(dead_letter_from_row).transpose()
+    }
+
+    /// Records another failed replay of the dead letter.
+    pub async fn update_dead_letter_error(&self, id: i64, error: &str) -> anyhow::Result<()> {
+        sqlx::query(
+            "UPDATE dead_letters SET error = ?, attempts = attempts + 1, failed_at = ?
+             WHERE id = ?",
+        )
+        .bind(error)
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns whether the dead letter existed.
+    pub async fn delete_dead_letter(&self, id: i64) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM dead_letters WHERE id = ?")
+            .bind(id)
+            .execute(&self.conn)
+            .await?;
+        sqlx::query("DELETE FROM dead_letter_profiles WHERE dead_letter_id = ?")
+            .bind(id)
+            .execute(&self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn restrict_assignment(
+        &self,
+        zammad_id: i32,
+        error: &str,
+        note_article_id: Option<u64>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO restricted_assignments
+             (zammad_id, error, note_article_id, restricted_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(zammad_id)
+        .bind(error)
+        .bind(note_article_id.map(|id| id as i64))
+        .bind(Utc::now())
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn is_assignment_restricted(&self, zammad_id: i32) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM restricted_assignments WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .fetch_optional(&self.conn)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    pub async fn get_sync_pause(&self) -> anyhow::Result<Option<SyncPause>> {
+        let row = sqlx::query("SELECT reason, paused_at FROM sync_pause WHERE id = 1")
+            .fetch_optional(&self.conn)
+            .await?;
+        match row {
+            Some(row) => Ok(Some(SyncPause {
+                reason: row.try_get("reason")?,
+                paused_at: row.try_get("paused_at")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_sync_pause(&self, pause: &SyncPause) -> anyhow::Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO sync_pause (id, reason, paused_at) VALUES (1, ?, ?)")
+            .bind(&pause.reason)
+            .bind(pause.paused_at)
+            .execute(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns whether syncing was paused.
+    pub async fn delete_sync_pause(&self) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM sync_pause WHERE id = 1")
+            .execute(&self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_paused_directions(&self) -> anyhow::Result<Vec<(String, SyncPause)>> {
+        let rows = sqlx::query("SELECT direction, reason, paused_at FROM paused_directions")
+            .fetch_all(&self.conn)
+            .await?;
+        rows.iter()
+            .map(|row| {
+                Ok((
+                    row.try_get("direction")?,
+                    SyncPause {
+                        reason: row.try_get("reason")?,
+                        paused_at: row.try_get("paused_at")?,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    pub async fn set_paused_direction(
+        &self,
+        direction: &str,
+        pause: &SyncPause,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO paused_directions (direction, reason, paused_at)
+             VALUES (?, ?, ?)",
+        )
+        .bind(direction)
+        .bind(&pause.reason)
+        .bind(pause.paused_at)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_paused_directions(&self) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM paused_directions")
+            .execute(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_paused_direction(&self, direction: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM paused_directions WHERE direction = ?")
+            .bind(direction)
+            .execute(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_restricted_assignments(&self) -> anyhow::Result<Vec<RestrictedAssignment>> {
+        let rows = sqlx::query(
+            "SELECT zammad_id, error, note_article_id, restricted_at FROM restricted_assignments
+             ORDER BY restricted_at",
+        )
+        .fetch_all(&self.conn)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(RestrictedAssignment {
+                    zammad_id: row.try_get("zammad_id")?,
+                    error: row.try_get("error")?,
+                    note_article_id: row.try_get("note_article_id")?,
+                    restricted_at: row.try_get("restricted_at")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns whether the assignment was restricted.
+    pub async fn delete_restricted_assignment(&self, zammad_id: i32) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM restricted_assignments WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .execute(&self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn create_outbox_job(
+        &self,
+        operation: &str,
+        zammad_id: Option<i32>,
+        profile: Option<&str>,
+        request_id: Option<&str>,
+        payload: &serde_json::Value,
+    ) -> anyhow::Result<()> {
+        let mut transaction = self.conn.begin().await?;
+        let result = sqlx::query(
+            "INSERT INTO outbox (operation, zammad_id, payload, received_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(operation)
+        .bind(zammad_id)
+        .bind(payload.to_string())
+        .bind(Utc::now())
+        .execute(&mut *transaction)
+        .await?;
+        if let Some(profile) = profile {
+            sqlx::query("INSERT INTO outbox_job_profiles (job_id, profile) VALUES (?, ?)")
+                .bind(result.last_insert_rowid())
+                .bind(profile)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        if let Some(request_id) = request_id {
+            sqlx::query("INSERT INTO outbox_job_request_ids (job_id, request_id) VALUES (?, ?)")
+                .bind(result.last_insert_rowid())
+                .bind(request_id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// The queued jobs without their payload, oldest first.
+    pub async fn get_outbox_queue(&self) -> anyhow::Result<Vec<QueuedJob>> {
+        let rows = sqlx::query(
+            "SELECT id, operation, zammad_id, received_at, not_before
+             FROM outbox
+             LEFT JOIN outbox_job_deferrals d ON d.job_id = id ORDER BY id",
+        )
+        .fetch_all(&self.conn)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(QueuedJob {
+                    id: row.try_get("id")?,
+                    operation: row.try_get("operation")?,
+                    zammad_id: row.try_get("zammad_id")?,
+                    received_at: row.try_get("received_at")?,
+                    not_before: row.try_get("not_before")?,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn get_outbox_job(&self, id: i64) -> anyhow::Result<Option<OutboxJob>> {
+        let row = sqlx::query(
+            "SELECT id, operation, zammad_id, payload, received_at, profile, request_id, not_before
+             FROM outbox
+             LEFT JOIN outbox_job_profiles p ON p.job_id = id
+             LEFT JOIN outbox_job_request_ids r ON r.job_id = id
+             LEFT JOIN outbox_job_deferrals d ON d.job_id = id WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.conn)
+        .await?;
+        row.as_ref().map(outbox_job_from_row).transpose()
+    }
+
+    pub async fn count_outbox_jobs(&self) -> anyhow::Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox")
+            .fetch_one(&self.conn)
+            .await?;
+        Ok(count as u64)
+    }
+
+    pub async fn get_outbox_jobs(&self) -> anyhow::Result<Vec<OutboxJob>> {
+        let rows = sqlx::query(
+            "SELECT id, operation, zammad_id, payload, received_at, profile, request_id, not_before
+             FROM outbox
+             LEFT JOIN outbox_job_profiles p ON p.job_id = id
+             LEFT JOIN outbox_job_request_ids r ON r.job_id = id
+             LEFT JOIN outbox_job_deferrals d ON d.job_id = id ORDER BY id",
+        )
+        .fetch_all(&self.conn)
+        .await?;
+        rows.iter().map(outbox_job_from_row).collect()
+    }
+
+    pub async fn delete_outbox_job(&self, id: i64) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM outbox WHERE id = ?")
+            .bind(id)
+            .execute(&self.conn)
+            .await?;
+        sqlx::query("DELETE FROM outbox_job_profiles WHERE job_id = ?")
+            .bind(id)
+            .execute(&self.conn)
+            .await?;
+        sqlx::query("DELETE FROM outbox_job_request_ids WHERE job_id = ?")
+            .bind(id)
+            .execute(&self.conn)
+            .await?;
+        sqlx::query("DELETE FROM outbox_job_deferrals WHERE job_id = ?")
+            .bind(id)
+            .execute(&self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Keeps the job from being synced before `not_before`.
+    pub async fn defer_outbox_job(&self, id: i64, not_before: DateTime<Utc>) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO outbox_job_deferrals (job_id, not_before) VALUES (?, ?)
+             ON CONFLICT (job_id) DO UPDATE SET not_before = excluded.not_before",
+        )
+        .bind(id)
+        .bind(not_before)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    /// When the first deferred job that isn't due yet becomes due. Due ones are left out, as
+    /// they may be waiting for something else, e.g. their direction to be resumed.
+    pub async fn get_next_outbox_deferral(&self) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let not_before = sqlx::query_scalar(
+            "SELECT MIN(not_before) FROM outbox_job_deferrals WHERE not_before > ?",
+        )
+        .bind(Utc::now())
+        .fetch_one(&self.conn)
+        .await?;
+        Ok(not_before)
+    }
+
+    pub async fn create_annotation(
+        &self,
+        target: &AnnotationTarget,
+        author: Option<&str>,
+        note: &str,
+    ) -> anyhow::Result<i64> {
+        let result = sqlx::query(
+            "INSERT INTO annotations
+             (zammad_id, sync_failure_id, dead_letter_id, author, note, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(target.zammad_id)
+        .bind(target.sync_failure_id)
+        .bind(target.dead_letter_id)
+        .bind(author)
+        .bind(note)
+        .bind(Utc::now())
+        .execute(&self.conn)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// The annotations matching every given part of `target`, oldest first.
+    pub async fn get_annotations(
+        &self,
+        target: &AnnotationTarget,
+    ) -> anyhow::Result<Vec<Annotation>> {
+        let rows = sqlx::query(
+            "SELECT id, zammad_id, sync_failure_id, dead_letter_id, author, note, created_at
+             FROM annotations
+             WHERE (?1 IS NULL OR zammad_id = ?1)
+               AND (?2 IS NULL OR sync_failure_id = ?2)
+               AND (?3 IS NULL OR dead_letter_id = ?3)
+             ORDER BY id",
+        )
+        .bind(target.zammad_id)
+        .bind(target.sync_failure_id)
+        .bind(target.dead_letter_id)
+        .fetch_all(&self.conn)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(Annotation {
+                    id: row.try_get("id")?,
+                    target: AnnotationTarget {
+                        zammad_id: row.try_get("zammad_id")?,
+                        sync_failure_id: row.try_get("sync_failure_id")?,
+                        dead_letter_id: row.try_get("dead_letter_id")?,
+                    },
+                    author: row.try_get("author")?,
+                    note: row.try_get("note")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn create_audit_entry(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        self.store.create_audit_entry(entry).await
+    }
+
+    pub async fn get_audit_entries(&self, filter: &AuditFilter) -> anyhow::Result<Vec<AuditEntry>> {
+        self.store.get_audit_entries(filter).await
+    }
+
+    pub async fn create_archived_payload(&self, archived: &ArchivedPayload) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO webhook_archive (route, profile, request_id, zammad_id, jira_id, status,
+             payload, size, received_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&archived.route)
+        .bind(&archived.profile)
+        .bind(&archived.request_id)
+        .bind(archived.zammad_id)
+        .bind(archived.jira_id)
+        .bind(archived.status)
+        .bind(&archived.payload)
+        .bind(archived.payload.len() as i64)
+        .bind(archived.received_at)
+        .execute(&self.conn)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_archived_payloads(
+        &self,
+        filter: &ArchiveFilter,
+    ) -> anyhow::Result<Vec<ArchivedPayload>> {
+        let rows = sqlx::query(
+            "SELECT id, route, profile, request_id, zammad_id, jira_id, status, payload, received_at
+             FROM webhook_archive
+             WHERE (?1 IS NULL OR zammad_id = ?1)
+               AND (?2 IS NULL OR jira_id = ?2)
+             ORDER BY id DESC LIMIT ?3",
+        )
+        .bind(filter.zammad_id)
+        .bind(filter.jira_id)
+        .bind(filter.limit.unwrap_or(100))
+        .fetch_all(&self.conn)
+        .await?;
+        rows.iter().map(archived_payload_from_row).collect()
+    }
+
+    pub async fn get_archived_payload(&self, id: i64) -> anyhow::Result<Option<ArchivedPayload>> {
+        let row = sqlx::query(
+            "SELECT id, route, profile, request_id, zammad_id, jira_id, status, payload, received_at
+             FROM webhook_archive WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.conn)
+        .await?;
+        row.as_ref().map(archived_payload_from_row).transpose()
+    }
+
+    /// Removes payloads received before `before`, then the oldest until the rest takes at most
+    /// `max_bytes`. Returns how many were removed.
+    pub async fn prune_archive(
+        &self,
+        before: DateTime<Utc>,
+        max_bytes: Option<u64>,
+    ) -> anyhow::Result<u64> {
+        let mut removed = sqlx::query("DELETE FROM webhook_archive WHERE received_at < ?")
+            .bind(before)
+            .execute(&self.conn)
+            .await?
+            .rows_affected();
+        if let Some(max_bytes) = max_bytes {
+            removed += sqlx::query(
+                "DELETE FROM webhook_archive WHERE id IN (
+                     SELECT id FROM (
+                         SELECT id, SUM(size) OVER (ORDER BY id DESC) AS total FROM webhook_archive
+                     ) WHERE total > ?
+                 )",
+            )
+            .bind(max_bytes as i64)
+            .execute(&self.conn)
+            .await?
+            .rows_affected();
+        }
+        Ok(removed)
+    }
+
+    pub async fn get_profiles(&self) -> anyhow::Result<Vec<StoredProfile>> {
+        let rows = sqlx::query("SELECT name, config, updated_at FROM profiles ORDER BY name")
+            .fetch_all(&self.conn)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(StoredProfile {
+                    name: row.try_get("name")?,
+                    config: row.try_get("config")?,
+                    updated_at: row.try_get("updated_at")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns whether the profile is new.
+    pub async fn set_profile(&self, name: &str, config: &str) -> anyhow::Result<bool> {
+        let existed = sqlx::query("SELECT 1 FROM profiles WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.conn)
+            .await?
+            .is_some();
+        sqlx::query(
+            "INSERT INTO profiles (name, config, updated_at) VALUES (?, ?, ?)
+             ON CONFLICT(name) DO UPDATE SET config = excluded.config,
+             updated_at = excluded.updated_at",
+        )
+        .bind(name)
+        .bind(config)
+        .bind(Utc::now())
+        .execute(&self.conn)
+        .await?;
+        Ok(!existed)
+    }
+
+    /// Returns whether the profile existed.
+    pub async fn delete_profile(&self, name: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM profiles WHERE name = ?")
+            .bind(name)
+            .execute(&self.conn)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes everything stored about the Zammad ticket and its Jira issue, with the mappings
+    /// of the given articles and comments, in one transaction, then in the store of the links.
+    /// Returns the number of rows removed per table.
+    pub async fn purge_ticket(
+        &self,
+        zammad_id: i32,
+        jira_id: Option<i32>,
+        article_ids: &[u64],
+        comment_ids: &[i32],
+    ) -> anyhow::Result<BTreeMap<&'static str, u64>> {
+        let mut transaction = self.conn.begin().await?;
+        let mut removed = BTreeMap::new();
+
+        let mut article_ids: Vec<i64> = article_ids.iter().map(|id| *id as i64).collect();
+        let description =
+            sqlx::query("SELECT zammad_article_id FROM description_articles WHERE zammad_id = ?")
+                .bind(zammad_id)
+                .fetch_optional(&mut *transaction)
+                .await?;
+        if let Some(row) = description {
+            article_ids.push(row.try_get("zammad_article_id")?);
+        }
+        let sql = format!(
+            "DELETE FROM attachment_mappings WHERE zammad_article_id IN ({})",
+            placeholders(article_ids.len())
+        );
+        let mut query = sqlx::query(&sql);
+        for id in &article_ids {
+            query = query.bind(id);
+        }
+        let result = query.execute(&mut *transaction).await?;
+        removed.insert("attachment_mappings", result.rows_affected());
+
+        // Rows referencing others go first, while those can still be found. ?1 is the ticket,
+        // ?2 the issue.
+        let statements: [(&'static str, &str); 18] = [
+            (
+                "annotations",
+                "DELETE FROM annotations WHERE zammad_id = ?1
+                 OR sync_failure_id IN (SELECT id FROM sync_failures WHERE zammad_id = ?1)
+                 OR dead_letter_id IN (SELECT id FROM dead_letters WHERE zammad_id = ?1)",
+            ),
+            (
+                "sync_failures",
+                "DELETE FROM sync_failures WHERE zammad_id = ?1",
+            ),
+            (
+                "dead_letter_profiles",
+                "DELETE FROM dead_letter_profiles
+                 WHERE dead_letter_id IN (SELECT id FROM dead_letters WHERE zammad_id = ?1)",
+            ),
+            (
+                "dead_letters",
+                "DELETE FROM dead_letters WHERE zammad_id = ?1",
+            ),
+            (
+                "outbox_job_profiles",
+                "DELETE FROM outbox_job_profiles
+                 WHERE job_id IN (SELECT id FROM outbox WHERE zammad_id = ?1)",
+            ),
+            (
+                "outbox_job_request_ids",
+                "DELETE FROM outbox_job_request_ids
+                 WHERE job_id IN (SELECT id FROM outbox WHERE zammad_id = ?1)",
+            ),
+            (
+                "outbox_job_deferrals",
+                "DELETE FROM outbox_job_deferrals
+                 WHERE job_id IN (SELECT id FROM outbox WHERE zammad_id = ?1)",
+            ),
+            ("outbox", "DELETE FROM outbox WHERE zammad_id = ?1"),
+            (
+                "webhook_archive",
+                "DELETE FROM webhook_archive WHERE zammad_id = ?1 OR jira_id = ?2",
+            ),
+            (
+                "quarantined_events",
+                "DELETE FROM quarantined_events WHERE CASE WHEN json_valid(payload) THEN
+                     json_extract(payload, '$.ticket.id') = ?1
+                     OR CAST(json_extract(payload, '$.issue.id') AS INTEGER) = ?2
+                 ELSE 0 END",
+            ),
+            (
+                "assignment_meta",
+                "DELETE FROM assignment_meta WHERE zammad_id = ?1",
+            ),
+            (
+                "field_sync_state",
+                "DELETE FROM field_sync_state WHERE zammad_id = ?1",
+            ),
+            (
+                "sync_hashes",
+                "DELETE FROM sync_hashes WHERE zammad_id = ?1",
+            ),
+            (
+                "source_updates",
+                "DELETE FROM source_updates WHERE zammad_id = ?1",
+            ),
+            (
+                "restricted_assignments",
+                "DELETE FROM restricted_assignments WHERE zammad_id = ?1",
+            ),
+            (
+                "description_articles",
+                "DELETE FROM description_articles WHERE zammad_id = ?1",
+            ),
+            (
+                "checklist_items",
+                "DELETE FROM checklist_items WHERE zammad_id = ?1 OR jira_id = ?2",
+            ),
+            (
+                "asset_references",
+                "DELETE FROM asset_references WHERE jira_id = ?2",
+            ),
+        ];
+        for (table, sql) in statements {
+            let result = sqlx::query(sql)
+                .bind(zammad_id)
+                .bind(jira_id)
+                .execute(&mut *transaction)
+                .await?;
+            removed.insert(table, result.rows_affected());
+        }
+        transaction.commit().await?;
+        removed.extend(
+            self.store
+                .purge_ticket(zammad_id, jira_id, &article_ids, comment_ids)
+                .await?,
+        );
+        Ok(removed)
+    }
+
+    /// Deletes the user mapping of the email address and every stored payload and failure
+    /// mentioning it, in one transaction. Returns the number of rows removed per table.
+    pub async fn purge_email(&self, email: &str) -> anyhow::Result<BTreeMap<&'static str, u64>> {
+        let mut transaction = self.conn.begin().await?;
+        let mut removed = BTreeMap::new();
+        let mentioned = "instr(lower(payload), lower(?1)) > 0";
+        let failed = "instr(lower(error), lower(?1)) > 0 OR instr(lower(decisions), lower(?1)) > 0";
+        let statements: [(&'static str, String); 7] = [
+            (
+                "annotations",
+                format!(
+                    "DELETE FROM annotations
+                     WHERE sync_failure_id IN (SELECT id FROM sync_failures WHERE {})
+                     OR dead_letter_id IN (SELECT id FROM dead_letters WHERE {})",
+                    failed, mentioned
+                ),
+            ),
+            (
+                "sync_failures",
+                format!("DELETE FROM sync_failures WHERE {}", failed),
+            ),
+            (
+                "dead_letter_profiles",
+                format!(
+                    "DELETE FROM dead_letter_profiles
+                     WHERE dead_letter_id IN (SELECT id FROM dead_letters WHERE {})",
+                    mentioned
+                ),
+            ),
+            (
+                "dead_letters",
+                format!("DELETE FROM dead_letters WHERE {}", mentioned),
+            ),
+            (
+                "webhook_archive",
+                format!("DELETE FROM webhook_archive WHERE {}", mentioned),
+            ),
+            (
+                "quarantined_events",
+                format!("DELETE FROM quarantined_events WHERE {}", mentioned),
+            ),
+            (
+                "user_mappings",
+                "DELETE FROM user_mappings WHERE lower(email) = lower(?1)".to_string(),
+            ),
+        ];
+        for (table, sql) in statements {
+            let result = sqlx::query(&sql)
+                .bind(email)
+                .execute(&mut *transaction)
+                .await?;
+            removed.insert(table, result.rows_affected());
+        }
+        transaction.commit().await?;
+        Ok(removed)
+    }
+}
+
+fn checklist_item_from_row(row: &SqliteRow) -> anyhow::Result<ChecklistItem> {
+    Ok(ChecklistItem {
+        zammad_item_id: row.try_get("zammad_item_id")?,
+        zammad_id: row.try_get("zammad_id")?,
+        jira_id: row.try_get("jira_id")?,
+        text: row.try_get("text")?,
+        checked: row.try_get("checked")?,
+    })
+}
+
+fn sync_failure_from_row(row: &SqliteRow) -> anyhow::Result<SyncFailure> {
+    Ok(SyncFailure {
+        id: row.try_get("id")?,
+        operation: row.try_get("operation")?,
+        zammad_id: row.try_get("zammad_id")?,
+        error: row.try_get("error")?,
+        decisions: serde_json::from_str(row.try_get("decisions")?)?,
+        failed_at: row.try_get("failed_at")?,
+    })
+}
+
+/// `?, ?, …` for `count` values of an `IN` list; `NULL` for none, which matches nothing.
+pub(crate) fn placeholders(count: usize) -> String {
+    if count == 0 {
+        return "NULL".to_string();
+    }
+    vec!["?"; count].join(", ")
+}
+
+fn dead_letter_from_row(row: &SqliteRow) -> anyhow::Result<DeadLetter> {
+    Ok(DeadLetter {
+        id: row.try_get("id")?,
+        operation: row.try_get("operation")?,
+        zammad_id: row.try_get("zammad_id")?,
+        payload: serde_json::from_str(row.try_get("payload")?)?,
+        error: row.try_get("error")?,
+        attempts: row.try_get("attempts")?,
+        failed_at: row.try_get("failed_at")?,
+        profile: row.try_get("profile")?,
+    })
+}
+
+fn archived_payload_from_row(row: &SqliteRow) -> anyhow::Result<ArchivedPayload> {
+    Ok(ArchivedPayload {
+        id: row.try_get("id")?,
+        route: row.try_get("route")?,
+        profile: row.try_get("profile")?,
+        request_id: row.try_get("request_id")?,
+        zammad_id: row.try_get("zammad_id")?,
+        jira_id: row.try_get("jira_id")?,
+        status: row.try_get("status")?,
+        received_at: row.try_get("received_at")?,
+        payload: row.try_get("payload")?,
+    })
+}
+
+fn outbox_job_from_row(row: &SqliteRow) -> anyhow::Result<OutboxJob> {
+    Ok(OutboxJob {
+        id: row.try_get("id")?,
+        operation: row.try_get("operation")?,
+        zammad_id: row.try_get("zammad_id")?,
+        payload: serde_json::from_str(row.try_get("payload")?)?,
+        received_at: row.try_get("received_at")?,
+        profile: row.try_get("profile")?,
+        request_id: row.try_get("request_id")?,
+        not_before: row.try_get("not_before")?,
+    })
 }
@@ -1,47 +1,120 @@
-use axum::{Json, Router, extract::Path, routing::post};
+use anyhow::Context;
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use chrono::{DateTime, Utc};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use tracing::{error, instrument};
+use serde_json::{Map, Value};
+use tracing::{debug, error, info, instrument, warn};
+use utoipa::{OpenApi, ToSchema};
 
-use super::zammad::ZammadState;
+use crate::config::{self, FieldDirections};
+use crate::conflicts::{FieldChange, FieldValue, Side};
+use crate::state::AppState;
+use crate::{checklists, filters, locks, logging, outbox};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use super::{
+    api_request::{
+        JiraDoTransitionRequest, JiraGetAssetObjectRequest, JiraGetCommentRequest,
+        JiraGetIssueRequest, JiraGetTransitionsRequest, ZammadCreateArticleRequest,
+        ZammadTagRequest, ZammadUpdateTicketRequest, content_hash,
+        convert_jira_priority_to_zammad_priority, convert_jira_status_category_to_zammad_state,
+        string_to_number,
+    },
+    attachments,
+    db::DB,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct JiraWebhook<T> {
     pub issue: T,
+    /// When the event happened, in milliseconds since the epoch
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    /// The comment that was added or edited, for comment events
+    #[serde(default)]
+    pub comment: Option<JiraComment>,
+    /// The fields changed by the event
+    #[serde(default)]
+    pub changelog: Option<JiraChangelog>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JiraChangelog {
+    #[serde(default)]
+    pub items: Vec<JiraChangelogItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JiraChangelogItem {
+    /// Name of the changed field, e.g. "issuetype"
+    pub field: String,
+    /// The old value as displayed, e.g. "Task"
+    #[serde(rename = "fromString", default)]
+    pub from_value: Option<String>,
+    #[serde(rename = "toString", default)]
+    pub to_value: Option<String>,
+    /// The new value's ID, e.g. of an added attachment
+    #[serde(rename = "to", default)]
+    pub to_id: Option<String>,
+}
+
+/// Assignment metadata key holding the current type of the Jira issue.
+pub const ISSUE_TYPE_META: &str = "jira_issue_type";
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JiraComment {
+    #[serde(deserialize_with = "string_to_number")]
+    #[schema(value_type = String)]
+    pub id: i32,
+    pub body: String,
+    #[serde(default)]
+    pub author: Option<JiraUser>,
+    /// Creation time, e.g. "2024-05-02T09:14:31.000+0000"
+    #[serde(default)]
+    pub created: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct JiraIssue {
     pub project: JiraProject,
     /// Additional fields for the ticket
     pub fields: JiraFields,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct JiraProject {
     pub id: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct JiraFields {
     pub project: JiraProject,
     pub summary: String,
     pub description: String,
     pub issuetype: JiraIssueType,
     pub priority: JiraPriority,
-    pub duedate: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duedate: Option<String>,
+    /// Configurable fields like components or custom fields
+    #[serde(flatten)]
+    pub extra_fields: Map<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct JiraIssueType {
     pub name: String,
 }
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct JiraPriority {
     pub name: JiraPriorityEnum,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub enum JiraPriorityEnum {
     Highest = 1,
     High = 2,
@@ -50,27 +123,244 @@ pub enum JiraPriorityEnum {
     Lowest = 5,
 }
 
-#[derive(Debug, Serialize, Clone, Copy)]
-pub enum JiraStatus {
-    Open,
-    Closed,
+/// Result page of the Jira issue search (`/search`).
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraSearchResponse {
+    pub start_at: u32,
+    pub total: u32,
+    pub issues: Vec<JiraApiIssue>,
+}
+
+/// An issue as represented by the Jira REST API (search results, issue API and webhooks).
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JiraApiIssue {
+    #[serde(deserialize_with = "string_to_number")]
+    #[schema(value_type = String)]
+    pub id: i32,
+    /// Human-readable issue key (e.g. "CUN-123")
+    pub key: String,
+    pub fields: JiraApiFields,
+}
+
+impl JiraApiIssue {
+    pub fn project_key(&self) -> Option<&str> {
+        self.fields
+            .extra_fields
+            .get("project")
+            .and_then(|project| project.get("key"))
+            .and_then(Value::as_str)
+    }
+
+    /// When the issue was last updated, if the payload contains the field.
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.fields
+            .extra_fields
+            .get("updated")
+            .and_then(Value::as_str)
+            .and_then(parse_jira_time)
+    }
+
+    pub fn issue_type(&self) -> Option<&str> {
+        self.fields
+            .extra_fields
+            .get("issuetype")
+            .and_then(|issue_type| issue_type.get("name"))
+            .and_then(Value::as_str)
+    }
+
+    pub fn labels(&self) -> Vec<&str> {
+        self.fields
+            .extra_fields
+            .get("labels")
+            .and_then(Value::as_array)
+            .map(|labels| labels.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Parses timestamps like "2024-05-02T09:14:31.000+0000", which aren't RFC 3339.
+pub fn parse_jira_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f%z")
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JiraApiFields {
+    /// Only missing if the request restricted the returned fields
+    #[serde(default)]
+    pub summary: String,
+    pub description: Option<String>,
+    pub priority: Option<JiraPriorityName>,
+    pub reporter: Option<JiraUser>,
+    pub status: Option<JiraIssueStatus>,
+    /// Custom fields and everything else not modelled above
+    #[serde(flatten)]
+    pub extra_fields: Map<String, Value>,
+}
+
+/// Workflow status of an issue. Status names are workflow specific, so syncing decisions
+/// are based on the status category instead.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraIssueStatus {
+    pub name: String,
+    pub status_category: Option<JiraStatusCategory>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JiraStatusCategory {
+    pub key: JiraStatusCategoryKey,
+}
+
+/// The fixed set of status categories every Jira status belongs to.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JiraStatusCategoryKey {
+    /// "To Do"
+    New,
+    /// "In Progress"
+    Indeterminate,
+    /// "Done"
+    Done,
+    #[serde(other)]
+    Undefined,
+}
+
+/// Priority as sent by Jira, which may use custom priority names.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JiraPriorityName {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraUser {
+    /// Only present if the user's privacy settings allow it
+    pub email_address: Option<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+/// An Assets (Insight) object, e.g. a CMDB entry referenced by an issue.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraAssetObject {
+    pub label: String,
+    pub object_key: Option<String>,
+}
+
+impl JiraAssetObject {
+    fn render(&self) -> String {
+        match &self.object_key {
+            Some(key) => format!("{} ({})", self.label, key),
+            None => self.label.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JiraTransitionsResponse {
+    pub transitions: Vec<JiraTransition>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JiraTransition {
+    pub id: String,
+    /// Status the issue ends up in after the transition
+    pub to: JiraIssueStatus,
 }
 
-impl JiraStatus {
-    pub fn from_zammad_state(state: ZammadState) -> JiraStatus {
-        match state {
-            ZammadState::Open => JiraStatus::Open,
-            ZammadState::Closed => JiraStatus::Closed,
+/// Moves the issue into the last status of `path`, discovering the transitions to use.
+/// If the target isn't reachable from the current status, the issue is moved through the
+/// earlier statuses of `path`, always jumping as far ahead as possible.
+pub async fn transition_issue(issue_id: i32, path: &[String]) -> anyhow::Result<()> {
+    let Some(target) = path.last() else {
+        return Ok(());
+    };
+
+    let current = JiraGetIssueRequest::new(issue_id)
+        .submit()
+        .await?
+        .fields
+        .status;
+    if current.is_some_and(|status| status.name.eq_ignore_ascii_case(target)) {
+        debug!("Jira issue {} already is in status {:?}", issue_id, target);
+        return Ok(());
+    }
+
+    for _ in 0..path.len() {
+        let transitions = JiraGetTransitionsRequest::new(issue_id)
+            .submit()
+            .await?
+            .transitions;
+
+        let (reached, transition) = path
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, status)| {
+                transitions
+                    .iter()
+                    .find(|transition| transition.to.name.eq_ignore_ascii_case(status))
+                    .map(|transition| (index, transition))
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "no transition of Jira issue {} leads to any of {:?}",
+                    issue_id,
+                    path
+                )
+            })?;
+
+        info!(
+            "Transitioning Jira issue {} to {:?} (transition {})",
+            issue_id, transition.to.name, transition.id
+        );
+        JiraDoTransitionRequest::new(&transition.id)
+            .submit(&issue_id)
+            .await?;
+
+        if reached == path.len() - 1 {
+            return Ok(());
         }
     }
+
+    anyhow::bail!(
+        "Jira issue {} did not reach status {:?} via {:?}",
+        issue_id,
+        target,
+        path
+    )
 }
 
-#[instrument(skip(webhook))]
-async fn create_ticket(id: String, webhook: JiraWebhook<JiraIssue>) -> anyhow::Result<()> {
+#[instrument(skip(_webhook))]
+async fn create_ticket(_id: String, _webhook: JiraWebhook<JiraIssue>) -> anyhow::Result<()> {
     // TODO: Implement Jira to Zammad ticket creation
     Ok(())
 }
 
+/// An issue was created in Jira; a Zammad ticket is created for it right away.
+#[utoipa::path(
+    post,
+    path = "/create-ticket/{id}",
+    params(
+        ("id" = String, Path, description = "Webhook ID of the Jira system"),
+        ("token" = Option<String>, Query, description = "The webhook token, if `webhook_auth` is a token"),
+        ("Authorization" = Option<String>, Header, description = "`JWT <token>`, if `webhook_auth` is a JWT secret"),
+    ),
+    request_body = JiraWebhook<JiraIssue>,
+    responses(
+        (status = 200, description = "The ticket was created"),
+        (status = 400, description = "The ticket couldn't be created"),
+        (status = 401, description = "Missing or invalid token or JWT"),
+        (status = 404, description = "Unknown webhook ID"),
+        (status = 422, description = "The payload lacks required fields"),
+    )
+)]
 #[instrument(skip(payload))]
 #[axum::debug_handler]
 async fn create_ticket_handler(
@@ -86,31 +376,408 @@ async fn create_ticket_handler(
     }
 }
 
-#[instrument(skip(webhook))]
-async fn update_ticket(webhook: JiraWebhook<JiraIssue>) -> anyhow::Result<()> {
-    // TODO: Implement Jira to Zammad ticket update
+/// Returns the status category of the issue, asking the Jira API if the webhook doesn't
+/// contain it.
+pub async fn get_status_category(issue: &JiraApiIssue) -> anyhow::Result<JiraStatusCategoryKey> {
+    let category = issue
+        .fields
+        .status
+        .as_ref()
+        .and_then(|status| status.status_category.as_ref());
+    if let Some(category) = category {
+        return Ok(category.key);
+    }
+
+    JiraGetIssueRequest::new(issue.id)
+        .submit()
+        .await?
+        .fields
+        .status
+        .and_then(|status| status.status_category)
+        .map(|category| category.key)
+        .ok_or_else(|| anyhow::anyhow!("Jira issue {} has no status category", issue.key))
+}
+
+/// Renders the Assets objects referenced by `fields` of the issue, one per line.
+///
+/// Depending on the Jira deployment, references are plain strings, objects carrying their
+/// label, or only IDs that have to be resolved through the Assets API.
+async fn render_asset_references(
+    issue: &JiraApiIssue,
+    fields: &[String],
+) -> anyhow::Result<String> {
+    let mut rendered = Vec::new();
+    for field in fields {
+        let references = match issue.fields.extra_fields.get(field) {
+            Some(Value::Array(references)) => references.clone(),
+            Some(Value::Null) | None => continue,
+            Some(reference) => vec![reference.clone()],
+        };
+
+        for reference in references {
+            if let Some(text) = reference.as_str() {
+                rendered.push(text.to_string());
+            } else if let Ok(object) = serde_json::from_value::<JiraAssetObject>(reference.clone())
+            {
+                rendered.push(object.render());
+            } else if let (Some(workspace_id), Some(object_id)) = (
+                reference.get("workspaceId").and_then(Value::as_str),
+                reference.get("objectId").and_then(Value::as_str),
+            ) {
+                let object = JiraGetAssetObjectRequest::new(workspace_id, object_id)
+                    .submit()
+                    .await?;
+                rendered.push(object.render());
+            }
+        }
+    }
+    Ok(rendered.join("\n"))
+}
+
+/// Remembers the new issue type in the assignment metadata and moves the Zammad tag and
+/// type configured for it along.
+async fn apply_issue_type_change(
+    db: &DB,
+    zammad_ticket_id: i32,
+    change: &JiraChangelogItem,
+    request: ZammadUpdateTicketRequest,
+) -> anyhow::Result<ZammadUpdateTicketRequest> {
+    let Some(issue_type) = change.to_value.as_deref() else {
+        return Ok(request);
+    };
+    info!(
+        "Jira issue type of Zammad ticket {} changed from {:?} to {:?}",
+        zammad_ticket_id, change.from_value, issue_type
+    );
+    db.set_assignment_meta(&zammad_ticket_id, ISSUE_TYPE_META, issue_type)
+        .await?;
+
+    let issue_types = &config::get_jira().issue_types;
+    if let Some(tag) = change
+        .from_value
+        .as_ref()
+        .and_then(|issue_type| issue_types.get(issue_type))
+        .and_then(|config| config.zammad_tag.as_deref())
+    {
+        ZammadTagRequest::remove(zammad_ticket_id, tag)
+            .submit()
+            .await?;
+    }
+
+    let Some(config) = issue_types.get(issue_type) else {
+        return Ok(request);
+    };
+    if let Some(tag) = &config.zammad_tag {
+        ZammadTagRequest::add(zammad_ticket_id, tag)
+            .submit()
+            .await?;
+    }
+    Ok(match &config.zammad_type {
+        Some(zammad_type) => request.with_field("type", Value::String(zammad_type.clone())),
+        None => request,
+    })
+}
+
+/// Syncs status, priority and the other fields of the issue to the Zammad ticket.
+async fn update_fields(
+    db: &DB,
+    webhook: &JiraWebhook<JiraApiIssue>,
+    zammad_ticket_id: i32,
+) -> anyhow::Result<()> {
+    let mut request = ZammadUpdateTicketRequest::new();
+
+    if let Some(change) = webhook
+        .changelog
+        .iter()
+        .flat_map(|changelog| &changelog.items)
+        .find(|item| item.field == "issuetype")
+    {
+        request = apply_issue_type_change(db, zammad_ticket_id, change, request).await?;
+    }
+
+    let directions = get_directions(db, &webhook.issue, zammad_ticket_id).await?;
+
+    // Fields synced both ways are only sent if they changed, resolving conflicting changes
+    let change = |value| FieldChange {
+        zammad_ticket_id,
+        jira_issue_id: webhook.issue.id,
+        side: Side::Jira,
+        value,
+        changed_at: webhook
+            .timestamp
+            .and_then(DateTime::from_timestamp_millis)
+            .unwrap_or_else(Utc::now),
+    };
+    let mut changes = Vec::new();
+
+    if directions.status.to_zammad() {
+        let status_category = get_status_category(&webhook.issue).await?;
+        let status_name = webhook
+            .issue
+            .fields
+            .status
+            .as_ref()
+            .map(|status| status.name.as_str());
+        info!(
+            "Jira issue {} is in status {:?} (category {:?})",
+            webhook.issue.key, status_name, status_category
+        );
+        let state = convert_jira_status_category_to_zammad_state(status_category);
+        let status = change(FieldValue::Status(state));
+        if status.should_apply(db).await? {
+            request = request.with_state(state);
+            changes.push(status);
+        }
+    }
+
+    if directions.priority.to_zammad()
+        && let Some(priority) = &webhook.issue.fields.priority
+    {
+        let priority_id = convert_jira_priority_to_zammad_priority(Some(&priority.name));
+        let priority = change(FieldValue::Priority(priority_id));
+        if priority.should_apply(db).await? {
+            request = request.with_priority_id(priority_id);
+            changes.push(priority);
+        }
+    }
+
+    if let Some(assets_config) = &config::get_jira().assets {
+        let assets = render_asset_references(&webhook.issue, &assets_config.fields).await?;
+        match &assets_config.zammad_field {
+            Some(field) => request = request.with_field(field, Value::String(assets)),
+            // Only post a note if the references changed, not on every update
+            None if db.get_asset_references(&webhook.issue.id).await? != Some(assets.clone())
+                && !assets.is_empty() =>
+            {
+                ZammadCreateArticleRequest::internal_note(
+                    zammad_ticket_id,
+                    format!("Referenced assets in Jira:\n{}", assets),
+                )
+                .submit()
+                .await?;
+                db.set_asset_references(&webhook.issue.id, &assets).await?;
+            }
+            None => {}
+        }
+    }
+
+    // Skip the update if it's the same as last time, e.g. for a new comment
+    if !request.is_empty() {
+        let hash = content_hash(&request)?;
+        if db
+            .get_sync_hash(&zammad_ticket_id, "zammad")
+            .await?
+            .as_ref()
+            == Some(&hash)
+        {
+            info!(
+                "Zammad ticket {} is up to date, skipping the update",
+                zammad_ticket_id
+            );
+        } else {
+            request.submit(&zammad_ticket_id).await?;
+            db.set_sync_hash(&zammad_ticket_id, "zammad", &hash).await?;
+            // Jira changed since its last update from Zammad, which has to be sent again
+            db.delete_sync_hash(&zammad_ticket_id, "jira").await?;
+        }
+    }
+    for change in &changes {
+        change.record(db).await?;
+    }
+
     Ok(())
 }
 
-#[instrument(skip(payload))]
-#[axum::debug_handler]
-async fn update_ticket_handler(
-    Path(id): Path<String>,
-    Json(payload): Json<JiraWebhook<JiraIssue>>,
-) -> StatusCode {
-    match update_ticket(payload).await {
-        Ok(_) => StatusCode::OK,
+/// The directions for the issue, which depend on its type.
+pub(crate) async fn get_directions(
+    db: &DB,
+    issue: &JiraApiIssue,
+    zammad_ticket_id: i32,
+) -> anyhow::Result<FieldDirections> {
+    let issue_type = match issue.issue_type() {
+        Some(issue_type) => Some(issue_type.to_string()),
+        None => db
+            .get_assignment_meta(&zammad_ticket_id)
+            .await?
+            .remove(ISSUE_TYPE_META),
+    };
+    Ok(config::get().directions_for(issue_type.as_deref()))
+}
+
+#[instrument(skip(db, webhook))]
+pub(crate) async fn update_ticket(
+    db: &DB,
+    webhook: JiraWebhook<JiraApiIssue>,
+) -> anyhow::Result<()> {
+    if !filters::is_jira_issue_synced(&webhook.issue) {
+        info!(
+            "Jira issue {} doesn't match the sync rules, skipping it",
+            webhook.issue.key
+        );
+        return Ok(());
+    }
+
+    // Sub-tasks of checklist items aren't linked to a ticket themselves
+    if checklists::sync_subtask_to_zammad(db, &webhook.issue).await? {
+        return Ok(());
+    }
+
+    let zammad_ticket_id = db
+        .get_zammad_id_by_jira_id(&webhook.issue.id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Jira issue {} is not linked", webhook.issue.key))?;
+    logging::record_zammad_ticket(zammad_ticket_id);
+    let _lock = locks::lock_ticket(db, zammad_ticket_id).await?;
+
+    // Delayed deliveries must not revert newer changes; their comment is still synced
+    let updated_at = webhook
+        .issue
+        .updated_at()
+        .or_else(|| webhook.timestamp.and_then(DateTime::from_timestamp_millis));
+    let is_stale = match updated_at {
+        Some(updated_at) => {
+            !db.record_source_update(&zammad_ticket_id, "jira", updated_at)
+                .await?
+        }
+        None => false,
+    };
+    if is_stale {
+        info!(
+            "Jira issue {} was updated since this event, skipping its field changes",
+            webhook.issue.key
+        );
+    } else {
+        update_fields(db, &webhook, zammad_ticket_id).await?;
+        checklists::sync_field_to_zammad(db, &webhook.issue, zammad_ticket_id).await?;
+    }
+
+    let directions = get_directions(db, &webhook.issue, zammad_ticket_id).await?;
+    // Comments created from Zammad articles must not be sent back to Zammad
+    if let Some(comment) = &webhook.comment
+        && directions.comments.to_zammad()
+        && !db.is_jira_comment_synced(&comment.id).await?
+    {
+        let meta = db.get_assignment_meta(&zammad_ticket_id).await?;
+        let article = ZammadCreateArticleRequest::from_jira_comment(
+            zammad_ticket_id,
+            &webhook.issue,
+            comment,
+            &meta,
+        )?
+        .submit()
+        .await?;
+        db.create_comment_mapping(&article.id, &comment.id).await?;
+    }
+    if directions.comments.to_zammad() {
+        attachments::announce_in_zammad(db, &webhook, zammad_ticket_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Fetches the referenced issue and comment from the Jira API and confirms that the webhook
+/// describes their actual state, so forged or stale payloads are not synced.
+async fn verify_webhook(webhook: &JiraWebhook<JiraApiIssue>) -> anyhow::Result<()> {
+    let issue = JiraGetIssueRequest::new(webhook.issue.id).submit().await?;
+    let status = |issue: &JiraApiIssue| issue.fields.status.as_ref().map(|s| s.name.clone());
+    if let Some(claimed) = status(&webhook.issue)
+        && status(&issue).as_ref() != Some(&claimed)
+    {
+        anyhow::bail!(
+            "issue {} is in status {:?} but the webhook claims {:?}",
+            issue.key,
+            status(&issue).unwrap_or_default(),
+            claimed
+        );
+    }
+    let priority = |issue: &JiraApiIssue| issue.fields.priority.as_ref().map(|p| p.name.clone());
+    if let Some(claimed) = priority(&webhook.issue)
+        && priority(&issue).as_ref() != Some(&claimed)
+    {
+        anyhow::bail!(
+            "issue {} has priority {:?} but the webhook claims {:?}",
+            issue.key,
+            priority(&issue).unwrap_or_default(),
+            claimed
+        );
+    }
+
+    if let Some(comment) = &webhook.comment {
+        JiraGetCommentRequest::new(webhook.issue.id, comment.id)
+            .submit()
+            .await
+            .with_context(|| {
+                format!(
+                    "comment {} doesn't exist on issue {}",
+                    comment.id, issue.key
+                )
+            })?;
+    }
+
+    Ok(())
+}
+
+/// Rejects the webhook if verification is enabled and the payload can't be confirmed.
+async fn is_verified(webhook: &JiraWebhook<JiraApiIssue>) -> bool {
+    if !config::get_jira().verify_webhooks {
+        return true;
+    }
+    match verify_webhook(webhook).await {
+        Ok(_) => true,
         Err(e) => {
-            error!("Failed to update ticket: {}", e);
-            StatusCode::BAD_REQUEST
+            warn!("Rejecting unverifiable Jira webhook: {}", e);
+            false
         }
     }
 }
 
-pub fn router() -> Router {
-    // Using specific Router<()> type to ensure we don't need state
-    let router: Router<()> = Router::new()
+/// A Jira issue was updated or commented on; it's synced to the linked ticket.
+#[utoipa::path(
+    post,
+    path = "/update-ticket/{id}",
+    params(
+        ("id" = String, Path, description = "Webhook ID of the Jira system"),
+        ("token" = Option<String>, Query, description = "The webhook token, if `webhook_auth` is a token"),
+        ("Authorization" = Option<String>, Header, description = "`JWT <token>`, if `webhook_auth` is a JWT secret"),
+    ),
+    request_body = JiraWebhook<JiraApiIssue>,
+    responses(
+        (status = 202, description = "Queued to be synced, later if the target system is unavailable"),
+        (status = 401, description = "Missing or invalid token or JWT"),
+        (status = 403, description = "The issue doesn't match Jira's, with `verify_webhooks`"),
+        (status = 404, description = "Unknown webhook ID"),
+        (status = 422, description = "The payload lacks required fields"),
+        (status = 503, description = "The outbox is full; retry after `Retry-After`"),
+    )
+)]
+#[instrument(skip(state, payload))]
+#[axum::debug_handler]
+async fn update_ticket_handler(
+    State(state): State<AppState>,
+    Path(_id): Path<String>,
+    Json(payload): Json<JiraWebhook<JiraApiIssue>>,
+) -> Response {
+    if !is_verified(&payload).await {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    // Known tickets are synced in order with their other webhooks
+    let zammad_ticket_id = state
+        .db
+        .get_zammad_id_by_jira_id(&payload.issue.id)
+        .await
+        .ok()
+        .flatten();
+    outbox::accept(&state.db, "jira.update", zammad_ticket_id, &payload).await
+}
+
+/// The webhooks Jira sends, under /ticket-sync/jira.
+#[derive(OpenApi)]
+#[openapi(paths(create_ticket_handler, update_ticket_handler))]
+pub struct JiraWebhookApi;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
         .route("/create-ticket/:id", post(create_ticket_handler))
-        .route("/update-ticket/:id", post(update_ticket_handler));
-    router
+        .route("/update-ticket/:id", post(update_ticket_handler))
 }
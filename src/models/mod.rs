@@ -1,5 +1,10 @@
 pub mod api_request;
-pub mod assignment;
+pub mod attachments;
 pub mod db;
 pub mod jira;
+#[cfg(feature = "mysql")]
+pub mod mysql;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod store;
 pub mod zammad;
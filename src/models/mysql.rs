@@ -0,0 +1,413 @@
+//! The links of tickets to issues, the comment mappings and the audit log in MySQL or MariaDB,
+//! with `db_path` set to a `mysql://` URL. Everything else stays in the local SQLite database.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    MySql, MySqlPool, Row,
+    migrate::Migrator,
+    mysql::{MySqlPoolOptions, MySqlRow},
+    pool::PoolConnection,
+};
+use tracing::{info, warn};
+
+use crate::models::db::{AuditEntry, AuditFilter, Lease, placeholders};
+use crate::models::store::{MappingStore, ReplicaLock};
+
+/// The versioned migrations of the tables in `migrations/mysql/`.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/mysql");
+
+pub struct MySqlStore {
+    pool: MySqlPool,
+    /// Connections holding a ticket's named lock, kept apart so the queries made while it's
+    /// held can't run out of connections
+    locks: MySqlPool,
+}
+
+impl MySqlStore {
+    /// Connects to the database and creates the tables, if they don't exist yet.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let pool = MySqlPool::connect(url)
+            .await
+            .context("failed to connect to MySQL")?;
+        MIGRATOR
+            .run(&pool)
+            .await
+            .context("failed to migrate MySQL")?;
+        info!("Storing the links, comment mappings and the audit log in MySQL");
+        Ok(Self {
+            pool,
+            locks: MySqlPoolOptions::new().connect_lazy(url)?,
+        })
+    }
+}
+
+/// A named lock of the session, released in the background when dropped.
+struct NamedLock {
+    conn: Option<PoolConnection<MySql>>,
+    zammad_id: i32,
+}
+
+impl Drop for NamedLock {
+    fn drop(&mut self) {
+        let Some(mut conn) = self.conn.take() else {
+            return;
+        };
+        let zammad_id = self.zammad_id;
+        tokio::spawn(async move {
+            let released = sqlx::query("SELECT RELEASE_LOCK(?)")
+                .bind(lock_name(zammad_id))
+                .execute(&mut *conn)
+                .await;
+            if let Err(e) = released {
+                warn!("Failed to unlock Zammad ticket {}: {}", zammad_id, e);
+                // Ending the session releases the lock
+                drop(conn.detach());
+            }
+        });
+    }
+}
+
+/// Named locks are server-wide, so the name carries the application.
+fn lock_name(zammad_id: i32) -> String {
+    format!("ticket-sync:{}", zammad_id)
+}
+
+#[async_trait]
+impl MappingStore for MySqlStore {
+    async fn create_assignment_from_zammad(&self, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query("INSERT IGNORE INTO assignments (zammad_id) VALUES (?)")
+            .bind(zammad_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Fails if the issue is linked to another ticket, by the unique index.
+    async fn create_assignment(&self, zammad_id: i32, jira_id: i32) -> anyhow::Result<()> {
+        // ON DUPLICATE KEY UPDATE would update the other ticket's row on a conflicting issue
+        let mut transaction = self.pool.begin().await?;
+        let updated = sqlx::query("UPDATE assignments SET jira_id = ? WHERE zammad_id = ?")
+            .bind(jira_id)
+            .bind(zammad_id)
+            .execute(&mut *transaction)
+            .await?;
+        if updated.rows_affected() == 0 {
+            sqlx::query("INSERT INTO assignments (zammad_id, jira_id) VALUES (?, ?)")
+                .bind(zammad_id)
+                .bind(jira_id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn add_jira_id_to_assignment(&self, jira_id: i32, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query("UPDATE assignments SET jira_id = ? WHERE zammad_id = ?")
+            .bind(jira_id)
+            .bind(zammad_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_assignment_by_zammad_id(
+        &self,
+        zammad_id: i32,
+    ) -> anyhow::Result<Option<Option<i32>>> {
+        let row = sqlx::query("SELECT jira_id FROM assignments WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| row.try_get("jira_id"))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    async fn get_zammad_id_by_jira_id(&self, jira_id: i32) -> anyhow::Result<Option<i32>> {
+        let row = sqlx::query("SELECT zammad_id FROM assignments WHERE jira_id = ?")
+            .bind(jira_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => Ok(row.try_get("zammad_id")?),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_assignments(&self) -> anyhow::Result<Vec<(i32, i32)>> {
+        let rows = sqlx::query(
+            "SELECT zammad_id, jira_id FROM assignments
+             WHERE zammad_id IS NOT NULL AND jira_id IS NOT NULL
+             ORDER BY zammad_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| Ok((row.try_get("zammad_id")?, row.try_get("jira_id")?)))
+            .collect()
+    }
+
+    async fn delete_assignment(&self, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM assignments WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_comment_mapping(
+        &self,
+        zammad_article_id: u64,
+        jira_comment_id: i32,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO comment_mappings (zammad_article_id, jira_comment_id) VALUES (?, ?)",
+        )
+        .bind(zammad_article_id as i64)
+        .bind(jira_comment_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn is_zammad_article_mapped(&self, zammad_article_id: u64) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM comment_mappings WHERE zammad_article_id = ?")
+            .bind(zammad_article_id as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn is_jira_comment_mapped(&self, jira_comment_id: i32) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM comment_mappings WHERE jira_comment_id = ?")
+            .bind(jira_comment_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn claim_delivery(&self, delivery_id: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "INSERT IGNORE INTO webhook_deliveries (delivery_id, received_at) VALUES (?, ?)",
+        )
+        .bind(delivery_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release_delivery(&self, delivery_id: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM webhook_deliveries WHERE delivery_id = ?")
+            .bind(delivery_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn prune_deliveries(&self, before: DateTime<Utc>) -> anyhow::Result<u64> {
+        let result = sqlx::query("DELETE FROM webhook_deliveries WHERE received_at < ?")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn lock_ticket(&self, zammad_id: i32) -> anyhow::Result<Option<ReplicaLock>> {
+        let mut conn = self.locks.acquire().await?;
+        // A negative timeout waits indefinitely
+        let locked: Option<i64> = sqlx::query_scalar("SELECT GET_LOCK(?, -1)")
+            .bind(lock_name(zammad_id))
+            .fetch_one(&mut *conn)
+            .await
+            .with_context(|| format!("failed to lock Zammad ticket {}", zammad_id))?;
+        if locked != Some(1) {
+            bail!("failed to lock Zammad ticket {}", zammad_id);
+        }
+        Ok(Some(Box::new(NamedLock {
+            conn: Some(conn),
+            zammad_id,
+        })))
+    }
+
+    async fn acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> anyhow::Result<bool> {
+        // The database's clock, so replicas with clocks apart agree on when it expires.
+        // Assignments are applied in order, so acquired_at still sees the previous holder.
+        let renewed = sqlx::query(
+            "UPDATE leases SET
+                 acquired_at = IF(holder = ?, acquired_at, UTC_TIMESTAMP(6)),
+                 holder = ?,
+                 renewed_at = UTC_TIMESTAMP(6),
+                 expires_at = UTC_TIMESTAMP(6) + INTERVAL ? MICROSECOND
+             WHERE name = ? AND (holder = ? OR expires_at < UTC_TIMESTAMP(6))",
+        )
+        .bind(holder)
+        .bind(holder)
+        .bind(ttl.as_micros() as i64)
+        .bind(name)
+        .bind(holder)
+        .execute(&self.pool)
+        .await?;
+        if renewed.rows_affected() == 1 {
+            return Ok(true);
+        }
+        // Only inserted if nobody holds it, not even an expired holder
+        let inserted = sqlx::query(
+            "INSERT IGNORE INTO leases (name, holder, acquired_at, renewed_at, expires_at)
+             VALUES (?, ?, UTC_TIMESTAMP(6), UTC_TIMESTAMP(6),
+                 UTC_TIMESTAMP(6) + INTERVAL ? MICROSECOND)",
+        )
+        .bind(name)
+        .bind(holder)
+        .bind(ttl.as_micros() as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(inserted.rows_affected() == 1)
+    }
+
+    async fn release_lease(&self, name: &str, holder: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM leases WHERE name = ? AND holder = ?")
+            .bind(name)
+            .bind(holder)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_lease(&self, name: &str) -> anyhow::Result<Option<Lease>> {
+        let row = sqlx::query(
+            "SELECT holder, acquired_at, renewed_at, expires_at FROM leases WHERE name = ?",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| {
+            Ok(Lease {
+                holder: row.try_get("holder")?,
+                acquired_at: row.try_get("acquired_at")?,
+                renewed_at: row.try_get("renewed_at")?,
+                expires_at: row.try_get("expires_at")?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn create_audit_entry(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (operation, direction, zammad_id, jira_id, request_id, fields,
+             calls, decisions, error, duration_ms, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&entry.operation)
+        .bind(&entry.direction)
+        .bind(entry.zammad_id)
+        .bind(entry.jira_id)
+        .bind(&entry.request_id)
+        .bind(serde_json::to_string(&entry.fields)?)
+        .bind(serde_json::to_string(&entry.calls)?)
+        .bind(entry.decisions.to_string())
+        .bind(&entry.error)
+        .bind(entry.duration_ms)
+        .bind(entry.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_audit_entries(&self, filter: &AuditFilter) -> anyhow::Result<Vec<AuditEntry>> {
+        // Placeholders are positional, so the filters are bound twice
+        let rows = sqlx::query(
+            "SELECT id, operation, direction, zammad_id, jira_id, request_id, fields, calls,
+             decisions, error, duration_ms, created_at
+             FROM audit_log
+             WHERE (? IS NULL OR zammad_id = ?)
+               AND (? IS NULL OR jira_id = ?)
+               AND (? IS NULL OR JSON_CONTAINS(fields, JSON_QUOTE(?)))
+               AND (? IS NULL OR created_at >= ?)
+               AND (? IS NULL OR created_at < ?)
+             ORDER BY id DESC LIMIT ?",
+        )
+        .bind(filter.zammad_id)
+        .bind(filter.zammad_id)
+        .bind(filter.jira_id)
+        .bind(filter.jira_id)
+        .bind(&filter.field)
+        .bind(&filter.field)
+        .bind(filter.since)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.until)
+        .bind(i64::from(filter.limit.unwrap_or(100)))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(audit_entry_from_row).collect()
+    }
+
+    async fn purge_ticket(
+        &self,
+        zammad_id: i32,
+        jira_id: Option<i32>,
+        article_ids: &[i64],
+        comment_ids: &[i32],
+    ) -> anyhow::Result<BTreeMap<&'static str, u64>> {
+        let mut transaction = self.pool.begin().await?;
+        let mut removed = BTreeMap::new();
+        let sql = format!(
+            "DELETE FROM comment_mappings
+             WHERE zammad_article_id IN ({}) OR jira_comment_id IN ({})",
+            placeholders(article_ids.len()),
+            placeholders(comment_ids.len())
+        );
+        let mut query = sqlx::query(&sql);
+        for id in article_ids {
+            query = query.bind(id);
+        }
+        for id in comment_ids {
+            query = query.bind(id);
+        }
+        let result = query.execute(&mut *transaction).await?;
+        removed.insert("comment_mappings", result.rows_affected());
+        for (table, sql) in [
+            (
+                "audit_log",
+                "DELETE FROM audit_log WHERE zammad_id = ? OR jira_id = ?",
+            ),
+            (
+                "assignments",
+                "DELETE FROM assignments WHERE zammad_id = ? OR jira_id = ?",
+            ),
+        ] {
+            let result = sqlx::query(sql)
+                .bind(zammad_id)
+                .bind(jira_id)
+                .execute(&mut *transaction)
+                .await?;
+            removed.insert(table, result.rows_affected());
+        }
+        transaction.commit().await?;
+        Ok(removed)
+    }
+}
+
+fn audit_entry_from_row(row: &MySqlRow) -> anyhow::Result<AuditEntry> {
+    Ok(AuditEntry {
+        id: row.try_get("id")?,
+        operation: row.try_get("operation")?,
+        direction: row.try_get("direction")?,
+        zammad_id: row.try_get("zammad_id")?,
+        jira_id: row.try_get("jira_id")?,
+        request_id: row.try_get("request_id")?,
+        fields: serde_json::from_str(row.try_get("fields")?)?,
+        calls: serde_json::from_str(row.try_get("calls")?)?,
+        decisions: serde_json::from_str(row.try_get("decisions")?)?,
+        error: row.try_get("error")?,
+        duration_ms: row.try_get("duration_ms")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
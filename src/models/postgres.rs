@@ -0,0 +1,380 @@
+//! The links of tickets to issues, the comment mappings and the audit log in PostgreSQL, with
+//! `db_path` set to a `postgres://` URL: they're kept in a managed database, which replicas can
+//! share. Everything else stays in the local SQLite database.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    PgPool, Postgres, Row,
+    migrate::Migrator,
+    pool::PoolConnection,
+    postgres::{PgPoolOptions, PgRow},
+};
+use tracing::{info, warn};
+
+use crate::models::db::{AuditEntry, AuditFilter, Lease};
+use crate::models::store::{MappingStore, ReplicaLock};
+
+/// The versioned migrations of the tables in `migrations/postgres/`.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// First key of the advisory locks, so they don't collide with those of other applications
+/// using the database; the second is the Zammad ticket.
+const LOCK_CLASS: i32 = 0x7473_796e;
+
+pub struct PostgresStore {
+    pool: PgPool,
+    /// Connections holding a ticket's advisory lock, kept apart so the queries made while
+    /// it's held can't run out of connections
+    locks: PgPool,
+}
+
+impl PostgresStore {
+    /// Connects to the database and creates the tables, if they don't exist yet.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let pool = PgPool::connect(url)
+            .await
+            .context("failed to connect to PostgreSQL")?;
+        MIGRATOR
+            .run(&pool)
+            .await
+            .context("failed to migrate PostgreSQL")?;
+        info!("Storing the links, comment mappings and the audit log in PostgreSQL");
+        Ok(Self {
+            pool,
+            locks: PgPoolOptions::new().connect_lazy(url)?,
+        })
+    }
+}
+
+/// A session-level advisory lock, unlocked in the background when dropped.
+struct AdvisoryLock {
+    conn: Option<PoolConnection<Postgres>>,
+    zammad_id: i32,
+}
+
+impl Drop for AdvisoryLock {
+    fn drop(&mut self) {
+        let Some(mut conn) = self.conn.take() else {
+            return;
+        };
+        let zammad_id = self.zammad_id;
+        tokio::spawn(async move {
+            let unlocked = sqlx::query("SELECT pg_advisory_unlock($1, $2)")
+                .bind(LOCK_CLASS)
+                .bind(zammad_id)
+                .execute(&mut *conn)
+                .await;
+            if let Err(e) = unlocked {
+                warn!("Failed to unlock Zammad ticket {}: {}", zammad_id, e);
+                // Ending the session releases the lock
+                drop(conn.detach());
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl MappingStore for PostgresStore {
+    async fn create_assignment_from_zammad(&self, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO assignments (zammad_id) VALUES ($1) ON CONFLICT (zammad_id) DO NOTHING",
+        )
+        .bind(zammad_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_assignment(&self, zammad_id: i32, jira_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO assignments (zammad_id, jira_id) VALUES ($1, $2)
+             ON CONFLICT (zammad_id) DO UPDATE SET jira_id = excluded.jira_id",
+        )
+        .bind(zammad_id)
+        .bind(jira_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn add_jira_id_to_assignment(&self, jira_id: i32, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query("UPDATE assignments SET jira_id = $1 WHERE zammad_id = $2")
+            .bind(jira_id)
+            .bind(zammad_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_assignment_by_zammad_id(
+        &self,
+        zammad_id: i32,
+    ) -> anyhow::Result<Option<Option<i32>>> {
+        let row = sqlx::query("SELECT jira_id FROM assignments WHERE zammad_id = $1")
+            .bind(zammad_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(|row| row.try_get("jira_id"))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    async fn get_zammad_id_by_jira_id(&self, jira_id: i32) -> anyhow::Result<Option<i32>> {
+        let row = sqlx::query("SELECT zammad_id FROM assignments WHERE jira_id = $1")
+            .bind(jira_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => Ok(row.try_get("zammad_id")?),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_assignments(&self) -> anyhow::Result<Vec<(i32, i32)>> {
+        let rows = sqlx::query(
+            "SELECT zammad_id, jira_id FROM assignments
+             WHERE zammad_id IS NOT NULL AND jira_id IS NOT NULL
+             ORDER BY zammad_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| Ok((row.try_get("zammad_id")?, row.try_get("jira_id")?)))
+            .collect()
+    }
+
+    async fn delete_assignment(&self, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM assignments WHERE zammad_id = $1")
+            .bind(zammad_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_comment_mapping(
+        &self,
+        zammad_article_id: u64,
+        jira_comment_id: i32,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO comment_mappings (zammad_article_id, jira_comment_id) VALUES ($1, $2)",
+        )
+        .bind(zammad_article_id as i64)
+        .bind(jira_comment_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn is_zammad_article_mapped(&self, zammad_article_id: u64) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM comment_mappings WHERE zammad_article_id = $1")
+            .bind(zammad_article_id as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn is_jira_comment_mapped(&self, jira_comment_id: i32) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM comment_mappings WHERE jira_comment_id = $1")
+            .bind(jira_comment_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn claim_delivery(&self, delivery_id: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO webhook_deliveries (delivery_id, received_at) VALUES ($1, $2)
+             ON CONFLICT (delivery_id) DO NOTHING",
+        )
+        .bind(delivery_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release_delivery(&self, delivery_id: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM webhook_deliveries WHERE delivery_id = $1")
+            .bind(delivery_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn prune_deliveries(&self, before: DateTime<Utc>) -> anyhow::Result<u64> {
+        let result = sqlx::query("DELETE FROM webhook_deliveries WHERE received_at < $1")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn lock_ticket(&self, zammad_id: i32) -> anyhow::Result<Option<ReplicaLock>> {
+        let mut conn = self.locks.acquire().await?;
+        sqlx::query("SELECT pg_advisory_lock($1, $2)")
+            .bind(LOCK_CLASS)
+            .bind(zammad_id)
+            .execute(&mut *conn)
+            .await
+            .with_context(|| format!("failed to lock Zammad ticket {}", zammad_id))?;
+        Ok(Some(Box::new(AdvisoryLock {
+            conn: Some(conn),
+            zammad_id,
+        })))
+    }
+
+    async fn acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> anyhow::Result<bool> {
+        // The database's clock, so replicas with clocks apart agree on when it expires
+        let result = sqlx::query(
+            "INSERT INTO leases (name, holder, acquired_at, renewed_at, expires_at)
+             VALUES ($1, $2, now(), now(), now() + make_interval(secs => $3))
+             ON CONFLICT (name) DO UPDATE SET
+                 holder = excluded.holder,
+                 acquired_at = CASE WHEN leases.holder = excluded.holder
+                     THEN leases.acquired_at ELSE excluded.acquired_at END,
+                 renewed_at = excluded.renewed_at,
+                 expires_at = excluded.expires_at
+             WHERE leases.holder = excluded.holder OR leases.expires_at < excluded.renewed_at",
+        )
+        .bind(name)
+        .bind(holder)
+        .bind(ttl.as_secs_f64())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release_lease(&self, name: &str, holder: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM leases WHERE name = $1 AND holder = $2")
+            .bind(name)
+            .bind(holder)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_lease(&self, name: &str) -> anyhow::Result<Option<Lease>> {
+        let row = sqlx::query(
+            "SELECT holder, acquired_at, renewed_at, expires_at FROM leases WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| {
+            Ok(Lease {
+                holder: row.try_get("holder")?,
+                acquired_at: row.try_get("acquired_at")?,
+                renewed_at: row.try_get("renewed_at")?,
+                expires_at: row.try_get("expires_at")?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn create_audit_entry(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (operation, direction, zammad_id, jira_id, request_id, fields,
+             calls, decisions, error, duration_ms, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(&entry.operation)
+        .bind(&entry.direction)
+        .bind(entry.zammad_id)
+        .bind(entry.jira_id)
+        .bind(&entry.request_id)
+        .bind(serde_json::to_string(&entry.fields)?)
+        .bind(serde_json::to_string(&entry.calls)?)
+        .bind(entry.decisions.to_string())
+        .bind(&entry.error)
+        .bind(entry.duration_ms)
+        .bind(entry.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_audit_entries(&self, filter: &AuditFilter) -> anyhow::Result<Vec<AuditEntry>> {
+        let rows = sqlx::query(
+            "SELECT id, operation, direction, zammad_id, jira_id, request_id, fields, calls,
+             decisions, error, duration_ms, created_at
+             FROM audit_log
+             WHERE ($1::INTEGER IS NULL OR zammad_id = $1)
+               AND ($2::INTEGER IS NULL OR jira_id = $2)
+               AND ($3::TEXT IS NULL OR fields::JSONB ? $3)
+               AND ($4::TIMESTAMPTZ IS NULL OR created_at >= $4)
+               AND ($5::TIMESTAMPTZ IS NULL OR created_at < $5)
+             ORDER BY id DESC LIMIT $6",
+        )
+        .bind(filter.zammad_id)
+        .bind(filter.jira_id)
+        .bind(&filter.field)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(i64::from(filter.limit.unwrap_or(100)))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(audit_entry_from_row).collect()
+    }
+
+    async fn purge_ticket(
+        &self,
+        zammad_id: i32,
+        jira_id: Option<i32>,
+        article_ids: &[i64],
+        comment_ids: &[i32],
+    ) -> anyhow::Result<BTreeMap<&'static str, u64>> {
+        let mut transaction = self.pool.begin().await?;
+        let mut removed = BTreeMap::new();
+        let result = sqlx::query(
+            "DELETE FROM comment_mappings
+             WHERE zammad_article_id = ANY($1) OR jira_comment_id = ANY($2)",
+        )
+        .bind(article_ids)
+        .bind(comment_ids)
+        .execute(&mut *transaction)
+        .await?;
+        removed.insert("comment_mappings", result.rows_affected());
+        for (table, sql) in [
+            (
+                "audit_log",
+                "DELETE FROM audit_log WHERE zammad_id = $1 OR jira_id = $2",
+            ),
+            (
+                "assignments",
+                "DELETE FROM assignments WHERE zammad_id = $1 OR jira_id = $2",
+            ),
+        ] {
+            let result = sqlx::query(sql)
+                .bind(zammad_id)
+                .bind(jira_id)
+                .execute(&mut *transaction)
+                .await?;
+            removed.insert(table, result.rows_affected());
+        }
+        transaction.commit().await?;
+        Ok(removed)
+    }
+}
+
+fn audit_entry_from_row(row: &PgRow) -> anyhow::Result<AuditEntry> {
+    Ok(AuditEntry {
+        id: row.try_get("id")?,
+        operation: row.try_get("operation")?,
+        direction: row.try_get("direction")?,
+        zammad_id: row.try_get("zammad_id")?,
+        jira_id: row.try_get("jira_id")?,
+        request_id: row.try_get("request_id")?,
+        fields: serde_json::from_str(row.try_get("fields")?)?,
+        calls: serde_json::from_str(row.try_get("calls")?)?,
+        decisions: serde_json::from_str(row.try_get("decisions")?)?,
+        error: row.try_get("error")?,
+        duration_ms: row.try_get("duration_ms")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
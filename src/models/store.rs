@@ -0,0 +1,497 @@
+//! The links of tickets to issues, of articles to comments, the claimed webhook deliveries
+//! and the audit log are kept by a `MappingStore`. `DB` delegates to it, so another store only
+//! needs to implement this trait: SQLite is the default, `db_path` set to a `postgres://` URL
+//! (with the `postgres` feature) or a `mysql://` URL for MySQL or MariaDB (with the `mysql`
+//! feature) selects a server database, which replicas can share. Everything else stays in the
+//! local SQLite database.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::bail;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Pool, Row, Sqlite, sqlite::SqliteRow};
+
+use crate::models::db::{AuditEntry, AuditFilter, Lease, placeholders};
+#[cfg(feature = "mysql")]
+use crate::models::mysql::MySqlStore;
+#[cfg(feature = "postgres")]
+use crate::models::postgres::PostgresStore;
+
+/// A lock of a ticket across replicas, released when dropped.
+pub type ReplicaLock = Box<dyn Send + Sync>;
+
+#[async_trait]
+pub trait MappingStore: Send + Sync {
+    /// Keeps an existing assignment, e.g. of an earlier attempt that failed to create the
+    /// Jira issue.
+    async fn create_assignment_from_zammad(&self, zammad_id: i32) -> anyhow::Result<()>;
+
+    /// Links the ticket to the issue, also if an assignment of the ticket exists already.
+    /// Fails if the issue is linked to another ticket.
+    async fn create_assignment(&self, zammad_id: i32, jira_id: i32) -> anyhow::Result<()>;
+
+    async fn add_jira_id_to_assignment(&self, jira_id: i32, zammad_id: i32) -> anyhow::Result<()>;
+
+    /// The issue of the ticket's assignment, if there is one; `Some(None)` until it's created.
+    async fn get_assignment_by_zammad_id(
+        &self,
+        zammad_id: i32,
+    ) -> anyhow::Result<Option<Option<i32>>>;
+
+    async fn get_zammad_id_by_jira_id(&self, jira_id: i32) -> anyhow::Result<Option<i32>>;
+
+    /// All linked tickets and issues as `(zammad_id, jira_id)`, ordered by ticket.
+    async fn get_assignments(&self) -> anyhow::Result<Vec<(i32, i32)>>;
+
+    async fn delete_assignment(&self, zammad_id: i32) -> anyhow::Result<()>;
+
+    /// Links a Zammad article to the Jira comment it was synced to (or from).
+    async fn create_comment_mapping(
+        &self,
+        zammad_article_id: u64,
+        jira_comment_id: i32,
+    ) -> anyhow::Result<()>;
+
+    async fn is_zammad_article_mapped(&self, zammad_article_id: u64) -> anyhow::Result<bool>;
+
+    async fn is_jira_comment_mapped(&self, jira_comment_id: i32) -> anyhow::Result<bool>;
+
+    /// Records a webhook delivery. Returns `false` if the delivery was already claimed, by
+    /// this or another replica, in which case it must not be processed again.
+    async fn claim_delivery(&self, delivery_id: &str) -> anyhow::Result<bool>;
+
+    async fn release_delivery(&self, delivery_id: &str) -> anyhow::Result<()>;
+
+    /// Forgets the deliveries claimed before `before`. Returns the number removed.
+    async fn prune_deliveries(&self, before: DateTime<Utc>) -> anyhow::Result<u64>;
+
+    /// Waits until no other replica sharing the store processes a webhook of the Zammad
+    /// ticket; the lock is held until the returned value is dropped. A store only this
+    /// process uses doesn't need to lock, as `locks::lock_ticket` serializes the webhooks of
+    /// a ticket within the process.
+    async fn lock_ticket(&self, _zammad_id: i32) -> anyhow::Result<Option<ReplicaLock>> {
+        Ok(None)
+    }
+
+    /// Takes the lease for `ttl`, or renews it if `holder` holds it already. Returns `false`
+    /// if another holder's lease hasn't expired yet.
+    async fn acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> anyhow::Result<bool>;
+
+    async fn release_lease(&self, name: &str, holder: &str) -> anyhow::Result<()>;
+
+    async fn get_lease(&self, name: &str) -> anyhow::Result<Option<Lease>>;
+
+    async fn create_audit_entry(&self, entry: &AuditEntry) -> anyhow::Result<()>;
+
+    /// The audit entries matching the filter, newest first.
+    async fn get_audit_entries(&self, filter: &AuditFilter) -> anyhow::Result<Vec<AuditEntry>>;
+
+    /// Deletes the link of the ticket or issue, the mappings of the given articles and
+    /// comments and their audit entries. Returns the number of rows removed per table.
+    async fn purge_ticket(
+        &self,
+        zammad_id: i32,
+        jira_id: Option<i32>,
+        article_ids: &[i64],
+        comment_ids: &[i32],
+    ) -> anyhow::Result<BTreeMap<&'static str, u64>>;
+}
+
+/// Connects to the server database at `url` and creates the tables, if they don't exist yet.
+pub async fn connect(url: &str) -> anyhow::Result<Arc<dyn MappingStore>> {
+    let scheme = url.split_once("://").map_or("", |(scheme, _)| scheme);
+    match scheme {
+        #[cfg(feature = "postgres")]
+        "postgres" | "postgresql" => Ok(Arc::new(PostgresStore::connect(url).await?)),
+        #[cfg(feature = "mysql")]
+        "mysql" => Ok(Arc::new(MySqlStore::connect(url).await?)),
+        #[cfg(not(feature = "postgres"))]
+        "postgres" | "postgresql" => {
+            bail!(
+                "db_path {}:// needs a build with the postgres feature",
+                scheme
+            )
+        }
+        #[cfg(not(feature = "mysql"))]
+        "mysql" => bail!("db_path mysql:// needs a build with the mysql feature"),
+        _ => bail!("db_path {}:// isn't a supported database", scheme),
+    }
+}
+
+/// The tables in the SQLite database, next to everything else.
+pub struct SqliteStore(pub Pool<Sqlite>);
+
+#[async_trait]
+impl MappingStore for SqliteStore {
+    async fn create_assignment_from_zammad(&self, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO assignments (zammad_id) VALUES (?) ON CONFLICT(zammad_id) DO NOTHING",
+        )
+        .bind(zammad_id)
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_assignment(&self, zammad_id: i32, jira_id: i32) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO assignments (zammad_id, jira_id) VALUES (?, ?)
+             ON CONFLICT(zammad_id) DO UPDATE SET jira_id = excluded.jira_id",
+        )
+        .bind(zammad_id)
+        .bind(jira_id)
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    async fn add_jira_id_to_assignment(&self, jira_id: i32, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query("UPDATE assignments SET jira_id = (?) WHERE zammad_id = (?)")
+            .bind(jira_id)
+            .bind(zammad_id)
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_assignment_by_zammad_id(
+        &self,
+        zammad_id: i32,
+    ) -> anyhow::Result<Option<Option<i32>>> {
+        let row = sqlx::query("SELECT jira_id FROM assignments WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .fetch_optional(&self.0)
+            .await?;
+        row.map(|row| row.try_get("jira_id"))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    async fn get_zammad_id_by_jira_id(&self, jira_id: i32) -> anyhow::Result<Option<i32>> {
+        let row = sqlx::query("SELECT zammad_id FROM assignments WHERE jira_id = ?")
+            .bind(jira_id)
+            .fetch_optional(&self.0)
+            .await?;
+        match row {
+            Some(row) => Ok(row.try_get("zammad_id")?),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_assignments(&self) -> anyhow::Result<Vec<(i32, i32)>> {
+        let rows = sqlx::query(
+            "SELECT zammad_id, jira_id FROM assignments
+             WHERE zammad_id IS NOT NULL AND jira_id IS NOT NULL
+             ORDER BY zammad_id",
+        )
+        .fetch_all(&self.0)
+        .await?;
+        rows.iter()
+            .map(|row| Ok((row.try_get("zammad_id")?, row.try_get("jira_id")?)))
+            .collect()
+    }
+
+    async fn delete_assignment(&self, zammad_id: i32) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM assignments WHERE zammad_id = ?")
+            .bind(zammad_id)
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_comment_mapping(
+        &self,
+        zammad_article_id: u64,
+        jira_comment_id: i32,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO comment_mappings (zammad_article_id, jira_comment_id) VALUES (?, ?)",
+        )
+        .bind(zammad_article_id as i64)
+        .bind(jira_comment_id)
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    async fn is_zammad_article_mapped(&self, zammad_article_id: u64) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM comment_mappings WHERE zammad_article_id = ?")
+            .bind(zammad_article_id as i64)
+            .fetch_optional(&self.0)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn is_jira_comment_mapped(&self, jira_comment_id: i32) -> anyhow::Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM comment_mappings WHERE jira_comment_id = ?")
+            .bind(jira_comment_id)
+            .fetch_optional(&self.0)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn claim_delivery(&self, delivery_id: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO webhook_deliveries (delivery_id, received_at) VALUES (?, ?)",
+        )
+        .bind(delivery_id)
+        .bind(Utc::now())
+        .execute(&self.0)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release_delivery(&self, delivery_id: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM webhook_deliveries WHERE delivery_id = ?")
+            .bind(delivery_id)
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn prune_deliveries(&self, before: DateTime<Utc>) -> anyhow::Result<u64> {
+        let result = sqlx::query("DELETE FROM webhook_deliveries WHERE received_at < ?")
+            .bind(before)
+            .execute(&self.0)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> anyhow::Result<bool> {
+        let now = Utc::now();
+        let result = sqlx::query(
+            "INSERT INTO leases (name, holder, acquired_at, renewed_at, expires_at)
+             VALUES (?1, ?2, ?3, ?3, ?4)
+             ON CONFLICT(name) DO UPDATE SET
+                 holder = excluded.holder,
+                 acquired_at = CASE WHEN leases.holder = excluded.holder
+                     THEN leases.acquired_at ELSE excluded.acquired_at END,
+                 renewed_at = excluded.renewed_at,
+                 expires_at = excluded.expires_at
+             WHERE leases.holder = excluded.holder OR leases.expires_at < excluded.renewed_at",
+        )
+        .bind(name)
+        .bind(holder)
+        .bind(now)
+        .bind(now + ttl)
+        .execute(&self.0)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release_lease(&self, name: &str, holder: &str) -> anyhow::Result<()> {
+        sqlx::query("DELETE FROM leases WHERE name = ? AND holder = ?")
+            .bind(name)
+            .bind(holder)
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_lease(&self, name: &str) -> anyhow::Result<Option<Lease>> {
+        let row = sqlx::query(
+            "SELECT holder, acquired_at, renewed_at, expires_at FROM leases WHERE name = ?",
+        )
+        .bind(name)
+        .fetch_optional(&self.0)
+        .await?;
+        row.map(|row| {
+            Ok(Lease {
+                holder: row.try_get("holder")?,
+                acquired_at: row.try_get("acquired_at")?,
+                renewed_at: row.try_get("renewed_at")?,
+                expires_at: row.try_get("expires_at")?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn create_audit_entry(&self, entry: &AuditEntry) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO audit_log (operation, direction, zammad_id, jira_id, request_id, fields,
+             calls, decisions, error, duration_ms, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&entry.operation)
+        .bind(&entry.direction)
+        .bind(entry.zammad_id)
+        .bind(entry.jira_id)
+        .bind(&entry.request_id)
+        .bind(serde_json::to_string(&entry.fields)?)
+        .bind(serde_json::to_string(&entry.calls)?)
+        .bind(entry.decisions.to_string())
+        .bind(&entry.error)
+        .bind(entry.duration_ms)
+        .bind(entry.created_at)
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_audit_entries(&self, filter: &AuditFilter) -> anyhow::Result<Vec<AuditEntry>> {
+        let rows = sqlx::query(
+            "SELECT id, operation, direction, zammad_id, jira_id, request_id, fields, calls,
+             decisions, error, duration_ms, created_at
+             FROM audit_log
+             WHERE (?1 IS NULL OR zammad_id = ?1)
+               AND (?2 IS NULL OR jira_id = ?2)
+               AND (?3 IS NULL OR EXISTS (SELECT 1 FROM json_each(fields) WHERE value = ?3))
+               AND (?4 IS NULL OR created_at >= ?4)
+               AND (?5 IS NULL OR created_at < ?5)
+             ORDER BY id DESC LIMIT ?6",
+        )
+        .bind(filter.zammad_id)
+        .bind(filter.jira_id)
+        .bind(&filter.field)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.limit.unwrap_or(100))
+        .fetch_all(&self.0)
+        .await?;
+        rows.iter().map(audit_entry_from_row).collect()
+    }
+
+    async fn purge_ticket(
+        &self,
+        zammad_id: i32,
+        jira_id: Option<i32>,
+        article_ids: &[i64],
+        comment_ids: &[i32],
+    ) -> anyhow::Result<BTreeMap<&'static str, u64>> {
+        let mut transaction = self.0.begin().await?;
+        let mut removed = BTreeMap::new();
+        let sql = format!(
+            "DELETE FROM comment_mappings
+             WHERE zammad_article_id IN ({}) OR jira_comment_id IN ({})",
+            placeholders(article_ids.len()),
+            placeholders(comment_ids.len())
+        );
+        let mut query = sqlx::query(&sql);
+        for id in article_ids {
+            query = query.bind(id);
+        }
+        for id in comment_ids {
+            query = query.bind(id);
+        }
+        let result = query.execute(&mut *transaction).await?;
+        removed.insert("comment_mappings", result.rows_affected());
+        for (table, sql) in [
+            (
+                "audit_log",
+                "DELETE FROM audit_log WHERE zammad_id = ?1 OR jira_id = ?2",
+            ),
+            (
+                "assignments",
+                "DELETE FROM assignments WHERE zammad_id = ?1 OR jira_id = ?2",
+            ),
+        ] {
+            let result = sqlx::query(sql)
+                .bind(zammad_id)
+                .bind(jira_id)
+                .execute(&mut *transaction)
+                .await?;
+            removed.insert(table, result.rows_affected());
+        }
+        transaction.commit().await?;
+        Ok(removed)
+    }
+}
+
+fn audit_entry_from_row(row: &SqliteRow) -> anyhow::Result<AuditEntry> {
+    Ok(AuditEntry {
+        id: row.try_get("id")?,
+        operation: row.try_get("operation")?,
+        direction: row.try_get("direction")?,
+        zammad_id: row.try_get("zammad_id")?,
+        jira_id: row.try_get("jira_id")?,
+        request_id: row.try_get("request_id")?,
+        fields: serde_json::from_str(row.try_get("fields")?)?,
+        calls: serde_json::from_str(row.try_get("calls")?)?,
+        decisions: serde_json::from_str(row.try_get("decisions")?)?,
+        error: row.try_get("error")?,
+        duration_ms: row.try_get("duration_ms")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::TimeDelta;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// A store on a fresh database file, with a pool of several connections like the
+    /// server's, so claims really race. The file is removed when it's dropped.
+    struct TestStore {
+        store: SqliteStore,
+        file: std::path::PathBuf,
+    }
+
+    impl TestStore {
+        async fn new() -> Self {
+            let file = std::env::temp_dir().join(format!("ticket-sync-test-{}.db", Uuid::new_v4()));
+            let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", file.display()))
+                .unwrap()
+                .create_if_missing(true)
+                .busy_timeout(Duration::from_secs(5));
+            let pool = SqlitePoolOptions::new()
+                .max_connections(4)
+                .connect_with(options)
+                .await
+                .unwrap();
+            sqlx::query(include_str!("../../migrations/0002_webhook_deliveries.sql"))
+                .execute(&pool)
+                .await
+                .unwrap();
+            Self {
+                store: SqliteStore(pool),
+                file,
+            }
+        }
+    }
+
+    impl Drop for TestStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.file);
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_claims_of_a_delivery_let_one_through() {
+        let TestStore { store, .. } = &TestStore::new().await;
+        for i in 0..50 {
+            let id = format!("x-zammad-delivery:{}", i);
+            let (first, second) =
+                tokio::join!(store.claim_delivery(&id), store.claim_delivery(&id));
+            let claimed = [first.unwrap(), second.unwrap()];
+            assert_eq!(
+                claimed.iter().filter(|claimed| **claimed).count(),
+                1,
+                "{}",
+                id
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn released_and_pruned_deliveries_can_be_claimed_again() {
+        let TestStore { store, .. } = &TestStore::new().await;
+        assert!(store.claim_delivery("a").await.unwrap());
+        store.release_delivery("a").await.unwrap();
+        assert!(store.claim_delivery("a").await.unwrap());
+
+        let removed = store
+            .prune_deliveries(Utc::now() + TimeDelta::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(store.claim_delivery("a").await.unwrap());
+    }
+}
@@ -1,24 +1,37 @@
 use super::{
-    api_request::{JiraAddCommentRequest, JiraUpdateIssueRequest},
+    api_request::{
+        JiraAddCommentRequest, JiraGetIssueRequest, JiraUpdateIssueRequest,
+        ZammadGetArticleRequest, ZammadGetTicketRequest, ZammadSearchKnowledgeBaseRequest,
+        content_hash, get_zammad_web_url, replace_synced_description, zammad_description,
+    },
+    attachments,
     db::DB,
+    jira,
 };
-use std::sync::Arc;
 
-use anyhow;
-use async_trait::async_trait;
-use axum::{Json, Router, extract::Path, routing::post};
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::post,
+};
 use chrono::{DateTime, Utc};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use serde_repr::Deserialize_repr;
-use tracing::{debug, error, info};
+use serde_json::{Map, Value};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use tracing::{info, warn};
+use utoipa::{OpenApi, ToSchema};
 
-use crate::models::{api_request::JiraCreateIssueRequest, assignment::Assignment, jira::JiraIssue};
+use crate::config::{self, KnowledgeBaseConfig};
+use crate::conflicts::{FieldChange, FieldValue, Side};
+use crate::models::api_request::{JiraCreateCustomerRequest, JiraCreateIssueRequest};
+use crate::state::AppState;
+use crate::{checklists, filters, locks, logging, outbox};
 
 /// Represents a Zammad webhook payload containing both ticket and article information.
 /// This is the top-level structure that Zammad sends when a ticket is created or updated.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ZammadWebhook {
     /// The ticket information including metadata, state, and relationships
     pub ticket: ZammadTicket,
@@ -29,7 +42,7 @@ pub struct ZammadWebhook {
 /// Represents a Zammad ticket with all its metadata and relationships.
 /// This structure contains all the essential information needed to create/update
 /// a corresponding ticket in another system (like Jira).
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ZammadTicket {
     /// Unique identifier for the ticket in Zammad
     pub id: i32,
@@ -46,23 +59,154 @@ pub struct ZammadTicket {
     /// When the ticket was last updated
     pub updated_at: DateTime<Utc>,
     /// Optional due date for the ticket
-    pub due_date: DateTime<Utc>,
+    #[serde(default)]
+    pub due_date: Option<DateTime<Utc>>,
     /// User who created the ticket
     pub created_by: ZammadUser,
     /// User who is currently assigned to the ticket
     pub owner: ZammadUser,
+    /// Organization of the customer, if any
+    #[serde(default)]
+    pub organization: Option<ZammadOrganization>,
+    /// Customer the ticket was opened for
+    #[serde(default)]
+    pub customer: Option<ZammadUser>,
+    /// Group the ticket is assigned to
+    #[serde(default)]
+    pub group: Option<ZammadGroup>,
+    /// Earliest of the SLA deadlines below, set while an SLA applies
+    #[serde(default)]
+    pub escalation_at: Option<DateTime<Utc>>,
+    /// SLA deadline for the first agent response
+    #[serde(default)]
+    pub first_response_escalation_at: Option<DateTime<Utc>>,
+    /// SLA deadline for the next agent update
+    #[serde(default)]
+    pub update_escalation_at: Option<DateTime<Utc>>,
+    /// SLA deadline for solving the ticket
+    #[serde(default)]
+    pub close_escalation_at: Option<DateTime<Utc>>,
+    /// Tags attached to the ticket
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Custom object attributes and everything else not modelled above
+    #[serde(flatten)]
+    pub extra_fields: Map<String, Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A ticket as returned by the Zammad API with `expand=true`, where related objects are
+/// only given by name.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ZammadApiTicket {
+    pub id: i32,
+    pub number: String,
+    pub title: String,
+    /// State name, e.g. "new" or "pending reminder"
+    pub state: String,
+    pub priority_id: ZammadPriorityId,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub group_id: Option<i32>,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub organization_id: Option<i32>,
+    #[serde(default)]
+    pub organization: Option<String>,
+    pub customer_id: u64,
+    /// Login of the customer, usually the email address
+    #[serde(default)]
+    pub customer: Option<String>,
+    pub owner_id: u64,
+    #[serde(default)]
+    pub owner: Option<String>,
+    pub created_by_id: u64,
+    #[serde(default)]
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub due_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub escalation_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub first_response_escalation_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub update_escalation_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub close_escalation_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub extra_fields: Map<String, Value>,
+}
+
+impl ZammadApiTicket {
+    /// The ticket as webhooks describe it. Users are only known by login, so their names
+    /// are empty, and tags aren't part of the API response.
+    pub fn into_ticket(self) -> ZammadTicket {
+        let user = |id, login: Option<String>| ZammadUser {
+            id,
+            email: login.unwrap_or_default(),
+            firstname: String::new(),
+            lastname: String::new(),
+        };
+        ZammadTicket {
+            id: self.id,
+            number: self.number,
+            title: self.title,
+            state: ZammadState::from_name(&self.state),
+            priority: ZammadPriority {
+                id: self.priority_id,
+                name: self.priority,
+            },
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            due_date: self.due_date,
+            created_by: user(self.created_by_id, self.created_by),
+            owner: user(self.owner_id, self.owner),
+            organization: self
+                .organization_id
+                .zip(self.organization)
+                .map(|(id, name)| ZammadOrganization { id, name }),
+            customer: Some(user(self.customer_id, self.customer)),
+            group: self
+                .group_id
+                .zip(self.group)
+                .map(|(id, name)| ZammadGroup { id, name }),
+            escalation_at: self.escalation_at,
+            first_response_escalation_at: self.first_response_escalation_at,
+            update_escalation_at: self.update_escalation_at,
+            close_escalation_at: self.close_escalation_at,
+            tags: Vec::new(),
+            extra_fields: self.extra_fields,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ZammadGroup {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ZammadOrganization {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ZammadPriority {
     pub id: ZammadPriorityId,
+    /// E.g. "2 normal"
+    #[serde(default)]
+    pub name: Option<String>,
 }
 
 /// Represents a Zammad priority level.
 /// Example: "2 normal" with ID 2
-
 #[repr(i32)] // store the enum as an 32-bit integer
-#[derive(Debug, Serialize, Deserialize_repr, Clone, Copy)]
+#[derive(Debug, Serialize_repr, Deserialize_repr, Clone, Copy, ToSchema)]
 pub enum ZammadPriorityId {
     /// Unique identifier for the priority
     Low = 1,
@@ -70,7 +214,7 @@ pub enum ZammadPriorityId {
     High = 3,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, ToSchema)]
 // We're expecting either "open" or "closed" as a string. Need to deserialize it to the enum.
 #[serde(rename_all = "lowercase")]
 pub enum ZammadState {
@@ -79,18 +223,26 @@ pub enum ZammadState {
 }
 
 impl ZammadState {
-    pub fn from_str(state: String) -> Result<ZammadState, String> {
-        match state.as_str() {
-            "open" => Ok(ZammadState::Open),
-            "closed" => Ok(ZammadState::Closed),
-            _ => Err(format!("Invalid state: {}", state)),
+    /// The state a Zammad state name like "pending reminder" is synced as.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "closed" | "merged" | "removed" => ZammadState::Closed,
+            _ => ZammadState::Open,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ZammadState::Open => "open",
+            ZammadState::Closed => "closed",
         }
     }
 }
+
 /// Represents a Zammad user with essential contact information.
 /// This is a simplified version of the full user object from Zammad,
 /// containing only the fields we need for ticket synchronization.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ZammadUser {
     /// Unique identifier for the user
     pub id: u64,
@@ -104,7 +256,7 @@ pub struct ZammadUser {
 
 /// Represents a Zammad article (comment/message) on a ticket.
 /// Each article represents a communication in the ticket's history.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ZammadArticle {
     /// Unique identifier for the article
     pub id: Option<u64>,
@@ -124,70 +276,420 @@ pub struct ZammadArticle {
     pub from: Option<String>,
     /// Optional "To" field (e.g., "Users")
     pub to: Option<String>,
+    /// Optional "Cc" field with additional recipients
+    #[serde(default)]
+    pub cc: Option<String>,
+    /// Files attached to the article, e.g. from an email
+    #[serde(default)]
+    pub attachments: Vec<ZammadAttachment>,
 }
 
-async fn create_ticket(id: String, webhook: ZammadWebhook) -> anyhow::Result<()> {
-    let db = DB::new().await?;
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ZammadAttachment {
+    pub id: u64,
+    pub filename: String,
+    /// Contains the MIME type as "Content-Type" or "Mime-Type"
+    #[serde(default)]
+    pub preferences: Map<String, Value>,
+}
 
-    db.create_assignment_from_zammad(&webhook.ticket.id).await?;
+impl ZammadAttachment {
+    /// MIME type without parameters, guessed from the file name if Zammad doesn't know it.
+    pub fn mime_type(&self) -> String {
+        let mime_type = ["Content-Type", "Mime-Type"]
+            .iter()
+            .find_map(|key| self.preferences.get(*key).and_then(Value::as_str));
+        match mime_type {
+            Some(mime_type) => mime_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase(),
+            None if self.filename.to_lowercase().ends_with(".ics") => "text/calendar".to_string(),
+            None => "application/octet-stream".to_string(),
+        }
+    }
+}
 
-    let jira_issue_id = JiraCreateIssueRequest::from_zammad_webhook(&webhook)
+/// Fetches the referenced ticket and article from the Zammad API and confirms that the
+/// webhook describes their actual state, so forged or stale payloads are not synced.
+async fn verify_webhook(webhook: &ZammadWebhook) -> anyhow::Result<()> {
+    let ticket = ZammadGetTicketRequest::new(webhook.ticket.id)
         .submit()
-        .await?
-        .id;
+        .await?;
+    if ticket.state != webhook.ticket.state.as_str() {
+        anyhow::bail!(
+            "ticket {} is in state {:?} but the webhook claims {:?}",
+            ticket.id,
+            ticket.state,
+            webhook.ticket.state.as_str()
+        );
+    }
+
+    if let Some(article_id) = webhook.article.id {
+        let article = ZammadGetArticleRequest::new(article_id).submit().await?;
+        if article.ticket_id != webhook.ticket.id as u64 {
+            anyhow::bail!(
+                "article {} belongs to ticket {}, not to ticket {}",
+                article.id,
+                article.ticket_id,
+                webhook.ticket.id
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Rejects the webhook if verification is enabled and the payload can't be confirmed.
+async fn is_verified(webhook: &ZammadWebhook) -> bool {
+    if !config::get_zammad().verify_webhooks {
+        return true;
+    }
+    match verify_webhook(webhook).await {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Rejecting unverifiable Zammad webhook: {}", e);
+            false
+        }
+    }
+}
+
+/// Creates the Jira issue, or customer request, for the ticket and returns its ID.
+pub async fn create_jira_issue(webhook: &ZammadWebhook) -> anyhow::Result<i32> {
+    let (id, key) = if config::get_jira().service_desk.is_some() {
+        let request = JiraCreateCustomerRequest::from_zammad_webhook(webhook)?
+            .submit()
+            .await?;
+        (request.issue_id, request.issue_key)
+    } else {
+        let issue = JiraCreateIssueRequest::from_zammad_webhook(webhook)?
+            .submit()
+            .await?;
+        (issue.id, issue.key)
+    };
+    logging::record_jira_issue(id, Some(&key));
+    Ok(id)
+}
+
+/// Returns the ID of the Jira issue linked to the ticket, which is only created if the ticket
+/// isn't linked yet, or `None` if the ticket isn't synced.
+pub(crate) async fn create_ticket(
+    db: &DB,
+    _id: String,
+    webhook: ZammadWebhook,
+) -> anyhow::Result<Option<i32>> {
+    if !filters::is_zammad_ticket_synced(&webhook.ticket) {
+        info!(
+            "Zammad ticket #{} doesn't match the sync rules, skipping it",
+            webhook.ticket.number
+        );
+        return Ok(None);
+    }
+
+    let _lock = locks::lock_ticket(db, webhook.ticket.id).await?;
+
+    // Retried or repeated triggers must not create a second issue
+    if let Some(jira_issue_id) = db.find_jira_id_by_zammad_id(&webhook.ticket.id).await? {
+        info!(
+            "Zammad ticket #{} is already linked to Jira issue {}, skipping it",
+            webhook.ticket.number, jira_issue_id
+        );
+        logging::record_jira_issue(jira_issue_id, None);
+        return Ok(Some(jira_issue_id));
+    }
+    db.create_assignment_from_zammad(&webhook.ticket.id).await?;
+
+    let jira_issue_id = create_jira_issue(&webhook).await?;
     db.add_jira_id_to_assignment(&jira_issue_id, &webhook.ticket.id)
         .await?;
+    record_description_article(db, &webhook).await?;
+    checklists::sync_to_jira(db, &webhook.ticket, jira_issue_id).await?;
+
+    // Suggestions are a convenience, so failing to post them doesn't fail the sync
+    if let Some(knowledge_base) = &config::get_zammad().knowledge_base
+        && let Err(e) =
+            suggest_knowledge_base_answers(knowledge_base, &webhook, jira_issue_id).await
+    {
+        warn!("Failed to suggest knowledge base answers: {}", e);
+    }
+    Ok(Some(jira_issue_id))
+}
+
+/// Posts links to the knowledge base answers matching the ticket title as an internal
+/// comment on the Jira issue.
+async fn suggest_knowledge_base_answers(
+    knowledge_base: &KnowledgeBaseConfig,
+    webhook: &ZammadWebhook,
+    jira_issue_id: i32,
+) -> anyhow::Result<()> {
+    let matches = ZammadSearchKnowledgeBaseRequest::new(
+        knowledge_base.id,
+        &knowledge_base.locale,
+        &webhook.ticket.title,
+    )
+    .submit()
+    .await?
+    .details;
+
+    let web_url = get_zammad_web_url();
+    let links: Vec<String> = matches
+        .iter()
+        .filter(|answer| answer.r#type.starts_with("KnowledgeBase::Answer"))
+        .take(knowledge_base.limit)
+        .map(|answer| format!("* [{}|{}{}]", answer.title, web_url, answer.url))
+        .collect();
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    JiraAddCommentRequest::internal(format!(
+        "Possibly related knowledge base articles:\n{}",
+        links.join("\n")
+    ))
+    .submit(&jira_issue_id)
+    .await?;
     Ok(())
 }
 
-#[tracing::instrument(skip(payload))]
+/// A ticket was created in Zammad; a Jira issue is created for it.
+#[utoipa::path(
+    post,
+    path = "/create-ticket/{id}",
+    params(
+        ("id" = String, Path, description = "Webhook ID of the Zammad system"),
+        ("X-Hub-Signature" = Option<String>, Header, description = "HMAC-SHA1 of the body, if `webhook_secret` is set"),
+    ),
+    request_body = ZammadWebhook,
+    responses(
+        (status = 202, description = "Queued to be synced, later if the target system is unavailable"),
+        (status = 401, description = "The signature doesn't match"),
+        (status = 403, description = "The ticket doesn't match Zammad's, with `verify_webhooks`"),
+        (status = 404, description = "Unknown webhook ID"),
+        (status = 422, description = "The payload lacks required fields"),
+        (status = 503, description = "The outbox is full; retry after `Retry-After`"),
+    )
+)]
+#[tracing::instrument(skip(state, payload))]
 async fn create_ticket_handler(
-    Path(id): Path<String>,
+    State(state): State<AppState>,
+    Path(_id): Path<String>,
     Json(payload): Json<ZammadWebhook>,
-) -> StatusCode {
-    match create_ticket(id, payload).await {
-        Ok(_) => StatusCode::OK,
-        Err(e) => {
-            error!("Failed to create ticket: {}", e);
-            StatusCode::BAD_REQUEST
-        }
+) -> Response {
+    if !is_verified(&payload).await {
+        return StatusCode::FORBIDDEN.into_response();
     }
+
+    let zammad_ticket_id = payload.ticket.id;
+    outbox::accept(
+        &state.db,
+        outbox::ZAMMAD_CREATE,
+        Some(zammad_ticket_id),
+        &payload,
+    )
+    .await
 }
 
-#[tracing::instrument(skip(payload))]
-async fn update_ticket_handler(Json(payload): Json<ZammadWebhook>) -> StatusCode {
-    match update_ticket(payload).await {
-        Ok(_) => StatusCode::OK,
-        Err(e) => {
-            error!("Failed to create ticket: {}", e);
-            StatusCode::BAD_REQUEST
-        }
+/// A Zammad ticket was updated, e.g. with a new article; it's synced to the linked issue.
+#[utoipa::path(
+    post,
+    path = "/update-ticket/{id}",
+    params(
+        ("id" = String, Path, description = "Webhook ID of the Zammad system"),
+        ("X-Hub-Signature" = Option<String>, Header, description = "HMAC-SHA1 of the body, if `webhook_secret` is set"),
+    ),
+    request_body = ZammadWebhook,
+    responses(
+        (status = 202, description = "Queued to be synced, later if the target system is unavailable"),
+        (status = 401, description = "The signature doesn't match"),
+        (status = 403, description = "The ticket doesn't match Zammad's, with `verify_webhooks`"),
+        (status = 404, description = "Unknown webhook ID"),
+        (status = 422, description = "The payload lacks required fields"),
+        (status = 503, description = "The outbox is full; retry after `Retry-After`"),
+    )
+)]
+#[tracing::instrument(skip(state, payload))]
+async fn update_ticket_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<ZammadWebhook>,
+) -> Response {
+    if !is_verified(&payload).await {
+        return StatusCode::FORBIDDEN.into_response();
     }
+
+    let zammad_ticket_id = payload.ticket.id;
+    outbox::accept(
+        &state.db,
+        outbox::ZAMMAD_UPDATE,
+        Some(zammad_ticket_id),
+        &payload,
+    )
+    .await
 }
 
-async fn update_ticket(payload: ZammadWebhook) -> anyhow::Result<()> {
-    let db = DB::new().await?;
+pub(crate) async fn update_ticket(db: &DB, payload: ZammadWebhook) -> anyhow::Result<()> {
+    let _lock = locks::lock_ticket(db, payload.ticket.id).await?;
     let jira_issue_id = db.get_jira_id_by_zammad_id(&payload.ticket.id).await?;
+    logging::record_jira_issue(jira_issue_id, None);
 
+    // Articles created from Jira comments must not be sent back to Jira
+    let already_synced = match payload.article.id {
+        Some(article_id) => db.is_zammad_article_synced(&article_id).await?,
+        None => false,
+    };
+
+    // The issue type may use its own directions
+    let meta = db.get_assignment_meta(&payload.ticket.id).await?;
+    let directions =
+        config::get().directions_for(meta.get(jira::ISSUE_TYPE_META).map(String::as_str));
+
+    // The article the description was created from stays the description
+    if payload.article.id.is_some()
+        && payload.article.id == db.get_description_article(&payload.ticket.id).await?
+    {
+        sync_description(db, &payload, jira_issue_id).await?;
+    }
     // We want to add a comment to the Jira issue if the article body is not empty
-    if payload.article.body.is_some() {
-        JiraAddCommentRequest::from_zammad_webhook(&payload)
+    else if payload.article.body.is_some() && !already_synced && directions.comments.to_jira() {
+        let notes = attachments::sync_to_jira(db, &payload, jira_issue_id).await?;
+        let comment = JiraAddCommentRequest::from_zammad_webhook(&payload, &meta)?
+            .with_notes(&notes)
             .submit(&jira_issue_id)
             .await?;
+        if let Some(article_id) = payload.article.id {
+            db.create_comment_mapping(&article_id, &comment.id).await?;
+        }
+    }
+
+    // The checklist isn't part of the webhook and is fetched as it is now
+    checklists::sync_to_jira(db, &payload.ticket, jira_issue_id).await?;
+
+    // Delayed deliveries must not revert newer changes; their article is synced above
+    if !db
+        .record_source_update(&payload.ticket.id, "zammad", payload.ticket.updated_at)
+        .await?
+    {
+        info!(
+            "Zammad ticket #{} was updated since this event, skipping its field changes",
+            payload.ticket.number
+        );
+        return Ok(());
     }
 
+    // Fields synced both ways are only sent if they changed, resolving conflicting changes
+    let change = |value| FieldChange {
+        zammad_ticket_id: payload.ticket.id,
+        jira_issue_id,
+        side: Side::Zammad,
+        value,
+        changed_at: payload.ticket.updated_at,
+    };
+    let priority = change(FieldValue::Priority(payload.ticket.priority.id));
+    let sync_priority = directions.priority.to_jira() && priority.should_apply(db).await?;
+
     // We want to update the Jira issue with the new values from the Zammad ticket
-    JiraUpdateIssueRequest::from_zammad_webhook(&payload)
-        .submit(&jira_issue_id)
+    let mut request = JiraUpdateIssueRequest::from_zammad_webhook(&payload);
+    if !sync_priority {
+        request = request.without_priority();
+    }
+    // Skip the update if it's the same as last time, e.g. for a new article
+    let hash = content_hash(&request)?;
+    if db.get_sync_hash(&payload.ticket.id, "jira").await?.as_ref() == Some(&hash) {
+        info!(
+            "Jira issue {} is up to date, skipping the update",
+            jira_issue_id
+        );
+    } else {
+        request.submit(&jira_issue_id).await?;
+        db.set_sync_hash(&payload.ticket.id, "jira", &hash).await?;
+        // Zammad changed since its last update from Jira, which has to be sent again
+        db.delete_sync_hash(&payload.ticket.id, "zammad").await?;
+    }
+    if sync_priority {
+        priority.record(db).await?;
+    }
+
+    // Jira doesn't allow setting the status directly, it has to be moved via transitions
+    let status = change(FieldValue::Status(payload.ticket.state));
+    if let Some(statuses) = &config::get_jira().statuses
+        && directions.status.to_jira()
+        && status.should_apply(db).await?
+    {
+        let path = match payload.ticket.state {
+            ZammadState::Open => &statuses.open,
+            ZammadState::Closed => &statuses.closed,
+        };
+        jira::transition_issue(jira_issue_id, path).await?;
+        status.record(db).await?;
+    }
+
+    Ok(())
+}
+
+/// Target of the sync hash of the article backing the Jira description.
+const DESCRIPTION_HASH: &str = "jira.description";
+
+/// Records the webhook's article as the one the Jira description was created from, so its
+/// edits update the description instead of being posted as comment.
+pub(crate) async fn record_description_article(
+    db: &DB,
+    webhook: &ZammadWebhook,
+) -> anyhow::Result<()> {
+    if let Some(article_id) = webhook.article.id {
+        db.set_description_article(&webhook.ticket.id, &article_id)
+            .await?;
+        db.set_sync_hash(
+            &webhook.ticket.id,
+            DESCRIPTION_HASH,
+            &content_hash(&webhook.article.body)?,
+        )
         .await?;
+    }
+    Ok(())
+}
+
+/// Updates the part of the Jira description synced from Zammad if the article backing it
+/// was edited.
+async fn sync_description(
+    db: &DB,
+    payload: &ZammadWebhook,
+    jira_issue_id: i32,
+) -> anyhow::Result<()> {
+    let hash = content_hash(&payload.article.body)?;
+    if db
+        .get_sync_hash(&payload.ticket.id, DESCRIPTION_HASH)
+        .await?
+        .as_ref()
+        == Some(&hash)
+    {
+        return Ok(());
+    }
 
+    let issue = JiraGetIssueRequest::new(jira_issue_id).submit().await?;
+    let description = replace_synced_description(
+        issue.fields.description.as_deref().unwrap_or_default(),
+        &zammad_description(payload)?,
+    );
+    JiraUpdateIssueRequest::field("description", Value::String(description))
+        .submit(&jira_issue_id)
+        .await?;
+    db.set_sync_hash(&payload.ticket.id, DESCRIPTION_HASH, &hash)
+        .await?;
+    info!(
+        "Updated the description of Jira issue {} from the edited Zammad article",
+        jira_issue_id
+    );
     Ok(())
 }
 
-pub fn router() -> Router {
-    // Using specific Router<()> type to ensure we don't need state
-    let router: Router<()> = Router::new()
+/// The webhooks Zammad sends, under /ticket-sync/zammad.
+#[derive(OpenApi)]
+#[openapi(paths(create_ticket_handler, update_ticket_handler))]
+pub struct ZammadWebhookApi;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
         .route("/create-ticket/:id", post(create_ticket_handler))
-        .route("/update-ticket/:id", post(update_ticket_handler));
-    router
+        .route("/update-ticket/:id", post(update_ticket_handler))
 }
@@ -0,0 +1,87 @@
+//! The OpenAPI document of the webhook, admin and lookup endpoints, with the payloads Zammad
+//! and Jira are expected to send, served at /docs/openapi.json with Swagger UI at /docs.
+//! The admin and lookup endpoints are documented even if they aren't enabled.
+
+use axum::Router;
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::openapi::{OpenApi as OpenApiDocument, PathItem, SecurityRequirement};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::models::{jira::JiraWebhookApi, zammad::ZammadWebhookApi};
+use crate::state::AppState;
+use crate::{admin::AdminApi, lookup::LookupApi};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Ticket System Sync",
+        description = "Syncs Zammad tickets with Jira issues."
+    ),
+    nest(
+        (path = "/ticket-sync/zammad", api = ZammadWebhookApi, tags = ["webhooks"]),
+        (path = "/ticket-sync/jira", api = JiraWebhookApi, tags = ["webhooks"]),
+        (path = "/admin", api = AdminApi, tags = ["admin"]),
+        (path = "/api", api = LookupApi, tags = ["lookup"]),
+    ),
+    modifiers(&BearerTokens),
+    tags(
+        (name = "webhooks", description = "Sent by Zammad and Jira"),
+        (name = "admin", description = "Enabled with `admin`; needs its token"),
+        (name = "lookup", description = "Enabled with `lookup`; needs its token"),
+    )
+)]
+struct ApiDoc;
+
+/// The admin and lookup endpoints need their own bearer token each.
+struct BearerTokens;
+
+impl Modify for BearerTokens {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        for scheme in ["admin_token", "lookup_token"] {
+            components.add_security_scheme(
+                scheme,
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            let scheme = match path.split('/').nth(1) {
+                Some("admin") => "admin_token",
+                Some("api") => "lookup_token",
+                _ => continue,
+            };
+            for operation in operations(item) {
+                operation.security =
+                    Some(vec![SecurityRequirement::new(scheme, Vec::<String>::new())]);
+            }
+        }
+    }
+}
+
+fn operations(item: &mut PathItem) -> impl Iterator<Item = &mut Operation> {
+    [
+        &mut item.get,
+        &mut item.put,
+        &mut item.post,
+        &mut item.delete,
+        &mut item.patch,
+    ]
+    .into_iter()
+    .flatten()
+}
+
+fn document() -> OpenApiDocument {
+    let mut document = ApiDoc::openapi();
+    // Taken from the manifest, which doesn't name one
+    document.info.license = None;
+    document
+}
+
+/// Swagger UI at /docs, reading the document from /docs/openapi.json.
+pub fn router() -> Router<AppState> {
+    SwaggerUi::new("/docs")
+        .url("/docs/openapi.json", document())
+        .into()
+}
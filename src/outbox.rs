@@ -0,0 +1,439 @@
+//! Webhooks are stored in the outbox and answered right away; a pool of workers does the
+//! Jira and Zammad calls, so slow APIs don't keep the sender waiting. If too many webhooks
+//! are queued, new ones are rejected until the workers caught up. Jobs whose target system
+//! is rate limited, failing or in maintenance stay queued until it can be called again.
+
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail};
+use axum::response::{IntoResponse, Response};
+use chrono::{TimeDelta, Utc};
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{Instrument, error, info, warn};
+
+use crate::config::{self, OutboxConfig};
+use crate::models::{
+    db::{DB, OutboxJob},
+    jira, zammad,
+};
+use crate::{
+    audit, dead_letters, decisions, logging, pause, profiles, request_id, restrictions, retry,
+    shutdown,
+};
+
+/// Operation of Zammad create webhooks, which create the Jira issue.
+pub const ZAMMAD_CREATE: &str = "zammad.create";
+/// Operation of Zammad update webhooks, which are debounced.
+pub const ZAMMAD_UPDATE: &str = "zammad.update";
+
+/// Wakes a worker when a job was added or a ticket's job finished.
+static ENQUEUED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+static CLAIMED: LazyLock<Mutex<Claims>> = LazyLock::new(Default::default);
+
+/// The jobs being synced, and their tickets, which no other worker may sync meanwhile.
+#[derive(Default)]
+struct Claims {
+    jobs: HashSet<i64>,
+    tickets: HashSet<i32>,
+}
+
+/// Wakes the idle workers, e.g. after the configuration was reloaded.
+pub fn wake_workers() {
+    ENQUEUED.notify_waiters();
+}
+
+/// Stores a webhook to be synced by the workers, for the profile and request in scope. Returns `false`
+/// if the queue is full.
+pub async fn enqueue(
+    db: &DB,
+    operation: &str,
+    zammad_ticket_id: Option<i32>,
+    payload: &impl Serialize,
+) -> anyhow::Result<bool> {
+    if db.count_outbox_jobs().await? >= config::get().outbox.queue_size {
+        return Ok(false);
+    }
+    let profile = profiles::current();
+    db.create_outbox_job(
+        operation,
+        zammad_ticket_id,
+        profile.as_ref().map(|profile| profile.name.as_str()),
+        request_id::current().as_deref(),
+        &serde_json::to_value(payload)?,
+    )
+    .await?;
+    ENQUEUED.notify_one();
+    Ok(true)
+}
+
+/// Queues a webhook and answers it: 202 once stored, or 503 with Retry-After if the queue is
+/// full.
+pub async fn accept(
+    db: &DB,
+    operation: &str,
+    zammad_ticket_id: Option<i32>,
+    payload: &impl Serialize,
+) -> Response {
+    match enqueue(db, operation, zammad_ticket_id, payload).await {
+        Ok(true) => StatusCode::ACCEPTED.into_response(),
+        Ok(false) => {
+            warn!("The outbox is full, rejecting the {} webhook", operation);
+            retry::deferred(Duration::from_secs(config::get().outbox.retry_after))
+        }
+        Err(e) => {
+            error!("Failed to queue the webhook: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Starts the workers, which sync the jobs until the shutdown starts. Jobs left over from a
+/// previous run are synced first.
+pub fn spawn_workers(db: DB, config: &OutboxConfig) -> Vec<JoinHandle<()>> {
+    (0..config.workers.max(1))
+        .map(|_| tokio::spawn(work(db.clone())))
+        .collect()
+}
+
+/// Waits for the workers to finish the jobs they started. Jobs still being synced after
+/// `timeout` are only removed once synced, so they are synced again after the next start.
+pub async fn drain(workers: Vec<JoinHandle<()>>, timeout: Duration) {
+    let finished = tokio::time::timeout(timeout, async {
+        for worker in workers {
+            let _ = worker.await;
+        }
+    })
+    .await;
+    if finished.is_err() {
+        warn!(
+            "The outbox workers didn't finish within {}s, their jobs are synced again after the next start",
+            timeout.as_secs()
+        );
+    }
+}
+
+async fn work(db: DB) {
+    // Jobs not claimed yet stay in the outbox
+    while !shutdown::is_stopping() {
+        match claim(&db).await {
+            Ok(Some((job, coalesced))) => {
+                match process(&db, &job).await {
+                    // The coalesced jobs are merged into it again once it's due
+                    Some(delay) => defer(&db, &job, delay).await,
+                    None => {
+                        for id in std::iter::once(job.id).chain(coalesced.iter().copied()) {
+                            if let Err(e) = db.delete_outbox_job(id).await {
+                                error!("Failed to remove outbox job {}: {}", id, e);
+                                tokio::time::sleep(Duration::from_secs(5)).await;
+                            }
+                        }
+                    }
+                }
+                release(&coalesced, None);
+                release(&[job.id], job.zammad_id);
+            }
+            // Debounced and deferred jobs become due without anything being queued
+            Ok(None) => {
+                let debounce = config::get().outbox.debounce;
+                let deferred = match db.get_next_outbox_deferral().await {
+                    Ok(not_before) => not_before
+                        .map(|not_before| (not_before - Utc::now()).to_std().unwrap_or_default()),
+                    Err(e) => {
+                        error!("Failed to read the outbox: {}", e);
+                        Some(Duration::from_secs(5))
+                    }
+                };
+                tokio::select! {
+                    _ = ENQUEUED.notified() => {}
+                    _ = tokio::time::sleep(Duration::from_millis(debounce)), if debounce > 0 => {}
+                    _ = tokio::time::sleep(deferred.unwrap_or_default()), if deferred.is_some() => {}
+                    _ = pause::changed() => {}
+                    _ = shutdown::stopping() => {}
+                }
+            }
+            Err(e) => {
+                error!("Failed to read the outbox: {}", e);
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(5)) => {}
+                    _ = shutdown::stopping() => {}
+                }
+            }
+        }
+    }
+}
+
+/// The oldest job that isn't being synced and whose ticket has no job being synced, so
+/// webhooks of a ticket are synced in the order they arrived. Zammad updates wait for the
+/// debounce window and are coalesced with the updates of the ticket that arrived meanwhile;
+/// their IDs are returned with the job. Jobs of paused directions wait until resumed,
+/// deferred jobs until they are due.
+async fn claim(db: &DB) -> anyhow::Result<Option<(OutboxJob, Vec<i64>)>> {
+    let debounce = config::get().outbox.debounce;
+    let now = Utc::now();
+    let due_before = now - TimeDelta::milliseconds(debounce as i64);
+    let queue = db.get_outbox_queue().await?;
+    let (id, zammad_id, followers) = {
+        let mut claimed = CLAIMED.lock().expect("outbox lock poisoned");
+        // Tickets whose oldest job waits for the debounce window, a paused direction or its
+        // target system
+        let mut waiting = HashSet::new();
+        let next = queue.iter().find(|job| {
+            if claimed.jobs.contains(&job.id)
+                || job.zammad_id.is_some_and(|zammad_id| {
+                    claimed.tickets.contains(&zammad_id) || waiting.contains(&zammad_id)
+                })
+            {
+                return false;
+            }
+            if pause::is_operation_paused(&job.operation)
+                || job.not_before.is_some_and(|not_before| not_before > now)
+            {
+                waiting.extend(job.zammad_id);
+                return false;
+            }
+            if job.operation == ZAMMAD_UPDATE && job.received_at > due_before {
+                waiting.extend(job.zammad_id);
+                return false;
+            }
+            true
+        });
+        let Some(next) = next else {
+            return Ok(None);
+        };
+
+        // The ticket's further updates, up to its next other webhook
+        let followers: Vec<i64> = match next.zammad_id {
+            Some(zammad_id) if debounce > 0 && next.operation == ZAMMAD_UPDATE => queue
+                .iter()
+                .filter(|job| job.id > next.id && job.zammad_id == Some(zammad_id))
+                .take_while(|job| job.operation == ZAMMAD_UPDATE)
+                .map(|job| job.id)
+                .collect(),
+            _ => Vec::new(),
+        };
+        claimed.jobs.insert(next.id);
+        claimed.jobs.extend(&followers);
+        claimed.tickets.extend(next.zammad_id);
+        (next.id, next.zammad_id, followers)
+    };
+
+    match load(db, id, &followers).await {
+        Ok(Some((job, coalesced))) => {
+            let skipped: Vec<i64> = followers
+                .into_iter()
+                .filter(|id| !coalesced.contains(id))
+                .collect();
+            release(&skipped, None);
+            Ok(Some((job, coalesced)))
+        }
+        result => {
+            release(&followers, None);
+            release(&[id], zammad_id);
+            result
+        }
+    }
+}
+
+/// Loads the job and merges the followers into it until one can't be merged.
+async fn load(
+    db: &DB,
+    id: i64,
+    followers: &[i64],
+) -> anyhow::Result<Option<(OutboxJob, Vec<i64>)>> {
+    // Removed or deferred since the queue was read
+    let Some(mut job) = db.get_outbox_job(id).await?.filter(|job| {
+        job.not_before
+            .is_none_or(|not_before| not_before <= Utc::now())
+    }) else {
+        return Ok(None);
+    };
+    let mut coalesced = Vec::new();
+    for follower in followers {
+        let Some(follower) = db.get_outbox_job(*follower).await? else {
+            break;
+        };
+        let Some(payload) = coalesce(&job.payload, &follower.payload) else {
+            break;
+        };
+        job.payload = payload;
+        coalesced.push(follower.id);
+    }
+    if !coalesced.is_empty() {
+        info!(
+            "Coalesced {} further updates of Zammad ticket {:?} into outbox job {}",
+            coalesced.len(),
+            job.zammad_id,
+            job.id
+        );
+    }
+    Ok(Some((job, coalesced)))
+}
+
+/// Merges a later Zammad update into `payload`: the later ticket state wins, the article is
+/// kept. Returns `None` if both carry a different article, which are synced separately.
+fn coalesce(payload: &Value, later: &Value) -> Option<Value> {
+    let has_article = |payload: &Value| !payload["article"]["body"].is_null();
+    if has_article(payload)
+        && has_article(later)
+        && payload["article"]["id"] != later["article"]["id"]
+    {
+        return None;
+    }
+    let mut merged = later.clone();
+    if !has_article(later) {
+        merged["article"] = payload["article"].clone();
+    }
+    Some(merged)
+}
+
+fn release(ids: &[i64], zammad_id: Option<i32>) {
+    let mut claimed = CLAIMED.lock().expect("outbox lock poisoned");
+    for id in ids {
+        claimed.jobs.remove(id);
+    }
+    if let Some(zammad_id) = zammad_id {
+        claimed.tickets.remove(&zammad_id);
+        // Another job of the ticket may be waiting for it
+        ENQUEUED.notify_one();
+    }
+}
+
+/// Keeps the job queued until `delay` passed.
+async fn defer(db: &DB, job: &OutboxJob, delay: Duration) {
+    let not_before = Utc::now() + delay;
+    info!(
+        "Deferring outbox job {} ({}) until {}",
+        job.id, job.operation, not_before
+    );
+    if let Err(e) = db.defer_outbox_job(job.id, not_before).await {
+        error!("Failed to defer outbox job {}: {}", job.id, e);
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+/// Syncs a job with the profile it arrived for. Failed jobs are kept as dead letters, with
+/// the decisions that led there; jobs of restricted tickets are kept without trying. Returns
+/// how long to defer the job instead if its target system can't be called.
+async fn process(db: &DB, job: &OutboxJob) -> Option<Duration> {
+    let span = logging::sync_span(&job.operation, job.zammad_id, &job.payload);
+    span.record("job", job.id);
+    if let Some(request_id) = &job.request_id {
+        span.record("request_id", request_id);
+    }
+    request_id::scope(job.request_id.clone(), process_with_profile(db, job))
+        .instrument(span)
+        .await
+}
+
+async fn process_with_profile(db: &DB, job: &OutboxJob) -> Option<Duration> {
+    let profile = match job
+        .profile
+        .as_deref()
+        .map(|name| (name, profiles::get(name)))
+    {
+        Some((name, None)) => {
+            let e = anyhow!("unknown profile {}", name);
+            error!("Failed to sync outbox job {}: {}", job.id, e);
+            dead_letters::store(db, job, &e).await;
+            return None;
+        }
+        Some((_, profile)) => profile,
+        None => None,
+    };
+    profiles::scope(profile, process_in_scope(db, job)).await
+}
+
+async fn process_in_scope(db: &DB, job: &OutboxJob) -> Option<Duration> {
+    if job.operation.starts_with("zammad.")
+        && let Some(zammad_id) = job.zammad_id
+        && matches!(db.is_assignment_restricted(zammad_id).await, Ok(true))
+    {
+        warn!(
+            "Zammad ticket {} is restricted in Jira, keeping outbox job {} as dead letter",
+            zammad_id, job.id
+        );
+        let e = anyhow!("Zammad ticket {} is restricted in Jira", zammad_id);
+        dead_letters::store(db, job, &e).await;
+        return None;
+    }
+    if let Some(delay) = target_unavailable_for(&job.operation) {
+        return Some(delay);
+    }
+
+    let started_at = Instant::now();
+    let (result, decisions) = audit::record(
+        db,
+        &job.operation,
+        job.zammad_id,
+        &job.payload,
+        sync(db, &job.operation, job.payload.clone()),
+    )
+    .await;
+    let duration_ms = started_at.elapsed().as_millis() as u64;
+    match result {
+        Ok(()) => info!(
+            duration_ms,
+            "Synced outbox job {} ({})", job.id, job.operation
+        ),
+        // Failed because the system started rate limiting, failing or its maintenance
+        Err(e)
+            if let Some(delay) =
+                target_unavailable_for(&job.operation).or_else(|| retry::unsent_for(&e)) =>
+        {
+            warn!(
+                duration_ms,
+                "Failed to sync outbox job {} ({}), keeping it queued: {}",
+                job.id,
+                job.operation,
+                e
+            );
+            return Some(delay);
+        }
+        Err(e) => {
+            error!(
+                duration_ms,
+                "Failed to sync outbox job {} ({}): {}", job.id, job.operation, e
+            );
+            decisions::store_failure(db, &job.operation, job.zammad_id, &e, &decisions).await;
+            dead_letters::store(db, job, &e).await;
+            if let Some(zammad_id) = job.zammad_id
+                && restrictions::is_permission_error(&e)
+            {
+                restrictions::restrict(db, zammad_id, &e).await;
+            }
+        }
+    }
+    None
+}
+
+/// How long the system a job is synced to can't be called: while it's rate limited, its
+/// circuit breaker is open or it's in an announced maintenance window.
+fn target_unavailable_for(operation: &str) -> Option<Duration> {
+    // Zammad webhooks are synced to Jira and the other way around
+    let endpoint = if operation.starts_with("zammad.") {
+        &config::get_jira().endpoint
+    } else {
+        &config::get_zammad().endpoint
+    };
+    retry::unavailable_for(endpoint)
+}
+
+/// Syncs a stored webhook of `operation`.
+pub async fn sync(db: &DB, operation: &str, payload: Value) -> anyhow::Result<()> {
+    match operation {
+        ZAMMAD_CREATE => {
+            zammad::create_ticket(db, String::new(), serde_json::from_value(payload)?).await?;
+        }
+        ZAMMAD_UPDATE => zammad::update_ticket(db, serde_json::from_value(payload)?).await?,
+        "jira.update" => jira::update_ticket(db, serde_json::from_value(payload)?).await?,
+        operation => bail!("unknown operation {}", operation),
+    }
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::{Value, json};
+
+/// Exit code for failed commands, e.g. unreachable APIs or failed checks.
+pub const EXIT_FAILURE: u8 = 1;
+// 2 is used by clap for invalid arguments
+/// Exit code for a missing or invalid configuration.
+pub const EXIT_CONFIG: u8 = 3;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Lesbare Ausgabe mit Fortschrittsbalken
+    #[default]
+    Text,
+    /// Ein JSON-Objekt pro Zeile, für Skripte
+    Json,
+}
+
+/// Reports the progress of a long-running command: a progress bar for interactive use, or
+/// one JSON record per line on stdout.
+pub struct Progress {
+    command: &'static str,
+    format: OutputFormat,
+    bar: ProgressBar,
+}
+
+impl Progress {
+    pub fn new(command: &'static str, format: OutputFormat) -> Self {
+        let bar = match format {
+            // Drawn on stderr and hidden if that isn't a terminal
+            OutputFormat::Text => ProgressBar::new(0).with_style(
+                ProgressStyle::with_template("{bar:40} {pos}/{len} ({eta}) {msg}")
+                    .expect("valid progress template"),
+            ),
+            OutputFormat::Json => ProgressBar::hidden(),
+        };
+        Self {
+            command,
+            format,
+            bar,
+        }
+    }
+
+    /// `total` is `None` if it isn't known up front.
+    pub fn update(&self, position: u64, total: Option<u64>) {
+        if let Some(total) = total {
+            self.bar.set_length(total);
+        }
+        self.bar.set_position(position);
+        if self.format == OutputFormat::Json {
+            record(json!({
+                "type": "progress",
+                "command": self.command,
+                "position": position,
+                "total": total,
+            }));
+        }
+    }
+
+    /// Reports a single step with its outcome, e.g. of a check.
+    pub fn step(&self, name: &str, passed: bool, duration_ms: u128, error: Option<String>) {
+        match self.format {
+            OutputFormat::Text => {
+                self.bar.suspend(|| {
+                    println!(
+                        "{} {:<30} {:>6.1}s",
+                        if passed { "PASS" } else { "FAIL" },
+                        name,
+                        duration_ms as f64 / 1000.0
+                    );
+                    if let Some(error) = &error {
+                        println!("     {}", error);
+                    }
+                });
+            }
+            OutputFormat::Json => record(json!({
+                "type": "step",
+                "command": self.command,
+                "name": name,
+                "status": if passed { "pass" } else { "fail" },
+                "duration_ms": duration_ms,
+                "error": error,
+            })),
+        }
+    }
+
+    /// Reports the result of a successful run: `summary` for humans, `result` for scripts.
+    pub fn finish(&self, summary: &str, result: Value) {
+        self.bar.finish_and_clear();
+        match self.format {
+            OutputFormat::Text => println!("{}", summary),
+            OutputFormat::Json => record(json!({
+                "type": "result",
+                "command": self.command,
+                "status": "ok",
+                "result": result,
+            })),
+        }
+    }
+}
+
+/// Reports an error that ended the command.
+pub fn report_error(format: OutputFormat, error: &anyhow::Error) {
+    match format {
+        OutputFormat::Text => eprintln!("Error: {:#}", error),
+        OutputFormat::Json => record(json!({
+            "type": "error",
+            "status": "error",
+            "message": format!("{:#}", error),
+        })),
+    }
+}
+
+fn record(value: Value) {
+    println!("{}", value);
+}
@@ -0,0 +1,157 @@
+//! Syncing can be paused, e.g. during a Jira upgrade: webhooks are still stored in the outbox
+//! and acknowledged, but the workers leave them there until syncing is resumed and then
+//! sync them in the order they arrived. The pause is stored, so it outlasts restarts.
+//!
+//! A single direction can be paused as well, through the admin API or `paused` in the
+//! configuration, e.g. during a Jira project migration while Jira → Zammad keeps flowing.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::sync::LazyLock;
+
+use chrono::Utc;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::{self, Direction};
+use crate::models::db::{DB, SyncPause};
+
+#[derive(Debug, Clone, Default)]
+struct Pauses {
+    /// Everything is paused
+    all: Option<SyncPause>,
+    directions: BTreeMap<Direction, SyncPause>,
+}
+
+static PAUSES: LazyLock<watch::Sender<Pauses>> =
+    LazyLock::new(|| watch::channel(Pauses::default()).0);
+
+/// Restores the pauses from before the last restart.
+pub async fn load(db: &DB) -> anyhow::Result<()> {
+    let mut pauses = Pauses {
+        all: db.get_sync_pause().await?,
+        directions: BTreeMap::new(),
+    };
+    for (direction, pause) in db.get_paused_directions().await? {
+        match Direction::ALL
+            .into_iter()
+            .find(|known| known.as_str() == direction)
+        {
+            Some(direction) => {
+                pauses.directions.insert(direction, pause);
+            }
+            None => warn!("Ignoring the pause of unknown direction {}", direction),
+        }
+    }
+    if let Some(pause) = &pauses.all {
+        info!("Syncing is paused since {}", pause.paused_at);
+    }
+    for (direction, pause) in &pauses.directions {
+        info!(
+            "Syncing {} is paused since {}",
+            direction.as_str(),
+            pause.paused_at
+        );
+    }
+    PAUSES.send_replace(pauses);
+    Ok(())
+}
+
+/// The pause of everything, if syncing is paused.
+pub fn current() -> Option<SyncPause> {
+    PAUSES.borrow().all.clone()
+}
+
+/// The pause of `direction` alone, if it was paused through the admin API.
+pub fn of_direction(direction: Direction) -> Option<SyncPause> {
+    PAUSES.borrow().directions.get(&direction).cloned()
+}
+
+/// Whether `direction` isn't synced, by a pause or the configuration.
+pub fn is_paused(direction: Direction) -> bool {
+    let pauses = PAUSES.borrow();
+    pauses.all.is_some()
+        || pauses.directions.contains_key(&direction)
+        || config::get().paused.contains(&direction)
+}
+
+/// Whether jobs of the outbox `operation` aren't synced.
+pub fn is_operation_paused(operation: &str) -> bool {
+    Direction::of_operation(operation).is_some_and(is_paused)
+}
+
+/// Resolves once something was paused or resumed.
+pub async fn changed() {
+    let mut pauses = PAUSES.subscribe();
+    // The sender lives in a static, so it's never dropped
+    let _ = pauses.changed().await;
+}
+
+/// Pauses syncing in `direction`, or everything; an existing pause is kept as it is.
+pub async fn pause(
+    db: &DB,
+    direction: Option<Direction>,
+    reason: Option<String>,
+) -> anyhow::Result<()> {
+    let pauses = PAUSES.borrow().clone();
+    let existing = match direction {
+        Some(direction) => pauses.directions.get(&direction),
+        None => pauses.all.as_ref(),
+    };
+    if existing.is_some() {
+        return Ok(());
+    }
+
+    let pause = SyncPause {
+        reason,
+        paused_at: Utc::now(),
+    };
+    match direction {
+        Some(direction) => db.set_paused_direction(direction.as_str(), &pause).await?,
+        None => db.set_sync_pause(&pause).await?,
+    }
+    info!(
+        "Syncing {} paused: {}",
+        direction.map_or("everything", Direction::as_str),
+        pause.reason.as_deref().unwrap_or("no reason given")
+    );
+    PAUSES.send_modify(|pauses| match direction {
+        Some(direction) => {
+            pauses.directions.insert(direction, pause);
+        }
+        None => pauses.all = Some(pause),
+    });
+    Ok(())
+}
+
+/// Resumes syncing in `direction`, or everything; the workers start with the webhooks queued
+/// meanwhile. Resuming one direction of a pause of everything keeps the other one paused.
+/// Directions paused in the configuration stay paused.
+pub async fn resume(db: &DB, direction: Option<Direction>) -> anyhow::Result<()> {
+    let mut pauses = PAUSES.borrow().clone();
+    match direction {
+        None => {
+            db.delete_sync_pause().await?;
+            db.delete_paused_directions().await?;
+            pauses = Pauses::default();
+        }
+        Some(direction) => {
+            if let Some(all) = pauses.all.take() {
+                let other = direction.reverse();
+                if let Entry::Vacant(entry) = pauses.directions.entry(other) {
+                    db.set_paused_direction(other.as_str(), &all).await?;
+                    entry.insert(all);
+                }
+                db.delete_sync_pause().await?;
+            }
+            db.delete_paused_direction(direction.as_str()).await?;
+            pauses.directions.remove(&direction);
+        }
+    }
+    info!(
+        "Syncing {} resumed",
+        direction.map_or("everything", Direction::as_str)
+    );
+    PAUSES.send_replace(pauses);
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Sync profiles connect further Jira and Zammad systems without restarting: the admin API
+//! stores them in the database and activates them right away. Webhooks carrying a profile's
+//! IDs are synced with its systems, which the configuration accessors return while the
+//! profile is in scope.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, LazyLock, OnceLock, RwLock};
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use subtle::ConstantTimeEq;
+use tracing::{error, info};
+
+use crate::config::{self, ProfileConfig};
+use crate::models::{api_request, db::DB};
+use crate::redact;
+
+/// An active profile. Replaced and deleted profiles are freed once the syncs started before,
+/// which may still use them, are done.
+#[derive(Debug)]
+pub struct Profile {
+    pub name: String,
+    pub config: ProfileConfig,
+    /// Clients sending the profile's headers, built on first use
+    pub jira_client: OnceLock<Client>,
+    pub zammad_client: OnceLock<Client>,
+}
+
+static PROFILES: LazyLock<RwLock<HashMap<String, Arc<Profile>>>> = LazyLock::new(Default::default);
+
+tokio::task_local! {
+    static CURRENT: Arc<Profile>;
+}
+
+/// The profile being synced, if not the systems of the file.
+pub fn current() -> Option<Arc<Profile>> {
+    CURRENT.try_with(Arc::clone).ok()
+}
+
+pub fn get(name: &str) -> Option<Arc<Profile>> {
+    PROFILES
+        .read()
+        .expect("profiles lock poisoned")
+        .get(name)
+        .cloned()
+}
+
+/// Names of the active profiles, sorted.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = PROFILES
+        .read()
+        .expect("profiles lock poisoned")
+        .keys()
+        .cloned()
+        .collect();
+    names.sort();
+    names
+}
+
+/// The profile whose webhook ID for `system` ("zammad" or "jira") is `id`.
+pub fn find(system: &str, id: &str) -> Option<Arc<Profile>> {
+    let profiles = PROFILES.read().expect("profiles lock poisoned");
+    // Every profile is compared, in constant time, as the IDs are secrets of their own
+    profiles.values().fold(None, |found, profile| {
+        let expected = match system {
+            "zammad" => &profile.config.zammad_id,
+            _ => &profile.config.jira_id,
+        };
+        let matches = bool::from(id.as_bytes().ct_eq(expected.as_bytes()));
+        if matches {
+            Some(Arc::clone(profile))
+        } else {
+            found
+        }
+    })
+}
+
+/// Runs `future` with the profile in scope, or with the systems of the file for `None`.
+pub async fn scope<T>(profile: Option<Arc<Profile>>, future: impl Future<Output = T>) -> T {
+    match profile {
+        Some(profile) => CURRENT.scope(profile, future).await,
+        None => future.await,
+    }
+}
+
+/// Runs `future` with the named profile in scope; fails if the profile was deleted since.
+pub async fn scope_named<T>(
+    name: Option<&str>,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let profile = match name {
+        Some(name) => Some(get(name).with_context(|| format!("unknown profile {}", name))?),
+        None => None,
+    };
+    scope(profile, future).await
+}
+
+/// Checks that both systems of the profile accept its credentials.
+pub async fn check(profile: &Arc<Profile>) -> Result<()> {
+    scope(Some(Arc::clone(profile)), async {
+        api_request::check_jira_access().await.context("Jira")?;
+        api_request::check_zammad_access().await.context("Zammad")
+    })
+    .await
+}
+
+/// Parses the profile; it's activated with `activate` once checked.
+pub async fn prepare(name: &str, config: &str) -> Result<Arc<Profile>> {
+    let profile = Profile {
+        name: name.to_string(),
+        config: ProfileConfig::parse(config).await?,
+        jira_client: OnceLock::new(),
+        zammad_client: OnceLock::new(),
+    };
+    Ok(Arc::new(profile))
+}
+
+pub fn activate(profile: Arc<Profile>) {
+    redact::add_secrets(config::system_secrets(
+        &profile.config.jira,
+        &profile.config.zammad,
+    ));
+    info!("Activated profile {}", profile.name);
+    PROFILES
+        .write()
+        .expect("profiles lock poisoned")
+        .insert(profile.name.clone(), profile);
+}
+
+pub fn deactivate(name: &str) {
+    PROFILES
+        .write()
+        .expect("profiles lock poisoned")
+        .remove(name);
+    info!("Deactivated profile {}", name);
+}
+
+/// Activates the stored profiles. Profiles that don't parse anymore, e.g. because an
+/// environment variable is missing, are skipped, so they don't keep the server from starting.
+pub async fn load(db: &DB) -> Result<()> {
+    for stored in db.get_profiles().await? {
+        match prepare(&stored.name, &stored.config).await {
+            Ok(profile) => activate(profile),
+            Err(e) => error!("Failed to load profile {}: {:#}", stored.name, e),
+        }
+    }
+    Ok(())
+}
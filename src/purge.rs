@@ -0,0 +1,123 @@
+//! Deletion requests: everything stored about a ticket is removed, i.e. its link to the Jira
+//! issue, the comment mappings, archived payloads, audit entries, failures and queued
+//! webhooks. Purging a ticket that still exists unlinks it, so its next change creates a new
+//! issue; purge it once it was deleted in Zammad.
+//!
+//! For a customer's email address, the user mapping and every stored payload or failure
+//! mentioning it are removed; links of tickets are kept, as other people may still work on
+//! them.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use clap::Args;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::locks;
+use crate::models::{
+    api_request::{JiraGetCommentsRequest, ZammadGetTicketArticlesRequest},
+    db::DB,
+};
+use crate::output::{OutputFormat, Progress};
+
+#[derive(Args, Debug)]
+pub struct PurgeArgs {
+    /// Alle Daten zu diesem Zammad-Ticket und seinem Jira-Issue löschen
+    #[arg(long, value_name = "ZAMMAD_ID", required_unless_present = "email")]
+    ticket: Option<i32>,
+
+    /// Benutzerzuordnung und alle gespeicherten Payloads mit dieser E-Mail-Adresse löschen
+    #[arg(long, conflicts_with = "ticket")]
+    email: Option<String>,
+}
+
+/// Deletes everything stored about the Zammad ticket. Returns the number of rows removed per
+/// table.
+pub async fn purge_ticket(db: &DB, zammad_id: i32) -> Result<BTreeMap<&'static str, u64>> {
+    let _lock = locks::lock_ticket(db, zammad_id).await?;
+    let jira_id = db.find_jira_id_by_zammad_id(&zammad_id).await?;
+
+    // Comment mappings only know the articles and comments, so they're looked up in both
+    // systems; either is enough, once the ticket or issue was deleted
+    let article_ids = match ZammadGetTicketArticlesRequest::new(zammad_id)
+        .submit()
+        .await
+    {
+        Ok(articles) => articles.into_iter().map(|article| article.id).collect(),
+        Err(e) => {
+            warn!(
+                "Failed to list the articles of Zammad ticket {}: {:#}",
+                zammad_id, e
+            );
+            Vec::new()
+        }
+    };
+    let comment_ids = match jira_id {
+        Some(jira_id) => match JiraGetCommentsRequest::new(jira_id).submit().await {
+            Ok(response) => response
+                .comments
+                .into_iter()
+                .map(|comment| comment.id)
+                .collect(),
+            Err(e) => {
+                warn!(
+                    "Failed to list the comments of Jira issue {}: {:#}",
+                    jira_id, e
+                );
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let removed = db
+        .purge_ticket(zammad_id, jira_id, &article_ids, &comment_ids)
+        .await?;
+    info!(
+        "Purged Zammad ticket {}: {} rows removed",
+        zammad_id,
+        removed.values().sum::<u64>()
+    );
+    Ok(removed)
+}
+
+/// Deletes the user mapping of the email address and everything stored mentioning it.
+/// Returns the number of rows removed per table.
+pub async fn purge_email(db: &DB, email: &str) -> Result<BTreeMap<&'static str, u64>> {
+    if !email.contains('@') {
+        bail!("{} isn't an email address", email);
+    }
+    let removed = db.purge_email(email).await?;
+    // The address itself isn't logged, that's what it's purged for
+    info!(
+        "Purged an email address: {} rows removed",
+        removed.values().sum::<u64>()
+    );
+    Ok(removed)
+}
+
+pub async fn run(args: PurgeArgs, output: OutputFormat) -> Result<()> {
+    let db = DB::new().await?;
+    let progress = Progress::new("purge", output);
+    let (subject, removed) = match (args.ticket, args.email) {
+        (Some(zammad_id), _) => (
+            format!("Zammad ticket {}", zammad_id),
+            purge_ticket(&db, zammad_id).await?,
+        ),
+        (None, Some(email)) => (
+            "the email address".to_string(),
+            purge_email(&db, &email).await?,
+        ),
+        (None, None) => bail!("either --ticket or --email is required"),
+    };
+    progress.finish(
+        &format!(
+            "Purged {}: {} rows removed",
+            subject,
+            removed.values().sum::<u64>()
+        ),
+        json!({ "removed": removed }),
+    );
+    Ok(())
+}
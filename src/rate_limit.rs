@@ -0,0 +1,132 @@
+//! Token buckets per webhook route and per sender, so a misconfigured trigger flooding the
+//! service can't fill the database or exhaust the Jira and Zammad rate limits.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::Ordering;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request},
+    http::header::RETRY_AFTER,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use reqwest::StatusCode;
+use tracing::warn;
+
+use crate::config::{self, RateLimit};
+use crate::{allowlist, metrics};
+
+/// Buckets beyond which full ones are dropped, as they behave like new ones anyway.
+const MAX_BUCKETS: usize = 10_000;
+
+/// A route, and the sender for per-sender limits.
+type BucketKey = (String, Option<IpAddr>);
+
+static BUCKETS: LazyLock<Mutex<HashMap<BucketKey, Bucket>>> = LazyLock::new(Default::default);
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// The limit the bucket was last checked against
+    limit: RateLimit,
+}
+
+impl Bucket {
+    /// Takes a token, or returns how long until the next one is available.
+    fn take(&mut self, limit: &RateLimit, now: Instant) -> Result<(), Duration> {
+        let burst = f64::from(limit.burst.max(1));
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.per_second).min(burst);
+        self.updated_at = now;
+        self.limit = *limit;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            // Rates are checked to be positive when the configuration is loaded; tiny ones
+            // still overflow a duration
+            Err(
+                Duration::try_from_secs_f64((1.0 - self.tokens) / limit.per_second)
+                    .unwrap_or(Duration::MAX),
+            )
+        }
+    }
+
+    fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens + elapsed * self.limit.per_second >= f64::from(self.limit.burst.max(1))
+    }
+}
+
+/// Answers webhooks beyond the configured limits with 429 and Retry-After.
+pub async fn limit_webhooks(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(limits) = &config::get().rate_limits else {
+        return next.run(request).await;
+    };
+    let Some(path) = request.extensions().get::<MatchedPath>() else {
+        return next.run(request).await;
+    };
+    let route = path
+        .as_str()
+        .trim_start_matches("/ticket-sync/")
+        .trim_end_matches("/:id")
+        .to_string();
+    let (route_limit, source_limit) = match limits.routes.get(&route) {
+        Some(overrides) => (overrides.route, overrides.source),
+        None => (limits.route, limits.source),
+    };
+    let trust_forwarded_for = config::get()
+        .allowlist
+        .as_ref()
+        .is_some_and(|allowlist| allowlist.trust_forwarded_for);
+    let sender = allowlist::sender(&request, peer.ip(), trust_forwarded_for);
+
+    let now = Instant::now();
+    let result = {
+        let mut buckets = BUCKETS.lock().expect("rate limit lock poisoned");
+        if buckets.len() > MAX_BUCKETS {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+        // The sender's bucket is checked first, so a flooding sender doesn't use up the
+        // route's tokens
+        let mut take = |sender: Option<IpAddr>, limit: Option<RateLimit>| match limit {
+            Some(limit) => buckets
+                .entry((route.clone(), sender))
+                .or_insert(Bucket {
+                    tokens: f64::from(limit.burst.max(1)),
+                    updated_at: now,
+                    limit,
+                })
+                .take(&limit, now),
+            None => Ok(()),
+        };
+        match sender {
+            Some(sender) => take(Some(sender), source_limit),
+            None => Ok(()),
+        }
+        .and_then(|()| take(None, route_limit))
+    };
+
+    match result {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            warn!("Rate limiting webhook from {:?} to {}", sender, route);
+            metrics::RATE_LIMITED_WEBHOOKS.fetch_add(1, Ordering::Relaxed);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(
+                    RETRY_AFTER,
+                    (wait.as_secs_f64().ceil() as u64).max(1).to_string(),
+                )],
+            )
+                .into_response()
+        }
+    }
+}
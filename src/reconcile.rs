@@ -0,0 +1,270 @@
+//! Webhooks missed while the service was down, or lost by Zammad or Jira, leave a ticket and
+//! its issue apart until the next change. Reconciliation walks all linked tickets, compares
+//! the fields synced both ways and repairs the side holding the outdated value, or only
+//! reports the discrepancies.
+//!
+//! The side whose value is still the one last synced is outdated, since the other side
+//! changed since. If neither is, both changed and the configured conflict strategy decides.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+
+use crate::config::{self, ConflictStrategy, Direction, ReconciliationConfig, SyncDirection};
+use crate::conflicts::{FieldChange, FieldValue, Side};
+use crate::models::{
+    api_request::{
+        JiraGetIssueRequest, ZammadGetTicketRequest, convert_jira_priority_to_zammad_priority,
+        convert_jira_status_category_to_zammad_state,
+    },
+    db::DB,
+    jira,
+    zammad::ZammadState,
+};
+use crate::{audit, leader, locks, pause, profiles};
+
+/// A field whose values differ between a ticket and its issue.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Discrepancy {
+    pub zammad_id: i32,
+    pub jira_id: i32,
+    pub field: &'static str,
+    /// Both values in Zammad terms
+    pub zammad: String,
+    pub jira: String,
+    /// The side holding the outdated value; unset if the conflict strategy leaves it open
+    pub outdated: Option<Side>,
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Report {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub checked: usize,
+    /// Tickets or issues that couldn't be fetched or repaired
+    pub failed: usize,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+static LAST_REPORT: Mutex<Option<Report>> = Mutex::new(None);
+
+/// Runs can take long with many tickets, so they don't overlap.
+static RUNNING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// The report of the last finished run.
+pub fn last_report() -> Option<Report> {
+    LAST_REPORT.lock().expect("report lock poisoned").clone()
+}
+
+/// Fails if a run can't start now.
+pub fn check() -> Result<()> {
+    if RUNNING.try_lock().is_err() {
+        bail!("a reconciliation is already running");
+    }
+    // Assignments don't know the profile they were synced with
+    if !profiles::names().is_empty() {
+        bail!("tickets can't be reconciled while profiles are active");
+    }
+    Ok(())
+}
+
+/// Compares all linked tickets with their issues, repairing discrepancies if `repair` is set.
+/// Tickets with queued webhooks are skipped, their sync is still to come.
+pub async fn run(db: &DB, repair: bool) -> Result<Report> {
+    check()?;
+    let Ok(_running) = RUNNING.try_lock() else {
+        bail!("a reconciliation is already running");
+    };
+
+    let started_at = Utc::now();
+    let queued: HashSet<i32> = db
+        .get_outbox_queue()
+        .await?
+        .into_iter()
+        .filter_map(|job| job.zammad_id)
+        .collect();
+    let mut report = Report {
+        started_at,
+        finished_at: started_at,
+        checked: 0,
+        failed: 0,
+        discrepancies: Vec::new(),
+    };
+    for (zammad_id, jira_id) in db.get_assignments().await? {
+        if queued.contains(&zammad_id) {
+            continue;
+        }
+        report.checked += 1;
+        match reconcile(db, zammad_id, jira_id, repair).await {
+            Ok(discrepancies) => report.discrepancies.extend(discrepancies),
+            Err(e) => {
+                warn!(
+                    "Failed to reconcile Zammad ticket {} with Jira issue {}: {:#}",
+                    zammad_id, jira_id, e
+                );
+                report.failed += 1;
+            }
+        }
+    }
+    report.finished_at = Utc::now();
+
+    info!(
+        "Reconciled {} tickets: {} discrepancies, {} repaired, {} failed",
+        report.checked,
+        report.discrepancies.len(),
+        report
+            .discrepancies
+            .iter()
+            .filter(|discrepancy| discrepancy.repaired)
+            .count(),
+        report.failed
+    );
+    *LAST_REPORT.lock().expect("report lock poisoned") = Some(report.clone());
+    Ok(report)
+}
+
+/// Runs [`run`] every `every` minutes, for the lifetime of the server.
+pub async fn run_periodically(db: DB, config: ReconciliationConfig) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.every.max(1) * 60));
+    loop {
+        interval.tick().await;
+        if !leader::is_leader() {
+            debug!("Another replica leads, skipping the reconciliation");
+            continue;
+        }
+        if Direction::ALL.into_iter().all(pause::is_paused) {
+            info!("Syncing is paused, skipping the reconciliation");
+            continue;
+        }
+        if let Err(e) = run(&db, config.repair).await {
+            warn!("Failed to reconcile tickets: {:#}", e);
+        }
+    }
+}
+
+async fn reconcile(
+    db: &DB,
+    zammad_id: i32,
+    jira_id: i32,
+    repair: bool,
+) -> Result<Vec<Discrepancy>> {
+    let _lock = locks::lock_ticket(db, zammad_id).await?;
+    let ticket = ZammadGetTicketRequest::new(zammad_id).submit().await?;
+    let issue = JiraGetIssueRequest::new(jira_id).submit().await?;
+    let directions = jira::get_directions(db, &issue, zammad_id).await?;
+
+    let jira_priority = convert_jira_priority_to_zammad_priority(
+        issue.fields.priority.as_ref().map(|p| p.name.as_str()),
+    );
+    let jira_state =
+        convert_jira_status_category_to_zammad_state(jira::get_status_category(&issue).await?);
+    let jira_updated_at = issue.updated_at().unwrap_or(ticket.updated_at);
+    let fields = [
+        (
+            directions.priority,
+            FieldValue::Priority(ticket.priority_id),
+            FieldValue::Priority(jira_priority),
+        ),
+        (
+            directions.status,
+            FieldValue::Status(ZammadState::from_name(&ticket.state)),
+            FieldValue::Status(jira_state),
+        ),
+    ];
+
+    let mut discrepancies = Vec::new();
+    for (direction, zammad, jira) in fields {
+        if direction == SyncDirection::None || zammad.to_stored() == jira.to_stored() {
+            continue;
+        }
+        let last = db.get_field_sync_state(&zammad_id, zammad.field()).await?;
+        let outdated = match (direction.to_jira(), direction.to_zammad()) {
+            (true, false) => Some(Side::Jira),
+            (false, true) => Some(Side::Zammad),
+            _ => match last {
+                Some(last) if last.value == zammad.to_stored() => Some(Side::Zammad),
+                Some(last) if last.value == jira.to_stored() => Some(Side::Jira),
+                _ => match config::get().conflicts {
+                    ConflictStrategy::LastWriteWins if ticket.updated_at >= jira_updated_at => {
+                        Some(Side::Jira)
+                    }
+                    ConflictStrategy::LastWriteWins => Some(Side::Zammad),
+                    ConflictStrategy::ZammadWins => Some(Side::Jira),
+                    ConflictStrategy::JiraWins => Some(Side::Zammad),
+                    ConflictStrategy::FlagAndSkip => None,
+                },
+            },
+        };
+        warn!(
+            "Discrepancy in {} of Zammad ticket {} ({:?}) and Jira issue {} ({:?}), outdated: {:?}",
+            zammad.field(),
+            zammad_id,
+            zammad.to_stored(),
+            jira_id,
+            jira.to_stored(),
+            outdated
+        );
+
+        // Jira statuses can only be written along the configured transitions, and paused
+        // directions not at all
+        let writable = match (outdated, jira) {
+            (Some(Side::Jira), _) if pause::is_paused(Direction::ZammadToJira) => false,
+            (Some(Side::Zammad), _) if pause::is_paused(Direction::JiraToZammad) => false,
+            (Some(Side::Jira), FieldValue::Status(_)) => config::get_jira().statuses.is_some(),
+            (outdated, _) => outdated.is_some(),
+        };
+        let repaired = repair && writable;
+        if let Some(outdated) = outdated.filter(|_| repaired) {
+            let (source, value, changed_at) = match outdated {
+                Side::Zammad => (Side::Jira, jira, jira_updated_at),
+                Side::Jira => (Side::Zammad, zammad, ticket.updated_at),
+            };
+            let change = FieldChange {
+                zammad_ticket_id: zammad_id,
+                jira_issue_id: jira_id,
+                side: source,
+                value,
+                changed_at,
+            };
+            // Logged like a sync from the side holding the newer value
+            let operation = format!("{}.reconcile", source.as_str());
+            let payload = json!({ "issue": { "id": jira_id } });
+            let (result, _) = audit::record(db, &operation, Some(zammad_id), &payload, async {
+                change.write_to(outdated).await?;
+                // One side missed a webhook and the other was repaired, so neither holds what was
+                // last sent to it anymore
+                for target in ["jira", "zammad"] {
+                    db.delete_sync_hash(&zammad_id, target).await?;
+                }
+                change.record(db).await
+            })
+            .await;
+            result?;
+            info!(
+                "Repaired {} of Zammad ticket {} in {}",
+                zammad.field(),
+                zammad_id,
+                outdated.as_str()
+            );
+        }
+
+        discrepancies.push(Discrepancy {
+            zammad_id,
+            jira_id,
+            field: zammad.field(),
+            zammad: zammad.to_stored(),
+            jira: jira.to_stored(),
+            outdated,
+            repaired,
+        });
+    }
+    Ok(discrepancies)
+}
@@ -0,0 +1,104 @@
+//! The configuration file is read again on SIGHUP, or once it changed on disk, and swapped in
+//! without a restart: syncs started afterwards use the new mapping rules, templates, settings
+//! and credentials. Secrets the credentials reference are resolved again periodically, and
+//! the configuration swapped in once they were rotated.
+//!
+//! A file that doesn't parse, or whose templates don't compile, is rejected and the current
+//! configuration stays in use. Endpoints, headers and webhook secrets are kept, as are the
+//! settings of the server and its background jobs; they take a restart.
+
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::time::{Instant, interval_at};
+use tracing::{error, info, warn};
+
+use crate::config::{self, Config};
+use crate::{outbox, shutdown, templates};
+
+/// How often the file is checked for changes.
+const POLL_EVERY: Duration = Duration::from_secs(5);
+
+/// Reloads the configuration on SIGHUP, when the file changed or the secrets were rotated,
+/// until the shutdown.
+pub async fn run() {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => Some(hangup),
+        Err(e) => {
+            warn!("Failed to listen for SIGHUP: {}", e);
+            None
+        }
+    };
+    let mut interval = tokio::time::interval(POLL_EVERY);
+    let refresh_every = Duration::from_secs(config::get().secrets_refresh_every.max(1) * 60);
+    let mut refresh = interval_at(Instant::now() + refresh_every, refresh_every);
+    let mut modified = modified_at();
+    loop {
+        let result = tokio::select! {
+            Some(_) = async { hangup.as_mut()?.recv().await } => {
+                info!("Received SIGHUP, reloading the configuration");
+                reload().await
+            }
+            _ = interval.tick() => {
+                if modified_at() == modified {
+                    continue;
+                }
+                info!("The configuration file changed, reloading it");
+                reload().await
+            }
+            _ = refresh.tick() => rotate().await,
+            _ = shutdown::stopping() => return,
+        };
+        modified = modified_at();
+        if let Err(e) = result {
+            error!(
+                "Failed to reload the configuration, keeping the current one: {:#}",
+                e
+            );
+        }
+    }
+}
+
+fn modified_at() -> Option<SystemTime> {
+    fs::metadata(config::path()?)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+async fn reload() -> Result<()> {
+    swap(config::load().await?)
+}
+
+/// Swaps in the configuration if the credentials resolved differently.
+async fn rotate() -> Result<()> {
+    let config = config::load().await?;
+    if credentials(&config) == credentials(&config::get()) {
+        return Ok(());
+    }
+    info!("Credentials were rotated, reloading the configuration");
+    swap(config)
+}
+
+fn credentials(config: &Config) -> [&str; 4] {
+    [
+        &config.jira.username,
+        &config.jira.token,
+        &config.zammad.username,
+        &config.zammad.token,
+    ]
+}
+
+fn swap(mut config: Config) -> Result<()> {
+    if config.keep_connections(&config::get()) {
+        warn!("Endpoints, headers and webhook secrets are only changed by a restart");
+    }
+    templates::init(&config.templates)?;
+    config::set(Arc::new(config));
+    // Idle workers pick up changes to the paused directions
+    outbox::wake_workers();
+    info!("Reloaded the configuration");
+    Ok(())
+}
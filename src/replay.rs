@@ -0,0 +1,110 @@
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::StatusCode;
+use serde_json::Value;
+use tracing::{error, info, warn};
+
+use crate::models::db::{DB, QuarantinedEvent};
+use crate::state::AppState;
+use crate::{archive, config, outbox, profiles, webhook_body};
+
+/// Keeps replayed webhooks, e.g. from queue backups, from resurrecting closed tickets:
+/// events whose timestamp is outside the configured freshness window are quarantined for
+/// review or rejected instead of being applied.
+pub async fn reject_stale_events(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(replay_config) = &config::get().replay_protection else {
+        return next.run(request).await;
+    };
+
+    let (parts, body) = request.into_parts();
+    let bytes = match webhook_body::read(body).await {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+
+    let path = parts.uri.path().to_string();
+    let (event_time, clock_skew) = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(payload) if path.starts_with("/ticket-sync/zammad") => (
+            payload
+                .pointer("/ticket/updated_at")
+                .and_then(Value::as_str)
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map(|time| time.with_timezone(&Utc)),
+            replay_config.zammad_clock_skew,
+        ),
+        Ok(payload) if path.starts_with("/ticket-sync/jira") => (
+            payload
+                .get("timestamp")
+                .and_then(Value::as_i64)
+                .and_then(DateTime::from_timestamp_millis),
+            replay_config.jira_clock_skew,
+        ),
+        _ => (None, 0),
+    };
+    // Events without a timestamp can't be checked and are applied as before
+    let Some(event_time) = event_time else {
+        return next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await;
+    };
+
+    let now = Utc::now();
+    let skew = TimeDelta::seconds(clock_skew as i64);
+    let is_fresh = event_time >= now - TimeDelta::seconds(replay_config.max_age as i64) - skew
+        && event_time <= now + skew;
+    if is_fresh {
+        return next
+            .run(Request::from_parts(parts, Body::from(bytes)))
+            .await;
+    }
+
+    warn!(
+        "Webhook to {} from {} is outside the freshness window",
+        path, event_time
+    );
+    if !replay_config.quarantine {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let payload = String::from_utf8_lossy(&bytes);
+    let quarantined = state.db.quarantine_event(&path, event_time, &payload).await;
+    match quarantined {
+        // Accepted, so the sender doesn't retry an event that will stay stale
+        Ok(_) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => {
+            error!("Failed to quarantine webhook to {}: {}", path, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Queues a quarantined webhook to be synced after all, with the profile whose webhook ID
+/// its URL carries, and removes it from the quarantine. Returns false if the outbox is full.
+pub async fn release(db: &DB, event: &QuarantinedEvent) -> anyhow::Result<bool> {
+    let path = event.path.trim_start_matches("/ticket-sync/");
+    let (route, id) = path.rsplit_once('/').unwrap_or((path, ""));
+    let operation = archive::operation(route)?;
+    let payload: Value = serde_json::from_str(&event.payload)
+        .with_context(|| format!("quarantined event {} isn't JSON", event.id))?;
+    let system = route.split('/').next().unwrap_or_default();
+    let (zammad_id, _) = archive::ticket(db, route, &payload).await;
+
+    let profile = profiles::find(system, id);
+    let queued =
+        profiles::scope(profile, outbox::enqueue(db, operation, zammad_id, &payload)).await?;
+    if queued {
+        db.delete_quarantined_event(event.id).await?;
+        info!("Released quarantined event {} ({})", event.id, operation);
+    }
+    Ok(queued)
+}
@@ -0,0 +1,60 @@
+//! Every webhook gets an `X-Request-Id`, taken from the sender or generated, which is logged
+//! with its lines, stored with its outbox job and forwarded with the Jira and Zammad requests
+//! of its sync, so one sync can be followed across the three systems.
+
+use std::future::Future;
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longer IDs from senders are replaced, so they can't bloat logs and headers.
+const MAX_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The request ID of the webhook being handled or synced.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// Runs `future` with the request ID in scope.
+pub async fn scope<T>(request_id: Option<String>, future: impl Future<Output = T>) -> T {
+    match request_id {
+        Some(request_id) => CURRENT.scope(request_id, future).await,
+        None => future.await,
+    }
+}
+
+/// Keeps the sender's request ID if it's a sensible one, generates one otherwise, and
+/// returns it with the response.
+pub async fn assign(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| {
+            !value.is_empty()
+                && value.len() <= MAX_LENGTH
+                && value.bytes().all(|byte| byte.is_ascii_graphic())
+        })
+        .map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+    let span = tracing::info_span!("webhook", request_id);
+    let mut response = CURRENT
+        .scope(request_id.clone(), next.run(request))
+        .instrument(span)
+        .await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(HEADER, value);
+    }
+    response
+}
@@ -0,0 +1,74 @@
+//! Jira answers 403 or 404 for issues the connector isn't allowed to see or edit, e.g.
+//! because of an issue security level. Retrying doesn't help until someone fixes the
+//! permissions, so the ticket is marked restricted: its webhooks are kept as dead letters
+//! without calling Jira, until an admin retries them.
+
+use anyhow::Result;
+use reqwest::{StatusCode, Url};
+use tracing::{error, info, warn};
+
+use crate::config;
+use crate::dead_letters;
+use crate::models::{api_request::ZammadCreateArticleRequest, db::DB};
+
+/// Whether Jira refused the request for lack of permissions.
+pub fn is_permission_error(e: &anyhow::Error) -> bool {
+    let jira_host = Url::parse(&config::get_jira().endpoint)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string));
+    e.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| {
+            matches!(
+                e.status(),
+                Some(StatusCode::FORBIDDEN | StatusCode::NOT_FOUND)
+            ) && e.url().and_then(Url::host_str) == jira_host.as_deref()
+        })
+}
+
+/// Marks the ticket restricted and leaves an internal note, so agents know why it isn't
+/// synced anymore.
+pub async fn restrict(db: &DB, zammad_ticket_id: i32, e: &anyhow::Error) {
+    error!(
+        "Jira refused to sync Zammad ticket {}, marking it restricted: {:#}",
+        zammad_ticket_id, e
+    );
+    let note = "This ticket isn't synced to Jira anymore, as the connector isn't allowed to edit the Jira issue, e.g. because of its security level. Changes are kept and synced once the permissions are fixed and an admin retried them.".to_string();
+    let note_article_id = match ZammadCreateArticleRequest::internal_note(zammad_ticket_id, note)
+        .submit()
+        .await
+    {
+        Ok(article) => Some(article.id),
+        Err(e) => {
+            warn!("Failed to notify about the restricted ticket: {}", e);
+            None
+        }
+    };
+    if let Err(e) = db
+        .restrict_assignment(zammad_ticket_id, &format!("{:#}", e), note_article_id)
+        .await
+    {
+        error!("Failed to mark the ticket restricted: {}", e);
+    }
+}
+
+/// Replays the ticket's dead letters in order, stopping at the first that fails, and lifts
+/// the restriction once all are synced. Returns how many were synced.
+pub async fn retry(db: &DB, zammad_ticket_id: i32) -> Result<usize> {
+    let dead_letters = db.get_dead_letters().await?;
+    let mut synced = 0;
+    // The note stays marked as synced while replaying, so it isn't posted to Jira
+    for dead_letter in dead_letters
+        .iter()
+        .filter(|dead_letter| dead_letter.zammad_id == Some(zammad_ticket_id))
+    {
+        dead_letters::replay(db, dead_letter).await?;
+        synced += 1;
+    }
+    db.delete_restricted_assignment(zammad_ticket_id).await?;
+    info!(
+        "Lifted the restriction of Zammad ticket {} after syncing {} webhooks",
+        zammad_ticket_id, synced
+    );
+    Ok(synced)
+}
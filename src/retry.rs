@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use axum::{
+    http::header::RETRY_AFTER,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::circuit_breaker::{self, CircuitOpen};
+use crate::maintenance::{self, InMaintenance};
+use crate::{audit, config, metrics, request_id};
+
+/// Until when a host asked not to be called, by host name.
+static THROTTLED_UNTIL: LazyLock<Mutex<HashMap<String, DateTime<Utc>>>> =
+    LazyLock::new(Default::default);
+
+pub trait SendWithRetry {
+    /// Sends the request like `send`, retrying transient failures with exponential backoff
+    /// and jitter. Returns the last response, so its status can still be checked. Fails
+    /// without sending anything while the host is in an announced maintenance window or its
+    /// circuit breaker is open.
+    fn send_with_retry(self) -> impl Future<Output = anyhow::Result<reqwest::Response>> + Send;
+}
+
+impl SendWithRetry for RequestBuilder {
+    async fn send_with_retry(self) -> anyhow::Result<reqwest::Response> {
+        let request = match request_id::current() {
+            Some(request_id) => self.header(request_id::HEADER, request_id),
+            None => self,
+        };
+        // Invalid requests fail the same way on every attempt
+        let (method, url, fields) = match request.try_clone().map(RequestBuilder::build) {
+            Some(Ok(built)) => (
+                built.method().clone(),
+                built.url().clone(),
+                audit::changed_fields(
+                    built.method(),
+                    built.body().and_then(|body| body.as_bytes()),
+                ),
+            ),
+            _ => return Ok(request.send().await?),
+        };
+        let host = url.host_str().unwrap_or_default().to_string();
+        // Checked first, so the breaker doesn't probe a host that is known to be down
+        if maintenance::announced_for(&host).is_some() {
+            return Err(InMaintenance { host }.into());
+        }
+        if !circuit_breaker::allow(&host) {
+            return Err(CircuitOpen { host }.into());
+        }
+
+        let result = send(request, &method, &url).await;
+        audit::record_call(
+            &method,
+            &url,
+            result.as_ref().ok().map(reqwest::Response::status),
+            fields,
+        );
+        match &result {
+            // Being rate limited doesn't mean the host is down
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                circuit_breaker::record_throttled(&host)
+            }
+            Ok(response) if !response.status().is_server_error() => {
+                circuit_breaker::record_success(&host)
+            }
+            _ => circuit_breaker::record_failure(&host),
+        }
+        Ok(result?)
+    }
+}
+
+async fn send(
+    request: RequestBuilder,
+    method: &Method,
+    url: &Url,
+) -> reqwest::Result<reqwest::Response> {
+    let retry_config = &config::get().retry;
+    let mut attempt = 1;
+    loop {
+        // Requests with a streamed body, e.g. uploads, can't be sent twice
+        let Some(retry) = request.try_clone() else {
+            return request.send().await;
+        };
+        let result = retry.send().await;
+        let backoff = backoff(attempt, retry_config.initial_delay, retry_config.max_delay);
+
+        // Rate limits say how long to wait; waiting longer than any backoff would block
+        // the webhook, so the sync is deferred instead
+        if let Ok(response) = &result
+            && response.status() == StatusCode::TOO_MANY_REQUESTS
+        {
+            metrics::THROTTLED_REQUESTS.fetch_add(1, Ordering::Relaxed);
+            let delay = retry_after(response).unwrap_or(backoff);
+            if attempt >= retry_config.max_attempts
+                || delay > Duration::from_millis(retry_config.max_delay)
+            {
+                throttle(url, delay);
+                return result;
+            }
+            warn!(
+                "{} is rate limited, retrying in {:?} (attempt {} of {})",
+                url.host_str().unwrap_or_default(),
+                delay,
+                attempt,
+                retry_config.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            continue;
+        }
+
+        if attempt >= retry_config.max_attempts || !is_transient(method, &result) {
+            return result;
+        }
+        match &result {
+            Ok(response) => warn!(
+                "Request failed with {}, retrying in {:?} (attempt {} of {})",
+                response.status(),
+                backoff,
+                attempt,
+                retry_config.max_attempts
+            ),
+            Err(e) => warn!(
+                "Request failed: {}, retrying in {:?} (attempt {} of {})",
+                e, backoff, attempt, retry_config.max_attempts
+            ),
+        }
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+/// Whether sending the request again may succeed. Requests that create something are only
+/// retried if the server can't have processed them, so retries don't create duplicates.
+fn is_transient(method: &Method, result: &reqwest::Result<reqwest::Response>) -> bool {
+    let idempotent = !matches!(*method, Method::POST | Method::PATCH);
+    match result {
+        Ok(response) => match response.status() {
+            StatusCode::SERVICE_UNAVAILABLE => true,
+            StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => idempotent,
+            _ => false,
+        },
+        Err(e) => e.is_connect() || (e.is_timeout() && idempotent),
+    }
+}
+
+/// Doubles the delay with every attempt, up to `max_delay`. A random part keeps replicas
+/// that failed at the same time from retrying at the same time.
+fn backoff(attempt: u32, initial_delay: u64, max_delay: u64) -> Duration {
+    let delay = initial_delay
+        .saturating_mul(1 << (attempt - 1).min(32))
+        .min(max_delay);
+    let jitter = (Uuid::new_v4().as_u128() as u64) % (delay / 2 + 1);
+    Duration::from_millis(delay / 2 + jitter)
+}
+
+/// The Retry-After header, given in seconds or as an HTTP date.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+fn throttle(url: &Url, delay: Duration) {
+    let Some(host) = url.host_str() else {
+        return;
+    };
+    info!("{} is rate limited for {:?}, deferring syncs", host, delay);
+    let until = Utc::now() + delay;
+    THROTTLED_UNTIL
+        .lock()
+        .expect("throttle lock poisoned")
+        .insert(host.to_string(), until);
+}
+
+/// How long requests to `host` should still be held back.
+fn throttled_for(host: &str) -> Option<Duration> {
+    let until = *THROTTLED_UNTIL
+        .lock()
+        .expect("throttle lock poisoned")
+        .get(host)?;
+    (until - Utc::now()).to_std().ok()
+}
+
+/// How long the system at `endpoint` shouldn't be called: while it's rate limited, its
+/// circuit breaker is open or it's in an announced maintenance window.
+pub fn unavailable_for(endpoint: &str) -> Option<Duration> {
+    host_unavailable_for(Url::parse(endpoint).ok()?.host_str()?)
+}
+
+/// Like `unavailable_for`, by host name.
+pub fn host_unavailable_for(host: &str) -> Option<Duration> {
+    throttled_for(host)
+        .max(circuit_breaker::open_for(host))
+        .max(maintenance::announced_for(host))
+}
+
+/// How long the host of a request that wasn't sent stays unavailable, if `e` is because of
+/// its maintenance window or open circuit breaker; also for requests to the system a sync
+/// reads from rather than the one it's synced to.
+pub fn unsent_for(e: &anyhow::Error) -> Option<Duration> {
+    e.chain().find_map(|cause| {
+        let host = cause
+            .downcast_ref::<InMaintenance>()
+            .map(|e| &e.host)
+            .or_else(|| cause.downcast_ref::<CircuitOpen>().map(|e| &e.host))?;
+        host_unavailable_for(host)
+    })
+}
+
+/// Asks the sender to deliver the webhook again after `delay`.
+pub fn deferred(delay: Duration) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(RETRY_AFTER, delay.as_secs().max(1).to_string())],
+    )
+        .into_response()
+}
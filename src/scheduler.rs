@@ -0,0 +1,96 @@
+//! Backfills run at configured times, like a cron job inside the service: each creates Jira
+//! issues for all open Zammad tickets of a group that aren't linked yet. Their position is
+//! stored after every page, so a run interrupted by a restart resumes once the service is
+//! back; a finished run starts over at its next time.
+
+use chrono::Local;
+use tracing::{debug, error, info};
+
+use crate::backfill;
+use crate::config::{Direction, ScheduledBackfillConfig};
+use crate::models::db::DB;
+use crate::{leader, pause, shutdown};
+
+/// Starts the configured backfills, each at its own times.
+pub fn spawn_backfills(db: &DB, backfills: &[ScheduledBackfillConfig]) {
+    for backfill in backfills {
+        tokio::spawn(run_scheduled(db.clone(), backfill.clone()));
+    }
+}
+
+async fn run_scheduled(db: DB, backfill: ScheduledBackfillConfig) {
+    let cursor_name = format!("scheduled:{}", backfill.name);
+    match db.get_backfill_cursor(&cursor_name).await {
+        Ok(Some(page)) => {
+            info!(
+                "Resuming scheduled backfill {} at page {}",
+                backfill.name, page
+            );
+            run(&db, &backfill, &cursor_name).await;
+        }
+        Ok(None) => {}
+        Err(e) => error!(
+            "Failed to read the position of backfill {}: {}",
+            backfill.name, e
+        ),
+    }
+
+    while let Some(next) = backfill.schedule.upcoming(Local).next() {
+        let wait = (next - Local::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown::stopping() => return,
+        }
+        run(&db, &backfill, &cursor_name).await;
+    }
+}
+
+async fn run(db: &DB, backfill: &ScheduledBackfillConfig, cursor_name: &str) {
+    if !leader::is_leader() {
+        debug!(
+            "Another replica leads, skipping scheduled backfill {}",
+            backfill.name
+        );
+        return;
+    }
+    if pause::is_paused(Direction::ZammadToJira) {
+        info!(
+            "Syncing is paused, skipping scheduled backfill {}",
+            backfill.name
+        );
+        return;
+    }
+    let query = format!(
+        "group.name:\"{}\" AND NOT state.name:(closed OR merged OR removed)",
+        backfill.group.replace('"', "\\\"")
+    );
+    let run = backfill::backfill_zammad_tickets(
+        db,
+        cursor_name,
+        &query,
+        backfill.batch_size,
+        backfill.max_per_minute,
+        |_| {},
+    );
+    let result = tokio::select! {
+        result = run => result,
+        _ = shutdown::stopping() => {
+            info!("Scheduled backfill {} interrupted, resuming after the restart", backfill.name);
+            return;
+        }
+    };
+
+    match result {
+        Ok(counts) => {
+            info!(
+                "Scheduled backfill {} finished: {} issues created, {} failed, {} already linked or excluded by rules",
+                backfill.name, counts.created, counts.failed, counts.skipped
+            );
+            if let Err(e) = db.delete_backfill_cursor(cursor_name).await {
+                error!("Failed to reset backfill {}: {}", backfill.name, e);
+            }
+        }
+        // The position is kept, so the next run continues after the last complete page
+        Err(e) => error!("Scheduled backfill {} failed: {:#}", backfill.name, e),
+    }
+}
@@ -0,0 +1,206 @@
+//! `username` and `token` can reference a secret instead of holding it:
+//!
+//! - `file:/run/secrets/jira_token`, e.g. Docker or Kubernetes secrets
+//! - `env:JIRA_TOKEN`
+//! - `vault:secret/data/jira#token`, a key of a HashiCorp Vault secret, read with `VAULT_ADDR`
+//!   and `VAULT_TOKEN`
+//! - `aws:prod/jira#token`, an AWS Secrets Manager secret, or a key of it if it holds JSON,
+//!   read with `AWS_REGION` and the `AWS_ACCESS_KEY_ID` credentials
+//!
+//! They're resolved when the configuration is loaded, and those of the file's systems again
+//! every `secrets_refresh_every` minutes, so rotated secrets are used without a restart.
+
+use std::env;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+
+use crate::config::{JiraConfig, ZammadConfig};
+
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("failed to build HTTP client")
+});
+
+/// Whether the value references a secret.
+pub fn is_reference(value: &str) -> bool {
+    ["file:", "env:", "vault:", "aws:"]
+        .iter()
+        .any(|scheme| value.starts_with(scheme))
+}
+
+/// Replaces references in the credentials of both systems with their secrets.
+pub async fn resolve_systems(jira: &mut JiraConfig, zammad: &mut ZammadConfig) -> Result<()> {
+    for (field, value) in [
+        ("jira.username", &mut jira.username),
+        ("jira.token", &mut jira.token),
+        ("zammad.username", &mut zammad.username),
+        ("zammad.token", &mut zammad.token),
+    ] {
+        resolve_field(field, value).await?;
+    }
+    Ok(())
+}
+
+/// Replaces the value with its secret, if it references one.
+pub async fn resolve_field(field: &str, value: &mut String) -> Result<()> {
+    if is_reference(value) {
+        *value = resolve(value)
+            .await
+            .with_context(|| format!("failed to resolve the secret of {}", field))?;
+    }
+    Ok(())
+}
+
+async fn resolve(reference: &str) -> Result<String> {
+    let (scheme, location) = reference.split_once(':').unwrap_or_default();
+    match scheme {
+        "file" => Ok(tokio::fs::read_to_string(location)
+            .await
+            .with_context(|| format!("failed to read {}", location))?
+            .trim_end()
+            .to_string()),
+        "env" => env::var(location)
+            .with_context(|| format!("environment variable {} is not set", location)),
+        "vault" => vault(location).await,
+        "aws" => aws(location).await,
+        _ => bail!("unknown secret reference {}", scheme),
+    }
+}
+
+/// Reads a key of a Vault secret, of the KV engine in version 1 or 2.
+async fn vault(location: &str) -> Result<String> {
+    let (path, key) = location
+        .split_once('#')
+        .context("Vault references need a key, e.g. vault:secret/data/jira#token")?;
+    let address = env::var("VAULT_ADDR").context("VAULT_ADDR is not set")?;
+    let token = env::var("VAULT_TOKEN").context("VAULT_TOKEN is not set")?;
+    let mut request = CLIENT
+        .get(format!(
+            "{}/v1/{}",
+            address.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        ))
+        .header("X-Vault-Token", token);
+    if let Ok(namespace) = env::var("VAULT_NAMESPACE") {
+        request = request.header("X-Vault-Namespace", namespace);
+    }
+    let secret: Value = request.send().await?.error_for_status()?.json().await?;
+    let data = &secret["data"];
+    // Version 2 nests the secret in another data object
+    let value = data["data"].get(key).or_else(|| data.get(key));
+    value
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .with_context(|| format!("Vault secret {} has no key {}", path, key))
+}
+
+/// Reads an AWS Secrets Manager secret, or a key of it if given.
+async fn aws(location: &str) -> Result<String> {
+    let (secret_id, key) = match location.split_once('#') {
+        Some((secret_id, key)) => (secret_id, Some(key)),
+        None => (location, None),
+    };
+    let region = env::var("AWS_REGION")
+        .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+        .context("AWS_REGION is not set")?;
+    let access_key = env::var("AWS_ACCESS_KEY_ID").context("AWS_ACCESS_KEY_ID is not set")?;
+    let secret_key =
+        env::var("AWS_SECRET_ACCESS_KEY").context("AWS_SECRET_ACCESS_KEY is not set")?;
+    let session_token = env::var("AWS_SESSION_TOKEN").ok();
+
+    let host = format!("secretsmanager.{}.amazonaws.com", region);
+    let body = json!({ "SecretId": secret_id }).to_string();
+    let now = Utc::now();
+    let date = now.format("%Y%m%d").to_string();
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+    // Signature Version 4, over the headers in alphabetical order
+    let mut headers = vec![
+        ("content-type", "application/x-amz-json-1.1".to_string()),
+        ("host", host.clone()),
+        ("x-amz-date", timestamp.clone()),
+    ];
+    if let Some(session_token) = session_token {
+        headers.push(("x-amz-security-token", session_token));
+    }
+    headers.push(("x-amz-target", "secretsmanager.GetSecretValue".to_string()));
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let canonical_request = format!(
+        "POST\n/\n\n{}\n{}\n{}",
+        canonical_headers,
+        signed_headers,
+        hex(&Sha256::digest(&body))
+    );
+    let scope = format!("{}/{}/secretsmanager/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        scope,
+        hex(&Sha256::digest(&canonical_request))
+    );
+    let mut signing_key = format!("AWS4{}", secret_key).into_bytes();
+    for part in [date.as_str(), &region, "secretsmanager", "aws4_request"] {
+        signing_key = hmac(&signing_key, part);
+    }
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key,
+        scope,
+        signed_headers,
+        hex(&hmac(&signing_key, &string_to_sign))
+    );
+
+    let mut request = CLIENT
+        .post(format!("https://{}/", host))
+        .header("Authorization", authorization)
+        .body(body);
+    for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+        request = request.header(name, value);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        bail!(
+            "AWS Secrets Manager answered {}: {}",
+            response.status(),
+            response.text().await.unwrap_or_default()
+        );
+    }
+    let secret: Value = response.json().await?;
+    let value = secret["SecretString"]
+        .as_str()
+        .with_context(|| format!("AWS secret {} has no string value", secret_id))?;
+    match key {
+        None => Ok(value.to_string()),
+        Some(key) => serde_json::from_str::<Value>(value)
+            .ok()
+            .and_then(|secret| secret.get(key)?.as_str().map(str::to_string))
+            .with_context(|| format!("AWS secret {} has no key {}", secret_id, key)),
+    }
+}
+
+fn hmac(key: &[u8], message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,41 @@
+//! On SIGTERM or SIGINT the server stops accepting connections and answers the requests in
+//! flight; the outbox workers finish the jobs they started. Jobs not started yet stay in
+//! the outbox and are synced after the next start, so rolling deploys don't lose webhooks.
+
+use std::sync::LazyLock;
+
+use tokio::signal;
+use tokio::sync::watch;
+use tracing::info;
+
+static STOPPING: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::channel(false).0);
+
+/// Waits for SIGTERM or SIGINT and starts the shutdown.
+pub async fn signal() {
+    let interrupt = async {
+        signal::ctrl_c().await.expect("failed to listen for SIGINT");
+    };
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
+    STOPPING.send_replace(true);
+}
+
+/// Whether the shutdown started.
+pub fn is_stopping() -> bool {
+    *STOPPING.borrow()
+}
+
+/// Resolves once the shutdown started.
+pub async fn stopping() {
+    let mut stopping = STOPPING.subscribe();
+    // The sender lives in a static, so it's never dropped
+    let _ = stopping.wait_for(|stopping| *stopping).await;
+}
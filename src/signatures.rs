@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, anyhow, bail};
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{Method, Uri, header::AUTHORIZATION},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tracing::warn;
+
+use crate::config::{self, JiraWebhookAuth};
+use crate::profiles;
+use crate::template_helpers::encode;
+use crate::webhook_body;
+
+const ZAMMAD_SIGNATURE_HEADER: &str = "X-Hub-Signature";
+
+/// Seconds an expired JWT is still accepted, for clocks that are slightly off.
+const JWT_LEEWAY: i64 = 30;
+
+/// The IDs Zammad and Jira use as last segment of their webhook URLs.
+#[derive(Debug, Clone)]
+pub struct WebhookIds {
+    pub zammad: String,
+    pub jira: String,
+}
+
+/// Answers webhooks whose URL doesn't carry the configured ID, or the ID of a profile, with
+/// 404, as if the route didn't exist. Webhooks of a profile are handled with it in scope.
+pub async fn verify_webhook_id(ids: WebhookIds, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    let (system, expected) = if path.starts_with("/ticket-sync/zammad/") {
+        ("zammad", &ids.zammad)
+    } else if path.starts_with("/ticket-sync/jira/") {
+        ("jira", &ids.jira)
+    } else {
+        return next.run(request).await;
+    };
+    let id = path.rsplit('/').next().unwrap_or_default();
+    // Compared in constant time, as the ID is a secret of its own
+    if bool::from(id.as_bytes().ct_eq(expected.as_bytes())) {
+        return next.run(request).await;
+    }
+    match profiles::find(system, id) {
+        Some(profile) => profiles::scope(Some(profile), next.run(request)).await,
+        None => {
+            warn!("Rejecting webhook with unknown ID in {}", path);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+/// Rejects Zammad webhooks without a valid HMAC signature, if a secret is configured. Runs
+/// before anything else reads the body, so unsigned payloads are never processed.
+pub async fn verify_zammad_signature(request: Request, next: Next) -> Response {
+    let Some(secret) = &config::get_zammad().webhook_secret else {
+        return next.run(request).await;
+    };
+    if !request.uri().path().starts_with("/ticket-sync/zammad") {
+        return next.run(request).await;
+    }
+
+    let signature = request
+        .headers()
+        .get(ZAMMAD_SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("sha1="))
+        .and_then(decode_hex);
+    let Some(signature) = signature else {
+        warn!("Rejecting Zammad webhook without signature");
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    let (parts, body) = request.into_parts();
+    let bytes = match webhook_body::read(body).await {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+    let mut mac =
+        Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(&bytes);
+    // Compared in constant time, so the signature can't be guessed byte by byte
+    if mac.verify_slice(&signature).is_err() {
+        warn!("Rejecting Zammad webhook with invalid signature");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Rejects Jira webhooks without the configured token or a valid JWT, so the Jira routes
+/// can't be used to post comments to Zammad.
+pub async fn verify_jira_webhook(request: Request, next: Next) -> Response {
+    let Some(auth) = &config::get_jira().webhook_auth else {
+        return next.run(request).await;
+    };
+    if !request.uri().path().starts_with("/ticket-sync/jira") {
+        return next.run(request).await;
+    }
+
+    let result = match auth {
+        JiraWebhookAuth::Token(secret) => match query_parameter(request.uri(), "token") {
+            // Compared in constant time, so the token can't be guessed byte by byte
+            Some(token) if bool::from(token.as_bytes().ct_eq(secret.as_bytes())) => Ok(()),
+            Some(_) => Err(anyhow!("invalid token")),
+            None => Err(anyhow!("no token")),
+        },
+        JiraWebhookAuth::Jwt {
+            shared_secret,
+            issuer,
+        } => verify_jwt(&request, shared_secret, issuer.as_deref()),
+    };
+    if let Err(e) = result {
+        warn!("Rejecting Jira webhook: {}", e);
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    iss: String,
+    exp: i64,
+    qsh: String,
+}
+
+/// Checks the Atlassian Connect JWT of the request: its HS256 signature, expiry, issuer and
+/// the query string hash, which ties it to this method, path and query.
+fn verify_jwt(request: &Request, secret: &str, issuer: Option<&str>) -> anyhow::Result<()> {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("JWT "))
+        .map(str::to_string)
+        .or_else(|| query_parameter(request.uri(), "jwt"))
+        .context("no JWT")?;
+    let parts: Vec<&str> = token.split('.').collect();
+    let [header, claims, signature] = parts[..] else {
+        bail!("malformed JWT");
+    };
+
+    let decoded: Value = serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(header)?)?;
+    if decoded["alg"] != "HS256" {
+        bail!("unsupported JWT algorithm {}", decoded["alg"]);
+    }
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", header, claims).as_bytes());
+    mac.verify_slice(&BASE64_URL_SAFE_NO_PAD.decode(signature)?)
+        .map_err(|_| anyhow!("invalid JWT signature"))?;
+
+    let claims: JwtClaims = serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(claims)?)?;
+    if claims.exp + JWT_LEEWAY < Utc::now().timestamp() {
+        bail!("expired JWT");
+    }
+    if let Some(issuer) = issuer
+        && claims.iss != issuer
+    {
+        bail!("JWT issued by {}", claims.iss);
+    }
+    if claims.qsh != query_string_hash(request.method(), request.uri()) {
+        bail!("JWT issued for another request");
+    }
+    Ok(())
+}
+
+/// The hash of the canonical request as defined by Atlassian Connect: method, path and
+/// sorted query without the JWT itself.
+fn query_string_hash(method: &Method, uri: &Uri) -> String {
+    let path = match uri.path().trim_end_matches('/') {
+        "" => "/".to_string(),
+        path => path.replace('&', "%26"),
+    };
+    let mut parameters: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (key, value) in query_pairs(uri) {
+        if key != "jwt" {
+            parameters
+                .entry(encode(&key))
+                .or_default()
+                .push(encode(&value));
+        }
+    }
+    let query = parameters
+        .into_iter()
+        .map(|(key, mut values)| {
+            values.sort();
+            format!("{}={}", key, values.join(","))
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let digest = Sha256::digest(format!("{}&{}&{}", method.as_str(), path, query));
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn query_pairs(uri: &Uri) -> Vec<(String, String)> {
+    Url::parse(&format!("http://localhost{}", uri))
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default()
+}
+
+fn query_parameter(uri: &Uri, name: &str) -> Option<String> {
+    query_pairs(uri)
+        .into_iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value)
+}
@@ -0,0 +1,212 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::Args;
+use serde_json::json;
+
+use crate::models::{
+    api_request::{
+        JiraAddCommentRequest, JiraDeleteIssueRequest, JiraGetCommentsRequest,
+        ZammadCreateArticleRequest, ZammadCreateTicketRequest, ZammadDeleteTicketRequest,
+        ZammadGetTicketArticlesRequest,
+    },
+    db::DB,
+};
+use crate::output::{OutputFormat, Progress};
+use crate::{config, profiles};
+
+#[derive(Args, Debug)]
+pub struct SmokeTestArgs {
+    /// Profil, dessen Systeme getestet werden; Standard sind die der Konfigurationsdatei
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Kunde des Test-Tickets; Standard ist `zammad.default_customer`
+    #[arg(long)]
+    customer: Option<String>,
+
+    /// Maximale Wartezeit pro Schritt in Sekunden
+    #[arg(long, default_value_t = 60)]
+    timeout: u64,
+
+    /// Abfrageintervall in Sekunden
+    #[arg(long, default_value_t = 2)]
+    interval: u64,
+}
+
+/// Everything created during the test, so it can be cleaned up even if a step fails.
+#[derive(Debug, Default)]
+struct Created {
+    zammad_ticket_id: Option<i32>,
+    jira_issue_id: Option<i32>,
+}
+
+/// Runs a full round-trip against the configured systems: creates a disposable Zammad
+/// ticket, waits for the Jira issue, syncs a comment in each direction and deletes both.
+///
+/// The webhooks have to reach a running instance that uses the same database. With
+/// `--profile`, the systems of that profile are tested instead of the file's.
+pub async fn run(args: SmokeTestArgs, output: OutputFormat) -> Result<()> {
+    let started = Instant::now();
+    let progress = Progress::new("smoke-test", output);
+    let db = DB::new().await?;
+    profiles::load(&db).await?;
+    let marker = format!("{}", Utc::now().format("%Y%m%d-%H%M%S-%3f"));
+
+    profiles::scope_named(args.profile.as_deref(), async {
+        let mut created = Created::default();
+        let result = round_trip(&args, &progress, &db, &marker, &mut created).await;
+        let cleanup = clean_up(&progress, &db, &created).await;
+        result.and(cleanup)
+    })
+    .await?;
+
+    let duration = started.elapsed();
+    progress.finish(
+        &format!(
+            "PASS smoke test {} ({:.1}s)",
+            marker,
+            duration.as_secs_f64()
+        ),
+        json!({
+            "marker": marker,
+            "profile": args.profile,
+            "duration_ms": duration.as_millis(),
+        }),
+    );
+    Ok(())
+}
+
+async fn round_trip(
+    args: &SmokeTestArgs,
+    progress: &Progress,
+    db: &DB,
+    marker: &str,
+    created: &mut Created,
+) -> Result<()> {
+    let customer = args
+        .customer
+        .clone()
+        .or_else(|| config::get_zammad().default_customer.clone())
+        .context("no customer given and no default customer is configured")?;
+
+    let ticket = step(progress, "create Zammad ticket", async {
+        ZammadCreateTicketRequest::smoke_test(marker, customer)
+            .submit()
+            .await
+    })
+    .await?;
+    created.zammad_ticket_id = Some(ticket.id);
+
+    let jira_issue_id = step(
+        progress,
+        "wait for Jira issue",
+        wait_for(args, || async {
+            // The assignment exists without a Jira ID while the issue is being created
+            Ok(db.get_jira_id_by_zammad_id(&ticket.id).await.ok())
+        }),
+    )
+    .await?;
+    created.jira_issue_id = Some(jira_issue_id);
+
+    let zammad_comment = format!("Smoke test {}: Zammad → Jira", marker);
+    step(progress, "sync comment Zammad → Jira", async {
+        ZammadCreateArticleRequest::internal_note(ticket.id, zammad_comment.clone())
+            .submit()
+            .await?;
+        wait_for(args, || async {
+            let comments = JiraGetCommentsRequest::new(jira_issue_id).submit().await?;
+            Ok(comments
+                .comments
+                .iter()
+                .any(|comment| comment.body.contains(&zammad_comment))
+                .then_some(()))
+        })
+        .await
+    })
+    .await?;
+
+    let jira_comment = format!("Smoke test {}: Jira → Zammad", marker);
+    step(progress, "sync comment Jira → Zammad", async {
+        JiraAddCommentRequest::new(jira_comment.clone())
+            .submit(&jira_issue_id)
+            .await?;
+        wait_for(args, || async {
+            let articles = ZammadGetTicketArticlesRequest::new(ticket.id)
+                .submit()
+                .await?;
+            Ok(articles
+                .iter()
+                .any(|article| article.body.contains(&jira_comment))
+                .then_some(()))
+        })
+        .await
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes whatever was created, attempting every deletion even if an earlier one fails.
+async fn clean_up(progress: &Progress, db: &DB, created: &Created) -> Result<()> {
+    let mut result = Ok(());
+    if let Some(issue_id) = created.jira_issue_id {
+        result = result.and(
+            step(
+                progress,
+                "delete Jira issue",
+                JiraDeleteIssueRequest::new(issue_id).submit(),
+            )
+            .await,
+        );
+    }
+    if let Some(ticket_id) = created.zammad_ticket_id {
+        result = result.and(
+            step(
+                progress,
+                "delete Zammad ticket",
+                ZammadDeleteTicketRequest::new(ticket_id).submit(),
+            )
+            .await,
+        );
+        result = result.and(db.delete_assignment(&ticket_id).await);
+    }
+    result
+}
+
+/// Runs one step of the test and prints its outcome and duration.
+async fn step<T>(
+    progress: &Progress,
+    name: &str,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = future.await;
+    progress.step(
+        name,
+        result.is_ok(),
+        started.elapsed().as_millis(),
+        result.as_ref().err().map(|e| format!("{:#}", e)),
+    );
+    result.with_context(|| format!("smoke test step {:?} failed", name))
+}
+
+/// Polls `check` until it returns a value or the timeout is reached.
+async fn wait_for<T, F, Fut>(args: &SmokeTestArgs, mut check: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>>>,
+{
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    loop {
+        if let Some(value) = check().await? {
+            return Ok(value);
+        }
+        if Instant::now() >= deadline {
+            anyhow::bail!("timed out after {}s", args.timeout);
+        }
+        tokio::time::sleep(Duration::from_secs(args.interval.max(1))).await;
+    }
+}
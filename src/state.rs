@@ -0,0 +1,20 @@
+use crate::models::db::DB;
+
+/// State shared by all handlers of the server. The database is opened and migrated once,
+/// and its connection pool is shared by all requests and background jobs. The configuration
+/// isn't part of it, as it's replaced on reload; handlers read it with [`config::get`]. Nor
+/// are the HTTP clients of Jira and Zammad, which depend on the profile being synced.
+///
+/// [`config::get`]: crate::config::get
+#[derive(Clone)]
+pub struct AppState {
+    pub db: DB,
+}
+
+impl AppState {
+    pub async fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            db: DB::new().await?,
+        })
+    }
+}
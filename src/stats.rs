@@ -0,0 +1,114 @@
+//! Statistics for an operations dashboard, counted from the audit log: issues created,
+//! comments synced, failures by their class and how long syncs took, in total and per day.
+
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::audit::UpstreamCall;
+use crate::models::db::{AuditEntry, AuditFilter, DB};
+use crate::outbox;
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct Counts {
+    pub syncs: u64,
+    pub created_issues: u64,
+    pub synced_comments: u64,
+    pub failures: u64,
+    /// Failures by what went wrong, e.g. "rate_limited" or "network"
+    pub failures_by_class: BTreeMap<&'static str, u64>,
+    /// Average duration of the syncs, in milliseconds
+    pub average_latency_ms: Option<f64>,
+    #[serde(skip)]
+    total_duration_ms: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Stats {
+    pub since: NaiveDate,
+    pub totals: Counts,
+    /// Days without syncs are left out
+    pub days: BTreeMap<NaiveDate, Counts>,
+}
+
+/// The statistics of the last `days` days, today included.
+pub async fn collect(db: &DB, days: u32) -> anyhow::Result<Stats> {
+    let since = Utc::now().date_naive() - Duration::days(i64::from(days.max(1)) - 1);
+    let filter = AuditFilter {
+        since: since.and_hms_opt(0, 0, 0).map(|since| since.and_utc()),
+        limit: Some(u32::MAX),
+        ..Default::default()
+    };
+    let entries = db.get_audit_entries(&filter).await?;
+
+    let mut stats = Stats {
+        since,
+        totals: Counts::default(),
+        days: BTreeMap::new(),
+    };
+    for entry in &entries {
+        let day = stats.days.entry(entry.created_at.date_naive()).or_default();
+        day.add(entry);
+        stats.totals.add(entry);
+    }
+    stats.totals.finish();
+    stats.days.values_mut().for_each(Counts::finish);
+    Ok(stats)
+}
+
+impl Counts {
+    fn add(&mut self, entry: &AuditEntry) {
+        self.syncs += 1;
+        self.total_duration_ms += entry.duration_ms;
+        let succeeded = |call: &&UpstreamCall| call.method == "POST" && is_success(call);
+        if entry.operation == outbox::ZAMMAD_CREATE {
+            self.created_issues += entry
+                .calls
+                .iter()
+                .filter(succeeded)
+                .filter(|call| call.url.ends_with("/issue"))
+                .count() as u64;
+        }
+        self.synced_comments += entry
+            .calls
+            .iter()
+            .filter(succeeded)
+            .filter(|call| call.url.ends_with("/comment") || call.url.ends_with("/ticket_articles"))
+            .count() as u64;
+        if entry.error.is_some() {
+            self.failures += 1;
+            *self
+                .failures_by_class
+                .entry(error_class(entry))
+                .or_default() += 1;
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.syncs > 0 {
+            self.average_latency_ms = Some(self.total_duration_ms as f64 / self.syncs as f64);
+        }
+    }
+}
+
+fn is_success(call: &UpstreamCall) -> bool {
+    call.status.is_some_and(|status| status < 400)
+}
+
+/// What made the sync fail, told by the last call that failed; failures without one are
+/// "other", e.g. invalid payloads or configuration mistakes.
+fn error_class(entry: &AuditEntry) -> &'static str {
+    let Some(call) = entry.calls.iter().rev().find(|call| !is_success(call)) else {
+        return "other";
+    };
+    match call.status {
+        None => "network",
+        Some(429) => "rate_limited",
+        Some(401 | 403) => "unauthorized",
+        Some(404) => "not_found",
+        Some(400..=499) => "client_error",
+        Some(_) => "server_error",
+    }
+}
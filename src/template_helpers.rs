@@ -0,0 +1,168 @@
+//! Template helpers that look up data the event doesn't contain, e.g. the account manager
+//! of an organization:
+//!
+//! - `{{table "account_managers" ticket.organization.name default="Support"}}` looks the key
+//!   up in a table of the configuration.
+//! - `{{http_lookup "crm" ticket.organization.name default="Support"}}` fetches it from a
+//!   configured URL, with a timeout and a cache.
+//!
+//! Templates can only reach the tables and URLs of the configuration.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, RenderErrorReason,
+    ScopedJson,
+};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::config::{HttpLookupConfig, TemplateConfig};
+
+/// A looked up value and when it expires.
+type CachedValue = (DateTime<Utc>, Value);
+
+/// Results of HTTP lookups by service and key.
+static CACHE: LazyLock<Mutex<HashMap<(String, String), CachedValue>>> =
+    LazyLock::new(Default::default);
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+
+pub fn register(registry: &mut Handlebars<'static>, config: &TemplateConfig) {
+    registry.register_helper("table", Box::new(TableHelper(config.tables.clone())));
+    registry.register_helper(
+        "http_lookup",
+        Box::new(HttpLookupHelper(config.http_lookups.clone())),
+    );
+}
+
+struct TableHelper(HashMap<String, HashMap<String, String>>);
+
+impl HelperDef for TableHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let (name, key) = name_and_key(h, "table")?;
+        let table = self.0.get(name).ok_or_else(|| {
+            RenderErrorReason::Other(format!("lookup table {} isn't configured", name))
+        })?;
+        let value = match table.get(&key) {
+            Some(value) => Value::String(value.clone()),
+            None => default(h),
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+struct HttpLookupHelper(HashMap<String, HttpLookupConfig>);
+
+impl HelperDef for HttpLookupHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let (name, key) = name_and_key(h, "http_lookup")?;
+        let lookup = self.0.get(name).ok_or_else(|| {
+            RenderErrorReason::Other(format!("HTTP lookup {} isn't configured", name))
+        })?;
+
+        let cache_key = (name.to_string(), key.clone());
+        let cached = CACHE
+            .lock()
+            .expect("lookup cache lock poisoned")
+            .get(&cache_key)
+            .filter(|(expires, _)| *expires > Utc::now())
+            .map(|(_, value)| value.clone());
+        let value = match cached {
+            Some(value) => value,
+            None => {
+                // Helpers are synchronous, the sync waits for the lookup anyway
+                let fetched = tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(fetch(lookup, &key))
+                });
+                match fetched {
+                    Ok(value) => {
+                        let expires = Utc::now() + TimeDelta::seconds(lookup.cache_for as i64);
+                        CACHE
+                            .lock()
+                            .expect("lookup cache lock poisoned")
+                            .insert(cache_key, (expires, value.clone()));
+                        value
+                    }
+                    Err(e) => {
+                        warn!("HTTP lookup {} of {:?} failed: {:#}", name, key, e);
+                        Value::Null
+                    }
+                }
+            }
+        };
+        Ok(ScopedJson::Derived(match value {
+            Value::Null => default(h),
+            value => value,
+        }))
+    }
+}
+
+async fn fetch(lookup: &HttpLookupConfig, key: &str) -> anyhow::Result<Value> {
+    let url = lookup.url.replace("{key}", &encode(key));
+    let mut request = CLIENT
+        .get(url)
+        .timeout(Duration::from_millis(lookup.timeout));
+    for (name, value) in &lookup.headers {
+        request = request.header(name, value);
+    }
+    let response = request.send().await?;
+    // Keys without a value are cached like keys with one
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Value::Null);
+    }
+    let body = response.error_for_status()?.text().await?;
+
+    let Some(pointer) = &lookup.pointer else {
+        return Ok(Value::String(body));
+    };
+    let json: Value = serde_json::from_str(&body)?;
+    Ok(json.pointer(pointer).cloned().unwrap_or(Value::Null))
+}
+
+/// The name of the table or service and the key, which may be any scalar.
+fn name_and_key<'a>(h: &'a Helper, helper: &'static str) -> Result<(&'a str, String), RenderError> {
+    let name = h
+        .param(0)
+        .and_then(|param| param.value().as_str())
+        .ok_or(RenderErrorReason::ParamNotFoundForIndex(helper, 0))?;
+    let key = match h.param(1).map(|param| param.value()) {
+        Some(Value::String(key)) => key.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(key) => key.to_string(),
+    };
+    Ok((name, key))
+}
+
+fn default(h: &Helper) -> Value {
+    h.hash_get("default")
+        .map(|default| default.value().clone())
+        .unwrap_or(Value::Null)
+}
+
+/// Percent-encodes everything but unreserved characters, so keys can't change the URL.
+pub(crate) fn encode(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,67 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use crate::config::{DirectionTemplates, TemplateConfig};
+use crate::{decisions, template_helpers};
+
+pub const ZAMMAD_TO_JIRA_SUMMARY: &str = "zammad_to_jira.summary";
+pub const ZAMMAD_TO_JIRA_DESCRIPTION: &str = "zammad_to_jira.description";
+pub const ZAMMAD_TO_JIRA_COMMENT: &str = "zammad_to_jira.comment";
+pub const JIRA_TO_ZAMMAD_SUMMARY: &str = "jira_to_zammad.summary";
+pub const JIRA_TO_ZAMMAD_DESCRIPTION: &str = "jira_to_zammad.description";
+pub const JIRA_TO_ZAMMAD_COMMENT: &str = "jira_to_zammad.comment";
+
+static TEMPLATES: RwLock<Option<Arc<Handlebars<'static>>>> = RwLock::new(None);
+
+/// Compiles the configured templates, so syntax errors show up on startup or reload, and
+/// replaces the ones in use.
+pub fn init(config: &TemplateConfig) -> Result<()> {
+    let mut registry = Handlebars::new();
+    // The output is plain text or Jira markup, not HTML
+    registry.register_escape_fn(handlebars::no_escape);
+    template_helpers::register(&mut registry, config);
+
+    register(&mut registry, "zammad_to_jira", &config.zammad_to_jira)?;
+    register(&mut registry, "jira_to_zammad", &config.jira_to_zammad)?;
+
+    *TEMPLATES.write().expect("templates lock poisoned") = Some(Arc::new(registry));
+    Ok(())
+}
+
+fn register(
+    registry: &mut Handlebars<'static>,
+    direction: &str,
+    templates: &DirectionTemplates,
+) -> Result<()> {
+    for (field, template) in [
+        ("summary", &templates.summary),
+        ("description", &templates.description),
+        ("comment", &templates.comment),
+    ] {
+        if let Some(template) = template {
+            let name = format!("{}.{}", direction, field);
+            registry
+                .register_template_string(&name, template)
+                .with_context(|| format!("invalid template {}", name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Renders the template `name` with `context`, or returns `None` if it isn't configured.
+pub fn render(name: &str, context: &impl Serialize) -> Result<Option<String>> {
+    let Some(registry) = TEMPLATES.read().expect("templates lock poisoned").clone() else {
+        return Ok(None);
+    };
+    if !registry.has_template(name) {
+        return Ok(None);
+    }
+    let rendered = registry
+        .render(name, context)
+        .with_context(|| format!("failed to render template {}", name))?;
+    decisions::record(format!("template {}", name), context, &rendered);
+    Ok(Some(rendered))
+}
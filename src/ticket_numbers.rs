@@ -0,0 +1,148 @@
+//! Ticket numbers and issue keys as people paste them, e.g. "Ticket#45 003", "[#45003]",
+//! "cun 123" or a Jira browse URL, reduced to the form the APIs expect.
+
+/// Characters people and mail clients put between the digits of a number.
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '.' | ',' | '\'' | '_' | '\u{2009}' | '\u{202f}')
+}
+
+/// Dashes that end up in keys, e.g. non-breaking or typographic ones.
+fn is_dash(c: char) -> bool {
+    matches!(
+        c,
+        '-' | '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}'
+    )
+}
+
+/// The ASCII digit for decimal digits of other scripts, e.g. full-width or Arabic-Indic ones.
+fn ascii_digit(c: char) -> Option<char> {
+    [
+        '0', '\u{ff10}', '\u{0660}', '\u{06f0}', '\u{0966}', '\u{09e6}',
+    ]
+    .into_iter()
+    .find_map(|zero| {
+        let digit = (c as u32)
+            .checked_sub(zero as u32)
+            .filter(|digit| *digit < 10)?;
+        char::from_digit(digit, 10)
+    })
+}
+
+fn is_digit(c: char) -> bool {
+    ascii_digit(c).is_some()
+}
+
+/// Normalizes a Zammad ticket number, whatever ticket hook ("Ticket#", "Anfrage#", ...) or
+/// grouping it is written with. Returns `None` if it doesn't contain a number.
+pub fn normalize_zammad_number(input: &str) -> Option<String> {
+    // The ticket hook ends with '#', the number follows it
+    let number = input.rsplit_once('#').map_or(input, |(_, number)| number);
+    let number = number.trim_matches(|c: char| !is_digit(c));
+
+    let mut normalized = String::new();
+    for c in number.chars() {
+        match ascii_digit(c) {
+            Some(digit) => normalized.push(digit),
+            None if is_separator(c) => {}
+            None => return None,
+        }
+    }
+    (!normalized.is_empty()).then_some(normalized)
+}
+
+/// Normalizes a Jira issue key, e.g. "cun 123" or ".../browse/CUN-123?focusedId=1", to
+/// "CUN-123". Returns `None` if it isn't a key.
+pub fn normalize_jira_key(input: &str) -> Option<String> {
+    let key = match input.split_once("/browse/") {
+        Some((_, path)) => path.split(['?', '#', '/']).next().unwrap_or_default(),
+        None => input,
+    };
+    let key = key.trim_matches(|c: char| !c.is_alphanumeric());
+
+    let digits_start = key
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_digit(*c))
+        .last()
+        .map(|(index, _)| index)?;
+    let (project, number) = key.split_at(digits_start);
+    let project = project.trim_end_matches(|c: char| is_dash(c) || is_separator(c));
+
+    let mut chars = project.chars();
+    if !chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+    // Jira doesn't pad issue numbers
+    let number: u64 = number
+        .chars()
+        .filter_map(ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    Some(format!("{}-{}", project.to_ascii_uppercase(), number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zammad_numbers() {
+        for (input, expected) in [
+            ("45003", Some("45003")),
+            ("Ticket#45 003", Some("45003")),
+            ("Anfrage#45.003", Some("45003")),
+            ("#45003", Some("45003")),
+            ("[#45003]", Some("45003")),
+            ("Re: [Ticket#45003] Drucker", Some("45003")),
+            ("\u{ff14}\u{ff15}\u{ff10}\u{ff10}\u{ff13}", Some("45003")),
+            ("\u{0664}\u{0665}\u{0660}\u{0660}\u{0663}", Some("45003")),
+            ("45\u{202f}003", Some("45003")),
+            ("45-003", None),
+            ("Ticket#", None),
+            ("", None),
+        ] {
+            assert_eq!(
+                normalize_zammad_number(input).as_deref(),
+                expected,
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn jira_keys() {
+        for (input, expected) in [
+            ("CUN-123", Some("CUN-123")),
+            ("cun 123", Some("CUN-123")),
+            ("cun123", Some("CUN-123")),
+            ("CUN\u{2011}0123", Some("CUN-123")),
+            ("CUN\u{2013}123", Some("CUN-123")),
+            ("(CUN-123)", Some("CUN-123")),
+            (
+                "https://example.atlassian.net/browse/CUN-123?focusedId=1",
+                Some("CUN-123"),
+            ),
+            (
+                "https://example.atlassian.net/browse/CUN-123/",
+                Some("CUN-123"),
+            ),
+            ("MY_PROJ-7", Some("MY_PROJ-7")),
+            ("CUN-\u{ff11}\u{ff12}\u{ff13}", Some("CUN-123")),
+            ("123", None),
+            ("1CUN-123", None),
+            ("CUN-", None),
+            ("", None),
+        ] {
+            assert_eq!(
+                normalize_jira_key(input).as_deref(),
+                expected,
+                "{:?}",
+                input
+            );
+        }
+    }
+}
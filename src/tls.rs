@@ -0,0 +1,162 @@
+//! Serves HTTPS directly, for deployments without a reverse proxy terminating TLS in front
+//! of the service. With a client CA, webhooks are only accepted from clients presenting a
+//! certificate it issued; other routes, e.g. the admin API, don't need one.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use axum::{
+    Extension, Router,
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use reqwest::StatusCode;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig, crypto::ring};
+use tower::{Layer, Service};
+use tracing::{debug, info, warn};
+
+use crate::config::{self, TlsConfig};
+use crate::shutdown;
+
+/// Connections that didn't finish the handshake by then are dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Loads the certificate and key, failing on startup instead of on the first connection.
+pub fn acceptor(config: &TlsConfig) -> Result<TlsAcceptor> {
+    let certs = read_certs(&config.cert)?;
+    let mut key_file = BufReader::new(
+        File::open(&config.key)
+            .with_context(|| format!("failed to open {}", config.key.display()))?,
+    );
+    let key = rustls_pemfile::private_key(&mut key_file)
+        .with_context(|| format!("failed to read {}", config.key.display()))?
+        .with_context(|| format!("no private key in {}", config.key.display()))?;
+
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match &config.client_ca {
+        Some(client_ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(client_ca)? {
+                roots.add(cert).with_context(|| {
+                    format!("invalid CA certificate in {}", client_ca.display())
+                })?;
+            }
+            // Clients without a certificate are let through to the routes that don't need
+            // one; certificates that are presented have to be valid
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .allow_unauthenticated()
+                .build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut server_config = builder
+        .with_single_cert(certs, key)
+        .context("invalid TLS certificate or key")?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let mut file = BufReader::new(
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?,
+    );
+    rustls_pemfile::certs(&mut file)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("failed to read {}", path.display()))
+}
+
+/// Rejects webhooks from clients that didn't present a trusted certificate, if a client CA
+/// is configured.
+pub async fn require_client_certificate(request: Request, next: Next) -> Response {
+    let required = config::get()
+        .tls
+        .as_ref()
+        .is_some_and(|tls| tls.client_ca.is_some());
+    if required
+        && request.uri().path().starts_with("/ticket-sync/")
+        && !request
+            .extensions()
+            .get::<ClientCertificate>()
+            .is_some_and(|certificate| certificate.verified)
+    {
+        warn!(
+            "Rejecting webhook to {} without client certificate",
+            request.uri().path()
+        );
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    next.run(request).await
+}
+
+/// Whether the client of the connection presented a certificate the client CA issued.
+#[derive(Debug, Clone, Copy)]
+struct ClientCertificate {
+    verified: bool,
+}
+
+/// Accepts TLS connections until the shutdown starts; each is served on its own task. Then
+/// waits for the connections to answer the requests in flight.
+pub async fn serve(listener: TcpListener, acceptor: TlsAcceptor, app: Router) -> Result<()> {
+    info!("Serving HTTPS on {}", listener.local_addr()?);
+    let mut make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let graceful = GracefulShutdown::new();
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = shutdown::stopping() => break,
+        };
+        let acceptor = acceptor.clone();
+        let watcher = graceful.watcher();
+        let service = make_service
+            .call(peer)
+            .await
+            .unwrap_or_else(|never| match never {});
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(e)) => {
+                        debug!("TLS handshake with {} failed: {}", peer, e);
+                        return;
+                    }
+                    Err(_) => {
+                        debug!("TLS handshake with {} timed out", peer);
+                        return;
+                    }
+                };
+            // rustls only keeps certificates it verified
+            let verified = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .is_some_and(|certs| !certs.is_empty());
+            let service = Extension(ClientCertificate { verified }).layer(service);
+            let builder = auto::Builder::new(TokioExecutor::new());
+            let connection =
+                builder.serve_connection(TokioIo::new(stream), TowerToHyperService::new(service));
+            if let Err(e) = watcher.watch(connection).await {
+                debug!("Connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+    // Idle keep-alive connections are closed right away, busy ones after their response
+    graceful.shutdown().await;
+    Ok(())
+}
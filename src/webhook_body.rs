@@ -0,0 +1,35 @@
+//! Webhook bodies are read by several middlewares before the handler, e.g. to check the
+//! signature. All of them stop at the same limit as the handlers, `webhook_body_limit`, so a
+//! sender can't make the service buffer an unbounded body; larger ones are answered with 413.
+
+use std::sync::OnceLock;
+
+use axum::body::{Body, Bytes};
+use axum::extract::DefaultBodyLimit;
+use axum::response::{IntoResponse, Response};
+use http_body_util::LengthLimitError;
+use reqwest::StatusCode;
+use tracing::warn;
+
+static LIMIT: OnceLock<usize> = OnceLock::new();
+
+/// Limits the webhook bodies to `bytes`, for the handlers and for `read`.
+pub fn limit(bytes: usize) -> DefaultBodyLimit {
+    let _ = LIMIT.set(bytes);
+    DefaultBodyLimit::max(bytes)
+}
+
+/// Reads the body of a webhook, up to the limit.
+pub async fn read(body: Body) -> Result<Bytes, Response> {
+    let limit = *LIMIT.get_or_init(crate::config::default_webhook_body_limit);
+    axum::body::to_bytes(body, limit).await.map_err(|e| {
+        let inner = e.into_inner();
+        if inner.is::<LengthLimitError>() {
+            warn!("Rejecting webhook larger than {} bytes", limit);
+            StatusCode::PAYLOAD_TOO_LARGE.into_response()
+        } else {
+            warn!("Failed to read webhook body: {}", inner);
+            StatusCode::BAD_REQUEST.into_response()
+        }
+    })
+}